@@ -0,0 +1,70 @@
+//! Capture build information reported by the health endpoint.
+//!
+//! Sets `DK_GIT_SHA`, `DK_BUILD_TIMESTAMP` and `DK_RUSTC_VERSION` for the
+//! crate. Each falls back to `"unknown"` when it cannot be determined, e.g.
+//! when building from a source tarball without git.
+
+// Cargo reads build script instructions from standard output
+#![allow(clippy::disallowed_macros)]
+
+use std::process::Command;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+const UNKNOWN: &str = "unknown";
+
+fn main() {
+    // Container builds often lack `.git`; they can pass the commit instead
+    println!("cargo:rerun-if-env-changed=GIT_SHA");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    if let Some(head) = output("git", &["rev-parse", "--git-path", "HEAD"]) {
+        println!("cargo:rerun-if-changed={head}");
+    }
+    if let Some(reference) = output("git", &["symbolic-ref", "-q", "HEAD"]) {
+        if let Some(path) = output("git", &["rev-parse", "--git-path", &reference]) {
+            println!("cargo:rerun-if-changed={path}");
+        }
+    }
+
+    let git_sha = std::env::var("GIT_SHA")
+        .ok()
+        .filter(|sha| !sha.trim().is_empty())
+        .or_else(|| output("git", &["rev-parse", "HEAD"]))
+        .unwrap_or_else(|| UNKNOWN.to_string());
+    println!("cargo:rustc-env=DK_GIT_SHA={git_sha}");
+
+    println!("cargo:rustc-env=DK_BUILD_TIMESTAMP={}", build_timestamp());
+
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = output(&rustc, &["--version"]).unwrap_or_else(|| UNKNOWN.to_string());
+    println!("cargo:rustc-env=DK_RUSTC_VERSION={rustc_version}");
+}
+
+/// Build time in RFC 3339, honouring `SOURCE_DATE_EPOCH` for reproducible
+/// builds.
+fn build_timestamp() -> String {
+    let time = match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => epoch
+            .trim()
+            .parse()
+            .ok()
+            .and_then(|secs| DateTime::<Utc>::from_timestamp(secs, 0)),
+        Err(_) => Some(Utc::now()),
+    };
+    time.map_or_else(
+        || UNKNOWN.to_string(),
+        |time| time.to_rfc3339_opts(SecondsFormat::Secs, true),
+    )
+}
+
+/// Trimmed standard output of `program`, if it ran successfully and printed
+/// something.
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    let stdout = stdout.trim();
+    (!stdout.is_empty()).then(|| stdout.to_string())
+}
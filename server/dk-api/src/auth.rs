@@ -0,0 +1,168 @@
+//! API-key authentication for protected endpoints.
+//!
+//! Clients send `Authorization: Bearer <key>`. Only SHA-256 digests of the
+//! accepted keys are configured, so a leaked configuration does not leak
+//! usable credentials.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use dk_common::config::AuthConfig;
+use dk_common::hash::sha256_bytes;
+use dk_common::Error;
+
+use crate::error::ApiError;
+use crate::state::AppState;
+
+/// Accepted API keys, held as lowercase hex SHA-256 digests.
+#[derive(Debug, Clone, Default)]
+pub struct ApiKeys(Arc<HashSet<String>>);
+
+impl ApiKeys {
+    /// Load the key digests from `config`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] if a digest is not 64 hex characters.
+    pub fn from_config(config: &AuthConfig) -> dk_common::Result<Self> {
+        let hashes = config
+            .api_key_hashes
+            .iter()
+            .map(|hash| {
+                let hash = hash.trim().to_ascii_lowercase();
+                if hash.len() == 64 && hash.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+                    Ok(hash)
+                } else {
+                    Err(Error::Config(format!(
+                        "auth.api_key_hashes: {hash:?} is not a hex SHA-256 digest"
+                    )))
+                }
+            })
+            .collect::<dk_common::Result<HashSet<_>>>()?;
+        Ok(Self(Arc::new(hashes)))
+    }
+
+    /// Whether no key is configured, so every protected request is rejected.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether `key` is one of the accepted keys.
+    #[must_use]
+    pub fn accepts(&self, key: &str) -> bool {
+        self.0.contains(&hash_key(key))
+    }
+}
+
+/// Lowercase hex SHA-256 of `key`, the form stored in configuration.
+#[must_use]
+pub fn hash_key(key: &str) -> String {
+    sha256_bytes(key.as_bytes()).to_string()
+}
+
+/// Middleware rejecting requests without a valid bearer API key.
+pub async fn require_api_key(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let key = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            let (scheme, key) = value.split_once(' ')?;
+            scheme.eq_ignore_ascii_case("bearer").then(|| key.trim())
+        })
+        .ok_or_else(|| ApiError::Unauthorized("missing bearer API key".to_string()))?;
+
+    if !state.api_keys.accepts(key) {
+        return Err(ApiError::Unauthorized("invalid API key".to_string()));
+    }
+    Ok(next.run(request).await)
+}
+
+/// Key check endpoint.
+///
+/// Returns 204 when the request carries a valid API key, letting clients
+/// verify their credentials before uploading.
+pub async fn verify_key() -> StatusCode {
+    StatusCode::NO_CONTENT
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    const KEY: &str = "dk-test-key";
+
+    fn app() -> Router {
+        let mut state = AppState::disconnected();
+        state.api_keys = ApiKeys::from_config(&AuthConfig {
+            api_key_hashes: vec![hash_key(KEY).to_uppercase()],
+        })
+        .expect("api keys");
+
+        Router::new()
+            .route("/protected", get(verify_key))
+            .route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                require_api_key,
+            ))
+            .with_state(state)
+    }
+
+    async fn request(authorization: Option<&str>) -> Response {
+        let mut builder = axum::http::Request::builder().uri("/protected");
+        if let Some(value) = authorization {
+            builder = builder.header(header::AUTHORIZATION, value);
+        }
+        app()
+            .oneshot(builder.body(Body::empty()).expect("request"))
+            .await
+            .expect("response")
+    }
+
+    #[tokio::test]
+    async fn test_missing_header_is_unauthorized() {
+        let response = request(None).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[header::WWW_AUTHENTICATE], "Bearer");
+    }
+
+    #[tokio::test]
+    async fn test_wrong_key_is_unauthorized() {
+        let response = request(Some("Bearer not-the-key")).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = request(Some(&format!("Basic {KEY}"))).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_valid_key_passes_through() {
+        let response = request(Some(&format!("Bearer {KEY}"))).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[test]
+    fn test_malformed_hash_is_config_error() {
+        let config = AuthConfig {
+            api_key_hashes: vec!["not-a-digest".to_string()],
+        };
+        assert!(matches!(
+            ApiKeys::from_config(&config),
+            Err(Error::Config(_))
+        ));
+    }
+}
@@ -0,0 +1,68 @@
+//! Cross-origin resource sharing policy.
+
+use axum::http::{HeaderValue, Method};
+use dk_common::config::CorsConfig;
+use dk_common::{Error, Result};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+/// Build the CORS layer described by `config`.
+///
+/// # Errors
+///
+/// Returns [`Error::Config`] if the policy is inconsistent or an origin or
+/// method cannot be parsed.
+pub fn layer(config: &CorsConfig) -> Result<CorsLayer> {
+    config.validate()?;
+
+    let origins = if config.allows_any_origin() {
+        AllowOrigin::any()
+    } else {
+        let origins = config
+            .allowed_origins
+            .iter()
+            .map(|origin| {
+                HeaderValue::from_str(origin)
+                    .map_err(|err| Error::Config(format!("cors origin {origin:?}: {err}")))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        AllowOrigin::list(origins)
+    };
+    let methods = config
+        .allowed_methods
+        .iter()
+        .map(|method| {
+            method
+                .parse::<Method>()
+                .map_err(|err| Error::Config(format!("cors method {method:?}: {err}")))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(methods)
+        .allow_credentials(config.allow_credentials))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wildcard_with_credentials_is_rejected() {
+        let config = CorsConfig {
+            allowed_origins: vec!["*".to_string()],
+            allow_credentials: true,
+            ..CorsConfig::default()
+        };
+        assert!(matches!(layer(&config), Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn test_invalid_method_is_rejected() {
+        let config = CorsConfig {
+            allowed_methods: vec!["GET POST".to_string()],
+            ..CorsConfig::default()
+        };
+        assert!(matches!(layer(&config), Err(Error::Config(_))));
+    }
+}
@@ -1,35 +1,121 @@
 //! Error handling for the API.
 
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use serde::Serialize;
+use utoipa::ToSchema;
 
 /// API error type.
-#[derive(Debug)]
+///
+/// Error responses carry a JSON body with a stable machine-readable `code`
+/// (see [`ApiError::code`]), a snake-case `error` type, and a human-readable
+/// `message` that may change at any time.
+#[derive(Debug, Clone)]
 pub enum ApiError {
     /// Resource not found.
     NotFound(String),
+    /// No application with the requested package ID.
+    AppNotFound(String),
+    /// No published version with the requested version code.
+    VersionNotFound(String),
     /// Invalid request.
     BadRequest(String),
+    /// The package ID in the request is not a valid Android package name.
+    InvalidPackageId(String),
+    /// Missing or invalid credentials.
+    Unauthorized(String),
+    /// Request conflicts with the current state of the resource.
+    Conflict(String),
+    /// Request is well-formed but cannot be processed as sent.
+    UnprocessableEntity(String),
+    /// Client exceeded its rate limit; retry after this many seconds.
+    TooManyRequests(u64),
     /// Internal server error.
     Internal(String),
 }
 
+impl ApiError {
+    /// Stable code identifying the kind of error, for clients to branch on.
+    ///
+    /// | Code                 | Status | Meaning                                   |
+    /// |----------------------|--------|-------------------------------------------|
+    /// | `NOT_FOUND`          | 404    | The resource does not exist               |
+    /// | `APP_NOT_FOUND`      | 404    | No app has the package ID                 |
+    /// | `VERSION_NOT_FOUND`  | 404    | The app has no such published version     |
+    /// | `BAD_REQUEST`        | 400    | The request is malformed or invalid       |
+    /// | `INVALID_PACKAGE_ID` | 400    | The package ID is not a valid Android one |
+    /// | `UNAUTHORIZED`       | 401    | The API key is missing or not accepted    |
+    /// | `CONFLICT`           | 409    | The resource already exists               |
+    /// | `UNPROCESSABLE`      | 422    | The request cannot be processed as sent   |
+    /// | `RATE_LIMITED`       | 429    | Too many requests; honour `Retry-After`   |
+    /// | `INTERNAL_ERROR`     | 500    | The server failed; retrying may help      |
+    ///
+    /// The specific codes refine a generic one with the same status, so
+    /// clients that only know `NOT_FOUND` can branch on the status instead.
+    ///
+    /// Codes are part of the API contract: existing codes never change
+    /// meaning and are never removed.
+    #[must_use]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "NOT_FOUND",
+            Self::AppNotFound(_) => "APP_NOT_FOUND",
+            Self::VersionNotFound(_) => "VERSION_NOT_FOUND",
+            Self::BadRequest(_) => "BAD_REQUEST",
+            Self::InvalidPackageId(_) => "INVALID_PACKAGE_ID",
+            Self::Unauthorized(_) => "UNAUTHORIZED",
+            Self::Conflict(_) => "CONFLICT",
+            Self::UnprocessableEntity(_) => "UNPROCESSABLE",
+            Self::TooManyRequests(_) => "RATE_LIMITED",
+            Self::Internal(_) => "INTERNAL_ERROR",
+        }
+    }
+}
+
 /// Error response body.
-#[derive(Serialize)]
-struct ErrorResponse {
+#[derive(Serialize, ToSchema)]
+pub struct ErrorResponse {
+    /// Stable error code; see [`ApiError::code`].
+    #[schema(value_type = String, example = "NOT_FOUND")]
+    code: &'static str,
+    /// Snake-case error type.
     error: String,
+    /// Human-readable description; may change at any time.
     message: String,
+    /// Correlation ID of the failed request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
+        let retry_after = match self {
+            Self::TooManyRequests(secs) => Some(secs),
+            _ => None,
+        };
+        let code = self.code();
         let (status, error_type, message) = match self {
-            Self::NotFound(msg) => (StatusCode::NOT_FOUND, "not_found", msg),
-            Self::BadRequest(msg) => (StatusCode::BAD_REQUEST, "bad_request", msg),
+            Self::NotFound(msg) | Self::AppNotFound(msg) | Self::VersionNotFound(msg) => {
+                (StatusCode::NOT_FOUND, "not_found", msg)
+            }
+            Self::BadRequest(msg) | Self::InvalidPackageId(msg) => {
+                (StatusCode::BAD_REQUEST, "bad_request", msg)
+            }
+            Self::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, "unauthorized", msg),
+            Self::Conflict(msg) => (StatusCode::CONFLICT, "conflict", msg),
+            Self::UnprocessableEntity(msg) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "unprocessable_entity",
+                msg,
+            ),
+            Self::TooManyRequests(secs) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "too_many_requests",
+                format!("rate limit exceeded, retry in {secs}s"),
+            ),
             Self::Internal(msg) => {
                 // Log internal errors but don't expose details
                 tracing::error!("Internal error: {}", msg);
@@ -42,11 +128,24 @@ impl IntoResponse for ApiError {
         };
 
         let body = ErrorResponse {
+            code,
             error: error_type.to_string(),
             message,
+            request_id: crate::request_id::current(),
         };
 
-        (status, Json(body)).into_response()
+        let mut response = (status, Json(body)).into_response();
+        if status == StatusCode::UNAUTHORIZED {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        }
+        if let Some(secs) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        }
+        response
     }
 }
 
@@ -55,9 +154,121 @@ impl From<dk_common::Error> for ApiError {
         match err {
             dk_common::Error::NotFound(msg) => Self::NotFound(msg),
             dk_common::Error::InvalidInput(msg) => Self::BadRequest(msg),
-            dk_common::Error::Database(msg) => Self::Internal(msg),
-            dk_common::Error::Config(msg) => Self::Internal(msg),
-            dk_common::Error::Internal(msg) => Self::Internal(msg),
+            dk_common::Error::Database(msg)
+            | dk_common::Error::Config(msg)
+            | dk_common::Error::Storage(msg)
+            | dk_common::Error::Webhook(msg)
+            | dk_common::Error::Internal(msg) => Self::Internal(msg),
+        }
+    }
+}
+
+impl From<dk_scanner::ScanError> for ApiError {
+    fn from(err: dk_scanner::ScanError) -> Self {
+        match err {
+            dk_scanner::ScanError::ApkNotFound(msg) => Self::NotFound(msg),
+            err => Self::Internal(err.to_string()),
+        }
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(err: sqlx::Error) -> Self {
+        Self::Internal(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    fn variants() -> [(ApiError, &'static str, StatusCode); 10] {
+        [
+            (
+                ApiError::NotFound("Build not found: 42".to_string()),
+                "NOT_FOUND",
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                ApiError::AppNotFound("App not found: dk.digst.mitid".to_string()),
+                "APP_NOT_FOUND",
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                ApiError::VersionNotFound("Version not found: dk.digst.mitid 7".to_string()),
+                "VERSION_NOT_FOUND",
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                ApiError::BadRequest("Missing multipart field 'apk'".to_string()),
+                "BAD_REQUEST",
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                ApiError::InvalidPackageId("package id '..' has invalid segment ''".to_string()),
+                "INVALID_PACKAGE_ID",
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                ApiError::Unauthorized("missing API key".to_string()),
+                "UNAUTHORIZED",
+                StatusCode::UNAUTHORIZED,
+            ),
+            (
+                ApiError::Conflict("Version already exists".to_string()),
+                "CONFLICT",
+                StatusCode::CONFLICT,
+            ),
+            (
+                ApiError::UnprocessableEntity("Idempotency key reused".to_string()),
+                "UNPROCESSABLE",
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ),
+            (
+                ApiError::TooManyRequests(30),
+                "RATE_LIMITED",
+                StatusCode::TOO_MANY_REQUESTS,
+            ),
+            (
+                ApiError::Internal("connection refused".to_string()),
+                "INTERNAL_ERROR",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_code_per_variant() {
+        for (err, code, _) in variants() {
+            assert_eq!(err.code(), code, "{err:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_body_carries_code() {
+        for (err, code, status) in variants() {
+            let response = err.into_response();
+            assert_eq!(response.status(), status);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .expect("body");
+            let body: Value = serde_json::from_slice(&body).expect("error json");
+            assert_eq!(body["code"], code);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_code_does_not_depend_on_message() {
+        for message in ["Build not found: a", "Icon not found: b"] {
+            let response = ApiError::NotFound(message.to_string()).into_response();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .expect("body");
+            let body: Value = serde_json::from_slice(&body).expect("error json");
+            assert_eq!(body["code"], "NOT_FOUND");
+            assert_eq!(body["message"], message);
         }
     }
 }
@@ -0,0 +1,331 @@
+//! Redis cache of serialized repository indexes.
+//!
+//! Entries are keyed by a fingerprint of the repository contents (see
+//! [`crate::repository::AppRepository::index_fingerprint`]), so any change
+//! to an app or version moves readers to a fresh key; stale entries simply
+//! expire.
+//! Redis being unavailable only costs the regeneration, never a request.
+//!
+//! Concurrent misses on the same key share a single generation: the first
+//! runs it and the rest wait for its result. Forced regenerations join the
+//! same flights. Generations in progress are tracked in [`Flights`], held
+//! in the application state.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use tokio::sync::OnceCell;
+
+use crate::error::ApiError;
+
+/// How long a cached index is kept. Entries are never served stale, so
+/// this only bounds the memory taken by superseded ones.
+pub const INDEX_CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// Maximum time a cache lookup or store may take before it is skipped.
+const CACHE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Outcome of a generation, shared by everyone who waited for it.
+type Flight = OnceCell<Result<Vec<u8>, ApiError>>;
+
+/// Generations in progress, by cache key.
+#[derive(Debug, Default)]
+pub struct Flights(Mutex<HashMap<String, Arc<Flight>>>);
+
+impl Flights {
+    /// The generation in progress for `key`, starting a new one if there is
+    /// none.
+    fn join(&self, key: &str) -> Arc<Flight> {
+        Arc::clone(self.lock().entry(key.to_string()).or_default())
+    }
+
+    /// Forget `flight` once done, so later misses on `key` generate afresh.
+    fn land(&self, key: &str, flight: &Arc<Flight>) {
+        let mut flights = self.lock();
+        if flights
+            .get(key)
+            .is_some_and(|current| Arc::ptr_eq(current, flight))
+        {
+            flights.remove(key);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Arc<Flight>>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Pattern matching the keys of all cached indexes.
+const INDEX_KEY_PATTERN: &str = "dk-appstore:index:*";
+
+/// Redis key of the `format` index of the repository with `fingerprint`.
+#[must_use]
+pub fn cache_key(format: &str, fingerprint: &str) -> String {
+    format!("dk-appstore:index:{format}:{fingerprint}")
+}
+
+/// The index cached under `key`, or else the output of `generate`, which is
+/// then stored under `key`.
+///
+/// If another caller is already generating the index for `key` in
+/// `flights`, its result is awaited and `generate` is not run.
+///
+/// # Errors
+///
+/// Returns the error of `generate`; cache failures are logged and
+/// otherwise ignored.
+pub async fn cached<F>(
+    redis: &redis::Client,
+    flights: &Flights,
+    key: &str,
+    generate: F,
+) -> Result<Vec<u8>, ApiError>
+where
+    F: Future<Output = Result<Vec<u8>, ApiError>>,
+{
+    match bounded(lookup(redis, key)).await {
+        Ok(Some(index)) => {
+            tracing::debug!(key, "Index cache hit");
+            return Ok(index);
+        }
+        Ok(None) => tracing::debug!(key, "Index cache miss"),
+        Err(err) => tracing::warn!(key, error = %err, "Index cache lookup failed"),
+    }
+    regenerate(redis, flights, key, generate).await
+}
+
+/// The output of `generate`, stored under `key` in place of any cached
+/// index.
+///
+/// If another caller is already generating the index for `key` in
+/// `flights`, its result is awaited and `generate` is not run.
+///
+/// # Errors
+///
+/// Returns the error of `generate`; cache failures are logged and
+/// otherwise ignored.
+pub async fn regenerate<F>(
+    redis: &redis::Client,
+    flights: &Flights,
+    key: &str,
+    generate: F,
+) -> Result<Vec<u8>, ApiError>
+where
+    F: Future<Output = Result<Vec<u8>, ApiError>>,
+{
+    let flight = flights.join(key);
+    let index = flight
+        .get_or_init(|| async {
+            let index = generate.await?;
+            if let Err(err) = bounded(store(redis, key, &index)).await {
+                tracing::warn!(key, error = %err, "Index cache store failed");
+            }
+            Ok(index)
+        })
+        .await
+        .clone();
+    flights.land(key, &flight);
+    index
+}
+
+/// Remove every cached index, returning how many were removed.
+///
+/// # Errors
+///
+/// Returns a description of the failure if Redis cannot be reached in time.
+pub async fn invalidate(redis: &redis::Client) -> Result<u64, String> {
+    bounded(remove_matching(redis, INDEX_KEY_PATTERN)).await
+}
+
+async fn lookup(redis: &redis::Client, key: &str) -> Result<Option<Vec<u8>>, String> {
+    let mut connection = redis
+        .get_multiplexed_tokio_connection()
+        .await
+        .map_err(|err| err.to_string())?;
+    redis::cmd("GET")
+        .arg(key)
+        .query_async(&mut connection)
+        .await
+        .map_err(|err| err.to_string())
+}
+
+async fn store(redis: &redis::Client, key: &str, index: &[u8]) -> Result<(), String> {
+    let mut connection = redis
+        .get_multiplexed_tokio_connection()
+        .await
+        .map_err(|err| err.to_string())?;
+    redis::cmd("SET")
+        .arg(key)
+        .arg(index)
+        .arg("EX")
+        .arg(INDEX_CACHE_TTL.as_secs())
+        .query_async(&mut connection)
+        .await
+        .map_err(|err| err.to_string())
+}
+
+async fn remove_matching(redis: &redis::Client, pattern: &str) -> Result<u64, String> {
+    let mut connection = redis
+        .get_multiplexed_tokio_connection()
+        .await
+        .map_err(|err| err.to_string())?;
+    let mut cursor = 0u64;
+    let mut removed = 0;
+    loop {
+        let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH")
+            .arg(pattern)
+            .query_async(&mut connection)
+            .await
+            .map_err(|err| err.to_string())?;
+        if !keys.is_empty() {
+            removed += redis::cmd("DEL")
+                .arg(&keys)
+                .query_async::<_, u64>(&mut connection)
+                .await
+                .map_err(|err| err.to_string())?;
+        }
+        if next == 0 {
+            return Ok(removed);
+        }
+        cursor = next;
+    }
+}
+
+/// Run `operation`, failing it if it exceeds [`CACHE_TIMEOUT`].
+async fn bounded<T>(operation: impl Future<Output = Result<T, String>>) -> Result<T, String> {
+    tokio::time::timeout(CACHE_TIMEOUT, operation)
+        .await
+        .unwrap_or_else(|_| Err(format!("timed out after {}ms", CACHE_TIMEOUT.as_millis())))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::state::AppState;
+
+    /// Cache `index` under `key`, counting generations in `generations`.
+    async fn cached_counting(
+        redis: &redis::Client,
+        key: &str,
+        index: &[u8],
+        generations: &AtomicUsize,
+    ) -> Vec<u8> {
+        cached(redis, &Flights::default(), key, async {
+            generations.fetch_add(1, Ordering::SeqCst);
+            Ok(index.to_vec())
+        })
+        .await
+        .expect("index")
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_cache_regenerates() {
+        let redis = AppState::disconnected().redis;
+        let key = cache_key("v1", &uuid::Uuid::new_v4().to_string());
+        let generations = AtomicUsize::new(0);
+
+        for _ in 0..2 {
+            let index = cached_counting(&redis, &key, b"{}", &generations).await;
+            assert_eq!(index, b"{}");
+        }
+        assert_eq!(generations.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_misses_share_one_generation() {
+        let redis = AppState::disconnected().redis;
+        let flights = Arc::new(Flights::default());
+        let key = cache_key("v1", &uuid::Uuid::new_v4().to_string());
+        let generations = Arc::new(AtomicUsize::new(0));
+
+        let requests: Vec<_> = (0..16)
+            .map(|_| {
+                let (redis, flights, key, generations) = (
+                    redis.clone(),
+                    flights.clone(),
+                    key.clone(),
+                    generations.clone(),
+                );
+                tokio::spawn(async move {
+                    cached(&redis, &flights, &key, async {
+                        generations.fetch_add(1, Ordering::SeqCst);
+                        // Long enough for every request to join the flight
+                        tokio::time::sleep(Duration::from_millis(300)).await;
+                        Ok(b"{}".to_vec())
+                    })
+                    .await
+                })
+            })
+            .collect();
+        for request in requests {
+            assert_eq!(request.await.expect("join").expect("index"), b"{}");
+        }
+
+        assert_eq!(generations.load(Ordering::SeqCst), 1);
+        assert!(!flights.lock().contains_key(&key));
+    }
+
+    #[tokio::test]
+    async fn test_generation_error_is_returned() {
+        let redis = AppState::disconnected().redis;
+        let key = cache_key("v1", &uuid::Uuid::new_v4().to_string());
+        let result = cached(&redis, &Flights::default(), &key, async {
+            Err(ApiError::Internal("database down".to_string()))
+        })
+        .await;
+        assert!(matches!(result, Err(ApiError::Internal(_))));
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_unavailable_cache_fails() {
+        assert!(invalidate(&AppState::disconnected().redis).await.is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires REDIS_URL"]
+    async fn test_regenerate_replaces_and_invalidate_removes_cached_index() {
+        let redis = redis::Client::open(std::env::var("REDIS_URL").expect("REDIS_URL"))
+            .expect("redis client");
+        let key = cache_key("v1", &uuid::Uuid::new_v4().to_string());
+        let generations = AtomicUsize::new(0);
+        cached_counting(&redis, &key, b"old", &generations).await;
+
+        let index = regenerate(&redis, &Flights::default(), &key, async {
+            generations.fetch_add(1, Ordering::SeqCst);
+            Ok(b"new".to_vec())
+        })
+        .await
+        .expect("index");
+        assert_eq!(index, b"new");
+        assert_eq!(generations.load(Ordering::SeqCst), 2);
+        let stored = lookup(&redis, &key).await.expect("lookup");
+        assert_eq!(stored.as_deref(), Some(&b"new"[..]));
+
+        assert!(invalidate(&redis).await.expect("invalidate") >= 1);
+        assert_eq!(lookup(&redis, &key).await.expect("lookup"), None);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires REDIS_URL"]
+    async fn test_miss_populates_and_hit_skips_generation() {
+        let redis = redis::Client::open(std::env::var("REDIS_URL").expect("REDIS_URL"))
+            .expect("redis client");
+        let key = cache_key("v1", &uuid::Uuid::new_v4().to_string());
+        let generations = AtomicUsize::new(0);
+
+        let first = cached_counting(&redis, &key, br#"{"repo":{}}"#, &generations).await;
+        assert_eq!(generations.load(Ordering::SeqCst), 1);
+        let stored = lookup(&redis, &key).await.expect("lookup");
+        assert_eq!(stored.as_deref(), Some(first.as_slice()));
+
+        let second = cached_counting(&redis, &key, b"regenerated", &generations).await;
+        assert_eq!(second, first);
+        assert_eq!(generations.load(Ordering::SeqCst), 1);
+    }
+}
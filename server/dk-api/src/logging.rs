@@ -0,0 +1,105 @@
+//! Log line formatting.
+
+use dk_common::config::LogFormat;
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Layer writing events to `writer` in `format`.
+///
+/// JSON lines carry the timestamp, level, target, message and other event
+/// fields, and the fields of the current span and its parents.
+pub fn fmt_layer<S, W>(format: LogFormat, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer().with_writer(writer);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::{Arc, Mutex, PoisonError};
+
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    /// Writer collecting everything logged through it.
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Buffer {
+        fn contents(&self) -> String {
+            let bytes = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+            String::from_utf8_lossy(&bytes).into_owned()
+        }
+    }
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'writer> MakeWriter<'writer> for Buffer {
+        type Writer = Self;
+
+        fn make_writer(&'writer self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    /// Output of logging one event inside a span in `format`.
+    fn log_in(format: LogFormat) -> String {
+        let buffer = Buffer::default();
+        let subscriber = tracing_subscriber::registry().with(fmt_layer(format, buffer.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("request", request_id = "req-1");
+            let _entered = span.enter();
+            tracing::info!(package_id = "dk.digst.mitid", "App published");
+        });
+        buffer.contents()
+    }
+
+    #[test]
+    fn test_json_format_writes_json_lines() {
+        let output = log_in(LogFormat::Json);
+        let line: serde_json::Value = serde_json::from_str(output.trim()).expect("one JSON line");
+
+        assert!(line["timestamp"].is_string());
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["target"], module_path!());
+        assert_eq!(line["fields"]["message"], "App published");
+        assert_eq!(line["fields"]["package_id"], "dk.digst.mitid");
+        assert_eq!(line["span"]["name"], "request");
+        assert_eq!(line["span"]["request_id"], "req-1");
+        assert_eq!(line["spans"][0]["request_id"], "req-1");
+    }
+
+    #[test]
+    fn test_text_format_writes_plain_lines() {
+        let output = log_in(LogFormat::Text);
+        assert!(output.contains("App published"));
+        assert!(output.contains("request_id"));
+        assert!(serde_json::from_str::<serde_json::Value>(output.trim()).is_err());
+    }
+}
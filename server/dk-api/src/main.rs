@@ -2,18 +2,50 @@
 //!
 //! The main entry point for the DK-AppStore repository API.
 
+use std::convert::Infallible;
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 
-use axum::{routing::get, Router};
+use arc_swap::ArcSwap;
+
+use axum::{
+    body::Body,
+    extract::DefaultBodyLimit,
+    http::Uri,
+    middleware,
+    routing::{delete, get, post},
+    Router,
+};
 use clap::Parser;
+use dk_common::{config::LogFormat, Config};
+use tower_http::compression::predicate::{
+    DefaultPredicate, NotForContentType, Predicate, SizeAbove,
+};
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::CorsLayer;
+use tower_http::limit::RequestBodyLimitLayer;
 use tower_http::trace::TraceLayer;
 use tracing::info;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+mod auth;
+mod cors;
 mod error;
+mod index_cache;
+mod logging;
+mod rate_limit;
+mod reload;
+mod repo_state;
+mod repository;
+mod request_id;
 mod routes;
+mod shutdown;
+mod state;
 
+use error::ApiError;
 use routes::{health, metrics};
+use state::AppState;
 
 /// DK-AppStore API Server
 #[derive(Parser, Debug)]
@@ -27,82 +59,423 @@ struct Args {
     /// Port to listen on
     #[arg(long, env = "API_PORT", default_value = "8080")]
     port: u16,
+
+    /// Log format, `json` or `text`; overrides `api.log_format`
+    #[arg(long, env = "LOG_FORMAT")]
+    log_format: Option<String>,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize tracing
+    // Parse command line arguments and load configuration
+    let args = Args::parse();
+    let config = Config::load()?;
+
+    // Initialize tracing; the filter stays replaceable on reload
+    let log_format = match &args.log_format {
+        Some(format) => format.parse::<LogFormat>()?,
+        None => config.api.log_format,
+    };
+    let (log_filter, log_filter_handle) = tracing_subscriber::reload::Layer::new(
+        reload::log_filter(config.api.log_level.as_deref())?,
+    );
     tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
-            "dk_api=debug,tower_http=debug,axum::rejection=trace".into()
-        }))
-        .with(tracing_subscriber::fmt::layer())
+        .with(log_filter)
+        .with(logging::fmt_layer(log_format, std::io::stdout))
         .init();
 
-    // Parse command line arguments
-    let args = Args::parse();
+    // Install the Prometheus recorder before any metric is recorded
+    metrics::install_recorder()?;
+
+    // Connect to the database
+    let mut state = AppState::connect(&config).await?;
+    state.signer = state::load_signer(&config.signing)?;
+    if state.signer.is_none() {
+        tracing::warn!("Repository signing is not configured; index.jar is unavailable");
+    }
+    if state.api_keys.is_empty() {
+        tracing::warn!("No API keys are configured; protected endpoints reject all requests");
+    }
+
+    // Reload what can change under a running server on SIGHUP
+    let reloader = reload::Reloader::new(
+        Arc::new(ArcSwap::from_pointee(config.clone())),
+        state.rate_limiter.clone(),
+        log_filter_handle,
+    );
+    #[cfg(unix)]
+    tokio::spawn(reload::reload_on_sighup(reloader));
+    #[cfg(not(unix))]
+    drop(reloader);
 
     // Build application
-    let app = create_app();
+    let app = create_app(state, cors::layer(&config.cors)?);
 
     // Start server
     let addr: SocketAddr = format!("{}:{}", args.host, args.port).parse()?;
     info!("Starting DK-AppStore API server on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    shutdown::serve(
+        listener,
+        app,
+        shutdown::signal(),
+        Duration::from_secs(config.api.shutdown_timeout_secs),
+    )
+    .await?;
 
     Ok(())
 }
 
+/// Responses smaller than this many bytes are sent uncompressed, as the
+/// encoding overhead would outweigh the savings.
+const COMPRESSION_MIN_SIZE: u16 = 128;
+
 /// Create the application router.
-fn create_app() -> Router {
+fn create_app(state: AppState, cors: CorsLayer) -> Router {
     Router::new()
         // Health and metrics endpoints
         .route("/health", get(health::health_check))
         .route("/health/ready", get(health::readiness_check))
         .route("/health/live", get(health::liveness_check))
+        .route("/health/deps", get(health::dependencies_check))
         .route("/metrics", get(metrics::metrics_handler))
         // API v1 routes
-        .nest("/api/v1", api_v1_routes())
+        .nest(
+            "/api/v1",
+            api_v1_routes()
+                .merge(protected_routes(&state))
+                // Outermost, so throttled clients cannot probe API keys
+                .route_layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    rate_limit::limit_by_ip,
+                )),
+        )
+        .fallback(route_not_found)
         // Middleware
-        .layer(TraceLayer::new_for_http())
+        .route_layer(middleware::from_fn(metrics::track_requests))
+        .layer(compression())
+        .layer(cors)
+        .layer(TraceLayer::new_for_http().make_span_with(request_id::span::<Body>))
+        // Outermost, so every response and log line carries the ID
+        .layer(middleware::from_fn(request_id::propagate))
+        .with_state(state)
+}
+
+/// Fallback for requests matching no route.
+async fn route_not_found(uri: Uri) -> ApiError {
+    ApiError::NotFound(format!("no route for {}", uri.path()))
+}
+
+/// Gzip/Brotli compression for responses worth compressing.
+///
+/// APKs and the index JAR are ZIP archives and already compressed.
+fn compression() -> CompressionLayer<impl Predicate> {
+    let predicate = DefaultPredicate::new()
+        .and(SizeAbove::new(COMPRESSION_MIN_SIZE))
+        .and(NotForContentType::const_new(
+            routes::download::APK_CONTENT_TYPE,
+        ))
+        .and(NotForContentType::const_new("application/java-archive"));
+    CompressionLayer::new().compress_when(predicate)
 }
 
 /// API v1 routes.
-fn api_v1_routes() -> Router {
+fn api_v1_routes() -> Router<AppState> {
     Router::new()
         .route("/apps", get(routes::apps::list_apps))
+        .route("/apps/batch", post(routes::apps::batch_get_apps))
         .route("/apps/:package_id", get(routes::apps::get_app))
         .route(
             "/apps/:package_id/versions",
             get(routes::apps::get_app_versions),
         )
+        .route("/apps/:package_id/icon", get(routes::icons::get_icon))
+        .route(
+            "/apps/:package_id/screenshots",
+            get(routes::screenshots::list_screenshots),
+        )
+        .route(
+            "/apps/:package_id/screenshots/:name",
+            get(routes::screenshots::get_screenshot),
+        )
+        .route(
+            "/apps/:package_id/versions/:version_code/download",
+            get(routes::download::download_apk),
+        )
+        .route(
+            "/apps/:package_id/versions/:version_code/scan",
+            get(routes::scan::get_scan_report),
+        )
+        .route(
+            "/apps/:package_id/versions/:version_code/permission-diff",
+            get(routes::permission_diff::get_permission_diff),
+        )
+        .route("/categories", get(routes::categories::list_categories))
+        .route("/stats", get(routes::stats::get_stats))
         .route("/index", get(routes::index::get_index))
+        .route("/index.jar", get(routes::index::get_index_jar))
+        .route("/index-v2", get(routes::index_v2::get_index_v2))
+        .route("/repo/fingerprint", get(routes::repo::get_fingerprint))
+        .route("/repo/cert", get(routes::repo::get_cert))
+        .route("/openapi.json", get(routes::openapi::get_openapi))
+}
+
+/// API v1 routes requiring an API key.
+fn protected_routes(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route("/auth/verify", get(auth::verify_key))
+        .route(
+            "/admin/quarantine",
+            get(routes::quarantine::list_quarantine),
+        )
+        .route("/admin/reindex", post(routes::reindex::reindex))
+        .route("/builds/:build_id", get(routes::builds::get_build))
+        .route(
+            "/builds/:build_id/logs",
+            get(routes::builds::stream_build_logs),
+        )
+        .route(
+            "/apps/:package_id/builds",
+            get(routes::builds::list_app_builds),
+        )
+        .route(
+            "/apps/:package_id/versions",
+            post(routes::upload::upload_version)
+                // Replaces the default cap on extracted bodies, which JSON
+                // endpoints keep
+                .layer::<_, Infallible>(DefaultBodyLimit::disable())
+                .layer::<_, Infallible>(RequestBodyLimitLayer::new(state.max_upload_bytes)),
+        )
+        .route(
+            "/apps/:package_id/versions/:version_code",
+            delete(routes::apps::delete_version),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_api_key,
+        ))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use axum::body::Body;
     use axum::http::{Request, StatusCode};
     use tower::ServiceExt;
 
     #[tokio::test]
     async fn test_health_endpoint() {
-        let app = create_app();
+        let app = create_app(AppState::disconnected(), CorsLayer::new());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/health")
+                    .body(Body::empty())
+                    .expect("request"),
+            )
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_cors_preflight() {
+        let config = dk_common::config::CorsConfig {
+            allowed_origins: vec!["https://appstore.digst.dk".to_string()],
+            ..dk_common::config::CorsConfig::default()
+        };
+        let app = create_app(
+            AppState::disconnected(),
+            cors::layer(&config).expect("cors layer"),
+        );
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("OPTIONS")
+                    .uri("/api/v1/apps")
+                    .header("Origin", "https://appstore.digst.dk")
+                    .header("Access-Control-Request-Method", "GET")
+                    .body(Body::empty())
+                    .expect("request"),
+            )
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()["access-control-allow-origin"],
+            "https://appstore.digst.dk"
+        );
+        assert!(response.headers()["access-control-allow-methods"]
+            .to_str()
+            .expect("header")
+            .contains("GET"));
+    }
+
+    #[tokio::test]
+    async fn test_read_endpoints_stay_public() {
+        let app = create_app(AppState::disconnected(), CorsLayer::new());
+
+        let (status, _) = get_body(app.clone(), "/api/v1/auth/verify").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = get_body(app.clone(), "/api/v1/admin/quarantine").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = get_body(app.clone(), "/api/v1/index").await;
+        assert_ne!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = get_body(app, "/health/deps").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_upload_is_rejected() {
+        const KEY: &str = "dk-test-key";
+        let mut state = AppState::disconnected();
+        state.max_upload_bytes = 1024;
+        state.api_keys = auth::ApiKeys::from_config(&dk_common::config::AuthConfig {
+            api_key_hashes: vec![auth::hash_key(KEY)],
+        })
+        .expect("api keys");
+        let app = create_app(state, CorsLayer::new());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/apps/dk.digst.mitid/versions")
+                    .header("Authorization", format!("Bearer {KEY}"))
+                    .header("Content-Type", "multipart/form-data; boundary=dk")
+                    .header("Content-Length", "1025")
+                    .body(Body::from(vec![0; 1025]))
+                    .expect("request"),
+            )
+            .await
+            .expect("response");
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    /// Send a GET request for `uri` accepting gzip.
+    async fn get_gzip(app: Router, uri: &str) -> axum::response::Response {
+        app.oneshot(
+            Request::builder()
+                .uri(uri)
+                .header("Accept-Encoding", "gzip")
+                .body(Body::empty())
+                .expect("request"),
+        )
+        .await
+        .expect("response")
+    }
+
+    /// State serving a catalogue of one app from memory.
+    fn index_state() -> AppState {
+        use routes::apps::tests::{in_memory_state, sample_app, sample_version};
+
+        let repository = repository::InMemoryAppRepository::default();
+        let app = sample_app();
+        repository.insert_version(sample_version(&app, 1, "1.0"));
+        repository.insert_app(app);
+        in_memory_state(repository)
+    }
+
+    #[tokio::test]
+    async fn test_index_is_gzip_compressed() {
+        let app = create_app(index_state(), CorsLayer::new());
+
+        let response = get_gzip(app, "/api/v1/index").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-encoding"], "gzip");
+    }
+
+    #[tokio::test]
+    async fn test_metrics_are_gzip_compressed() {
+        routes::metrics::install_recorder().expect("recorder");
+        let app = create_app(AppState::disconnected(), CorsLayer::new());
+        get_body(app.clone(), "/health").await;
+
+        let response = get_gzip(app, "/metrics").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-encoding"], "gzip");
+    }
+
+    #[tokio::test]
+    async fn test_apk_download_is_not_compressed() {
+        let storage = routes::download::tests::TempStorage::new();
+        storage.seed("dk.digst.mitid", 123, 4096);
+        let app = create_app(storage.state(), CorsLayer::new());
 
         let response = app
-            .oneshot(Request::builder().uri("/health").body(Body::empty()).expect("request"))
+            .oneshot(
+                Request::builder()
+                    .uri("/api/v1/apps/dk.digst.mitid/versions/123/download")
+                    .header("Accept-Encoding", "gzip, br")
+                    .body(Body::empty())
+                    .expect("request"),
+            )
             .await
             .expect("response");
 
         assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get("content-encoding").is_none());
+    }
+
+    /// Send a request with `method` for `uri`.
+    async fn send(app: Router, method: &str, uri: &str) -> axum::response::Response {
+        app.oneshot(
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(Body::empty())
+                .expect("request"),
+        )
+        .await
+        .expect("response")
+    }
+
+    /// Assert that HEAD `uri` answers with the headers of GET `uri` and no body.
+    async fn assert_head_matches_get(app: Router, uri: &str) {
+        let get = send(app.clone(), "GET", uri).await;
+        let head = send(app, "HEAD", uri).await;
+
+        assert_eq!(head.status(), get.status());
+        for name in ["content-length", "content-type", "etag"] {
+            assert_eq!(head.headers().get(name), get.headers().get(name), "{name}");
+        }
+        let body = axum::body::to_bytes(head.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_head_apk_download() {
+        let storage = routes::download::tests::TempStorage::new();
+        storage.seed("dk.digst.mitid", 123, 4096);
+        let app = create_app(storage.state(), CorsLayer::new());
+        let uri = "/api/v1/apps/dk.digst.mitid/versions/123/download";
+
+        let response = send(app.clone(), "HEAD", uri).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-length"], "4096");
+
+        assert_head_matches_get(app, uri).await;
+    }
+
+    #[tokio::test]
+    async fn test_head_index() {
+        let app = create_app(index_state(), CorsLayer::new());
+
+        for uri in ["/api/v1/index", "/api/v1/index.jar", "/api/v1/index-v2"] {
+            assert_head_matches_get(app.clone(), uri).await;
+        }
     }
 
     #[tokio::test]
     async fn test_not_found() {
-        let app = create_app();
+        let app = create_app(AppState::disconnected(), CorsLayer::new());
 
         let response = app
             .oneshot(
@@ -116,4 +489,119 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
+
+    /// Send a GET request for `uri` and return the response body.
+    async fn get_body(app: Router, uri: &str) -> (StatusCode, String) {
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(uri)
+                    .body(Body::empty())
+                    .expect("request"),
+            )
+            .await
+            .expect("response");
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        (
+            status,
+            String::from_utf8(body.to_vec()).expect("utf-8 body"),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_request_id_in_header_and_error_body() {
+        let app = create_app(AppState::disconnected(), CorsLayer::new());
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/nonexistent")
+                    .body(Body::empty())
+                    .expect("request"),
+            )
+            .await
+            .expect("response");
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let id = response.headers()["x-request-id"]
+            .to_str()
+            .expect("header")
+            .to_string();
+        assert!(uuid::Uuid::parse_str(&id).is_ok());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        let error: serde_json::Value = serde_json::from_slice(&body).expect("error json");
+        assert_eq!(error["error"], "not_found");
+        assert_eq!(error["code"], "NOT_FOUND");
+        assert_eq!(error["request_id"], id.as_str());
+
+        // An incoming ID is propagated
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/health")
+                    .header("X-Request-Id", "lb-1234")
+                    .body(Body::empty())
+                    .expect("request"),
+            )
+            .await
+            .expect("response");
+        assert_eq!(response.headers()["x-request-id"], "lb-1234");
+    }
+
+    /// Sum of `http_requests_total` samples for `route` whose labels also
+    /// include all of `labels`.
+    fn requests_for(metrics: &str, route: &str, labels: &[&str]) -> f64 {
+        let route = format!("route=\"{route}\"");
+        metrics
+            .lines()
+            .filter(|line| line.starts_with("http_requests_total{") && line.contains(&route))
+            .filter(|line| labels.iter().all(|label| line.contains(label)))
+            .filter_map(|line| line.rsplit(' ').next()?.parse::<f64>().ok())
+            .sum()
+    }
+
+    #[tokio::test]
+    async fn test_metrics_count_requests() {
+        routes::metrics::install_recorder().expect("recorder");
+        let app = create_app(AppState::disconnected(), CorsLayer::new());
+
+        let (_, before) = get_body(app.clone(), "/metrics").await;
+        get_body(app.clone(), "/api/v1/apps").await;
+        let (status, after) = get_body(app, "/metrics").await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert!(after.contains("# TYPE http_requests_total counter"));
+        assert!(after.contains("# HELP http_requests_total"));
+        let apps_before = requests_for(&before, "/api/v1/apps", &[]);
+        assert!(requests_for(&after, "/api/v1/apps", &[]) >= apps_before + 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_label_route_template_and_status_class() {
+        routes::metrics::install_recorder().expect("recorder");
+        let storage = routes::download::tests::TempStorage::new();
+        let app = create_app(storage.state(), CorsLayer::new());
+        let download = "/api/v1/apps/:package_id/versions/:version_code/download";
+
+        let (status, _) = get_body(app.clone(), "/health").await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = get_body(
+            app.clone(),
+            "/api/v1/apps/dk.digst.mitid/versions/1/download",
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (_, metrics) = get_body(app, "/metrics").await;
+
+        assert!(requests_for(&metrics, "/health", &["method=\"GET\"", "status=\"2xx\""]) >= 1.0);
+        assert!(requests_for(&metrics, download, &["status=\"4xx\""]) >= 1.0);
+        // The raw path never becomes a label
+        assert!(!metrics.contains("dk.digst.mitid/versions/1"));
+        assert!(metrics.contains("# TYPE http_response_size_bytes histogram"));
+    }
 }
@@ -0,0 +1,266 @@
+//! Per-client token-bucket rate limiting.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use arc_swap::ArcSwap;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+use dk_common::config::RateLimitConfig;
+
+use crate::error::ApiError;
+use crate::state::AppState;
+
+/// Number of tracked clients above which idle buckets are evicted.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Remaining allowance of one client.
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token buckets keyed by client IP.
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: ArcSwap<RateLimitConfig>,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Create a limiter enforcing `config`.
+    #[must_use]
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config: ArcSwap::from_pointee(config),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Enforce `config` from now on.
+    ///
+    /// Clients keep their buckets; a bucket holding more than the new burst
+    /// is capped at its next request.
+    pub fn reconfigure(&self, config: RateLimitConfig) {
+        self.config.store(std::sync::Arc::new(config));
+    }
+
+    /// Take one request from the bucket of `client`.
+    ///
+    /// # Errors
+    ///
+    /// Returns how long the client must wait if its bucket is empty.
+    pub fn check(&self, client: IpAddr) -> Result<(), Duration> {
+        self.check_at(client, Instant::now())
+    }
+
+    fn check_at(&self, client: IpAddr, now: Instant) -> Result<(), Duration> {
+        let config = self.config.load();
+        if config.requests_per_minute == 0 {
+            return Ok(());
+        }
+        let capacity = f64::from(config.burst.max(1));
+        let per_second = f64::from(config.requests_per_minute) / 60.0;
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        if buckets.len() > PRUNE_THRESHOLD {
+            // A bucket that has refilled completely carries no state
+            let refill = Duration::from_secs_f64(capacity / per_second);
+            buckets.retain(|_, bucket| now.duration_since(bucket.updated) < refill);
+        }
+
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = elapsed.mul_add(per_second, bucket.tokens).min(capacity);
+        bucket.updated = now;
+
+        let result = if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+        };
+        drop(buckets);
+        result
+    }
+
+    /// Address of the client that sent a request received from `peer`.
+    ///
+    /// `X-Forwarded-For` is honoured only when `peer` is a trusted proxy;
+    /// the client is the rightmost address not itself a trusted proxy, as
+    /// earlier entries can be set by the client.
+    #[must_use]
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let config = self.config.load();
+        let trusted = &config.trusted_proxies;
+        if !trusted.contains(&peer) {
+            return peer;
+        }
+        headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|address| address.trim().parse::<IpAddr>().ok())
+            .rev()
+            .find(|address| !trusted.contains(address))
+            .unwrap_or(peer)
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(RateLimitConfig::default())
+    }
+}
+
+/// Middleware rejecting clients that exceed their rate limit with 429.
+pub async fn limit_by_ip(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED), |info| info.0.ip());
+    let client = state.rate_limiter.client_ip(peer, request.headers());
+
+    if let Err(wait) = state.rate_limiter.check(client) {
+        // Round up so a client honouring Retry-After is not rejected again
+        let secs = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+        return Err(ApiError::TooManyRequests(secs));
+    }
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{body::Body, http::StatusCode, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn limiter(requests_per_minute: u32, burst: u32, trusted: &[&str]) -> RateLimiter {
+        RateLimiter::new(RateLimitConfig {
+            requests_per_minute,
+            burst,
+            trusted_proxies: trusted.iter().map(|ip| ip.parse().expect("ip")).collect(),
+        })
+    }
+
+    fn ip(address: &str) -> IpAddr {
+        address.parse().expect("ip")
+    }
+
+    #[test]
+    fn test_bucket_refills_over_time() {
+        let limiter = limiter(60, 2, &[]);
+        let client = ip("192.0.2.1");
+        let start = Instant::now();
+
+        assert!(limiter.check_at(client, start).is_ok());
+        assert!(limiter.check_at(client, start).is_ok());
+        let wait = limiter.check_at(client, start).expect_err("bucket empty");
+        assert_eq!(wait, Duration::from_secs(1));
+
+        assert!(limiter
+            .check_at(client, start + Duration::from_secs(1))
+            .is_ok());
+        // Other clients have their own bucket
+        assert!(limiter.check_at(ip("192.0.2.2"), start).is_ok());
+    }
+
+    #[test]
+    fn test_zero_rate_disables_limiting() {
+        let limiter = limiter(0, 1, &[]);
+        let now = Instant::now();
+        for _ in 0..10 {
+            assert!(limiter.check_at(ip("192.0.2.1"), now).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_reconfigure_applies_new_limits() {
+        let limiter = limiter(60, 1, &[]);
+        let client = ip("192.0.2.1");
+        let now = Instant::now();
+        assert!(limiter.check_at(client, now).is_ok());
+        assert!(limiter.check_at(client, now).is_err());
+
+        limiter.reconfigure(RateLimitConfig {
+            requests_per_minute: 0,
+            ..RateLimitConfig::default()
+        });
+        assert!(limiter.check_at(client, now).is_ok());
+    }
+
+    #[test]
+    fn test_forwarded_for_requires_trusted_proxy() {
+        let limiter = limiter(60, 1, &["10.0.0.1", "10.0.0.2"]);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            "198.51.100.7, 203.0.113.9, 10.0.0.2"
+                .parse()
+                .expect("header"),
+        );
+
+        assert_eq!(
+            limiter.client_ip(ip("10.0.0.1"), &headers),
+            ip("203.0.113.9")
+        );
+        // A direct client cannot choose its own address
+        assert_eq!(
+            limiter.client_ip(ip("192.0.2.1"), &headers),
+            ip("192.0.2.1")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_bucket_returns_429() {
+        let mut state = AppState::disconnected();
+        state.rate_limiter = Arc::new(limiter(1, 2, &[]));
+        let app = Router::new()
+            .route("/limited", get(|| async { "ok" }))
+            .route_layer(middleware::from_fn_with_state(state.clone(), limit_by_ip))
+            .with_state(state);
+
+        let mut statuses = Vec::new();
+        let mut retry_after = None;
+        for _ in 0..3 {
+            let mut request = axum::http::Request::builder()
+                .uri("/limited")
+                .body(Body::empty())
+                .expect("request");
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::from(([192, 0, 2, 1], 40000))));
+            let response = app.clone().oneshot(request).await.expect("response");
+            statuses.push(response.status());
+            retry_after = response.headers().get("retry-after").cloned();
+        }
+
+        assert_eq!(
+            statuses,
+            [
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::TOO_MANY_REQUESTS
+            ]
+        );
+        assert_eq!(retry_after.expect("Retry-After"), "60");
+    }
+}
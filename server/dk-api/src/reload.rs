@@ -0,0 +1,238 @@
+//! Configuration reload on SIGHUP.
+//!
+//! Only settings a running server can adopt are reloaded: the log filter
+//! and rate limits. Changes to anything else, such as the bind address or
+//! the database URL, are logged and take effect on the next restart.
+
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use dk_common::Config;
+use tracing_subscriber::{reload::Handle, EnvFilter, Registry};
+
+use crate::rate_limit::RateLimiter;
+
+/// Configuration shared with the reload task; readers see each reload
+/// atomically.
+pub type SharedConfig = Arc<ArcSwap<Config>>;
+
+/// Handle replacing the log filter of the running subscriber.
+pub type LogFilter = Handle<EnvFilter, Registry>;
+
+/// Log filter used when neither the configuration nor `RUST_LOG` sets one.
+const DEFAULT_LOG_FILTER: &str = "dk_api=debug,tower_http=debug,axum::rejection=trace";
+
+/// Log filter from `directives`, or else from `RUST_LOG`, or else the
+/// built-in default.
+///
+/// # Errors
+///
+/// Returns a description of the problem if `directives` do not parse.
+pub fn log_filter(directives: Option<&str>) -> Result<EnvFilter, String> {
+    match directives {
+        Some(directives) => EnvFilter::try_new(directives)
+            .map_err(|err| format!("invalid api.log_level {directives:?}: {err}")),
+        None => Ok(EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER))),
+    }
+}
+
+/// Applies reloaded configuration to the running server.
+pub struct Reloader {
+    config: SharedConfig,
+    rate_limiter: Arc<RateLimiter>,
+    log_filter: LogFilter,
+}
+
+impl Reloader {
+    /// Reloader updating `config` and, on change, `rate_limiter` and
+    /// `log_filter`.
+    pub const fn new(
+        config: SharedConfig,
+        rate_limiter: Arc<RateLimiter>,
+        log_filter: LogFilter,
+    ) -> Self {
+        Self {
+            config,
+            rate_limiter,
+            log_filter,
+        }
+    }
+
+    /// Load the configuration again from its sources and apply it.
+    ///
+    /// Configuration that fails to load is logged and the current one kept.
+    pub fn reload(&self) {
+        match Config::load() {
+            Ok(config) => {
+                let changed = self.apply(&config);
+                if changed.is_empty() {
+                    tracing::info!("Reloaded configuration; nothing to change");
+                } else {
+                    tracing::info!(?changed, "Reloaded configuration");
+                }
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "Failed to reload configuration; keeping it");
+            }
+        }
+    }
+
+    /// Adopt the reloadable settings of `new` and store the result as the
+    /// current configuration.
+    ///
+    /// Returns the settings that changed. Changes to other settings are
+    /// logged and ignored.
+    pub fn apply(&self, new: &Config) -> Vec<&'static str> {
+        let current = self.config.load_full();
+        let mut next = Config::clone(&current);
+        let mut changed = Vec::new();
+
+        if new.api.log_level != current.api.log_level {
+            let reloaded = log_filter(new.api.log_level.as_deref()).and_then(|filter| {
+                self.log_filter
+                    .reload(filter)
+                    .map_err(|err| err.to_string())
+            });
+            match reloaded {
+                Ok(()) => {
+                    next.api.log_level.clone_from(&new.api.log_level);
+                    changed.push("api.log_level");
+                }
+                Err(err) => tracing::warn!(error = %err, "Keeping the current log filter"),
+            }
+        }
+        if new.rate_limit != current.rate_limit {
+            self.rate_limiter.reconfigure(new.rate_limit.clone());
+            next.rate_limit = new.rate_limit.clone();
+            changed.push("rate_limit");
+        }
+
+        let ignored = restart_required(&next, new);
+        if !ignored.is_empty() {
+            tracing::warn!(
+                ?ignored,
+                "Ignoring configuration changes that need a restart"
+            );
+        }
+
+        self.config.store(Arc::new(next));
+        changed
+    }
+}
+
+/// Sections that differ between `current` and `new` but cannot be changed
+/// without a restart.
+fn restart_required(current: &Config, new: &Config) -> Vec<&'static str> {
+    [
+        ("database", current.database != new.database),
+        ("redis", current.redis != new.redis),
+        ("api", current.api != new.api),
+        ("storage", current.storage != new.storage),
+        ("object_store", current.object_store != new.object_store),
+        ("signing", current.signing != new.signing),
+        ("build", current.build != new.build),
+        ("scanner", current.scanner != new.scanner),
+        ("cors", current.cors != new.cors),
+        ("auth", current.auth != new.auth),
+        ("repo", current.repo != new.repo),
+        ("webhooks", current.webhooks != new.webhooks),
+    ]
+    .into_iter()
+    .filter_map(|(section, differs)| differs.then_some(section))
+    .collect()
+}
+
+/// Reload the configuration with `reloader` on every SIGHUP.
+#[cfg(unix)]
+pub async fn reload_on_sighup(reloader: Reloader) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(stream) => stream,
+        Err(err) => {
+            tracing::error!("Failed to listen for SIGHUP: {err}");
+            return;
+        }
+    };
+    while hangups.recv().await.is_some() {
+        tracing::info!("Received SIGHUP, reloading configuration");
+        reloader.reload();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dk_common::config::RateLimitConfig;
+    use serde_json::json;
+    use tracing_subscriber::reload::Layer;
+
+    use super::*;
+
+    fn config() -> Config {
+        serde_json::from_value(json!({
+            "database": { "url": "postgres://localhost/dk_appstore" },
+            "redis": { "url": "redis://localhost" },
+            "api": { "port": 8080 },
+        }))
+        .expect("config")
+    }
+
+    /// Reloader over `config`, with the filter layer its handle points to.
+    fn reloader_over(config: &Config) -> (Reloader, SharedConfig, Layer<EnvFilter, Registry>) {
+        let shared: SharedConfig = Arc::new(ArcSwap::from_pointee(config.clone()));
+        let (layer, log_filter) = Layer::new(EnvFilter::new("info"));
+        let reloader = Reloader::new(shared.clone(), Arc::default(), log_filter);
+        (reloader, shared, layer)
+    }
+
+    #[test]
+    fn test_apply_updates_shared_config() {
+        let (reloader, shared, _layer) = reloader_over(&config());
+        let mut new = config();
+        new.rate_limit = RateLimitConfig {
+            requests_per_minute: 10,
+            burst: 2,
+            trusted_proxies: Vec::new(),
+        };
+        new.api.log_level = Some("dk_api=warn".to_string());
+
+        let changed = reloader.apply(&new);
+
+        assert_eq!(changed, ["api.log_level", "rate_limit"]);
+        let current = shared.load();
+        assert_eq!(current.rate_limit.requests_per_minute, 10);
+        assert_eq!(current.rate_limit.burst, 2);
+        assert_eq!(current.api.log_level.as_deref(), Some("dk_api=warn"));
+    }
+
+    #[test]
+    fn test_apply_ignores_settings_needing_restart() {
+        let (reloader, shared, _layer) = reloader_over(&config());
+        let mut new = config();
+        new.api.port = 9090;
+        new.database.url = "postgres://elsewhere/dk_appstore".to_string();
+
+        assert!(reloader.apply(&new).is_empty());
+        assert_eq!(**shared.load(), config());
+    }
+
+    #[test]
+    fn test_invalid_log_level_is_not_applied() {
+        let (reloader, shared, _layer) = reloader_over(&config());
+        let mut new = config();
+        new.api.log_level = Some("dk_api=loud".to_string());
+
+        assert!(reloader.apply(&new).is_empty());
+        assert_eq!(shared.load().api.log_level, None);
+    }
+
+    #[test]
+    fn test_restart_required_names_changed_sections() {
+        let mut new = config();
+        new.api.host = "0.0.0.0".to_string();
+        new.repo.name = "Other".to_string();
+        assert_eq!(restart_required(&config(), &new), ["api", "repo"]);
+        assert!(restart_required(&config(), &config()).is_empty());
+    }
+}
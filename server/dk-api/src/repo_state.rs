@@ -0,0 +1,96 @@
+//! Persistent repository state.
+//!
+//! F-Droid clients refuse an index with an earlier timestamp than the one
+//! they already have, as a guard against rollback. The index timestamp is
+//! derived from when apps and versions last changed, which can move
+//! backwards: a newest version is unpublished, or a writer's clock is
+//! behind. The last timestamp issued is therefore kept, and no index is
+//! given an earlier one. It is kept with the fingerprint of the repository
+//! it was issued for, so a changed repository is given a later timestamp
+//! and clients do not keep the index they already have.
+
+use sqlx::PgPool;
+
+use crate::error::ApiError;
+
+/// Repository state, stored in the `repo_state` table.
+#[derive(Debug, Clone)]
+pub struct RepoState {
+    db: PgPool,
+}
+
+impl RepoState {
+    /// Use the repository state in `db`.
+    pub const fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Issue an index timestamp for an index last changed at `candidate`,
+    /// in milliseconds since the epoch, of the repository with
+    /// `fingerprint`.
+    ///
+    /// Returns `candidate`, or the last timestamp issued if that is later.
+    /// If the fingerprint differs from the one last issued for, the result
+    /// is also later than the last timestamp. Records the result and
+    /// fingerprint as the last issued.
+    pub async fn issue(&self, candidate: i64, fingerprint: &str) -> Result<i64, ApiError> {
+        Ok(sqlx::query_scalar(
+            "INSERT INTO repo_state (last_index_timestamp, last_fingerprint) VALUES ($1, $2) \
+             ON CONFLICT (id) DO UPDATE SET last_index_timestamp = CASE \
+             WHEN repo_state.last_fingerprint IS DISTINCT FROM EXCLUDED.last_fingerprint \
+             THEN greatest(repo_state.last_index_timestamp + 1, EXCLUDED.last_index_timestamp) \
+             ELSE greatest(repo_state.last_index_timestamp, EXCLUDED.last_index_timestamp) END, \
+             last_fingerprint = EXCLUDED.last_fingerprint \
+             RETURNING last_index_timestamp",
+        )
+        .bind(candidate)
+        .bind(fingerprint)
+        .fetch_one(&self.db)
+        .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_database_error_is_internal() {
+        let state = RepoState::new(crate::state::AppState::disconnected().db);
+        assert!(matches!(
+            state.issue(1, "a").await,
+            Err(ApiError::Internal(_))
+        ));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_issued_timestamp_never_regresses() {
+        let db = crate::state::migrated_db().await;
+        let state = RepoState::new(db);
+
+        let last = state.issue(0, "a").await.expect("issue");
+        assert_eq!(
+            state.issue(last + 1_000, "a").await.expect("issue"),
+            last + 1_000
+        );
+        // The clock moved backwards
+        assert_eq!(
+            state.issue(last + 500, "a").await.expect("issue"),
+            last + 1_000
+        );
+        assert_eq!(
+            state.issue(last + 2_000, "a").await.expect("issue"),
+            last + 2_000
+        );
+        // The repository changed, but its content is dated earlier
+        assert_eq!(
+            state.issue(last + 500, "b").await.expect("issue"),
+            last + 2_001
+        );
+        assert_eq!(
+            state.issue(last + 500, "b").await.expect("issue"),
+            last + 2_001
+        );
+    }
+}
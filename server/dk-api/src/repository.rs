@@ -0,0 +1,573 @@
+//! Access to apps and their versions.
+//!
+//! Handlers depend on the [`AppRepository`] trait rather than on sqlx, so
+//! they can be tested against [`InMemoryAppRepository`] without a database.
+//! [`PgAppRepository`] is the implementation used by the server.
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use dk_common::config::RepoConfig;
+use dk_common::types::{App, AppVersion, Category};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::routes::apps::{app_from_row, version_from_row, APP_COLUMNS};
+use crate::routes::index::Repo;
+
+/// A client device, as far as it limits which versions can be installed.
+///
+/// Unset fields match every version.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Device {
+    /// SDK level the device runs.
+    pub sdk: Option<i32>,
+    /// ABI of the device, e.g. `arm64-v8a`.
+    pub abi: Option<String>,
+}
+
+#[cfg(test)]
+impl Device {
+    /// Whether the device is unspecified, so every app matches it even
+    /// without a published version.
+    pub const fn is_any(&self) -> bool {
+        self.sdk.is_none() && self.abi.is_none()
+    }
+
+    /// Whether the device can install `version`: its SDK level is high
+    /// enough, and the version has no native code or libraries for its ABI.
+    pub fn can_install(&self, version: &AppVersion) -> bool {
+        self.sdk.map_or(true, |sdk| version.min_sdk <= sdk)
+            && self.abi.as_ref().map_or(true, |abi| {
+                version.abis.is_empty() || version.abis.contains(abi)
+            })
+    }
+}
+
+/// Selection of apps listed by [`AppRepository::list_apps`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppFilter {
+    /// Only apps with a package ID after this one.
+    pub after: Option<String>,
+    /// Maximum number of apps returned.
+    pub limit: usize,
+    /// Case-insensitive search across package ID, name, and summary.
+    pub search: Option<String>,
+    /// Only apps with a published version this device can install.
+    pub device: Device,
+}
+
+/// A page of apps.
+#[derive(Debug)]
+pub struct AppPage {
+    /// The apps on the page, ordered by package ID.
+    pub apps: Vec<App>,
+    /// Number of apps matching the filter across all pages.
+    pub total: usize,
+}
+
+/// Store of apps and their versions.
+///
+/// Only published versions are ever returned.
+#[async_trait]
+pub trait AppRepository: Send + Sync {
+    /// List the apps matching `filter`, ordered by package ID.
+    async fn list_apps(&self, filter: &AppFilter) -> Result<AppPage, ApiError>;
+
+    /// The published versions of `apps` that `device` can install, grouped
+    /// by app ID.
+    async fn compatible_versions(
+        &self,
+        apps: &[Uuid],
+        device: &Device,
+    ) -> Result<HashMap<Uuid, Vec<AppVersion>>, ApiError>;
+
+    /// Look up the app `package_id`.
+    async fn get_app(&self, package_id: &str) -> Result<Option<App>, ApiError>;
+
+    /// The categories apps are filed under, each with its number of apps,
+    /// in no particular order.
+    async fn category_counts(&self) -> Result<Vec<(Category, usize)>, ApiError>;
+
+    /// Look up the apps among `package_ids`, leaving out unknown ones.
+    async fn get_apps(&self, package_ids: &[String]) -> Result<Vec<App>, ApiError>;
+
+    /// The published versions of the app `package_id`, in no particular
+    /// order, or `None` if there is no such app.
+    async fn get_versions(&self, package_id: &str) -> Result<Option<Vec<AppVersion>>, ApiError>;
+
+    /// Unpublish version `version_code` of `package_id`, keeping its record.
+    ///
+    /// Returns whether a published version was found.
+    async fn unpublish_version(
+        &self,
+        package_id: &str,
+        version_code: i64,
+    ) -> Result<bool, ApiError>;
+
+    /// When a version of `package_id` was last unpublished, if ever.
+    async fn last_unpublished(&self, package_id: &str) -> Result<Option<DateTime<Utc>>, ApiError>;
+
+    /// Fingerprint of everything the index is built from, with the
+    /// repository metadata `info`; it changes whenever the index would.
+    async fn index_fingerprint(&self, info: &RepoConfig) -> Result<String, ApiError>;
+
+    /// The repository to index, with its index timestamp issued, restricted
+    /// to `category` if given.
+    async fn index_repo(
+        &self,
+        info: &RepoConfig,
+        category: Option<&Category>,
+    ) -> Result<Repo, ApiError>;
+}
+
+/// [`AppRepository`] backed by PostgreSQL.
+#[derive(Debug, Clone)]
+pub struct PgAppRepository {
+    db: PgPool,
+}
+
+impl PgAppRepository {
+    /// Repository over the database `db`.
+    pub const fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+}
+
+/// `ILIKE` pattern matching `term` anywhere, with wildcards in it escaped.
+fn like_pattern(term: &str) -> String {
+    let escaped = term
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+/// SQL condition matching apps whose package ID, or any localized name or
+/// summary, matches the `ILIKE` pattern bound to `param`. A NULL pattern
+/// matches every app.
+fn search_condition(param: &str) -> String {
+    format!(
+        "({param}::text IS NULL \
+         OR package_id ILIKE {param} \
+         OR EXISTS (SELECT 1 FROM jsonb_each_text(name) n WHERE n.value ILIKE {param}) \
+         OR EXISTS (SELECT 1 FROM jsonb_each_text(summary) s WHERE s.value ILIKE {param}))"
+    )
+}
+
+/// SQL condition matching published `app_versions` rows, aliased `v`, that
+/// a device with the SDK level bound to `sdk` and the ABI bound to `abi` can
+/// install. A NULL parameter matches every version.
+fn compatible_condition(sdk: &str, abi: &str) -> String {
+    format!(
+        "(v.deleted_at IS NULL \
+         AND ({sdk}::integer IS NULL OR v.min_sdk <= {sdk}) \
+         AND ({abi}::text IS NULL OR cardinality(v.abis) = 0 OR {abi} = ANY(v.abis)))"
+    )
+}
+
+/// SQL condition matching apps with a version the device described by `sdk`
+/// and `abi` can install. Without either parameter every app matches, even
+/// one with no published version.
+fn device_condition(sdk: &str, abi: &str) -> String {
+    format!(
+        "(({sdk}::integer IS NULL AND {abi}::text IS NULL) \
+         OR EXISTS (SELECT 1 FROM app_versions v WHERE v.app_id = apps.id AND {}))",
+        compatible_condition(sdk, abi)
+    )
+}
+
+#[async_trait]
+impl AppRepository for PgAppRepository {
+    async fn list_apps(&self, filter: &AppFilter) -> Result<AppPage, ApiError> {
+        let pattern = filter.search.as_deref().map(like_pattern);
+        let limit = i64::try_from(filter.limit).unwrap_or(i64::MAX);
+
+        let apps = sqlx::query(&format!(
+            "SELECT {APP_COLUMNS} FROM apps WHERE ($1::text IS NULL OR package_id > $1) AND {} \
+             AND {} ORDER BY package_id LIMIT $2",
+            search_condition("$3"),
+            device_condition("$4", "$5")
+        ))
+        .bind(&filter.after)
+        .bind(limit)
+        .bind(&pattern)
+        .bind(filter.device.sdk)
+        .bind(&filter.device.abi)
+        .fetch_all(&self.db)
+        .await?
+        .iter()
+        .map(app_from_row)
+        .collect::<Result<Vec<_>, _>>()?;
+
+        let total: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM apps WHERE {} AND {}",
+            search_condition("$1"),
+            device_condition("$2", "$3")
+        ))
+        .bind(&pattern)
+        .bind(filter.device.sdk)
+        .bind(&filter.device.abi)
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(AppPage {
+            apps,
+            total: usize::try_from(total).map_err(|err| ApiError::Internal(err.to_string()))?,
+        })
+    }
+
+    async fn compatible_versions(
+        &self,
+        apps: &[Uuid],
+        device: &Device,
+    ) -> Result<HashMap<Uuid, Vec<AppVersion>>, ApiError> {
+        let rows = sqlx::query(&format!(
+            "SELECT v.* FROM app_versions v WHERE v.app_id = ANY($1) AND {}",
+            compatible_condition("$2", "$3")
+        ))
+        .bind(apps)
+        .bind(device.sdk)
+        .bind(&device.abi)
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut versions: HashMap<Uuid, Vec<AppVersion>> = HashMap::new();
+        for row in &rows {
+            let version = version_from_row(row)?;
+            versions.entry(version.app_id).or_default().push(version);
+        }
+        Ok(versions)
+    }
+
+    async fn get_app(&self, package_id: &str) -> Result<Option<App>, ApiError> {
+        let row = sqlx::query(&format!(
+            "SELECT {APP_COLUMNS} FROM apps WHERE package_id = $1"
+        ))
+        .bind(package_id)
+        .fetch_optional(&self.db)
+        .await?;
+        Ok(row.as_ref().map(app_from_row).transpose()?)
+    }
+
+    async fn category_counts(&self) -> Result<Vec<(Category, usize)>, ApiError> {
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT category, count(DISTINCT id) FROM apps, unnest(categories) AS category \
+             GROUP BY category",
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        rows.into_iter()
+            .map(|(category, count)| {
+                let category = Category::try_from(category)
+                    .map_err(|err| ApiError::Internal(err.to_string()))?;
+                let count =
+                    usize::try_from(count).map_err(|err| ApiError::Internal(err.to_string()))?;
+                Ok((category, count))
+            })
+            .collect()
+    }
+
+    async fn get_apps(&self, package_ids: &[String]) -> Result<Vec<App>, ApiError> {
+        Ok(sqlx::query(&format!(
+            "SELECT {APP_COLUMNS} FROM apps WHERE package_id = ANY($1) ORDER BY package_id"
+        ))
+        .bind(package_ids)
+        .fetch_all(&self.db)
+        .await?
+        .iter()
+        .map(app_from_row)
+        .collect::<Result<_, _>>()?)
+    }
+
+    async fn get_versions(&self, package_id: &str) -> Result<Option<Vec<AppVersion>>, ApiError> {
+        let app: Option<Uuid> = sqlx::query_scalar("SELECT id FROM apps WHERE package_id = $1")
+            .bind(package_id)
+            .fetch_optional(&self.db)
+            .await?;
+        let Some(app) = app else {
+            return Ok(None);
+        };
+
+        let versions =
+            sqlx::query("SELECT * FROM app_versions WHERE app_id = $1 AND deleted_at IS NULL")
+                .bind(app)
+                .fetch_all(&self.db)
+                .await?
+                .iter()
+                .map(version_from_row)
+                .collect::<Result<Vec<_>, _>>()?;
+        Ok(Some(versions))
+    }
+
+    async fn unpublish_version(
+        &self,
+        package_id: &str,
+        version_code: i64,
+    ) -> Result<bool, ApiError> {
+        let result = sqlx::query(
+            "UPDATE app_versions v SET deleted_at = now() FROM apps a \
+             WHERE a.id = v.app_id AND a.package_id = $1 AND v.version_code = $2 \
+             AND v.deleted_at IS NULL",
+        )
+        .bind(package_id)
+        .bind(version_code)
+        .execute(&self.db)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn last_unpublished(&self, package_id: &str) -> Result<Option<DateTime<Utc>>, ApiError> {
+        Ok(sqlx::query_scalar(
+            "SELECT max(v.deleted_at) FROM app_versions v JOIN apps a ON a.id = v.app_id \
+             WHERE a.package_id = $1",
+        )
+        .bind(package_id)
+        .fetch_one(&self.db)
+        .await?)
+    }
+
+    async fn index_fingerprint(&self, info: &RepoConfig) -> Result<String, ApiError> {
+        Repo::fingerprint(&self.db, info).await
+    }
+
+    async fn index_repo(
+        &self,
+        info: &RepoConfig,
+        category: Option<&Category>,
+    ) -> Result<Repo, ApiError> {
+        Repo::load_for_index(&self.db, info, category).await
+    }
+}
+
+/// [`AppRepository`] held in memory, for handler tests.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct InMemoryAppRepository {
+    apps: std::sync::Mutex<std::collections::BTreeMap<String, App>>,
+    versions: std::sync::Mutex<Vec<StoredVersion>>,
+}
+
+/// A version held by [`InMemoryAppRepository`], soft-deleted like a row of
+/// `app_versions` once unpublished.
+#[cfg(test)]
+#[derive(Debug)]
+struct StoredVersion {
+    version: AppVersion,
+    deleted_at: Option<DateTime<Utc>>,
+}
+
+#[cfg(test)]
+impl InMemoryAppRepository {
+    /// Add `app`, replacing any app with the same package ID.
+    pub fn insert_app(&self, app: App) {
+        lock(&self.apps).insert(app.package_id.to_string(), app);
+    }
+
+    /// The app with `package_id`, if any.
+    pub fn app(&self, package_id: &str) -> Option<App> {
+        lock(&self.apps).get(package_id).cloned()
+    }
+
+    /// Publish `version`.
+    pub fn insert_version(&self, version: AppVersion) {
+        lock(&self.versions).push(StoredVersion {
+            version,
+            deleted_at: None,
+        });
+    }
+
+    /// All versions that have not been unpublished.
+    fn published(&self) -> Vec<AppVersion> {
+        lock(&self.versions)
+            .iter()
+            .filter(|stored| stored.deleted_at.is_none())
+            .map(|stored| stored.version.clone())
+            .collect()
+    }
+
+    /// Whether `app` matches the case-insensitive search `term`.
+    fn matches(app: &App, term: &str) -> bool {
+        let term = term.to_lowercase();
+        std::iter::once(app.package_id.as_str())
+            .chain(app.name.iter().map(|(_, name)| name.as_str()))
+            .chain(app.summary.iter().map(|(_, summary)| summary.as_str()))
+            .any(|text| text.to_lowercase().contains(&term))
+    }
+}
+
+#[cfg(test)]
+fn lock<T>(mutex: &std::sync::Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+#[cfg(test)]
+#[async_trait]
+impl AppRepository for InMemoryAppRepository {
+    async fn list_apps(&self, filter: &AppFilter) -> Result<AppPage, ApiError> {
+        let versions = self.published();
+        let matching: Vec<App> = lock(&self.apps)
+            .values()
+            .filter(|app| {
+                filter
+                    .search
+                    .as_deref()
+                    .map_or(true, |term| Self::matches(app, term))
+            })
+            .filter(|app| {
+                filter.device.is_any()
+                    || versions.iter().any(|version| {
+                        version.app_id == app.id && filter.device.can_install(version)
+                    })
+            })
+            .cloned()
+            .collect();
+
+        let total = matching.len();
+        let apps = matching
+            .into_iter()
+            .filter(|app| {
+                filter
+                    .after
+                    .as_deref()
+                    .map_or(true, |after| app.package_id.as_str() > after)
+            })
+            .take(filter.limit)
+            .collect();
+        Ok(AppPage { apps, total })
+    }
+
+    async fn compatible_versions(
+        &self,
+        apps: &[Uuid],
+        device: &Device,
+    ) -> Result<HashMap<Uuid, Vec<AppVersion>>, ApiError> {
+        let mut versions: HashMap<Uuid, Vec<AppVersion>> = HashMap::new();
+        for version in self.published() {
+            if apps.contains(&version.app_id) && device.can_install(&version) {
+                versions.entry(version.app_id).or_default().push(version);
+            }
+        }
+        Ok(versions)
+    }
+
+    async fn get_app(&self, package_id: &str) -> Result<Option<App>, ApiError> {
+        Ok(self.app(package_id))
+    }
+
+    async fn category_counts(&self) -> Result<Vec<(Category, usize)>, ApiError> {
+        let mut counts: HashMap<Category, usize> = HashMap::new();
+        for app in lock(&self.apps).values() {
+            let categories: std::collections::HashSet<&Category> = app.categories.iter().collect();
+            for category in categories {
+                *counts.entry(category.clone()).or_default() += 1;
+            }
+        }
+        Ok(counts.into_iter().collect())
+    }
+
+    async fn get_apps(&self, package_ids: &[String]) -> Result<Vec<App>, ApiError> {
+        let apps = lock(&self.apps);
+        Ok(apps
+            .values()
+            .filter(|app| package_ids.iter().any(|id| app.package_id.as_str() == id))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_versions(&self, package_id: &str) -> Result<Option<Vec<AppVersion>>, ApiError> {
+        let Some(app) = self.get_app(package_id).await? else {
+            return Ok(None);
+        };
+        Ok(Some(
+            self.published()
+                .into_iter()
+                .filter(|version| version.app_id == app.id)
+                .collect(),
+        ))
+    }
+
+    async fn unpublish_version(
+        &self,
+        package_id: &str,
+        version_code: i64,
+    ) -> Result<bool, ApiError> {
+        let Some(app) = self.get_app(package_id).await? else {
+            return Ok(false);
+        };
+        let now = Utc::now();
+        let mut unpublished = false;
+        for stored in lock(&self.versions).iter_mut().filter(|stored| {
+            stored.deleted_at.is_none()
+                && stored.version.app_id == app.id
+                && stored.version.version_code == version_code
+        }) {
+            stored.deleted_at = Some(now);
+            unpublished = true;
+        }
+        Ok(unpublished)
+    }
+
+    async fn last_unpublished(&self, package_id: &str) -> Result<Option<DateTime<Utc>>, ApiError> {
+        let Some(app) = self.app(package_id) else {
+            return Ok(None);
+        };
+        Ok(lock(&self.versions)
+            .iter()
+            .filter(|stored| stored.version.app_id == app.id)
+            .filter_map(|stored| stored.deleted_at)
+            .max())
+    }
+
+    async fn index_fingerprint(&self, info: &RepoConfig) -> Result<String, ApiError> {
+        let content = format!("{:?}|{:?}|{info:?}", lock(&self.apps), lock(&self.versions));
+        Ok(dk_common::hash::sha256_bytes(content.as_bytes()).to_string())
+    }
+
+    async fn index_repo(
+        &self,
+        _info: &RepoConfig,
+        category: Option<&Category>,
+    ) -> Result<Repo, ApiError> {
+        let apps: Vec<App> = lock(&self.apps).values().cloned().collect();
+        let versions = self
+            .published()
+            .into_iter()
+            .filter_map(|version| {
+                let app = apps.iter().find(|app| app.id == version.app_id)?;
+                Some(crate::routes::index::IndexedVersion {
+                    package_id: app.package_id.clone(),
+                    version,
+                    sig: None,
+                })
+            })
+            .collect();
+        let mut repo = Repo {
+            apps,
+            versions,
+            issued_timestamp: 0,
+        };
+        repo.issued_timestamp = repo.timestamp();
+        if let Some(category) = category {
+            repo.retain_category(category);
+        }
+        Ok(repo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_like_pattern_escapes_wildcards() {
+        assert_eq!(like_pattern("MitID"), "%MitID%");
+        assert_eq!(like_pattern("50%_off"), "%50\\%\\_off%");
+        assert_eq!(like_pattern("a\\b"), "%a\\\\b%");
+    }
+}
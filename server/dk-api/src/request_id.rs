@@ -0,0 +1,104 @@
+//! Request correlation IDs.
+//!
+//! Every request carries an ID, taken from an incoming `X-Request-Id` header
+//! or generated. It is echoed in the response, recorded on the tracing span,
+//! and included in error bodies so clients can quote it in support tickets.
+
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Span;
+
+/// Header carrying the request ID.
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest incoming request ID that is accepted as is.
+const MAX_LENGTH: usize = 128;
+
+/// ID of the request being handled, stored in request extensions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+tokio::task_local! {
+    static CURRENT: RequestId;
+}
+
+/// ID of the request handled by the current task, if any.
+#[must_use]
+pub fn current() -> Option<String> {
+    CURRENT.try_with(|id| id.0.clone()).ok()
+}
+
+/// Middleware assigning the request ID.
+///
+/// Incoming IDs are reused only if short and made of characters that are
+/// safe to log; anything else is replaced by a fresh UUID.
+pub async fn propagate(mut request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| is_valid(value))
+        .map_or_else(|| uuid::Uuid::new_v4().to_string(), ToString::to_string);
+
+    let request_id = RequestId(id);
+    request.extensions_mut().insert(request_id.clone());
+    let header = HeaderValue::from_str(&request_id.0).ok();
+
+    let mut response = CURRENT.scope(request_id, next.run(request)).await;
+    if let Some(header) = header {
+        response
+            .headers_mut()
+            .insert(REQUEST_ID_HEADER.clone(), header);
+    }
+    response
+}
+
+/// Tracing span for `request`, tagged with its request ID.
+pub fn span<B>(request: &axum::http::Request<B>) -> Span {
+    let id = request
+        .extensions()
+        .get::<RequestId>()
+        .map_or("", |id| id.0.as_str());
+    tracing::debug_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        request_id = %id,
+    )
+}
+
+fn is_valid(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_LENGTH
+        && id
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid() {
+        assert!(is_valid("3f2c1a9e-7b4d-4e2a-9c1f-0a1b2c3d4e5f"));
+        assert!(is_valid("lb.trace_42"));
+        assert!(!is_valid(""));
+        assert!(!is_valid("id with spaces"));
+        assert!(!is_valid("id\"injected"));
+        assert!(!is_valid(&"a".repeat(MAX_LENGTH + 1)));
+    }
+
+    #[tokio::test]
+    async fn test_current_outside_request() {
+        assert_eq!(current(), None);
+        let id = CURRENT
+            .scope(RequestId("abc".to_string()), async { current() })
+            .await;
+        assert_eq!(id.as_deref(), Some("abc"));
+    }
+}
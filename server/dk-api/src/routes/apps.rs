@@ -1,32 +1,250 @@
 //! Application-related API endpoints.
 
+use std::collections::BTreeMap;
+
 use axum::{
-    extract::Path,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
-use serde::Serialize;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use dk_common::localized::{Localized, DEFAULT_LOCALE};
+use dk_common::types::{
+    latest_version, Antifeature, App, AppId, AppVersion, Category, DonationLink, License,
+    Permission, Screenshot, Sha256, WebUrl,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::types::Json as SqlJson;
+use sqlx::Row;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
 
 use crate::error::ApiError;
+use crate::repository::{AppFilter, Device};
+use crate::routes::metrics::APP_NOT_FOUND_TOTAL;
+use crate::state::AppState;
+
+/// Query parameters for selecting the response locale.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LocaleQuery {
+    /// BCP-47 locale code, e.g. `da` or `en-US`.
+    locale: Option<String>,
+}
+
+impl LocaleQuery {
+    /// Returns the requested locale, or the default locale if none was given.
+    fn locale(&self) -> &str {
+        requested_locale(self.locale.as_deref())
+    }
+}
+
+/// Default number of apps returned per page.
+const DEFAULT_PAGE_LIMIT: u32 = 50;
+
+/// Maximum number of apps returned per page; larger limits are clamped.
+const MAX_PAGE_LIMIT: u32 = 200;
+
+/// Maximum number of package IDs in one batch lookup.
+pub const MAX_BATCH_SIZE: usize = 100;
+
+/// Query parameters for listing applications.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListAppsQuery {
+    /// Maximum number of apps to return.
+    limit: Option<u32>,
+    /// Opaque cursor from a previous page's `next_cursor`.
+    cursor: Option<String>,
+    /// BCP-47 locale code, e.g. `da` or `en-US`.
+    locale: Option<String>,
+    /// Case-insensitive search across package ID, name, and summary.
+    q: Option<String>,
+    /// SDK level of the client device; only versions it can install count.
+    device_sdk: Option<i32>,
+    /// ABI of the client device, e.g. `arm64-v8a`; only versions without
+    /// native code or with libraries for this ABI count.
+    abi: Option<String>,
+}
+
+impl ListAppsQuery {
+    /// Returns the page size, defaulted and clamped to `1..=MAX_PAGE_LIMIT`.
+    fn limit(&self) -> u32 {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_LIMIT)
+            .clamp(1, MAX_PAGE_LIMIT)
+    }
+
+    /// Returns the package ID to continue after, decoded from the cursor.
+    fn after(&self) -> Result<Option<String>, ApiError> {
+        self.cursor
+            .as_deref()
+            .filter(|cursor| !cursor.is_empty())
+            .map(decode_cursor)
+            .transpose()
+    }
+
+    /// Returns the requested locale, or the default locale if none was given.
+    fn locale(&self) -> &str {
+        requested_locale(self.locale.as_deref())
+    }
+
+    /// Returns the device ABI, treating a blank ABI as none.
+    fn abi(&self) -> Option<&str> {
+        self.abi
+            .as_deref()
+            .map(str::trim)
+            .filter(|abi| !abi.is_empty())
+    }
+
+    /// Returns the search term, if one was given.
+    ///
+    /// A blank search term is treated the same as no search term.
+    fn search_term(&self) -> Option<&str> {
+        self.q.as_deref().map(str::trim).filter(|q| !q.is_empty())
+    }
+
+    /// Returns the client device the listed apps must be installable on.
+    fn device(&self) -> Device {
+        Device {
+            sdk: self.device_sdk,
+            abi: self.abi().map(ToString::to_string),
+        }
+    }
+}
+
+/// Order of listed versions; always newest first.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionSort {
+    /// By version code, then by publication time.
+    #[default]
+    VersionCode,
+    /// By publication time, then by version code.
+    CreatedAt,
+}
+
+/// Query parameters for listing application versions.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct VersionsQuery {
+    /// Sort order.
+    #[serde(default)]
+    #[param(inline)]
+    sort: VersionSort,
+    /// Only versions whose minimum SDK is at least this level.
+    min_sdk: Option<i32>,
+    /// Only versions whose minimum SDK is at most this level, i.e. those
+    /// installable on a device running it.
+    max_sdk: Option<i32>,
+}
+
+impl VersionsQuery {
+    /// Filter `versions` by SDK level and sort them.
+    fn select(&self, mut versions: Vec<AppVersion>) -> Vec<AppVersion> {
+        versions.retain(|version| {
+            self.min_sdk.map_or(true, |min| version.min_sdk >= min)
+                && self.max_sdk.map_or(true, |max| version.min_sdk <= max)
+        });
+        match self.sort {
+            VersionSort::VersionCode => versions.sort_by(|a, b| b.cmp_release(a)),
+            VersionSort::CreatedAt => versions.sort_by(|a, b| {
+                b.created_at
+                    .cmp(&a.created_at)
+                    .then_with(|| b.version_code.cmp(&a.version_code))
+            }),
+        }
+        versions
+    }
+}
+
+/// Returns `locale` if non-empty, otherwise the default locale.
+fn requested_locale(locale: Option<&str>) -> &str {
+    locale
+        .filter(|locale| !locale.is_empty())
+        .unwrap_or(DEFAULT_LOCALE)
+}
+
+/// Encode the last package ID of a page into a cursor.
+fn encode_cursor(package_id: &str) -> String {
+    URL_SAFE_NO_PAD.encode(package_id)
+}
+
+/// Decode a cursor back into the package ID it was created from.
+fn decode_cursor(cursor: &str) -> Result<String, ApiError> {
+    URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .ok_or_else(|| ApiError::BadRequest("Invalid cursor".to_string()))
+}
+
+/// Split `items` (fetched with one extra row) into a page and its next cursor.
+///
+/// The next cursor is only set when more items exist beyond `limit`.
+fn split_page<T>(
+    mut items: Vec<T>,
+    limit: usize,
+    package_id: impl Fn(&T) -> &str,
+) -> (Vec<T>, Option<String>) {
+    if items.len() <= limit {
+        return (items, None);
+    }
+
+    items.truncate(limit);
+    let next_cursor = items.last().map(|item| encode_cursor(package_id(item)));
+    (items, next_cursor)
+}
 
 /// Response for listing applications.
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct AppsListResponse {
     apps: Vec<AppSummary>,
+    /// Number of apps matching the query across all pages.
     total: usize,
+    /// Cursor of the next page; absent on the last page.
+    next_cursor: Option<String>,
 }
 
 /// Summary of an application.
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct AppSummary {
     package_id: String,
     name: String,
     summary: String,
     version_name: String,
     version_code: i64,
+    /// Last change to the app's metadata, in RFC 3339.
+    updated_at: String,
+}
+
+impl AppSummary {
+    /// Build the summary view of `app`, resolving localized strings for `locale`.
+    ///
+    /// Reports the `latest` published version, falling back to the version
+    /// recorded on the app when none is published.
+    fn from_app(app: &App, latest: Option<&AppVersion>, locale: &str) -> Self {
+        let (version_name, version_code) = latest.map_or_else(
+            || (app.version_name.clone(), app.version_code),
+            |version| (version.version_name.clone(), version.version_code),
+        );
+        Self {
+            package_id: app.package_id.to_string(),
+            name: resolve(&app.name, locale),
+            summary: resolve(&app.summary, locale),
+            version_name,
+            version_code,
+            updated_at: app.updated_at.to_rfc3339(),
+        }
+    }
 }
 
 /// Detailed application information.
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct AppDetail {
     package_id: String,
     name: String,
@@ -34,54 +252,1344 @@ pub struct AppDetail {
     description: String,
     version_name: String,
     version_code: i64,
+    /// SPDX license identifier; `NOASSERTION` if undetermined.
+    license: Option<String>,
+    source_url: Option<String>,
+    issue_tracker_url: Option<String>,
+    web_url: Option<String>,
     created_at: String,
     updated_at: String,
 }
 
+impl AppDetail {
+    /// Build the detail view of `app`, resolving localized strings for `locale`.
+    fn from_app(app: &App, locale: &str) -> Self {
+        Self {
+            package_id: app.package_id.to_string(),
+            name: resolve(&app.name, locale),
+            summary: resolve(&app.summary, locale),
+            description: resolve(&app.description, locale),
+            version_name: app.version_name.clone(),
+            version_code: app.version_code,
+            license: app.license.as_ref().map(ToString::to_string),
+            source_url: app.source_url.as_ref().map(ToString::to_string),
+            issue_tracker_url: app.issue_tracker_url.as_ref().map(ToString::to_string),
+            web_url: app.web_url.as_ref().map(ToString::to_string),
+            created_at: app.created_at.to_rfc3339(),
+            updated_at: app.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Resolve a localized string, falling back to an empty string.
+pub fn resolve(value: &Localized<String>, locale: &str) -> String {
+    value.get(locale).cloned().unwrap_or_default()
+}
+
 /// Application version information.
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct AppVersionResponse {
     version_name: String,
     version_code: i64,
-    sha256: String,
+    /// Lowercase hex SHA-256 of the APK.
+    #[schema(value_type = String)]
+    sha256: Sha256,
     size: i64,
     min_sdk: i32,
     target_sdk: i32,
+    /// Requested permissions, as `{"name": ..., "maxSdk": ...}` objects.
+    #[schema(value_type = Vec<Object>)]
+    permissions: Vec<Permission>,
+    abis: Vec<String>,
+    native_code: bool,
     created_at: String,
 }
 
-/// List all applications.
-///
-/// GET /api/v1/apps
-pub async fn list_apps() -> Json<AppsListResponse> {
-    // TODO: Implement database query
-    // For now, return placeholder data
-    Json(AppsListResponse {
-        apps: vec![],
-        total: 0,
+impl From<AppVersion> for AppVersionResponse {
+    fn from(version: AppVersion) -> Self {
+        Self {
+            version_name: version.version_name,
+            version_code: version.version_code,
+            sha256: version.sha256,
+            size: version.size,
+            min_sdk: version.min_sdk,
+            target_sdk: version.target_sdk,
+            permissions: version.permissions,
+            abis: version.abis,
+            native_code: version.native_code,
+            created_at: version.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Columns selected when loading an [`App`] row.
+pub const APP_COLUMNS: &str = "id, package_id, name, summary, description, categories, \
+                           screenshots, antifeatures, license, source_url, issue_tracker_url, \
+                           web_url, donate, version_code, version_name, created_at, updated_at";
+
+/// Read the nullable text `column` of `row`, validated with `parse`.
+fn optional_column<T>(
+    row: &PgRow,
+    column: &str,
+    parse: impl Fn(&str) -> dk_common::Result<T>,
+) -> Result<Option<T>, sqlx::Error> {
+    row.try_get::<Option<String>, _>(column)?
+        .map(|value| parse(&value))
+        .transpose()
+        .map_err(|err| sqlx::Error::Decode(Box::new(err)))
+}
+
+/// Map an `apps` row into an [`App`].
+pub fn app_from_row(row: &PgRow) -> Result<App, sqlx::Error> {
+    let categories: Vec<String> = row.try_get("categories")?;
+    let categories = categories
+        .into_iter()
+        .map(Category::try_from)
+        .collect::<Result<_, _>>()
+        .map_err(|err| sqlx::Error::Decode(Box::new(err)))?;
+    let antifeatures: Vec<String> = row.try_get("antifeatures")?;
+    let antifeatures = antifeatures
+        .iter()
+        .map(|code| code.parse::<Antifeature>())
+        .collect::<Result<_, _>>()
+        .map_err(|err| sqlx::Error::Decode(Box::new(err)))?;
+
+    Ok(App {
+        id: row.try_get("id")?,
+        package_id: AppId::new(row.try_get::<String, _>("package_id")?),
+        name: row.try_get::<SqlJson<Localized<String>>, _>("name")?.0,
+        summary: row.try_get::<SqlJson<Localized<String>>, _>("summary")?.0,
+        description: row
+            .try_get::<SqlJson<Localized<String>>, _>("description")?
+            .0,
+        categories,
+        screenshots: row.try_get::<SqlJson<Vec<Screenshot>>, _>("screenshots")?.0,
+        antifeatures,
+        license: optional_column(row, "license", License::parse)?,
+        source_url: optional_column(row, "source_url", WebUrl::parse)?,
+        issue_tracker_url: optional_column(row, "issue_tracker_url", WebUrl::parse)?,
+        web_url: optional_column(row, "web_url", WebUrl::parse)?,
+        donate: row.try_get::<SqlJson<Vec<DonationLink>>, _>("donate")?.0,
+        version_code: row.try_get("version_code")?,
+        version_name: row.try_get("version_name")?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+/// Map an `app_versions` row into an [`AppVersion`].
+pub fn version_from_row(row: &PgRow) -> Result<AppVersion, sqlx::Error> {
+    let abis: Vec<String> = row.try_get("abis")?;
+    Ok(AppVersion {
+        id: row.try_get("id")?,
+        app_id: row.try_get("app_id")?,
+        version_code: row.try_get("version_code")?,
+        version_name: row.try_get("version_name")?,
+        sha256: Sha256::parse(row.try_get::<&str, _>("sha256")?)
+            .map_err(|err| sqlx::Error::Decode(Box::new(err)))?,
+        size: row.try_get("size")?,
+        min_sdk: row.try_get("min_sdk")?,
+        target_sdk: row.try_get("target_sdk")?,
+        permissions: row.try_get::<SqlJson<Vec<Permission>>, _>("permissions")?.0,
+        native_code: !abis.is_empty(),
+        abis,
+        created_at: row.try_get("created_at")?,
     })
 }
 
+/// List applications, ordered by package ID.
+///
+/// GET /api/v1/apps?q=mitid&limit=50&cursor=...&locale=da&device_sdk=24&abi=arm64-v8a
+///
+/// With `device_sdk` or `abi`, apps are summarized by their latest version
+/// the device can install, and apps without one are left out.
+#[utoipa::path(
+    get,
+    path = "/apps",
+    tag = "apps",
+    params(ListAppsQuery),
+    responses(
+        (status = 200, description = "A page of applications", body = AppsListResponse),
+        (status = 400, description = "Invalid cursor", body = ErrorResponse),
+    )
+)]
+pub async fn list_apps(
+    State(state): State<AppState>,
+    Query(query): Query<ListAppsQuery>,
+) -> Result<Json<AppsListResponse>, ApiError> {
+    let limit = query.limit();
+    let device = query.device();
+    let page = state
+        .apps
+        .list_apps(&AppFilter {
+            after: query.after()?,
+            limit: limit as usize + 1,
+            search: query.search_term().map(ToString::to_string),
+            device: device.clone(),
+        })
+        .await?;
+
+    let ids: Vec<Uuid> = page.apps.iter().map(|app| app.id).collect();
+    let versions = state.apps.compatible_versions(&ids, &device).await?;
+    let apps = page
+        .apps
+        .iter()
+        .map(|app| {
+            let latest = versions
+                .get(&app.id)
+                .and_then(|versions| latest_version(versions));
+            AppSummary::from_app(app, latest, query.locale())
+        })
+        .collect();
+    let (apps, next_cursor) = split_page(apps, limit as usize, |app| app.package_id.as_str());
+
+    Ok(Json(AppsListResponse {
+        apps,
+        total: page.total,
+        next_cursor,
+    }))
+}
+
 /// Get a specific application by package ID.
 ///
-/// GET /api/v1/apps/:package_id
-pub async fn get_app(Path(package_id): Path<String>) -> Result<Json<AppDetail>, ApiError> {
-    // TODO: Implement database query
-    // For now, return not found
-    Err(ApiError::NotFound(format!(
-        "Application not found: {package_id}"
-    )))
+/// `GET /api/v1/apps/:package_id?locale=da`
+#[utoipa::path(
+    get,
+    path = "/apps/{package_id}",
+    tag = "apps",
+    params(
+        ("package_id" = String, Path, description = "Package identifier, e.g. `dk.digst.mitid`"),
+        LocaleQuery,
+    ),
+    responses(
+        (status = 200, description = "The application", body = AppDetail),
+        (status = 404, description = "Unknown application", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all, fields(package_id = %package_id), err(level = "info", Debug))]
+pub async fn get_app(
+    State(state): State<AppState>,
+    Path(package_id): Path<String>,
+    Query(query): Query<LocaleQuery>,
+) -> Result<Json<AppDetail>, ApiError> {
+    let app = state
+        .apps
+        .get_app(&package_id)
+        .await?
+        .ok_or_else(|| app_not_found(&package_id))?;
+    Ok(Json(AppDetail::from_app(&app, query.locale())))
+}
+
+/// Request body for looking up several applications at once.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BatchAppsRequest {
+    /// Package identifiers to look up; at most [`MAX_BATCH_SIZE`].
+    package_ids: Vec<String>,
+}
+
+/// Applications found by a batch lookup.
+#[derive(Serialize, ToSchema)]
+pub struct BatchAppsResponse {
+    /// Found applications by package ID; unknown IDs are left out.
+    apps: BTreeMap<String, AppDetail>,
+}
+
+/// Look up several applications by package ID.
+///
+/// POST /api/v1/apps/batch?locale=da
+///
+/// Unknown package IDs are left out of the response rather than failing the
+/// request.
+#[utoipa::path(
+    post,
+    path = "/apps/batch",
+    tag = "apps",
+    params(LocaleQuery),
+    request_body = BatchAppsRequest,
+    responses(
+        (status = 200, description = "The applications found", body = BatchAppsResponse),
+        (status = 400, description = "Too many package IDs", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(
+    skip_all,
+    fields(count = request.package_ids.len()),
+    err(level = "info", Debug)
+)]
+pub async fn batch_get_apps(
+    State(state): State<AppState>,
+    Query(query): Query<LocaleQuery>,
+    Json(request): Json<BatchAppsRequest>,
+) -> Result<Json<BatchAppsResponse>, ApiError> {
+    if request.package_ids.len() > MAX_BATCH_SIZE {
+        return Err(ApiError::BadRequest(format!(
+            "At most {MAX_BATCH_SIZE} package IDs may be looked up at once, got {}",
+            request.package_ids.len()
+        )));
+    }
+
+    let apps = state
+        .apps
+        .get_apps(&request.package_ids)
+        .await?
+        .iter()
+        .map(|app| {
+            (
+                app.package_id.to_string(),
+                AppDetail::from_app(app, query.locale()),
+            )
+        })
+        .collect();
+    Ok(Json(BatchAppsResponse { apps }))
 }
 
-/// Get version history for an application.
+/// Get version history for an application, newest version first.
+///
+/// `GET /api/v1/apps/:package_id/versions?sort=created_at&max_sdk=30`
 ///
-/// GET /api/v1/apps/:package_id/versions
+/// Sorted by version code unless `sort=created_at`. Unpublished versions are
+/// left out. `Last-Modified` is the latest upload or unpublish of a version,
+/// and a request with an `If-Modified-Since` no earlier than it gets `304`.
+#[utoipa::path(
+    get,
+    path = "/apps/{package_id}/versions",
+    tag = "apps",
+    params(
+        ("package_id" = String, Path, description = "Package identifier, e.g. `dk.digst.mitid`"),
+        VersionsQuery,
+    ),
+    responses(
+        (status = 200, description = "Published versions", body = [AppVersionResponse]),
+        (status = 304, description = "No version uploaded or unpublished since `If-Modified-Since`"),
+        (status = 404, description = "Unknown application", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(skip_all, fields(package_id = %package_id), err(level = "info", Debug))]
 pub async fn get_app_versions(
+    State(state): State<AppState>,
     Path(package_id): Path<String>,
-) -> Result<Json<Vec<AppVersionResponse>>, ApiError> {
-    // TODO: Implement database query
-    // For now, return not found
-    Err(ApiError::NotFound(format!(
-        "Application not found: {package_id}"
-    )))
+    Query(query): Query<VersionsQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let versions = state
+        .apps
+        .get_versions(&package_id)
+        .await?
+        .ok_or_else(|| app_not_found(&package_id))?;
+    // Unpublishing the newest version must not move the validator backwards
+    let last_modified = versions
+        .iter()
+        .map(|version| version.created_at)
+        .chain(state.apps.last_unpublished(&package_id).await?)
+        .max();
+
+    let response = if last_modified.is_some_and(|at| is_unmodified_since(&headers, at)) {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        let versions: Vec<AppVersionResponse> = query
+            .select(versions)
+            .into_iter()
+            .map(AppVersionResponse::from)
+            .collect();
+        Json(versions).into_response()
+    };
+    Ok(with_last_modified(response, last_modified))
+}
+
+/// `strftime` format of HTTP dates, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// Whether `If-Modified-Since` in `headers` is no earlier than
+/// `last_modified`, compared to the second, the precision of HTTP dates.
+/// Unparseable dates are ignored.
+fn is_unmodified_since(headers: &HeaderMap, last_modified: DateTime<Utc>) -> bool {
+    headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+        .is_some_and(|since| last_modified.timestamp() <= since.timestamp())
+}
+
+/// Add a `Last-Modified` header for `last_modified` to `response`, if known.
+fn with_last_modified(mut response: Response, last_modified: Option<DateTime<Utc>>) -> Response {
+    if let Some(at) = last_modified {
+        if let Ok(value) = HeaderValue::from_str(&at.format(HTTP_DATE).to_string()) {
+            response.headers_mut().insert(header::LAST_MODIFIED, value);
+        }
+    }
+    response
+}
+
+/// Unpublish an application version.
+///
+/// `DELETE /api/v1/apps/:package_id/versions/:version_code`
+///
+/// The version is soft-deleted: it leaves the index and version listings but
+/// its record is kept for audit. The app stays listed even without any
+/// published version.
+#[utoipa::path(
+    delete,
+    path = "/apps/{package_id}/versions/{version_code}",
+    tag = "apps",
+    params(
+        ("package_id" = String, Path, description = "Package identifier, e.g. `dk.digst.mitid`"),
+        ("version_code" = i64, Path, description = "Android versionCode of the version"),
+    ),
+    responses(
+        (status = 204, description = "The version was unpublished"),
+        (status = 401, description = "Missing or invalid API key", body = ErrorResponse),
+        (status = 404, description = "No such published version", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(
+    skip_all,
+    fields(package_id = %package_id, version_code = version_code),
+    err(level = "info", Debug)
+)]
+pub async fn delete_version(
+    State(state): State<AppState>,
+    Path((package_id, version_code)): Path<(String, i64)>,
+) -> Result<StatusCode, ApiError> {
+    if !state
+        .apps
+        .unpublish_version(&package_id, version_code)
+        .await?
+    {
+        return Err(version_not_found(&package_id, version_code));
+    }
+    tracing::info!(%package_id, version_code, "Unpublished APK version");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Not-found error for `package_id`, counted in the app-not-found metric.
+pub fn app_not_found(package_id: &str) -> ApiError {
+    metrics::counter!(APP_NOT_FOUND_TOTAL).increment(1);
+    ApiError::AppNotFound(format!("Application not found: {package_id}"))
+}
+
+/// Not-found error for version `version_code` of `package_id`.
+pub fn version_not_found(package_id: &str, version_code: i64) -> ApiError {
+    ApiError::VersionNotFound(format!("Version not found: {package_id} {version_code}"))
+}
+
+/// Parse the package ID `package_id` from a request path.
+///
+/// # Errors
+///
+/// Returns [`ApiError::InvalidPackageId`] if it is not a valid Android
+/// package name.
+pub fn parse_package_id(package_id: &str) -> Result<AppId, ApiError> {
+    AppId::parse(package_id).map_err(|err| match err {
+        dk_common::Error::InvalidInput(msg) => ApiError::InvalidPackageId(msg),
+        err => ApiError::from(err),
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use chrono::{Duration, TimeZone};
+    use dk_common::types::AppId;
+    use tracing::span;
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::registry::LookupSpan;
+    use tracing_subscriber::Layer;
+
+    use super::*;
+    use crate::repository::{InMemoryAppRepository, PgAppRepository};
+
+    pub fn sample_app() -> App {
+        let mut name = Localized::single("en", "MitID".to_string());
+        name.insert("da", "MitID".to_string());
+        let mut summary = Localized::single("en", "Digital identity".to_string());
+        summary.insert("da", "Digital identitet".to_string());
+
+        App {
+            id: Uuid::new_v4(),
+            package_id: AppId::new("dk.digst.mitid"),
+            name,
+            summary,
+            description: Localized::single("en", "The MitID app".to_string()),
+            categories: vec![],
+            screenshots: vec![],
+            antifeatures: vec![],
+            license: None,
+            source_url: None,
+            issue_tracker_url: None,
+            web_url: None,
+            donate: vec![],
+            version_code: 1,
+            version_name: "1.0".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    pub fn sample_version(app: &App, version_code: i64, version_name: &str) -> AppVersion {
+        AppVersion {
+            id: Uuid::new_v4(),
+            app_id: app.id,
+            version_code,
+            version_name: version_name.to_string(),
+            sha256: Sha256::from_bytes(&[0; 32]),
+            size: 1024,
+            min_sdk: 24,
+            target_sdk: 34,
+            permissions: vec![],
+            abis: vec![],
+            native_code: false,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_app_summary_reports_latest_version() {
+        let app = sample_app();
+        let mut rebuilt = sample_version(&app, 12, "1.2.0-rebuild");
+        rebuilt.created_at += Duration::seconds(1);
+        // Inserted out of order, with version code 12 published twice
+        let versions = [
+            sample_version(&app, 12, "1.2.0"),
+            sample_version(&app, 3, "1.0.3"),
+            rebuilt,
+            sample_version(&app, 7, "1.1.0"),
+        ];
+
+        let summary = AppSummary::from_app(&app, latest_version(&versions), DEFAULT_LOCALE);
+        assert_eq!(summary.version_code, 12);
+        assert_eq!(summary.version_name, "1.2.0-rebuild");
+
+        // Without published versions the app's own version is reported
+        let summary = AppSummary::from_app(&app, None, DEFAULT_LOCALE);
+        assert_eq!(
+            (summary.version_code, summary.version_name.as_str()),
+            (1, "1.0")
+        );
+    }
+
+    #[test]
+    fn test_app_summary_reports_updated_at() {
+        let app = sample_app();
+        let summary = AppSummary::from_app(&app, None, DEFAULT_LOCALE);
+        let json = serde_json::to_value(&summary).expect("summary json");
+        assert_eq!(json["updated_at"], app.updated_at.to_rfc3339());
+    }
+
+    #[test]
+    fn test_app_detail_resolves_locale() {
+        let query = LocaleQuery {
+            locale: Some("da".to_string()),
+        };
+        let detail = AppDetail::from_app(&sample_app(), query.locale());
+        assert_eq!(detail.summary, "Digital identitet");
+        // No Danish description, so English is used
+        assert_eq!(detail.description, "The MitID app");
+    }
+
+    #[test]
+    fn test_app_detail_reports_license() {
+        let mut app = sample_app();
+        let json = serde_json::to_value(AppDetail::from_app(&app, DEFAULT_LOCALE)).expect("json");
+        assert!(json["license"].is_null());
+
+        app.license = Some(License::parse("EUPL-1.2").expect("license"));
+        let json = serde_json::to_value(AppDetail::from_app(&app, DEFAULT_LOCALE)).expect("json");
+        assert_eq!(json["license"], "EUPL-1.2");
+    }
+
+    /// Versions 3, 12, and 7 with minimum SDKs 21, 29, and 26, published in
+    /// that order.
+    fn unordered_versions(app: &App) -> Vec<AppVersion> {
+        [(3, 21), (12, 29), (7, 26)]
+            .into_iter()
+            .enumerate()
+            .map(|(i, (version_code, min_sdk))| {
+                let mut version = sample_version(app, version_code, &format!("{version_code}.0"));
+                version.min_sdk = min_sdk;
+                version.created_at += Duration::seconds(i64::try_from(i).expect("few versions"));
+                version
+            })
+            .collect()
+    }
+
+    fn codes(versions: &[AppVersion]) -> Vec<i64> {
+        versions
+            .iter()
+            .map(|version| version.version_code)
+            .collect()
+    }
+
+    #[test]
+    fn test_versions_sorted_by_version_code_descending_by_default() {
+        let app = sample_app();
+        let versions = VersionsQuery::default().select(unordered_versions(&app));
+        assert_eq!(codes(&versions), [12, 7, 3]);
+    }
+
+    #[test]
+    fn test_versions_sorted_by_created_at() {
+        let app = sample_app();
+        let query = VersionsQuery {
+            sort: VersionSort::CreatedAt,
+            ..VersionsQuery::default()
+        };
+        assert_eq!(codes(&query.select(unordered_versions(&app))), [7, 12, 3]);
+    }
+
+    #[test]
+    fn test_sdk_filter_excludes_incompatible_versions() {
+        let app = sample_app();
+        let device = VersionsQuery {
+            max_sdk: Some(26),
+            ..VersionsQuery::default()
+        };
+        assert_eq!(codes(&device.select(unordered_versions(&app))), [7, 3]);
+
+        let range = VersionsQuery {
+            min_sdk: Some(22),
+            max_sdk: Some(28),
+            ..VersionsQuery::default()
+        };
+        assert_eq!(codes(&range.select(unordered_versions(&app))), [7]);
+    }
+
+    #[test]
+    fn test_versions_query_from_uri() {
+        let uri = "/apps/dk.digst.mitid/versions?sort=created_at&min_sdk=24"
+            .parse()
+            .expect("uri");
+        let Query(query) = Query::<VersionsQuery>::try_from_uri(&uri).expect("query");
+        assert_eq!(query.sort, VersionSort::CreatedAt);
+        assert_eq!((query.min_sdk, query.max_sdk), (Some(24), None));
+
+        let uri = "/apps/dk.digst.mitid/versions?sort=size"
+            .parse()
+            .expect("uri");
+        assert!(Query::<VersionsQuery>::try_from_uri(&uri).is_err());
+    }
+
+    #[test]
+    fn test_version_response_permissions() {
+        let mut version = sample_version(&sample_app(), 1, "1.0");
+        version.permissions = vec![Permission {
+            name: "android.permission.INTERNET".to_string(),
+            max_sdk: None,
+        }];
+
+        let mut response = AppVersionResponse::from(version);
+        let json = serde_json::to_value(&response).expect("serialize");
+        assert_eq!(
+            json["permissions"],
+            serde_json::json!([{"name": "android.permission.INTERNET", "maxSdk": null}])
+        );
+
+        response.permissions.clear();
+        let json = serde_json::to_value(&response).expect("serialize");
+        assert_eq!(json["permissions"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_app_detail_defaults_to_english() {
+        let detail = AppDetail::from_app(&sample_app(), LocaleQuery::default().locale());
+        assert_eq!(detail.summary, "Digital identity");
+    }
+
+    #[test]
+    fn test_list_apps_limit_defaults_and_clamps() {
+        assert_eq!(ListAppsQuery::default().limit(), DEFAULT_PAGE_LIMIT);
+
+        let query = ListAppsQuery {
+            limit: Some(10_000),
+            ..ListAppsQuery::default()
+        };
+        assert_eq!(query.limit(), MAX_PAGE_LIMIT);
+
+        let query = ListAppsQuery {
+            limit: Some(0),
+            ..ListAppsQuery::default()
+        };
+        assert_eq!(query.limit(), 1);
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let query = ListAppsQuery {
+            cursor: Some(encode_cursor("dk.digst.mitid")),
+            ..ListAppsQuery::default()
+        };
+        assert_eq!(
+            query.after().expect("valid cursor").as_deref(),
+            Some("dk.digst.mitid")
+        );
+    }
+
+    #[test]
+    fn test_invalid_cursor_is_bad_request() {
+        let query = ListAppsQuery {
+            cursor: Some("not base64!".to_string()),
+            ..ListAppsQuery::default()
+        };
+        assert!(matches!(query.after(), Err(ApiError::BadRequest(_))));
+    }
+
+    #[test]
+    fn test_paging_returns_every_app_once() {
+        let mut seeded: Vec<String> = (0..23).map(|i| format!("dk.example.app{i:02}")).collect();
+        seeded.sort();
+
+        let limit = 5;
+        let mut seen = Vec::new();
+        let mut cursor: Option<String> = None;
+        let mut pages = 0;
+
+        loop {
+            let after = cursor
+                .as_deref()
+                .map(|cursor| decode_cursor(cursor).expect("valid cursor"));
+            // Mirrors the database query: rows after the cursor, one extra row
+            let fetched: Vec<String> = seeded
+                .iter()
+                .filter(|id| after.as_ref().map_or(true, |after| *id > after))
+                .take(limit + 1)
+                .cloned()
+                .collect();
+
+            let (page, next_cursor) = split_page(fetched, limit, String::as_str);
+            seen.extend(page);
+            pages += 1;
+
+            match next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        assert_eq!(seen, seeded);
+        assert_eq!(pages, 5);
+    }
+
+    #[test]
+    fn test_exact_final_page_has_no_cursor() {
+        let items = vec!["a.a".to_string(), "a.b".to_string()];
+        let (page, next_cursor) = split_page(items, 2, String::as_str);
+        assert_eq!(page.len(), 2);
+        assert!(next_cursor.is_none());
+    }
+
+    /// State serving `repository` from memory.
+    pub fn in_memory_state(repository: InMemoryAppRepository) -> AppState {
+        AppState {
+            apps: Arc::new(repository),
+            ..AppState::disconnected()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_app_from_repository() {
+        let repository = InMemoryAppRepository::default();
+        repository.insert_app(sample_app());
+
+        let Json(detail) = get_app(
+            State(in_memory_state(repository)),
+            Path("dk.digst.mitid".to_string()),
+            Query(LocaleQuery {
+                locale: Some("da".to_string()),
+            }),
+        )
+        .await
+        .expect("app");
+        assert_eq!(detail.package_id, "dk.digst.mitid");
+        assert_eq!(detail.summary, "Digital identitet");
+    }
+
+    /// GET the versions of `package_id`, with an optional `If-Modified-Since`.
+    async fn versions_since(
+        state: AppState,
+        package_id: &str,
+        since: Option<&HeaderValue>,
+    ) -> Result<Response, ApiError> {
+        let mut headers = HeaderMap::new();
+        if let Some(since) = since {
+            headers.insert(header::IF_MODIFIED_SINCE, since.clone());
+        }
+        get_app_versions(
+            State(state),
+            Path(package_id.to_string()),
+            Query(VersionsQuery::default()),
+            headers,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_versions_conditional_get() {
+        let repository = InMemoryAppRepository::default();
+        let app = sample_app();
+        let mut newest = sample_version(&app, 2, "2.0");
+        newest.created_at = Utc
+            .with_ymd_and_hms(2024, 5, 1, 12, 30, 15)
+            .single()
+            .expect("date");
+        let mut older = sample_version(&app, 1, "1.0");
+        older.created_at = newest.created_at - Duration::days(30);
+        repository.insert_version(older);
+        repository.insert_version(newest);
+        repository.insert_app(app);
+        let state = in_memory_state(repository);
+
+        let response = versions_since(state.clone(), "dk.digst.mitid", None)
+            .await
+            .expect("versions");
+        assert_eq!(response.status(), StatusCode::OK);
+        let last_modified = response.headers()[header::LAST_MODIFIED].clone();
+        assert_eq!(last_modified, "Wed, 01 May 2024 12:30:15 GMT");
+
+        let response = versions_since(state.clone(), "dk.digst.mitid", Some(&last_modified))
+            .await
+            .expect("versions");
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::LAST_MODIFIED], last_modified);
+
+        // Before the newest upload, or not a date: the full listing
+        for since in ["Wed, 01 May 2024 12:30:14 GMT", "yesterday"] {
+            let since = HeaderValue::from_static(since);
+            let response = versions_since(state.clone(), "dk.digst.mitid", Some(&since))
+                .await
+                .expect("versions");
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_versions_modified_by_unpublish() {
+        let repository = InMemoryAppRepository::default();
+        let app = sample_app();
+        let mut newest = sample_version(&app, 2, "2.0");
+        newest.created_at -= Duration::days(1);
+        let mut older = sample_version(&app, 1, "1.0");
+        older.created_at = newest.created_at - Duration::days(30);
+        repository.insert_version(older);
+        repository.insert_version(newest);
+        repository.insert_app(app);
+        let state = in_memory_state(repository);
+
+        let response = versions_since(state.clone(), "dk.digst.mitid", None)
+            .await
+            .expect("versions");
+        let last_modified = response.headers()[header::LAST_MODIFIED].clone();
+
+        delete(&state, "dk.digst.mitid", 2).await.expect("delete");
+
+        let response = versions_since(state.clone(), "dk.digst.mitid", Some(&last_modified))
+            .await
+            .expect("versions");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()[header::LAST_MODIFIED], last_modified);
+    }
+
+    #[tokio::test]
+    async fn test_get_unknown_app_is_not_found() {
+        let repository = InMemoryAppRepository::default();
+        repository.insert_app(sample_app());
+
+        let result = get_app(
+            State(in_memory_state(repository)),
+            Path("dk.example.missing".to_string()),
+            Query(LocaleQuery::default()),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiError::AppNotFound(_))));
+    }
+
+    async fn batch(
+        state: AppState,
+        package_ids: Vec<String>,
+    ) -> Result<BatchAppsResponse, ApiError> {
+        let request = BatchAppsRequest { package_ids };
+        let Json(response) =
+            batch_get_apps(State(state), Query(LocaleQuery::default()), Json(request)).await?;
+        Ok(response)
+    }
+
+    #[tokio::test]
+    async fn test_batch_leaves_out_unknown_apps() {
+        let repository = InMemoryAppRepository::default();
+        let mut tastselv = sample_app();
+        tastselv.package_id = AppId::new("dk.skat.tastselv");
+        repository.insert_app(sample_app());
+        repository.insert_app(tastselv);
+
+        let ids = ["dk.digst.mitid", "dk.example.missing", "dk.skat.tastselv"];
+        let response = batch(in_memory_state(repository), ids.map(String::from).to_vec())
+            .await
+            .expect("batch");
+        let found: Vec<&str> = response.apps.keys().map(String::as_str).collect();
+        assert_eq!(found, ["dk.digst.mitid", "dk.skat.tastselv"]);
+        assert_eq!(response.apps["dk.digst.mitid"].name, "MitID");
+    }
+
+    #[tokio::test]
+    async fn test_batch_over_cap_is_bad_request() {
+        let ids = (0..=MAX_BATCH_SIZE)
+            .map(|i| format!("dk.example.app{i}"))
+            .collect();
+        let result = batch(in_memory_state(InMemoryAppRepository::default()), ids).await;
+        assert!(matches!(result, Err(ApiError::BadRequest(_))));
+
+        let ids = (0..MAX_BATCH_SIZE)
+            .map(|i| format!("dk.example.app{i}"))
+            .collect();
+        let response = batch(in_memory_state(InMemoryAppRepository::default()), ids).await;
+        assert!(response.expect("batch at cap").apps.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unpublish_from_repository() {
+        let repository = InMemoryAppRepository::default();
+        let app = sample_app();
+        repository.insert_version(sample_version(&app, 1, "1.0"));
+        repository.insert_version(sample_version(&app, 2, "2.0"));
+        repository.insert_app(app);
+        let state = in_memory_state(repository);
+
+        assert_eq!(version_codes(&state, "dk.digst.mitid").await, [2, 1]);
+        assert_eq!(
+            delete(&state, "dk.digst.mitid", 2).await.expect("delete"),
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(version_codes(&state, "dk.digst.mitid").await, [1]);
+        assert!(matches!(
+            delete(&state, "dk.digst.mitid", 2).await,
+            Err(ApiError::VersionNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_list_apps_database_error_is_internal() {
+        let result = list_apps(
+            State(AppState::disconnected()),
+            Query(ListAppsQuery::default()),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiError::Internal(_))));
+    }
+
+    #[test]
+    fn test_search_term() {
+        let query = |q: &str| ListAppsQuery {
+            q: Some(q.to_string()),
+            ..ListAppsQuery::default()
+        };
+
+        assert_eq!(ListAppsQuery::default().search_term(), None);
+        assert_eq!(query("").search_term(), None);
+        assert_eq!(query("   ").search_term(), None);
+        assert_eq!(query(" MitID ").search_term(), Some("MitID"));
+        assert_eq!(query("50%_off").search_term(), Some("50%_off"));
+    }
+
+    /// Connect to `DATABASE_URL`, apply migrations, and seed two apps.
+    async fn seeded_db() -> sqlx::PgPool {
+        let db = crate::state::migrated_db().await;
+
+        let mut tastselv = sample_app();
+        tastselv.package_id = AppId::new("dk.skat.tastselv");
+        tastselv.name = Localized::single("en", "TastSelv".to_string());
+        tastselv.summary = Localized::single("da", "Selvbetjening hos Skattestyrelsen".to_string());
+
+        for app in [sample_app(), tastselv] {
+            sqlx::query(
+                "INSERT INTO apps (id, package_id, name, summary, description, categories, \
+                 version_code, version_name) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) \
+                 ON CONFLICT (package_id) DO NOTHING",
+            )
+            .bind(app.id)
+            .bind(app.package_id.as_str())
+            .bind(SqlJson(&app.name))
+            .bind(SqlJson(&app.summary))
+            .bind(SqlJson(&app.description))
+            .bind(vec!["Security".to_string()])
+            .bind(app.version_code)
+            .bind(&app.version_name)
+            .execute(&db)
+            .await
+            .expect("seed");
+        }
+
+        db
+    }
+
+    /// List apps from a seeded database with the given query.
+    async fn list_seeded(query: ListAppsQuery) -> AppsListResponse {
+        let db = seeded_db().await;
+        let state = AppState {
+            apps: Arc::new(PgAppRepository::new(db.clone())),
+            db,
+            ..AppState::disconnected()
+        };
+        let Json(response) = list_apps(State(state), Query(query))
+            .await
+            .expect("list apps");
+        response
+    }
+
+    fn search(q: &str) -> ListAppsQuery {
+        ListAppsQuery {
+            q: Some(q.to_string()),
+            ..ListAppsQuery::default()
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_list_apps_from_database() {
+        let response = list_seeded(ListAppsQuery {
+            locale: Some("da".to_string()),
+            ..ListAppsQuery::default()
+        })
+        .await;
+
+        let mitid = response
+            .apps
+            .iter()
+            .find(|summary| summary.package_id == "dk.digst.mitid")
+            .expect("seeded app listed");
+        assert_eq!(mitid.summary, "Digital identitet");
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_search_matches_name() {
+        let response = list_seeded(search("tastSELV")).await;
+        assert_eq!(response.total, 1);
+        assert_eq!(response.apps[0].package_id, "dk.skat.tastselv");
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_search_matches_summary() {
+        let response = list_seeded(search("skattestyrelsen")).await;
+        assert_eq!(response.total, 1);
+        assert_eq!(response.apps[0].package_id, "dk.skat.tastselv");
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_search_no_results() {
+        let response = list_seeded(search("no-such-app-anywhere")).await;
+        assert_eq!(response.total, 0);
+        assert!(response.apps.is_empty());
+        assert!(response.next_cursor.is_none());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_empty_search_lists_everything() {
+        let all = list_seeded(ListAppsQuery::default()).await;
+        let blank = list_seeded(search("")).await;
+        assert_eq!(all.total, blank.total);
+    }
+
+    /// Seed `package_id` in a migrated database with versions 1 and 2.
+    async fn seeded_versions(package_id: &str) -> AppState {
+        let db = seeded_db().await;
+        let mut app = sample_app();
+        app.package_id = AppId::new(package_id);
+
+        sqlx::query("DELETE FROM apps WHERE package_id = $1")
+            .bind(package_id)
+            .execute(&db)
+            .await
+            .expect("reset");
+        sqlx::query(
+            "INSERT INTO apps (id, package_id, name, summary, description, version_code, \
+             version_name) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        )
+        .bind(app.id)
+        .bind(app.package_id.as_str())
+        .bind(SqlJson(&app.name))
+        .bind(SqlJson(&app.summary))
+        .bind(SqlJson(&app.description))
+        .bind(app.version_code)
+        .bind(&app.version_name)
+        .execute(&db)
+        .await
+        .expect("seed app");
+        for version in [
+            sample_version(&app, 1, "1.0"),
+            sample_version(&app, 2, "2.0"),
+        ] {
+            sqlx::query(
+                "INSERT INTO app_versions (id, app_id, version_code, version_name, sha256, size, \
+                 min_sdk, target_sdk) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            )
+            .bind(version.id)
+            .bind(version.app_id)
+            .bind(version.version_code)
+            .bind(&version.version_name)
+            .bind(version.sha256.to_string())
+            .bind(version.size)
+            .bind(version.min_sdk)
+            .bind(version.target_sdk)
+            .execute(&db)
+            .await
+            .expect("seed version");
+        }
+
+        AppState {
+            apps: Arc::new(PgAppRepository::new(db.clone())),
+            db,
+            ..AppState::disconnected()
+        }
+    }
+
+    async fn version_codes(state: &AppState, package_id: &str) -> Vec<i64> {
+        let response = versions_since(state.clone(), package_id, None)
+            .await
+            .expect("versions");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        let versions: Vec<serde_json::Value> = serde_json::from_slice(&body).expect("json");
+        versions
+            .iter()
+            .filter_map(|version| version["version_code"].as_i64())
+            .collect()
+    }
+
+    async fn delete(
+        state: &AppState,
+        package_id: &str,
+        version_code: i64,
+    ) -> Result<StatusCode, ApiError> {
+        delete_version(
+            State(state.clone()),
+            Path((package_id.to_string(), version_code)),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_deleted_version_leaves_versions_endpoint() {
+        const PACKAGE: &str = "dk.example.pulled";
+        let state = seeded_versions(PACKAGE).await;
+        assert_eq!(version_codes(&state, PACKAGE).await, [2, 1]);
+
+        assert_eq!(
+            delete(&state, PACKAGE, 2).await.expect("delete"),
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(version_codes(&state, PACKAGE).await, [1]);
+        let unpublished = state.apps.last_unpublished(PACKAGE).await.expect("query");
+        assert!(unpublished.is_some());
+
+        // Already unpublished, or never published
+        assert!(matches!(
+            delete(&state, PACKAGE, 2).await,
+            Err(ApiError::VersionNotFound(_))
+        ));
+        assert!(matches!(
+            delete(&state, PACKAGE, 9).await,
+            Err(ApiError::VersionNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_deleted_version_leaves_index() {
+        use crate::routes::index::Repo;
+
+        const PACKAGE: &str = "dk.example.withdrawn";
+        let state = seeded_versions(PACKAGE).await;
+        let app_id = AppId::new(PACKAGE);
+
+        delete(&state, PACKAGE, 2).await.expect("delete");
+        let repo = Repo::load(&state.db).await.expect("load repo");
+        let codes: Vec<i64> = repo
+            .versions_of(&app_id)
+            .iter()
+            .map(|indexed| indexed.version.version_code)
+            .collect();
+        assert_eq!(codes, [1]);
+
+        // Deleting the only version keeps the app listed, with no versions
+        delete(&state, PACKAGE, 1).await.expect("delete");
+        let repo = Repo::load(&state.db).await.expect("load repo");
+        assert!(repo.apps.iter().any(|app| app.package_id == app_id));
+        assert!(repo.versions_of(&app_id).is_empty());
+        assert!(version_codes(&state, PACKAGE).await.is_empty());
+    }
+
+    #[test]
+    fn test_blank_abi_is_ignored() {
+        let query = |abi: &str| ListAppsQuery {
+            abi: Some(abi.to_string()),
+            ..ListAppsQuery::default()
+        };
+        assert_eq!(query(" arm64-v8a ").abi(), Some("arm64-v8a"));
+        assert_eq!(query("  ").abi(), None);
+        assert_eq!(ListAppsQuery::default().abi(), None);
+    }
+
+    /// List apps matching `package_id` for a device with `sdk` and `abi`.
+    async fn list_for_device(
+        state: &AppState,
+        package_id: &str,
+        sdk: Option<i32>,
+        abi: Option<&str>,
+    ) -> Vec<String> {
+        let query = ListAppsQuery {
+            q: Some(package_id.to_string()),
+            device_sdk: sdk,
+            abi: abi.map(ToString::to_string),
+            ..ListAppsQuery::default()
+        };
+        let Json(response) = list_apps(State(state.clone()), Query(query))
+            .await
+            .expect("list apps");
+        response
+            .apps
+            .into_iter()
+            .map(|summary| summary.package_id)
+            .collect()
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_app_without_compatible_sdk_is_hidden() {
+        const PACKAGE: &str = "dk.example.sdk30";
+        let state = seeded_versions(PACKAGE).await;
+        sqlx::query(
+            "UPDATE app_versions SET min_sdk = 30 \
+             WHERE app_id = (SELECT id FROM apps WHERE package_id = $1)",
+        )
+        .bind(PACKAGE)
+        .execute(&state.db)
+        .await
+        .expect("require SDK 30");
+
+        assert!(list_for_device(&state, PACKAGE, Some(24), None)
+            .await
+            .is_empty());
+        assert_eq!(
+            list_for_device(&state, PACKAGE, Some(30), None).await,
+            [PACKAGE]
+        );
+        assert_eq!(
+            list_for_device(&state, PACKAGE, None, None).await,
+            [PACKAGE]
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_device_filter_picks_latest_compatible_version() {
+        const PACKAGE: &str = "dk.example.native";
+        let state = seeded_versions(PACKAGE).await;
+        sqlx::query(
+            "UPDATE app_versions SET abis = '{arm64-v8a}' \
+             WHERE version_code = 2 AND app_id = (SELECT id FROM apps WHERE package_id = $1)",
+        )
+        .bind(PACKAGE)
+        .execute(&state.db)
+        .await
+        .expect("make version 2 native");
+
+        let query = ListAppsQuery {
+            q: Some(PACKAGE.to_string()),
+            abi: Some("x86_64".to_string()),
+            ..ListAppsQuery::default()
+        };
+        let Json(response) = list_apps(State(state.clone()), Query(query))
+            .await
+            .expect("list apps");
+        // Version 2 has no x86_64 libraries; pure-Java version 1 runs anywhere
+        assert_eq!(response.apps[0].version_code, 1);
+        assert_eq!(
+            list_for_device(&state, PACKAGE, None, Some("arm64-v8a")).await,
+            [PACKAGE]
+        );
+    }
+
+    /// Field values recorded by a span or event, formatted with `Debug`.
+    #[derive(Default)]
+    struct Fields(BTreeMap<String, String>);
+
+    impl tracing::field::Visit for Fields {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{value:?}"));
+        }
+    }
+
+    /// Name and fields of a span.
+    type SpanFields = (String, BTreeMap<String, String>);
+
+    /// Layer capturing the fields of new spans and the spans errors are
+    /// logged in.
+    #[derive(Clone, Default)]
+    struct Capture {
+        spans: Arc<Mutex<Vec<SpanFields>>>,
+        error_spans: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl<S> Layer<S> for Capture
+    where
+        S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+    {
+        fn on_new_span(&self, attrs: &span::Attributes<'_>, _: &span::Id, _: Context<'_, S>) {
+            let mut fields = Fields::default();
+            attrs.record(&mut fields);
+            let name = attrs.metadata().name().to_string();
+            self.spans.lock().expect("spans").push((name, fields.0));
+        }
+
+        fn on_event(&self, event: &tracing::Event<'_>, ctx: Context<'_, S>) {
+            let mut fields = Fields::default();
+            event.record(&mut fields);
+            if !fields.0.contains_key("error") {
+                return;
+            }
+            if let Some(span) = ctx.event_span(event) {
+                self.error_spans
+                    .lock()
+                    .expect("error spans")
+                    .push(span.name().to_string());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_version_handler_span_fields() {
+        let capture = Capture::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(capture.clone()));
+
+        let result = delete_version(
+            State(AppState::disconnected()),
+            Path(("dk.digst.mitid".to_string(), 42)),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiError::Internal(_))));
+
+        let (_, fields) = capture
+            .spans
+            .lock()
+            .expect("spans")
+            .iter()
+            .find(|(name, _)| name == "delete_version")
+            .cloned()
+            .expect("handler span");
+        assert_eq!(fields["package_id"], "dk.digst.mitid");
+        assert_eq!(fields["version_code"], "42");
+        // The error is logged inside the handler span
+        assert!(capture
+            .error_spans
+            .lock()
+            .expect("error spans")
+            .iter()
+            .any(|name| name == "delete_version"));
+    }
 }
@@ -0,0 +1,356 @@
+//! Build status and log streaming endpoints.
+
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, State},
+    response::sse::{Event, KeepAlive, Sse},
+    Json,
+};
+use dk_build::{BuildId, BuildRecord, LogEvent, LogSubscription};
+use dk_common::types::{BuildStatus, Sha256};
+use futures_util::stream::{self, Stream};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::routes::apps::parse_package_id;
+use crate::state::AppState;
+
+/// Interval of keep-alive comments on idle log streams.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Number of builds listed per app, newest first.
+const RECENT_BUILDS: usize = 20;
+
+/// The stored APK of a successful build.
+#[derive(Serialize)]
+pub struct ArtifactResponse {
+    /// Storage key of the APK.
+    key: String,
+    /// Lowercase hex SHA-256 of the APK.
+    sha256: Sha256,
+}
+
+/// Status and timing of a build.
+#[derive(Serialize)]
+pub struct BuildResponse {
+    build_id: String,
+    package_id: String,
+    status: BuildStatus,
+    /// When the build was queued, in RFC 3339.
+    queued_at: String,
+    started_at: Option<String>,
+    /// When the build succeeded, failed, or was cancelled.
+    finished_at: Option<String>,
+    /// Seconds from start to finish, once finished.
+    duration_secs: Option<i64>,
+    /// The stored APK, once the build succeeded.
+    artifact: Option<ArtifactResponse>,
+}
+
+impl BuildResponse {
+    /// Build the response view of `record`.
+    fn from_record(record: BuildRecord) -> Self {
+        let duration_secs = record
+            .started_at
+            .zip(record.finished_at)
+            .map(|(started, finished)| (finished - started).num_seconds());
+        Self {
+            build_id: record.build_id.to_string(),
+            package_id: record.package_id.to_string(),
+            status: record.status,
+            queued_at: record.queued_at.to_rfc3339(),
+            started_at: record.started_at.map(|at| at.to_rfc3339()),
+            finished_at: record.finished_at.map(|at| at.to_rfc3339()),
+            duration_secs,
+            artifact: record.artifact.map(|artifact| ArtifactResponse {
+                key: artifact.key,
+                sha256: artifact.sha256,
+            }),
+        }
+    }
+}
+
+/// Response for listing an app's builds.
+#[derive(Serialize)]
+pub struct BuildsListResponse {
+    builds: Vec<BuildResponse>,
+}
+
+/// Get the status of a build.
+///
+/// `GET /api/v1/builds/:build_id`
+pub async fn get_build(
+    State(state): State<AppState>,
+    Path(build_id): Path<String>,
+) -> Result<Json<BuildResponse>, ApiError> {
+    let id = parse_build_id(&build_id)?;
+    let record = state
+        .builds
+        .get(id)
+        .ok_or_else(|| ApiError::NotFound(format!("Build not found: {build_id}")))?;
+    Ok(Json(BuildResponse::from_record(record)))
+}
+
+/// List the most recent builds of an app, newest first.
+///
+/// `GET /api/v1/apps/:package_id/builds`
+pub async fn list_app_builds(
+    State(state): State<AppState>,
+    Path(package_id): Path<String>,
+) -> Result<Json<BuildsListResponse>, ApiError> {
+    let app_id = parse_package_id(&package_id)?;
+    let builds = state
+        .builds
+        .builds_for(&app_id, RECENT_BUILDS)
+        .into_iter()
+        .map(BuildResponse::from_record)
+        .collect();
+    Ok(Json(BuildsListResponse { builds }))
+}
+
+/// Parse a build ID path segment.
+fn parse_build_id(build_id: &str) -> Result<BuildId, ApiError> {
+    Uuid::parse_str(build_id)
+        .map(BuildId::from_uuid)
+        .map_err(|_| ApiError::BadRequest(format!("Invalid build ID: {build_id}")))
+}
+
+/// Data of the terminal `end` event.
+#[derive(Serialize)]
+struct EndEvent {
+    status: BuildStatus,
+}
+
+/// Stream the output of a build as Server-Sent Events.
+///
+/// `GET /api/v1/builds/:build_id/logs`
+///
+/// Each output line is sent as a `log` event. Output captured before the
+/// client connected is replayed first; a running build is then followed
+/// until it finishes. A final `end` event carries the build status, after
+/// which the stream closes.
+pub async fn stream_build_logs(
+    State(state): State<AppState>,
+    Path(build_id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, ApiError> {
+    let id = parse_build_id(&build_id)?;
+    let log = state
+        .build_logs
+        .get(id.as_uuid())
+        .ok_or_else(|| ApiError::NotFound(format!("Build not found: {build_id}")))?;
+
+    Ok(
+        Sse::new(events(log.subscribe()))
+            .keep_alive(KeepAlive::new().interval(KEEP_ALIVE_INTERVAL)),
+    )
+}
+
+/// SSE events for the log read by `subscription`.
+fn events(subscription: LogSubscription) -> impl Stream<Item = Result<Event, axum::Error>> {
+    stream::unfold(subscription, |mut subscription| async move {
+        let event = match subscription.next().await? {
+            LogEvent::Line(line) => Ok(Event::default().event("log").data(line)),
+            LogEvent::Finished(status) => {
+                Event::default().event("end").json_data(EndEvent { status })
+            }
+        };
+        Some((event, subscription))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::{Path as FsPath, PathBuf};
+    use std::sync::Arc;
+
+    use axum::response::IntoResponse;
+    use dk_build::{ArtifactStore, BuildQueue, BuildSpec};
+    use dk_common::config::BuildConfig;
+    use dk_common::storage::FilesystemStorage;
+    use dk_common::types::AppId;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::routes::download::tests::TempStorage;
+
+    /// State whose builds run `script` in place of the container runtime,
+    /// on at most `slots` builds at a time, storing APKs under `dir`.
+    fn build_state(dir: &FsPath, script: &str, slots: usize) -> AppState {
+        let runtime = dir.join("fake-runtime");
+        std::fs::write(
+            &runtime,
+            format!("#!/bin/sh\ncase \"$1\" in\nrun)\n{script}\n;;\nesac\n"),
+        )
+        .expect("write runtime");
+        std::fs::set_permissions(&runtime, std::fs::Permissions::from_mode(0o755))
+            .expect("chmod runtime");
+
+        let state = AppState::disconnected();
+        let config = BuildConfig {
+            container_runtime: runtime.display().to_string(),
+            max_concurrent_builds: slots,
+            ..BuildConfig::default()
+        };
+        let storage = Arc::new(FilesystemStorage::new(dir.join("storage")));
+        let queue = BuildQueue::new(config, Arc::clone(&state.build_logs))
+            .with_artifacts(ArtifactStore::new(storage));
+        AppState {
+            builds: Arc::new(queue),
+            ..state
+        }
+    }
+
+    fn build_spec(source_dir: PathBuf) -> BuildSpec {
+        std::fs::create_dir_all(&source_dir).expect("source dir");
+        BuildSpec {
+            package_id: AppId::new("dk.digst.mitid"),
+            source_url: "https://git.example/mitid.git".to_string(),
+            git_ref: "v1.0".to_string(),
+            image: "docker.io/example/android-build@sha256:0000".to_string(),
+            source_dir,
+            build_commands: vec!["./gradlew --no-daemon assembleRelease".to_string()],
+            output_apk_path: PathBuf::from("app-release.apk"),
+            timeout_secs: None,
+            env: std::collections::BTreeMap::new(),
+        }
+    }
+
+    async fn build_json(state: &AppState, build_id: BuildId) -> Value {
+        let Json(build) = get_build(State(state.clone()), Path(build_id.to_string()))
+            .await
+            .expect("build");
+        serde_json::to_value(build).expect("json")
+    }
+
+    #[tokio::test]
+    async fn test_pending_build_status() {
+        let dir = TempStorage::new();
+        // Without build slots, queued builds stay pending
+        let state = build_state(&dir.dir, "exit 1", 0);
+        let id = state.builds.enqueue(build_spec(dir.dir.join("src")));
+
+        let build = build_json(&state, id).await;
+        assert_eq!(build["build_id"], id.to_string());
+        assert_eq!(build["package_id"], "dk.digst.mitid");
+        assert_eq!(build["status"], "pending");
+        assert!(build["queued_at"].is_string());
+        for field in ["started_at", "finished_at", "duration_secs", "artifact"] {
+            assert!(build[field].is_null(), "{field}: {build}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_completed_build_references_artifact() {
+        let dir = TempStorage::new();
+        let source_dir = dir.dir.join("src");
+        let script = format!(
+            "printf apk > '{}'",
+            source_dir.join("app-release.apk").display()
+        );
+        let state = build_state(&dir.dir, &script, 1);
+        let id = state.builds.enqueue(build_spec(source_dir));
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while state.builds.status(id) != Some(BuildStatus::Success) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("build finished in time");
+
+        let build = build_json(&state, id).await;
+        assert_eq!(build["status"], "success");
+        assert!(build["started_at"].is_string());
+        assert!(build["finished_at"].is_string());
+        assert!(build["duration_secs"].is_i64());
+        assert_eq!(build["artifact"]["key"], format!("builds/{id}.apk"));
+        assert_eq!(
+            build["artifact"]["sha256"],
+            dk_common::hash::sha256_bytes(b"apk").to_string()
+        );
+
+        let Json(list) = list_app_builds(State(state.clone()), Path("dk.digst.mitid".to_string()))
+            .await
+            .expect("builds");
+        let list = serde_json::to_value(list).expect("json");
+        assert_eq!(list["builds"], Value::Array(vec![build]));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_build_status_is_not_found() {
+        let result = get_build(
+            State(AppState::disconnected()),
+            Path(Uuid::new_v4().to_string()),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiError::NotFound(_))));
+    }
+
+    async fn body(state: AppState, build_id: String) -> String {
+        let response = stream_build_logs(State(state), Path(build_id))
+            .await
+            .expect("stream")
+            .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        String::from_utf8(body.to_vec()).expect("utf-8")
+    }
+
+    #[tokio::test]
+    async fn test_finished_build_replays_logs_then_ends() {
+        let state = AppState::disconnected();
+        let id = Uuid::new_v4();
+        let log = state.build_logs.start(id);
+        log.push("> Task :app:assembleRelease");
+        log.push("BUILD SUCCESSFUL in 42s");
+        log.finish(BuildStatus::Success);
+
+        assert_eq!(
+            body(state, id.to_string()).await,
+            "event: log\ndata: > Task :app:assembleRelease\n\n\
+             event: log\ndata: BUILD SUCCESSFUL in 42s\n\n\
+             event: end\ndata: {\"status\":\"success\"}\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_running_build_streams_until_finished() {
+        let state = AppState::disconnected();
+        let id = Uuid::new_v4();
+        let log = state.build_logs.start(id);
+        log.push("started");
+
+        let writer = std::sync::Arc::clone(&log);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            writer.push("compiling");
+            writer.finish(BuildStatus::Failed);
+        });
+
+        let body = body(state, id.to_string()).await;
+        assert!(body.contains("data: started\n\n"));
+        assert!(body.contains("data: compiling\n\n"));
+        assert!(body.ends_with("event: end\ndata: {\"status\":\"failed\"}\n\n"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_build_is_not_found() {
+        let result = stream_build_logs(
+            State(AppState::disconnected()),
+            Path(Uuid::new_v4().to_string()),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_build_id_is_bad_request() {
+        let result =
+            stream_build_logs(State(AppState::disconnected()), Path("latest".to_string())).await;
+        assert!(matches!(result, Err(ApiError::BadRequest(_))));
+    }
+}
@@ -0,0 +1,131 @@
+//! Category browsing endpoint.
+
+use axum::{extract::State, Json};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::error::ApiError;
+use crate::state::AppState;
+
+/// A category and the number of apps in it.
+#[derive(Debug, PartialEq, Eq, Serialize, ToSchema)]
+pub struct CategoryCount {
+    /// Display name of the category, e.g. `Public Services`.
+    name: String,
+    /// Number of apps in the category.
+    count: usize,
+}
+
+/// Categories in the repository.
+#[derive(Serialize, ToSchema)]
+pub struct CategoriesResponse {
+    /// Categories with at least one app, the largest first.
+    categories: Vec<CategoryCount>,
+}
+
+/// List the categories apps are filed under, with their app counts.
+///
+/// GET /api/v1/categories
+///
+/// Ordered by count, largest first, then by name. Categories no app is in
+/// are left out.
+#[utoipa::path(
+    get,
+    path = "/categories",
+    tag = "apps",
+    responses(
+        (status = 200, description = "Categories in use", body = CategoriesResponse),
+    )
+)]
+pub async fn list_categories(
+    State(state): State<AppState>,
+) -> Result<Json<CategoriesResponse>, ApiError> {
+    let mut categories: Vec<CategoryCount> = state
+        .apps
+        .category_counts()
+        .await?
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(category, count)| CategoryCount {
+            name: category.to_string(),
+            count,
+        })
+        .collect();
+    categories.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+
+    Ok(Json(CategoriesResponse { categories }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::Utc;
+    use dk_common::localized::Localized;
+    use dk_common::types::{App, AppId, Category};
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::repository::InMemoryAppRepository;
+
+    fn app(package_id: &str, categories: Vec<Category>) -> App {
+        App {
+            id: Uuid::new_v4(),
+            package_id: AppId::new(package_id),
+            name: Localized::single("en", package_id.to_string()),
+            summary: Localized::default(),
+            description: Localized::default(),
+            categories,
+            screenshots: vec![],
+            antifeatures: vec![],
+            license: None,
+            source_url: None,
+            issue_tracker_url: None,
+            web_url: None,
+            donate: vec![],
+            version_code: 1,
+            version_name: "1.0".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn count(name: &str, count: usize) -> CategoryCount {
+        CategoryCount {
+            name: name.to_string(),
+            count,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_categories_counted_largest_first() {
+        let repository = InMemoryAppRepository::default();
+        repository.insert_app(app(
+            "dk.digst.mitid",
+            vec![Category::Security, Category::PublicServices],
+        ));
+        repository.insert_app(app("dk.skat.tastselv", vec![Category::PublicServices]));
+        repository.insert_app(app("dk.example.notes", vec![Category::Writing]));
+        repository.insert_app(app("dk.example.uncategorized", vec![]));
+        let state = AppState {
+            apps: Arc::new(repository),
+            ..AppState::disconnected()
+        };
+
+        let Json(response) = list_categories(State(state)).await.expect("categories");
+        assert_eq!(
+            response.categories,
+            [
+                count("Public Services", 2),
+                count("Security", 1),
+                count("Writing", 1)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_categories_database_error_is_internal() {
+        let result = list_categories(State(AppState::disconnected())).await;
+        assert!(matches!(result, Err(ApiError::Internal(_))));
+    }
+}
@@ -0,0 +1,531 @@
+//! APK download endpoint.
+
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, HeaderMap, Method, StatusCode},
+    response::{IntoResponse, Response},
+};
+use dk_common::hash::sha256_reader;
+use dk_common::types::Sha256;
+use tokio_util::io::ReaderStream;
+
+use crate::error::ApiError;
+use crate::routes::apps::{app_not_found, parse_package_id, version_not_found};
+use crate::state::AppState;
+
+/// MIME type of Android application packages.
+pub const APK_CONTENT_TYPE: &str = "application/vnd.android.package-archive";
+
+/// The portion of a file selected by a `Range` request header.
+#[derive(Debug, PartialEq, Eq)]
+enum ByteRange {
+    /// The whole file; no usable range was requested.
+    Full,
+    /// An inclusive byte range within the file.
+    Partial {
+        /// First byte offset.
+        start: u64,
+        /// Last byte offset (inclusive).
+        end: u64,
+    },
+    /// The requested range lies outside the file.
+    Unsatisfiable,
+}
+
+/// Parse a single-range `Range` header against a file of `size` bytes.
+///
+/// Supports `bytes=start-end`, `bytes=start-` and `bytes=-suffix`. Malformed
+/// headers and multi-range requests are ignored and served as the full file.
+fn parse_range(value: Option<&str>, size: u64) -> ByteRange {
+    let Some(spec) = value.and_then(|value| value.trim().strip_prefix("bytes=")) else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((first, last)) = spec.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+
+    if first.is_empty() {
+        // Suffix range: the final `last` bytes
+        return match last.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if size == 0 => ByteRange::Unsatisfiable,
+            Ok(suffix) => ByteRange::Partial {
+                start: size.saturating_sub(suffix),
+                end: size - 1,
+            },
+            Err(_) => ByteRange::Full,
+        };
+    }
+
+    let Ok(start) = first.parse::<u64>() else {
+        return ByteRange::Full;
+    };
+    let end = if last.is_empty() {
+        None
+    } else {
+        match last.parse::<u64>() {
+            Ok(end) if end >= start => Some(end),
+            _ => return ByteRange::Full,
+        }
+    };
+
+    if start >= size {
+        return ByteRange::Unsatisfiable;
+    }
+
+    ByteRange::Partial {
+        start,
+        end: end.map_or(size - 1, |end| end.min(size - 1)),
+    }
+}
+
+/// Download the APK for a specific application version.
+///
+/// `GET /api/v1/apps/:package_id/versions/:version_code/download`
+///
+/// Streams from the configured storage backend and honours a single
+/// `Range` header so interrupted downloads can resume. A `HEAD` request
+/// gets the same headers without opening the object. Only published
+/// versions are served; an unpublished APK stays in storage but is 404.
+#[utoipa::path(
+    get,
+    path = "/apps/{package_id}/versions/{version_code}/download",
+    tag = "apps",
+    params(
+        ("package_id" = String, Path, description = "Package identifier, e.g. `dk.digst.mitid`"),
+        ("version_code" = i64, Path, description = "Android versionCode of the version"),
+    ),
+    responses(
+        (
+            status = 200,
+            description = "The APK",
+            content_type = "application/vnd.android.package-archive",
+        ),
+        (status = 206, description = "The requested byte range of the APK"),
+        (status = 404, description = "No such published version", body = ErrorResponse),
+        (status = 416, description = "The requested range lies outside the APK"),
+    )
+)]
+#[tracing::instrument(
+    skip_all,
+    fields(package_id = %package_id, version_code = version_code),
+    err(level = "info", Debug)
+)]
+pub async fn download_apk(
+    State(state): State<AppState>,
+    method: Method,
+    Path((package_id, version_code)): Path<(String, i64)>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    // Validating the package ID also keeps the key inside the APK namespace
+    let app_id = parse_package_id(&package_id)?;
+    let published = state
+        .apps
+        .get_versions(app_id.as_str())
+        .await?
+        .ok_or_else(|| app_not_found(&package_id))?
+        .into_iter()
+        .find(|version| version.version_code == version_code)
+        .ok_or_else(|| version_not_found(&package_id, version_code))?;
+
+    let key = app_id.apk_file_name(version_code);
+    let storage_error = |err| match err {
+        dk_common::Error::NotFound(_) => {
+            ApiError::NotFound(format!("APK not found: {package_id} {version_code}"))
+        }
+        err => ApiError::from(err),
+    };
+
+    let size = state.storage.head(&key).await.map_err(storage_error)?.size;
+
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok());
+    let (status, start, len) = match parse_range(range, size) {
+        ByteRange::Full => (StatusCode::OK, 0, size),
+        ByteRange::Partial { start, end } => (StatusCode::PARTIAL_CONTENT, start, end - start + 1),
+        ByteRange::Unsatisfiable => {
+            return Ok((
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [
+                    (header::ACCEPT_RANGES, "bytes".to_string()),
+                    (header::CONTENT_RANGE, format!("bytes */{size}")),
+                ],
+            )
+                .into_response());
+        }
+    };
+
+    let body = if method == Method::HEAD {
+        Body::empty()
+    } else {
+        if state.verify_downloads {
+            verify_integrity(&state, &key, &published.sha256).await?;
+        }
+        let range = (status == StatusCode::PARTIAL_CONTENT).then_some(start..start + len);
+        let object = state
+            .storage
+            .get(&key, range)
+            .await
+            .map_err(storage_error)?;
+        Body::from_stream(ReaderStream::new(object))
+    };
+
+    let mut response = (
+        status,
+        [
+            (header::CONTENT_TYPE, APK_CONTENT_TYPE.to_string()),
+            (header::CONTENT_LENGTH, len.to_string()),
+            (header::ACCEPT_RANGES, "bytes".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{key}\""),
+            ),
+        ],
+        body,
+    )
+        .into_response();
+
+    if status == StatusCode::PARTIAL_CONTENT {
+        let content_range = format!("bytes {start}-{}/{size}", start + len - 1);
+        if let Ok(value) = content_range.parse() {
+            response.headers_mut().insert(header::CONTENT_RANGE, value);
+        }
+    }
+
+    Ok(response)
+}
+
+/// Check the stored APK at `key` against the digest `expected` recorded
+/// when it was published.
+async fn verify_integrity(state: &AppState, key: &str, expected: &Sha256) -> Result<(), ApiError> {
+    let actual = sha256_reader(state.storage.get(key, None).await?).await?;
+    if actual != *expected {
+        tracing::error!(%key, %expected, %actual, "Stored APK does not match its digest");
+        return Err(ApiError::Internal(format!(
+            "APK failed integrity check: {key}"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use axum::http::StatusCode;
+    use dk_common::hash::sha256_bytes;
+    use dk_common::storage::FilesystemStorage;
+    use dk_common::types::{App, AppId};
+
+    use super::*;
+    use crate::repository::InMemoryAppRepository;
+    use crate::routes::apps::delete_version;
+    use crate::routes::apps::tests::{sample_app, sample_version};
+
+    /// A temporary storage directory, removed on drop, with the versions
+    /// seeded into it published in `apps`.
+    pub struct TempStorage {
+        pub dir: PathBuf,
+        pub apps: Arc<InMemoryAppRepository>,
+    }
+
+    impl TempStorage {
+        pub fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("dk-api-test-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).expect("create temp dir");
+            Self {
+                dir,
+                apps: Arc::default(),
+            }
+        }
+
+        /// Write and publish an APK of `len` bytes for
+        /// `package_id`/`version_code`.
+        pub fn seed(&self, package_id: &str, version_code: i64, len: usize) -> Vec<u8> {
+            let bytes: Vec<u8> = (0..=u8::MAX).cycle().take(len).collect();
+            let app_id = AppId::new(package_id);
+            std::fs::write(self.dir.join(app_id.apk_file_name(version_code)), &bytes)
+                .expect("write apk");
+
+            let app = self.apps.app(package_id).unwrap_or_else(|| {
+                let app = App {
+                    package_id: app_id,
+                    ..sample_app()
+                };
+                self.apps.insert_app(app.clone());
+                app
+            });
+            let mut version = sample_version(&app, version_code, "1.2.3");
+            version.sha256 = sha256_bytes(&bytes);
+            self.apps.insert_version(version);
+            bytes
+        }
+
+        pub fn state(&self) -> AppState {
+            AppState {
+                storage: Arc::new(FilesystemStorage::new(&self.dir)),
+                apps: self.apps.clone(),
+                ..AppState::disconnected()
+            }
+        }
+    }
+
+    impl Drop for TempStorage {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_seeded_apk() {
+        let storage = TempStorage::new();
+        let bytes = storage.seed("dk.digst.mitid", 123, 4096);
+
+        let response = download_apk(
+            State(storage.state()),
+            Method::GET,
+            Path(("dk.digst.mitid".to_string(), 123)),
+            HeaderMap::new(),
+        )
+        .await
+        .expect("download");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(headers[header::CONTENT_TYPE], APK_CONTENT_TYPE);
+        assert_eq!(headers[header::CONTENT_LENGTH], "4096");
+        assert_eq!(
+            headers[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"dk.digst.mitid_123.apk\""
+        );
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        assert_eq!(body.len(), 4096);
+        assert_eq!(body.as_ref(), bytes.as_slice());
+    }
+
+    #[tokio::test]
+    async fn test_head_matches_get_without_body() {
+        let storage = TempStorage::new();
+        storage.seed("dk.digst.mitid", 123, 4096);
+        let download = |method| {
+            download_apk(
+                State(storage.state()),
+                method,
+                Path(("dk.digst.mitid".to_string(), 123)),
+                HeaderMap::new(),
+            )
+        };
+
+        let get = download(Method::GET).await.expect("get");
+        let head = download(Method::HEAD).await.expect("head");
+
+        assert_eq!(head.status(), StatusCode::OK);
+        assert_eq!(head.headers(), get.headers());
+        assert_eq!(head.headers()[header::CONTENT_LENGTH], "4096");
+        let body = axum::body::to_bytes(head.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_download_missing_apk() {
+        let storage = TempStorage::new();
+
+        let result = download_apk(
+            State(storage.state()),
+            Method::GET,
+            Path(("dk.digst.mitid".to_string(), 1)),
+            HeaderMap::new(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(ApiError::AppNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_download_of_unpublished_version_is_not_found() {
+        let storage = TempStorage::new();
+        storage.seed("dk.digst.mitid", 123, 4096);
+        // Only the version record goes; the APK stays in storage
+        let deleted = delete_version(
+            State(storage.state()),
+            Path(("dk.digst.mitid".to_string(), 123)),
+        )
+        .await
+        .expect("delete");
+        assert_eq!(deleted, StatusCode::NO_CONTENT);
+
+        for method in [Method::GET, Method::HEAD] {
+            let result = download_apk(
+                State(storage.state()),
+                method,
+                Path(("dk.digst.mitid".to_string(), 123)),
+                HeaderMap::new(),
+            )
+            .await;
+
+            assert!(matches!(result, Err(ApiError::VersionNotFound(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_of_unlisted_apk_is_not_found() {
+        let storage = TempStorage::new();
+        storage.seed("dk.digst.mitid", 123, 4096);
+        std::fs::write(storage.dir.join("dk.digst.mitid_124.apk"), b"unpublished")
+            .expect("write apk");
+
+        let result = verified_download(verifying_state(&storage), 124).await;
+
+        assert_eq!(result.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_download_rejects_invalid_package_id() {
+        let storage = TempStorage::new();
+
+        let result = download_apk(
+            State(storage.state()),
+            Method::GET,
+            Path(("../etc/passwd".to_string(), 1)),
+            HeaderMap::new(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(ApiError::InvalidPackageId(_))));
+    }
+
+    /// Download the seeded 4096-byte APK with the given `Range` header.
+    async fn download_range(storage: &TempStorage, range: &str) -> Response {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, range.parse().expect("header value"));
+
+        download_apk(
+            State(storage.state()),
+            Method::GET,
+            Path(("dk.digst.mitid".to_string(), 123)),
+            headers,
+        )
+        .await
+        .expect("download")
+    }
+
+    #[tokio::test]
+    async fn test_download_mid_file_range() {
+        let storage = TempStorage::new();
+        let bytes = storage.seed("dk.digst.mitid", 123, 4096);
+
+        let response = download_range(&storage, "bytes=100-199").await;
+
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            response.headers()[header::CONTENT_RANGE],
+            "bytes 100-199/4096"
+        );
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "100");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        assert_eq!(body.as_ref(), &bytes[100..200]);
+    }
+
+    #[tokio::test]
+    async fn test_download_open_ended_range() {
+        let storage = TempStorage::new();
+        let bytes = storage.seed("dk.digst.mitid", 123, 4096);
+
+        let response = download_range(&storage, "bytes=500-").await;
+
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            response.headers()[header::CONTENT_RANGE],
+            "bytes 500-4095/4096"
+        );
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "3596");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        assert_eq!(body.as_ref(), &bytes[500..]);
+    }
+
+    #[tokio::test]
+    async fn test_download_out_of_bounds_range() {
+        let storage = TempStorage::new();
+        storage.seed("dk.digst.mitid", 123, 4096);
+
+        let response = download_range(&storage, "bytes=5000-6000").await;
+
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes */4096");
+    }
+
+    /// State over `storage` verifying downloads.
+    fn verifying_state(storage: &TempStorage) -> AppState {
+        AppState {
+            verify_downloads: true,
+            ..storage.state()
+        }
+    }
+
+    async fn verified_download(state: AppState, version_code: i64) -> Response {
+        download_apk(
+            State(state),
+            Method::GET,
+            Path(("dk.digst.mitid".to_string(), version_code)),
+            HeaderMap::new(),
+        )
+        .await
+        .into_response()
+    }
+
+    #[tokio::test]
+    async fn test_verified_download_of_intact_apk() {
+        let storage = TempStorage::new();
+        storage.seed("dk.digst.mitid", 123, 4096);
+
+        let response = verified_download(verifying_state(&storage), 123).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_verified_download_of_corrupted_apk_fails() {
+        let storage = TempStorage::new();
+        let mut bytes = storage.seed("dk.digst.mitid", 123, 4096);
+        let state = verifying_state(&storage);
+        bytes[1000] ^= 0xff;
+        std::fs::write(storage.dir.join("dk.digst.mitid_123.apk"), &bytes).expect("corrupt apk");
+
+        let response = verified_download(state, 123).await;
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range(None, 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("items=0-1"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=9-3"), 100), ByteRange::Full);
+        assert_eq!(
+            parse_range(Some("bytes=10-1000"), 100),
+            ByteRange::Partial { start: 10, end: 99 }
+        );
+        assert_eq!(
+            parse_range(Some("bytes=-30"), 100),
+            ByteRange::Partial { start: 70, end: 99 }
+        );
+        assert_eq!(parse_range(Some("bytes=-0"), 100), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=0-"), 0), ByteRange::Unsatisfiable);
+    }
+}
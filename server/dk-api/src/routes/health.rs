@@ -1,46 +1,308 @@
 //! Health check endpoints.
 
-use axum::Json;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{extract::State, http::StatusCode, Json};
+use dk_common::storage::Storage;
+use dk_signing::HsmSigner;
 use serde::Serialize;
 
+use crate::state::AppState;
+
+/// Maximum time a single dependency check may take.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Storage key looked up by the storage check; it need not exist.
+const STORAGE_PROBE_KEY: &str = "health/probe";
+
+/// Commit the server was built from, or `"unknown"`.
+pub const GIT_SHA: &str = env!("DK_GIT_SHA");
+
+/// When the server was built, in RFC 3339, or `"unknown"`.
+pub const BUILD_TIMESTAMP: &str = env!("DK_BUILD_TIMESTAMP");
+
+/// Compiler the server was built with, or `"unknown"`.
+pub const RUST_VERSION: &str = env!("DK_RUSTC_VERSION");
+
 /// Health check response.
 #[derive(Serialize)]
 pub struct HealthResponse {
     status: &'static str,
     version: &'static str,
+    git_sha: &'static str,
+    build_timestamp: &'static str,
+    rust_version: &'static str,
+}
+
+impl HealthResponse {
+    /// Response with `status` and the build information of this server.
+    const fn new(status: &'static str) -> Self {
+        Self {
+            status,
+            version: env!("CARGO_PKG_VERSION"),
+            git_sha: GIT_SHA,
+            build_timestamp: BUILD_TIMESTAMP,
+            rust_version: RUST_VERSION,
+        }
+    }
+}
+
+/// Readiness check response.
+#[derive(Serialize)]
+pub struct ReadinessResponse {
+    status: &'static str,
+    version: &'static str,
+    /// Outcome per dependency: `"ok"` or the failure reason.
+    checks: BTreeMap<&'static str, String>,
+    /// Dependencies that failed their check.
+    failed: Vec<&'static str>,
+}
+
+/// Health of one dependency.
+#[derive(Serialize)]
+pub struct DependencyHealth {
+    /// `"up"`, `"down"`, or `"not_configured"`.
+    status: &'static str,
+    /// Whether the overall status depends on this dependency.
+    required: bool,
+    /// Duration of the check; absent if the dependency is not configured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency_ms: Option<u64>,
+}
+
+/// Dependency health matrix response.
+#[derive(Serialize)]
+pub struct DependenciesResponse {
+    /// Whether every required dependency is up.
+    healthy: bool,
+    dependencies: BTreeMap<&'static str, DependencyHealth>,
+}
+
+/// Outcome of checking one dependency.
+struct Probe {
+    name: &'static str,
+    required: bool,
+    /// Check result and duration, or `None` if the dependency is not
+    /// configured.
+    outcome: Option<(Result<(), String>, Duration)>,
 }
 
 /// Basic health check endpoint.
 ///
-/// Returns OK if the service is running.
+/// Returns OK if the service is running, with the version and build
+/// information so operators can tell what is deployed.
 pub async fn health_check() -> Json<HealthResponse> {
-    Json(HealthResponse {
-        status: "ok",
-        version: env!("CARGO_PKG_VERSION"),
-    })
+    Json(HealthResponse::new("ok"))
 }
 
 /// Readiness check endpoint.
 ///
-/// Returns OK if the service is ready to accept traffic.
-/// In production, this should check database connectivity.
-pub async fn readiness_check() -> Json<HealthResponse> {
-    // TODO: Check database and Redis connectivity
-    Json(HealthResponse {
-        status: "ready",
-        version: env!("CARGO_PKG_VERSION"),
-    })
+/// Checks PostgreSQL and Redis connectivity concurrently, each bounded by
+/// [`CHECK_TIMEOUT`]. Returns 503 naming the failed dependencies if either
+/// check fails.
+pub async fn readiness_check(
+    State(state): State<AppState>,
+) -> (StatusCode, Json<ReadinessResponse>) {
+    let (postgres, redis) = tokio::join!(
+        bounded(query_postgres(&state.db)),
+        bounded(ping_redis(&state.redis)),
+    );
+
+    readiness([("postgres", postgres), ("redis", redis)])
+}
+
+/// Dependency health matrix endpoint.
+///
+/// `GET /health/deps`
+///
+/// Checks PostgreSQL, Redis, storage, and the HSM holding the repository
+/// signing key concurrently, reporting each one's status and check latency.
+/// Returns 503 if a required dependency is down; the HSM is optional, so
+/// its failure is reported without affecting the overall status, and it is
+/// `not_configured` unless the repository key lives in a PKCS#11 token.
+///
+/// Every probe is read-only: nothing is written to storage and the signing
+/// key is never used. Failure reasons are logged rather than returned.
+pub async fn dependencies_check(
+    State(state): State<AppState>,
+) -> (StatusCode, Json<DependenciesResponse>) {
+    let (postgres, redis, storage, hsm) = tokio::join!(
+        timed(query_postgres(&state.db)),
+        timed(ping_redis(&state.redis)),
+        timed(probe_storage(state.storage.as_ref())),
+        async {
+            match &state.hsm {
+                Some(hsm) => Some(timed(probe_hsm(Arc::clone(hsm))).await),
+                None => None,
+            }
+        },
+    );
+
+    dependency_matrix([
+        Probe {
+            name: "postgres",
+            required: true,
+            outcome: Some(postgres),
+        },
+        Probe {
+            name: "redis",
+            required: true,
+            outcome: Some(redis),
+        },
+        Probe {
+            name: "storage",
+            required: true,
+            outcome: Some(storage),
+        },
+        Probe {
+            name: "hsm",
+            required: false,
+            outcome: hsm,
+        },
+    ])
 }
 
 /// Liveness check endpoint.
 ///
 /// Returns OK if the service is alive.
 /// Used by Kubernetes to determine if the pod should be restarted.
+/// Deliberately checks no dependencies, so an outage does not restart pods.
 pub async fn liveness_check() -> Json<HealthResponse> {
-    Json(HealthResponse {
-        status: "alive",
-        version: env!("CARGO_PKG_VERSION"),
-    })
+    Json(HealthResponse::new("alive"))
+}
+
+/// Run a trivial query on the database.
+async fn query_postgres(db: &sqlx::PgPool) -> Result<(), String> {
+    sqlx::query("SELECT 1")
+        .execute(db)
+        .await
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+/// Look up the metadata of a probe key in storage.
+///
+/// A missing object still proves the backend answers.
+async fn probe_storage(storage: &dyn Storage) -> Result<(), String> {
+    match storage.head(STORAGE_PROBE_KEY).await {
+        Ok(_) | Err(dk_common::Error::NotFound(_)) => Ok(()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Open a session on the HSM and locate the signing key, off the async
+/// runtime since HSM calls block.
+async fn probe_hsm(hsm: Arc<HsmSigner>) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || hsm.check())
+        .await
+        .map_err(|err| err.to_string())?
+        .map_err(|err| err.to_string())
+}
+
+/// Send `PING` over a fresh Redis connection.
+async fn ping_redis(client: &redis::Client) -> Result<(), String> {
+    let mut connection = client
+        .get_multiplexed_tokio_connection()
+        .await
+        .map_err(|err| err.to_string())?;
+    redis::cmd("PING")
+        .query_async::<_, String>(&mut connection)
+        .await
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+/// Run `check`, failing it if it exceeds [`CHECK_TIMEOUT`].
+async fn bounded(check: impl Future<Output = Result<(), String>>) -> Result<(), String> {
+    tokio::time::timeout(CHECK_TIMEOUT, check)
+        .await
+        .unwrap_or_else(|_| Err(format!("timed out after {}s", CHECK_TIMEOUT.as_secs())))
+}
+
+/// Run `check` like [`bounded`], measuring how long it took.
+async fn timed(check: impl Future<Output = Result<(), String>>) -> (Result<(), String>, Duration) {
+    let start = Instant::now();
+    let result = bounded(check).await;
+    (result, start.elapsed())
+}
+
+/// Build the dependency matrix response from per-dependency probes.
+fn dependency_matrix<const N: usize>(
+    probes: [Probe; N],
+) -> (StatusCode, Json<DependenciesResponse>) {
+    let mut healthy = true;
+    let mut dependencies = BTreeMap::new();
+    for probe in probes {
+        let health = match probe.outcome {
+            None => DependencyHealth {
+                status: "not_configured",
+                required: probe.required,
+                latency_ms: None,
+            },
+            Some((result, latency)) => {
+                healthy &= result.is_ok() || !probe.required;
+                if let Err(error) = &result {
+                    tracing::warn!(dependency = probe.name, %error, "Dependency check failed");
+                }
+                DependencyHealth {
+                    status: if result.is_ok() { "up" } else { "down" },
+                    required: probe.required,
+                    latency_ms: Some(u64::try_from(latency.as_millis()).unwrap_or(u64::MAX)),
+                }
+            }
+        };
+        dependencies.insert(probe.name, health);
+    }
+
+    let code = if healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        code,
+        Json(DependenciesResponse {
+            healthy,
+            dependencies,
+        }),
+    )
+}
+
+/// Build the readiness response from per-dependency results.
+fn readiness<const N: usize>(
+    results: [(&'static str, Result<(), String>); N],
+) -> (StatusCode, Json<ReadinessResponse>) {
+    let mut checks = BTreeMap::new();
+    let mut failed = Vec::new();
+    for (name, result) in results {
+        match result {
+            Ok(()) => {
+                checks.insert(name, "ok".to_string());
+            }
+            Err(reason) => {
+                checks.insert(name, reason);
+                failed.push(name);
+            }
+        }
+    }
+
+    let (code, status) = if failed.is_empty() {
+        (StatusCode::OK, "ready")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "unavailable")
+    };
+    (
+        code,
+        Json(ReadinessResponse {
+            status,
+            version: env!("CARGO_PKG_VERSION"),
+            checks,
+            failed,
+        }),
+    )
 }
 
 #[cfg(test)]
@@ -54,11 +316,161 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_readiness_check() {
-        let response = readiness_check().await;
+    async fn test_health_check_reports_build_info() {
+        let response = serde_json::to_value(health_check().await.0).expect("health json");
+        assert_eq!(response["version"], env!("CARGO_PKG_VERSION"));
+        for field in ["git_sha", "build_timestamp", "rust_version"] {
+            let value = response[field].as_str().expect(field);
+            assert!(!value.is_empty(), "{field}");
+        }
+        let git_sha = response["git_sha"].as_str().expect("git_sha");
+        assert!(
+            git_sha == "unknown" || git_sha.chars().all(|c| c.is_ascii_hexdigit()),
+            "{git_sha}"
+        );
+    }
+
+    #[test]
+    fn test_readiness_all_healthy() {
+        let (code, response) = readiness([("postgres", Ok(())), ("redis", Ok(()))]);
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(response.status, "ready");
+        assert!(response.failed.is_empty());
+        assert_eq!(response.checks["postgres"], "ok");
+    }
+
+    #[test]
+    fn test_readiness_names_failed_dependency() {
+        let (code, response) = readiness([
+            ("postgres", Ok(())),
+            ("redis", Err("connection refused".to_string())),
+        ]);
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.status, "unavailable");
+        assert_eq!(response.failed, ["redis"]);
+        assert_eq!(response.checks["redis"], "connection refused");
+    }
+
+    #[tokio::test]
+    async fn test_readiness_closed_pool_is_unavailable() {
+        let state = AppState::disconnected();
+        state.db.close().await;
+
+        let (code, response) = readiness_check(State(state)).await;
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(response.failed.contains(&"postgres"));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL and REDIS_URL"]
+    async fn test_readiness_healthy_dependencies() {
+        let mut state = AppState::disconnected();
+        state.db = sqlx::PgPool::connect(&std::env::var("DATABASE_URL").expect("DATABASE_URL"))
+            .await
+            .expect("connect");
+        state.redis = redis::Client::open(std::env::var("REDIS_URL").expect("REDIS_URL"))
+            .expect("redis client");
+
+        let (code, response) = readiness_check(State(state)).await;
+        assert_eq!(code, StatusCode::OK);
         assert_eq!(response.status, "ready");
     }
 
+    fn probe(name: &'static str, required: bool, result: Option<Result<(), String>>) -> Probe {
+        Probe {
+            name,
+            required,
+            outcome: result.map(|result| (result, Duration::from_millis(3))),
+        }
+    }
+
+    #[test]
+    fn test_dependencies_all_up() {
+        let (code, response) = dependency_matrix([
+            probe("postgres", true, Some(Ok(()))),
+            probe("redis", true, Some(Ok(()))),
+            probe("storage", true, Some(Ok(()))),
+            probe("hsm", false, Some(Ok(()))),
+        ]);
+        assert_eq!(code, StatusCode::OK);
+        let response = serde_json::to_value(response.0).expect("json");
+        assert_eq!(response["healthy"], true);
+        for name in ["postgres", "redis", "storage", "hsm"] {
+            let dependency = &response["dependencies"][name];
+            assert_eq!(dependency["status"], "up", "{name}");
+            assert_eq!(dependency["latency_ms"], 3, "{name}");
+        }
+        assert_eq!(response["dependencies"]["postgres"]["required"], true);
+        assert_eq!(response["dependencies"]["hsm"]["required"], false);
+    }
+
+    #[test]
+    fn test_required_dependency_down_is_unavailable() {
+        let (code, response) = dependency_matrix([
+            probe(
+                "postgres",
+                true,
+                Some(Err("connection refused".to_string())),
+            ),
+            probe("redis", true, Some(Ok(()))),
+            probe("hsm", false, Some(Ok(()))),
+        ]);
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(!response.healthy);
+        assert_eq!(response.dependencies["postgres"].status, "down");
+        let response = serde_json::to_value(response.0).expect("json");
+        assert!(response["dependencies"]["postgres"].get("error").is_none());
+    }
+
+    #[test]
+    fn test_optional_dependency_does_not_fail_overall_status() {
+        let (code, response) = dependency_matrix([
+            probe("postgres", true, Some(Ok(()))),
+            probe("hsm", false, Some(Err("token not present".to_string()))),
+        ]);
+        assert_eq!(code, StatusCode::OK);
+        assert!(response.healthy);
+        assert_eq!(response.dependencies["hsm"].status, "down");
+
+        let (code, response) = dependency_matrix([probe("hsm", false, None)]);
+        assert_eq!(code, StatusCode::OK);
+        let hsm = &response.dependencies["hsm"];
+        assert_eq!(hsm.status, "not_configured");
+        assert_eq!(hsm.latency_ms, None);
+    }
+
+    #[tokio::test]
+    async fn test_dependencies_closed_pool_is_unavailable() {
+        let storage = crate::routes::download::tests::TempStorage::new();
+        let state = AppState {
+            // A software key is not an HSM
+            signer: Some(Arc::new(
+                dk_signing::SigningService::ephemeral("dk-appstore.test").expect("signer"),
+            )),
+            ..storage.state()
+        };
+        state.db.close().await;
+
+        let (code, response) = dependencies_check(State(state)).await;
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.dependencies["postgres"].status, "down");
+        assert_eq!(response.dependencies["storage"].status, "up");
+        assert_eq!(response.dependencies["hsm"].status, "not_configured");
+    }
+
+    #[tokio::test]
+    async fn test_dependencies_check_writes_nothing() {
+        let storage = crate::routes::download::tests::TempStorage::new();
+
+        let (_, response) = dependencies_check(State(storage.state())).await;
+
+        assert_eq!(response.dependencies["storage"].status, "up");
+        let entries = std::fs::read_dir(&storage.dir)
+            .expect("storage dir")
+            .count();
+        assert_eq!(entries, 0);
+    }
+
     #[tokio::test]
     async fn test_liveness_check() {
         let response = liveness_check().await;
@@ -0,0 +1,171 @@
+//! App icon endpoint.
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use dk_common::types::AppId;
+use serde::Deserialize;
+use tokio_util::io::ReaderStream;
+
+use crate::error::ApiError;
+use crate::routes::apps::parse_package_id;
+use crate::state::AppState;
+
+/// MIME type of stored icons.
+pub const ICON_CONTENT_TYPE: &str = "image/png";
+
+/// `Cache-Control` of icon responses. Icons rarely change, and a new icon
+/// only needs to reach clients eventually.
+const ICON_CACHE_CONTROL: &str = "public, max-age=86400";
+
+/// Query parameters for selecting an icon variant.
+#[derive(Debug, Default, Deserialize)]
+pub struct IconQuery {
+    /// Edge length in pixels of a pre-generated variant, e.g. 48, 96, 192.
+    size: Option<u32>,
+}
+
+/// Storage key of the icon of `app_id`: the original, or the variant of
+/// `size` pixels.
+#[must_use]
+pub fn icon_key(app_id: &AppId, size: Option<u32>) -> String {
+    match size {
+        Some(size) => format!("icons/{size}/{app_id}.png"),
+        None => format!("icons/{app_id}.png"),
+    }
+}
+
+/// Get the icon of an application.
+///
+/// `GET /api/v1/apps/:package_id/icon?size=96`
+///
+/// Serves the pre-generated variant of the requested size if there is one,
+/// otherwise the original icon.
+pub async fn get_icon(
+    State(state): State<AppState>,
+    Path(package_id): Path<String>,
+    Query(query): Query<IconQuery>,
+) -> Result<Response, ApiError> {
+    // Validating the package ID also keeps the key inside the icon namespace
+    let app_id = parse_package_id(&package_id)?;
+    let not_found = |err| match err {
+        dk_common::Error::NotFound(_) => {
+            ApiError::NotFound(format!("Icon not found: {package_id}"))
+        }
+        err => ApiError::from(err),
+    };
+
+    let variant = match query.size {
+        Some(size) => {
+            let key = icon_key(&app_id, Some(size));
+            match state.storage.head(&key).await {
+                Ok(meta) => Some((key, meta.size)),
+                Err(dk_common::Error::NotFound(_)) => None,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        None => None,
+    };
+    let (key, size) = if let Some(variant) = variant {
+        variant
+    } else {
+        let key = icon_key(&app_id, None);
+        let size = state.storage.head(&key).await.map_err(not_found)?.size;
+        (key, size)
+    };
+    let object = state.storage.get(&key, None).await.map_err(not_found)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, ICON_CONTENT_TYPE.to_string()),
+            (header::CONTENT_LENGTH, size.to_string()),
+            (header::CACHE_CONTROL, ICON_CACHE_CONTROL.to_string()),
+        ],
+        Body::from_stream(ReaderStream::new(object)),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use super::*;
+    use crate::routes::download::tests::TempStorage;
+
+    const PACKAGE: &str = "dk.digst.mitid";
+
+    /// Store `bytes` under `key` in `storage`.
+    fn seed(storage: &TempStorage, key: &str, bytes: &[u8]) {
+        let path = storage.dir.join(key);
+        std::fs::create_dir_all(path.parent().expect("parent")).expect("icon dir");
+        std::fs::write(path, bytes).expect("write icon");
+    }
+
+    async fn icon(storage: &TempStorage, size: Option<u32>) -> Result<Response, ApiError> {
+        get_icon(
+            State(storage.state()),
+            Path(PACKAGE.to_string()),
+            Query(IconQuery { size }),
+        )
+        .await
+    }
+
+    async fn body(response: Response) -> Vec<u8> {
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body")
+            .to_vec()
+    }
+
+    #[tokio::test]
+    async fn test_default_icon() {
+        let storage = TempStorage::new();
+        seed(&storage, "icons/dk.digst.mitid.png", b"original");
+
+        let response = icon(&storage, None).await.expect("icon");
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(headers[header::CONTENT_TYPE], ICON_CONTENT_TYPE);
+        assert_eq!(headers[header::CONTENT_LENGTH], "8");
+        assert_eq!(headers[header::CACHE_CONTROL], ICON_CACHE_CONTROL);
+        assert_eq!(body(response).await, b"original");
+    }
+
+    #[tokio::test]
+    async fn test_requested_size_variant() {
+        let storage = TempStorage::new();
+        seed(&storage, "icons/dk.digst.mitid.png", b"original");
+        seed(&storage, "icons/96/dk.digst.mitid.png", b"96px");
+
+        let response = icon(&storage, Some(96)).await.expect("icon");
+        assert_eq!(body(response).await, b"96px");
+
+        // Sizes without a variant fall back to the original
+        let response = icon(&storage, Some(48)).await.expect("icon");
+        assert_eq!(body(response).await, b"original");
+    }
+
+    #[tokio::test]
+    async fn test_missing_icon() {
+        let storage = TempStorage::new();
+        assert!(matches!(
+            icon(&storage, None).await,
+            Err(ApiError::NotFound(_))
+        ));
+        assert!(matches!(
+            icon(&storage, Some(96)).await,
+            Err(ApiError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_icon_key() {
+        let app_id = AppId::new(PACKAGE);
+        assert_eq!(icon_key(&app_id, None), "icons/dk.digst.mitid.png");
+        assert_eq!(icon_key(&app_id, Some(192)), "icons/192/dk.digst.mitid.png");
+    }
+}
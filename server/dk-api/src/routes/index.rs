@@ -1,7 +1,42 @@
 //! Repository index endpoint.
+//!
+//! Serves the F-Droid `index-v1` format, as read by `fdroidclient`, and
+//! holds the [`Repo`] model shared with [`super::index_v2`]. `/index`
+//! negotiates between both formats.
 
-use axum::Json;
-use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use dk_common::config::RepoConfig;
+use dk_common::hash::sha256_bytes;
+use dk_common::localized::DEFAULT_LOCALE;
+use dk_common::types::{App, AppId, AppVersion, Category, DonationKind, DonationLink};
+use dk_scanner::{QuarantineStore, QuarantinedVersion};
+use dk_signing::SigningService;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use tracing::{field, Instrument};
+
+use crate::error::ApiError;
+use crate::index_cache::{self, cache_key};
+use crate::repo_state::RepoState;
+use crate::repository::AppRepository;
+use crate::routes::apps::{app_from_row, resolve, version_from_row, APP_COLUMNS};
+use crate::state::AppState;
+
+/// Name of the index entry inside the signed JAR.
+pub const INDEX_ENTRY_NAME: &str = "index-v1.json";
+
+/// F-Droid index format version.
+const INDEX_VERSION: i32 = 21;
+
+/// `Cache-Control` of index responses; clients revalidate with the ETag.
+const INDEX_CACHE_CONTROL: &str = "public, max-age=300";
 
 /// Repository index response.
 ///
@@ -9,8 +44,9 @@ use serde::Serialize;
 #[derive(Serialize)]
 pub struct IndexResponse {
     repo: RepoInfo,
-    apps: Vec<serde_json::Value>,
-    packages: std::collections::HashMap<String, Vec<serde_json::Value>>,
+    requests: IndexRequests,
+    apps: Vec<IndexApp>,
+    packages: BTreeMap<String, Vec<IndexPackage>>,
 }
 
 /// Repository information.
@@ -18,26 +54,1442 @@ pub struct IndexResponse {
 pub struct RepoInfo {
     name: String,
     description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<String>,
+    /// Mirror URLs clients may fetch the repository from instead.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    mirrors: Vec<String>,
+    /// Repository icon, relative to `address`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon: Option<String>,
+    /// Last change to the repository, in milliseconds since the epoch.
     timestamp: i64,
     version: i32,
 }
 
-/// Get the repository index.
-///
-/// GET /api/v1/index
+/// Install and uninstall requests pushed to clients; always empty.
+#[derive(Serialize)]
+pub struct IndexRequests {
+    install: Vec<String>,
+    uninstall: Vec<String>,
+}
+
+/// Application entry of the index.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexApp {
+    package_name: String,
+    name: String,
+    summary: String,
+    description: String,
+    categories: Vec<String>,
+    /// F-Droid anti-feature codes, e.g. `Tracking`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    anti_features: Vec<String>,
+    /// SPDX license identifier.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    license: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    issue_tracker: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    web_site: Option<String>,
+    /// URL of the first custom donation page; v1 has room for only one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    donate: Option<String>,
+    /// Liberapay account name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    liberapay: Option<String>,
+    /// Open Collective collective name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    open_collective: Option<String>,
+    /// Newest published version code, as a string per the format.
+    suggested_version_code: String,
+    added: i64,
+    last_updated: i64,
+    localized: BTreeMap<String, LocalizedMetadata>,
+}
+
+/// Per-locale application metadata.
+#[derive(Default, Serialize)]
+pub struct LocalizedMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+}
+
+/// APK entry of the index.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexPackage {
+    package_name: String,
+    version_code: i64,
+    version_name: String,
+    apk_name: String,
+    hash: String,
+    hash_type: &'static str,
+    size: i64,
+    min_sdk_version: i32,
+    target_sdk_version: i32,
+    /// ABIs of the bundled native libraries; left out for pure-Java APKs.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    nativecode: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sig: Option<String>,
+    added: i64,
+    /// `[name, maxSdkVersion]` pairs.
+    #[serde(rename = "uses-permission")]
+    uses_permission: Vec<(String, Option<i32>)>,
+}
+
+/// A published version with its F-Droid signature fingerprint.
+pub struct IndexedVersion {
+    /// Package the version belongs to.
+    pub package_id: AppId,
+    /// The published version.
+    pub version: AppVersion,
+    /// MD5 of the hex-encoded signing certificate, if known.
+    pub sig: Option<String>,
+}
+
+/// Apps and their published versions: the model every index format is
+/// built from, so the formats cannot drift apart.
+#[derive(Default)]
+pub struct Repo {
+    /// All apps, ordered by package ID.
+    pub apps: Vec<App>,
+    /// All published versions.
+    pub versions: Vec<IndexedVersion>,
+    /// Last index timestamp issued for the repository; the index never
+    /// carries an earlier one.
+    pub issued_timestamp: i64,
+}
+
+impl Repo {
+    /// Load all apps and published versions, leaving out unpublished and
+    /// quarantined ones.
+    pub async fn load(db: &PgPool) -> Result<Self, ApiError> {
+        let apps = sqlx::query(&format!(
+            "SELECT {APP_COLUMNS} FROM apps ORDER BY package_id"
+        ))
+        .fetch_all(db)
+        .await?
+        .iter()
+        .map(app_from_row)
+        .collect::<Result<Vec<_>, _>>()?;
+
+        let versions = sqlx::query(
+            "SELECT v.*, a.package_id FROM app_versions v JOIN apps a ON a.id = v.app_id \
+             WHERE v.deleted_at IS NULL ORDER BY a.package_id, v.version_code DESC",
+        )
+        .fetch_all(db)
+        .await?
+        .iter()
+        .map(|row| {
+            Ok(IndexedVersion {
+                package_id: AppId::new(row.try_get::<String, _>("package_id")?),
+                version: version_from_row(row)?,
+                sig: row.try_get("sig")?,
+            })
+        })
+        .collect::<Result<Vec<_>, sqlx::Error>>()?;
+
+        let mut repo = Self {
+            apps,
+            versions,
+            issued_timestamp: 0,
+        };
+        repo.exclude_quarantined(&QuarantineStore::new(db.clone()).list().await?);
+        Ok(repo)
+    }
+
+    /// Load the repository as for [`Self::load`] and issue its index
+    /// timestamp, so it is no earlier than any index served before, and
+    /// later if the repository or its metadata `info` changed since.
+    ///
+    /// With a `category`, only apps in it are kept; the timestamp is still
+    /// that of the whole repository.
+    pub async fn load_for_index(
+        db: &PgPool,
+        info: &RepoConfig,
+        category: Option<&Category>,
+    ) -> Result<Self, ApiError> {
+        // Taken before loading, so a concurrent change is at worst counted
+        // again by the next index
+        let fingerprint = Self::fingerprint(db, info).await?;
+        let mut repo = Self::load(db).await?;
+        repo.issued_timestamp = RepoState::new(db.clone())
+            .issue(repo.timestamp(), &fingerprint)
+            .await?;
+        if let Some(category) = category {
+            repo.retain_category(category);
+        }
+        Ok(repo)
+    }
+
+    /// Drop the apps not in `category`, and their versions.
+    pub fn retain_category(&mut self, category: &Category) {
+        self.apps.retain(|app| app.categories.contains(category));
+        let apps = &self.apps;
+        self.versions
+            .retain(|indexed| apps.iter().any(|app| app.package_id == indexed.package_id));
+    }
+
+    /// Fingerprint of everything the index is built from: the apps, their
+    /// published and quarantined versions, the repository metadata `info`,
+    /// and the server version.
+    ///
+    /// Computed from row counts and last-change times rather than the rows
+    /// themselves, so it is cheap enough to check on every request. Any
+    /// insert, update, unpublish, or quarantine changes it.
+    pub async fn fingerprint(db: &PgPool, info: &RepoConfig) -> Result<String, ApiError> {
+        let state: String = sqlx::query_scalar(
+            "SELECT concat_ws('|', \
+             (SELECT count(*) FROM apps), (SELECT max(updated_at) FROM apps), \
+             (SELECT count(*) FROM app_versions), (SELECT max(created_at) FROM app_versions), \
+             (SELECT count(*) FROM app_versions WHERE deleted_at IS NOT NULL), \
+             (SELECT max(deleted_at) FROM app_versions), \
+             (SELECT count(*) FROM quarantined_versions), \
+             (SELECT max(quarantined_at) FROM quarantined_versions))",
+        )
+        .fetch_one(db)
+        .await?;
+
+        let content = format!("{state}|{info:?}|{}", env!("CARGO_PKG_VERSION"));
+        Ok(sha256_bytes(content.as_bytes()).to_string()[..32].to_string())
+    }
+
+    /// Drop the versions listed in `quarantined`.
+    pub fn exclude_quarantined(&mut self, quarantined: &[QuarantinedVersion]) {
+        self.versions.retain(|indexed| {
+            !quarantined
+                .iter()
+                .any(|entry| entry.matches(&indexed.package_id, indexed.version.version_code))
+        });
+    }
+
+    /// Last change to the repository, in milliseconds since the epoch, but
+    /// no earlier than the issued timestamp; 0 for an empty repository that
+    /// was never issued one.
+    #[must_use]
+    pub fn timestamp(&self) -> i64 {
+        self.apps
+            .iter()
+            .map(|app| app.updated_at.timestamp_millis())
+            .chain(
+                self.versions
+                    .iter()
+                    .map(|indexed| indexed.version.created_at.timestamp_millis()),
+            )
+            .fold(self.issued_timestamp, i64::max)
+    }
+
+    /// Published versions of `package_id`, newest first.
+    #[must_use]
+    pub fn versions_of(&self, package_id: &AppId) -> Vec<&IndexedVersion> {
+        let mut versions: Vec<&IndexedVersion> = self
+            .versions
+            .iter()
+            .filter(|indexed| indexed.package_id == *package_id)
+            .collect();
+        versions.sort_by(|a, b| b.version.cmp_release(&a.version));
+        versions
+    }
+
+    /// Apps with at least one published version; others cannot be installed.
+    pub fn published_apps(&self) -> impl Iterator<Item = (&App, Vec<&IndexedVersion>)> {
+        self.apps.iter().filter_map(|app| {
+            let versions = self.versions_of(&app.package_id);
+            (!versions.is_empty()).then_some((app, versions))
+        })
+    }
+}
+
+impl From<&IndexedVersion> for IndexPackage {
+    fn from(indexed: &IndexedVersion) -> Self {
+        let version = &indexed.version;
+        Self {
+            package_name: indexed.package_id.to_string(),
+            version_code: version.version_code,
+            version_name: version.version_name.clone(),
+            apk_name: indexed.package_id.apk_file_name(version.version_code),
+            hash: version.sha256.to_string(),
+            hash_type: "sha256",
+            size: version.size,
+            min_sdk_version: version.min_sdk,
+            target_sdk_version: version.target_sdk,
+            nativecode: version.abis.clone(),
+            sig: indexed.sig.clone(),
+            added: version.created_at.timestamp_millis(),
+            uses_permission: version
+                .permissions
+                .iter()
+                .map(|permission| (permission.name.clone(), permission.max_sdk))
+                .collect(),
+        }
+    }
+}
+
+/// Collect the `name`, `summary`, and `description` of `app` per locale.
+fn localized_metadata(app: &App) -> BTreeMap<String, LocalizedMetadata> {
+    let mut localized: BTreeMap<String, LocalizedMetadata> = BTreeMap::new();
+    for (locale, name) in app.name.iter() {
+        localized.entry(locale.to_string()).or_default().name = Some(name.clone());
+    }
+    for (locale, summary) in app.summary.iter() {
+        localized.entry(locale.to_string()).or_default().summary = Some(summary.clone());
+    }
+    for (locale, description) in app.description.iter() {
+        localized.entry(locale.to_string()).or_default().description = Some(description.clone());
+    }
+    localized
+}
+
+/// URLs of the custom donation pages of `app`.
+pub fn custom_donations(app: &App) -> impl Iterator<Item = String> + '_ {
+    app.donate
+        .iter()
+        .filter(|link| link.kind == DonationKind::Custom)
+        .map(|link| link.url.to_string())
+}
+
+/// Account name of the first donation link of `kind` of `app`.
+pub fn donation_account(app: &App, kind: DonationKind) -> Option<String> {
+    app.donate
+        .iter()
+        .filter(|link| link.kind == kind)
+        .find_map(DonationLink::account)
+        .map(ToString::to_string)
+}
+
+/// Build the index-v1 representation of `repo`, advertised as `info`.
 ///
-/// Returns the repository index in a format compatible with F-Droid clients.
-pub async fn get_index() -> Json<IndexResponse> {
-    // TODO: Generate actual index from database
-    // This should be cached and regenerated when apps change
-    Json(IndexResponse {
+/// Apps without a published version are left out, as clients cannot
+/// install them.
+fn build_index(repo: &Repo, info: &RepoConfig) -> IndexResponse {
+    let mut apps = Vec::new();
+    let mut packages = BTreeMap::new();
+    for (app, versions) in repo.published_apps() {
+        apps.push(IndexApp {
+            package_name: app.package_id.to_string(),
+            name: resolve(&app.name, DEFAULT_LOCALE),
+            summary: resolve(&app.summary, DEFAULT_LOCALE),
+            description: resolve(&app.description, DEFAULT_LOCALE),
+            categories: app.categories.iter().map(ToString::to_string).collect(),
+            anti_features: app.antifeatures.iter().map(ToString::to_string).collect(),
+            license: app.license.as_ref().map(ToString::to_string),
+            source_code: app.source_url.as_ref().map(ToString::to_string),
+            issue_tracker: app.issue_tracker_url.as_ref().map(ToString::to_string),
+            web_site: app.web_url.as_ref().map(ToString::to_string),
+            donate: custom_donations(app).next(),
+            liberapay: donation_account(app, DonationKind::Liberapay),
+            open_collective: donation_account(app, DonationKind::OpenCollective),
+            suggested_version_code: versions[0].version.version_code.to_string(),
+            added: app.created_at.timestamp_millis(),
+            // Never before `added`, which clients would show as a future date
+            last_updated: app.updated_at.max(app.created_at).timestamp_millis(),
+            localized: localized_metadata(app),
+        });
+        packages.insert(
+            app.package_id.to_string(),
+            versions.into_iter().map(IndexPackage::from).collect(),
+        );
+    }
+    // Signed and cached by digest, so the bytes must not depend on the order
+    // apps were loaded in, which follows the database collation
+    apps.sort_by(|a, b| a.package_name.cmp(&b.package_name));
+
+    IndexResponse {
         repo: RepoInfo {
-            name: "DK-AppStore".to_string(),
-            description: "Danish sovereign app distribution platform".to_string(),
-            timestamp: chrono::Utc::now().timestamp(),
-            version: 21, // F-Droid index version
+            name: info.name.clone(),
+            description: info.description.clone(),
+            address: info.address.clone(),
+            mirrors: info.mirrors.clone(),
+            icon: info.icon.clone(),
+            timestamp: repo.timestamp(),
+            version: INDEX_VERSION,
+        },
+        requests: IndexRequests {
+            install: Vec::new(),
+            uninstall: Vec::new(),
         },
-        apps: vec![],
-        packages: std::collections::HashMap::new(),
-    })
+        apps,
+        packages,
+    }
+}
+
+/// Load the repository and build its index-v1 representation.
+///
+/// Runs in a `generate_index` span recording how many apps and packages the
+/// index includes and how long generation took.
+async fn generate_index(
+    apps: &dyn AppRepository,
+    info: &RepoConfig,
+    category: Option<&Category>,
+) -> Result<IndexResponse, ApiError> {
+    let span = tracing::info_span!(
+        "generate_index",
+        apps = field::Empty,
+        packages = field::Empty,
+        duration_ms = field::Empty,
+    );
+    let started = Instant::now();
+    let index = async {
+        apps.index_repo(info, category)
+            .await
+            .map(|repo| build_index(&repo, info))
+    }
+    .instrument(span.clone())
+    .await?;
+
+    span.record("apps", index.apps.len());
+    span.record(
+        "packages",
+        index.packages.values().map(Vec::len).sum::<usize>(),
+    );
+    span.record(
+        "duration_ms",
+        u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+    );
+    span.in_scope(|| tracing::debug!("Generated index"));
+    Ok(index)
+}
+
+/// Load the repository and serialize its `index-v1` representation,
+/// restricted to `category` if given.
+///
+/// # Errors
+///
+/// Returns [`ApiError::Internal`] if the repository cannot be loaded.
+pub async fn generate(
+    apps: &dyn AppRepository,
+    info: &RepoConfig,
+    category: Option<&Category>,
+) -> Result<Vec<u8>, ApiError> {
+    serialize_index(&generate_index(apps, info, category).await?)
+}
+
+/// The serialized index-v1, restricted to `category` if given, from the
+/// cache if the repository is unchanged.
+async fn cached_index_v1(
+    state: &AppState,
+    category: Option<&Category>,
+) -> Result<Vec<u8>, ApiError> {
+    if !is_known_category(state.apps.as_ref(), category).await? {
+        return serialize_index(&build_index(&Repo::default(), &state.repo));
+    }
+    let fingerprint = state.apps.index_fingerprint(&state.repo).await?;
+    index_cache::cached(
+        &state.redis,
+        &state.index_flights,
+        &cache_key(&sliced("v1", category), &fingerprint),
+        generate(state.apps.as_ref(), &state.repo, category),
+    )
+    .await
+}
+
+/// Whether apps can be in `category`: a standard category, or a custom one
+/// some app uses.
+///
+/// Indexes of other categories are empty. They are built without loading
+/// the repository and never cached, so arbitrary `?category=` values cannot
+/// fill the cache.
+pub async fn is_known_category(
+    apps: &dyn AppRepository,
+    category: Option<&Category>,
+) -> Result<bool, ApiError> {
+    match category {
+        Some(category @ Category::Other(_)) => Ok(apps
+            .category_counts()
+            .await?
+            .iter()
+            .any(|(known, _)| known == category)),
+        _ => Ok(true),
+    }
+}
+
+/// Cache format name of the `format` index restricted to `category`.
+#[must_use]
+pub fn sliced(format: &str, category: Option<&Category>) -> String {
+    match category {
+        Some(category) => format!("{format}:category:{category}"),
+        None => format.to_string(),
+    }
+}
+
+/// Format of the index served by [`get_index`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum IndexFormat {
+    /// F-Droid `index-v1`.
+    #[default]
+    V1,
+    /// F-Droid `index-v2`.
+    V2,
+}
+
+impl IndexFormat {
+    /// Parse a requested version: `1`, `v1`, `2` or `v2`.
+    fn parse(version: &str) -> Option<Self> {
+        match version.trim() {
+            "1" | "v1" => Some(Self::V1),
+            "2" | "v2" => Some(Self::V2),
+            _ => None,
+        }
+    }
+
+    /// Select the format requested by `?version=`, or else by the `version`
+    /// parameter of an `Accept` media type, e.g.
+    /// `Accept: application/json; version=2`.
+    ///
+    /// Defaults to v1 so existing clients keep working.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::BadRequest`] for an unknown version.
+    pub fn negotiate(query: &IndexQuery, headers: &HeaderMap) -> Result<Self, ApiError> {
+        let Some(version) = query.version.as_deref().or_else(|| accept_version(headers)) else {
+            return Ok(Self::default());
+        };
+        Self::parse(version)
+            .ok_or_else(|| ApiError::BadRequest(format!("Unsupported index version: {version}")))
+    }
+}
+
+/// The `version` parameter of the first `Accept` media type carrying one.
+fn accept_version(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .flat_map(|media_type| media_type.split(';').skip(1))
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("version"))
+        .map(|(_, value)| value.trim().trim_matches('"'))
+}
+
+/// Longest accepted `?category=` value, in bytes.
+const MAX_CATEGORY_LEN: usize = 64;
+
+/// Query parameters for selecting the index format and slice.
+#[derive(Debug, Default, Deserialize)]
+pub struct IndexQuery {
+    /// Index format version: `1` (default) or `2`.
+    version: Option<String>,
+    /// Only include apps in this category, e.g. `Security`.
+    category: Option<String>,
+}
+
+impl IndexQuery {
+    /// The requested category, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ApiError::BadRequest`] for a blank category, or one longer
+    /// than [`MAX_CATEGORY_LEN`].
+    fn category(&self) -> Result<Option<Category>, ApiError> {
+        if self
+            .category
+            .as_ref()
+            .is_some_and(|category| category.len() > MAX_CATEGORY_LEN)
+        {
+            return Err(ApiError::BadRequest(format!(
+                "category must be at most {MAX_CATEGORY_LEN} bytes"
+            )));
+        }
+        self.category
+            .clone()
+            .map(Category::try_from)
+            .transpose()
+            .map_err(ApiError::from)
+    }
+}
+
+/// Get the repository index.
+///
+/// GET /api/v1/index?version=2&category=Security
+///
+/// Returns the repository index in a format compatible with F-Droid clients:
+/// `index-v1` unless v2 is requested, see [`IndexFormat::negotiate`].
+/// With `category`, only apps in that category are included; an unknown
+/// category gives an index without apps, which is not cached.
+/// Answers `304 Not Modified` when `If-None-Match` holds the current ETag.
+pub async fn get_index(
+    State(state): State<AppState>,
+    Query(query): Query<IndexQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let category = query.category()?;
+    let index = match IndexFormat::negotiate(&query, &headers)? {
+        IndexFormat::V1 => cached_index_v1(&state, category.as_ref()).await?,
+        IndexFormat::V2 => super::index_v2::cached(&state, category.as_ref()).await?,
+    };
+    let mut response = json_response(&headers, index);
+    response
+        .headers_mut()
+        .insert(header::VARY, HeaderValue::from_static("Accept"));
+    Ok(response)
+}
+
+/// Get the signed repository index.
+///
+/// GET /api/v1/index.jar
+///
+/// Returns the index wrapped in a JAR signed with the repository key, as
+/// verified by F-Droid clients.
+pub async fn get_index_jar(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let signer = state
+        .signer
+        .as_deref()
+        .ok_or_else(|| ApiError::Internal("repository signing is not configured".to_string()))?;
+
+    let index = cached_index_v1(&state, None).await?;
+    jar_response(signer, &headers, &index)
+}
+
+/// Serve a JSON index, answering `304 Not Modified` when `If-None-Match`
+/// holds its ETag.
+pub fn json_response(headers: &HeaderMap, index: Vec<u8>) -> Response {
+    let etag = index_etag(&index, "json");
+    if is_not_modified(headers, &etag) {
+        return not_modified(&etag);
+    }
+
+    cacheable(
+        &etag,
+        ([(header::CONTENT_TYPE, "application/json")], index).into_response(),
+    )
+}
+
+fn jar_response(
+    signer: &SigningService,
+    headers: &HeaderMap,
+    index: &[u8],
+) -> Result<Response, ApiError> {
+    // Signatures are randomized, so the ETag is derived from the signed
+    // content; a match also skips signing altogether
+    let etag = index_etag(index, "jar");
+    if is_not_modified(headers, &etag) {
+        return Ok(not_modified(&etag));
+    }
+
+    let jar = dk_signing::jar::sign_jar(signer, INDEX_ENTRY_NAME, index)
+        .map_err(|err| ApiError::Internal(format!("failed to sign index: {err}")))?;
+
+    Ok(cacheable(
+        &etag,
+        ([(header::CONTENT_TYPE, "application/java-archive")], jar).into_response(),
+    ))
+}
+
+fn serialize_index(index: &impl Serialize) -> Result<Vec<u8>, ApiError> {
+    serde_json::to_vec(index)
+        .map_err(|err| ApiError::Internal(format!("failed to serialize index: {err}")))
+}
+
+/// Strong ETag of the `variant` representation of `index`.
+fn index_etag(index: &[u8], variant: &str) -> String {
+    let hash = sha256_bytes(index).to_string();
+    format!("\"{}-{variant}\"", &hash[..32])
+}
+
+/// Whether `If-None-Match` in `headers` matches `etag`.
+///
+/// Uses the weak comparison required for `If-None-Match`, so `W/` prefixes
+/// are ignored.
+fn is_not_modified(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+fn not_modified(etag: &str) -> Response {
+    cacheable(etag, StatusCode::NOT_MODIFIED.into_response())
+}
+
+/// Add the validator and caching headers to `response`.
+fn cacheable(etag: &str, mut response: Response) -> Response {
+    let headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(etag) {
+        headers.insert(header::ETAG, value);
+    }
+    headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(INDEX_CACHE_CONTROL),
+    );
+    response
+}
+
+#[cfg(test)]
+pub mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+    use dk_common::localized::Localized;
+    use dk_common::types::{Antifeature, Category, License, Permission, Sha256, WebUrl};
+    use serde_json::{json, Value};
+    use sqlx::types::Json as SqlJson;
+    use uuid::Uuid;
+
+    use super::*;
+
+    /// Package IDs of the fixture apps, distinct from other tests' seeds.
+    pub const BORGER: &str = "dk.example.borger";
+    pub const SUNDHED: &str = "dk.example.sundhed";
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(secs, 0)
+            .single()
+            .expect("valid timestamp")
+    }
+
+    fn app(package_id: &str, name: &str) -> App {
+        let mut summary = Localized::single("en", format!("{name} summary"));
+        summary.insert("da", format!("{name} resumé"));
+
+        App {
+            id: Uuid::new_v4(),
+            package_id: AppId::new(package_id),
+            name: Localized::single("en", name.to_string()),
+            summary,
+            description: Localized::single("en", format!("{name} description")),
+            categories: vec![Category::PublicServices, Category::Security],
+            screenshots: vec![],
+            antifeatures: vec![],
+            license: None,
+            source_url: None,
+            issue_tracker_url: None,
+            web_url: None,
+            donate: vec![],
+            version_code: 2,
+            version_name: "2.0".to_string(),
+            created_at: at(1_700_000_000),
+            updated_at: at(1_700_000_100),
+        }
+    }
+
+    fn version(app: &App, version_code: i64, sig: Option<&str>) -> IndexedVersion {
+        IndexedVersion {
+            package_id: app.package_id.clone(),
+            version: AppVersion {
+                id: Uuid::new_v4(),
+                app_id: app.id,
+                version_code,
+                version_name: format!("{version_code}.0"),
+                sha256: Sha256::parse(&format!("{version_code:064x}")).expect("sha256"),
+                size: 1024 * version_code,
+                min_sdk: 24,
+                target_sdk: 34,
+                permissions: vec![
+                    Permission {
+                        name: "android.permission.INTERNET".to_string(),
+                        max_sdk: None,
+                    },
+                    Permission {
+                        name: "android.permission.READ_EXTERNAL_STORAGE".to_string(),
+                        max_sdk: Some(32),
+                    },
+                ],
+                abis: vec![],
+                native_code: false,
+                created_at: at(1_700_000_000 + version_code),
+            },
+            sig: sig.map(ToString::to_string),
+        }
+    }
+
+    /// Two apps, the first with two versions.
+    pub fn fixture() -> Repo {
+        let borger = app(BORGER, "Borger");
+        let sundhed = app(SUNDHED, "Sundhed");
+        let mut native = version(&borger, 2, Some("0123456789abcdef0123456789abcdef"));
+        native.version.abis = vec!["arm64-v8a".to_string()];
+        native.version.native_code = true;
+        let versions = vec![
+            version(&borger, 1, Some("0123456789abcdef0123456789abcdef")),
+            native,
+            version(&sundhed, 7, None),
+        ];
+        Repo {
+            apps: vec![borger, sundhed],
+            versions,
+            issued_timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn test_app_timestamps_are_millis() {
+        let mut repo = fixture();
+        // Clock skew between writers must not put the update before creation
+        repo.apps[1].updated_at = repo.apps[1].created_at - chrono::Duration::seconds(5);
+        let index =
+            serde_json::to_value(build_index(&repo, &RepoConfig::default())).expect("index json");
+
+        for (app, indexed) in repo
+            .apps
+            .iter()
+            .zip(index["apps"].as_array().expect("apps"))
+        {
+            let added = indexed["added"].as_i64().expect("added");
+            let last_updated = indexed["lastUpdated"].as_i64().expect("lastUpdated");
+            assert_eq!(added / 1000, app.created_at.timestamp());
+            assert!(added > 1_000_000_000_000, "{added} is not in milliseconds");
+            assert!(last_updated >= added, "{last_updated} < {added}");
+        }
+    }
+
+    #[test]
+    fn test_index_lists_antifeatures() {
+        let mut repo = fixture();
+        repo.apps[0].antifeatures = vec![Antifeature::Tracking, Antifeature::NonFreeNet];
+        let index =
+            serde_json::to_value(build_index(&repo, &RepoConfig::default())).expect("index json");
+
+        let apps = index["apps"].as_array().expect("apps");
+        assert_eq!(apps[0]["packageName"], BORGER);
+        assert_eq!(apps[0]["antiFeatures"], json!(["Tracking", "NonFreeNet"]));
+        // Apps without anti-features leave the field out
+        assert_eq!(apps[1]["packageName"], SUNDHED);
+        assert!(apps[1].get("antiFeatures").is_none());
+    }
+
+    #[test]
+    fn test_index_lists_license() {
+        let mut repo = fixture();
+        repo.apps[0].license = Some(License::parse("GPL-3.0-only").expect("license"));
+        let index =
+            serde_json::to_value(build_index(&repo, &RepoConfig::default())).expect("index json");
+
+        let apps = index["apps"].as_array().expect("apps");
+        assert_eq!(apps[0]["license"], "GPL-3.0-only");
+        assert!(apps[1].get("license").is_none());
+    }
+
+    #[test]
+    fn test_index_lists_links() {
+        let mut repo = fixture();
+        let url = |value| Some(WebUrl::parse(value).expect("url"));
+        repo.apps[0].source_url = url("https://github.com/digst/borger-android");
+        repo.apps[0].issue_tracker_url = url("https://github.com/digst/borger-android/issues");
+        repo.apps[0].web_url = url("https://www.borger.dk/");
+        let index =
+            serde_json::to_value(build_index(&repo, &RepoConfig::default())).expect("index json");
+
+        let apps = index["apps"].as_array().expect("apps");
+        assert_eq!(
+            apps[0]["sourceCode"],
+            "https://github.com/digst/borger-android"
+        );
+        assert_eq!(
+            apps[0]["issueTracker"],
+            "https://github.com/digst/borger-android/issues"
+        );
+        assert_eq!(apps[0]["webSite"], "https://www.borger.dk/");
+        for field in ["sourceCode", "issueTracker", "webSite"] {
+            assert!(apps[1].get(field).is_none(), "{field}");
+        }
+    }
+
+    fn donation(kind: DonationKind, url: &str) -> DonationLink {
+        DonationLink::new(kind, WebUrl::parse(url).expect("url")).expect("donation link")
+    }
+
+    #[test]
+    fn test_index_maps_donation_links() {
+        let mut repo = fixture();
+        repo.apps[0].donate = vec![
+            donation(DonationKind::Liberapay, "https://liberapay.com/borger"),
+            donation(DonationKind::Custom, "https://borger.dk/doner"),
+            donation(
+                DonationKind::OpenCollective,
+                "https://opencollective.com/borger-dk",
+            ),
+            donation(DonationKind::Custom, "https://example.dk/donate"),
+        ];
+        let index =
+            serde_json::to_value(build_index(&repo, &RepoConfig::default())).expect("index json");
+
+        let apps = index["apps"].as_array().expect("apps");
+        assert_eq!(apps[0]["liberapay"], "borger");
+        assert_eq!(apps[0]["openCollective"], "borger-dk");
+        assert_eq!(apps[0]["donate"], "https://borger.dk/doner");
+        for field in ["donate", "liberapay", "openCollective"] {
+            assert!(apps[1].get(field).is_none(), "{field}");
+        }
+    }
+
+    #[test]
+    fn test_index_advertises_mirrors() {
+        let info = RepoConfig {
+            address: Some("https://appstore.digst.dk/repo".to_string()),
+            mirrors: vec!["https://mirror.appstore.digst.dk/repo".to_string()],
+            ..RepoConfig::default()
+        };
+        let index = serde_json::to_value(build_index(&fixture(), &info)).expect("index json");
+        assert_eq!(index["repo"]["address"], "https://appstore.digst.dk/repo");
+        assert_eq!(
+            index["repo"]["mirrors"],
+            json!(["https://mirror.appstore.digst.dk/repo"])
+        );
+
+        // Without mirrors the field is left out
+        let index = serde_json::to_value(build_index(&fixture(), &RepoConfig::default()))
+            .expect("index json");
+        assert!(index["repo"].get("mirrors").is_none());
+    }
+
+    #[test]
+    fn test_index_describes_configured_repo() {
+        let info = RepoConfig {
+            name: "Borgerapps".to_string(),
+            description: "Apps fra det offentlige".to_string(),
+            address: Some("https://appstore.digst.dk/repo".to_string()),
+            icon: Some("icons/borgerapps.png".to_string()),
+            ..RepoConfig::default()
+        };
+        let index = serde_json::to_value(build_index(&fixture(), &info)).expect("index json");
+        assert_eq!(index["repo"]["name"], "Borgerapps");
+        assert_eq!(index["repo"]["description"], "Apps fra det offentlige");
+        assert_eq!(index["repo"]["address"], "https://appstore.digst.dk/repo");
+        assert_eq!(index["repo"]["icon"], "icons/borgerapps.png");
+
+        let index = serde_json::to_value(build_index(&fixture(), &RepoConfig::default()))
+            .expect("index json");
+        assert_eq!(index["repo"]["name"], RepoConfig::default().name);
+        assert!(index["repo"].get("icon").is_none());
+    }
+
+    fn sample_index() -> Value {
+        let mut repo = fixture();
+        repo.apps.push(app("dk.example.unpublished", "Draft"));
+        serde_json::to_value(build_index(&repo, &RepoConfig::default())).expect("index json")
+    }
+
+    /// Assert the fields `fdroidclient` reads from an index with the apps of
+    /// [`sample_index`] (or the database seed, which matches it).
+    fn assert_index_structure(index: &Value) {
+        assert_eq!(index["repo"]["version"], INDEX_VERSION);
+        assert!(index["repo"]["timestamp"].as_i64().expect("timestamp") > 0);
+        assert_eq!(index["requests"], json!({"install": [], "uninstall": []}));
+
+        let apps = index["apps"].as_array().expect("apps");
+        let borger = apps
+            .iter()
+            .find(|app| app["packageName"] == BORGER)
+            .expect("app listed");
+        assert_eq!(borger["name"], "Borger");
+        assert_eq!(borger["summary"], "Borger summary");
+        assert_eq!(borger["categories"], json!(["Public Services", "Security"]));
+        assert_eq!(borger["suggestedVersionCode"], "2");
+        assert_eq!(borger["added"], 1_700_000_000_000_i64);
+        assert_eq!(borger["lastUpdated"], 1_700_000_100_000_i64);
+        assert_eq!(
+            borger["localized"]["da"],
+            json!({"summary": "Borger resumé"})
+        );
+        assert_eq!(
+            borger["localized"]["en"]["description"],
+            "Borger description"
+        );
+
+        let packages = index["packages"][BORGER].as_array().expect("packages");
+        assert_eq!(packages.len(), 2);
+        let newest = &packages[0];
+        assert_eq!(newest["packageName"], BORGER);
+        assert_eq!(newest["versionCode"], 2);
+        assert_eq!(newest["versionName"], "2.0");
+        assert_eq!(newest["apkName"], "dk.example.borger_2.apk");
+        assert_eq!(newest["hash"], format!("{:064x}", 2));
+        assert_eq!(newest["hashType"], "sha256");
+        assert_eq!(newest["size"], 2048);
+        assert_eq!(newest["minSdkVersion"], 24);
+        assert_eq!(newest["targetSdkVersion"], 34);
+        assert_eq!(newest["nativecode"], json!(["arm64-v8a"]));
+        assert_eq!(newest["sig"], "0123456789abcdef0123456789abcdef");
+        assert_eq!(
+            newest["uses-permission"],
+            json!([
+                ["android.permission.INTERNET", null],
+                ["android.permission.READ_EXTERNAL_STORAGE", 32]
+            ])
+        );
+
+        let sundhed = &index["packages"][SUNDHED][0];
+        assert_eq!(sundhed["versionCode"], 7);
+        assert!(sundhed.get("sig").is_none());
+        // Pure-Java APKs run on every ABI
+        assert!(sundhed.get("nativecode").is_none());
+    }
+
+    #[test]
+    fn test_index_structure() {
+        let index = sample_index();
+        assert_index_structure(&index);
+        // Apps without versions are not installable and not listed
+        assert_eq!(index["apps"].as_array().expect("apps").len(), 2);
+        assert!(index["packages"].get("dk.example.unpublished").is_none());
+        assert_eq!(index["repo"]["timestamp"], 1_700_000_100_000_i64);
+    }
+
+    fn quarantined(package_id: &str, version_code: i64) -> QuarantinedVersion {
+        QuarantinedVersion {
+            package_id: AppId::new(package_id),
+            version_code,
+            reason: "signature.invalid: APK has no v2/v3 signature".to_string(),
+            quarantined_at: at(1_700_000_200),
+        }
+    }
+
+    #[test]
+    fn test_quarantined_versions_are_excluded() {
+        let mut repo = fixture();
+        repo.exclude_quarantined(&[quarantined(BORGER, 2), quarantined(SUNDHED, 7)]);
+        let index =
+            serde_json::to_value(build_index(&repo, &RepoConfig::default())).expect("index json");
+
+        let packages = index["packages"][BORGER].as_array().expect("packages");
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0]["versionCode"], 1);
+        assert_eq!(index["apps"][0]["suggestedVersionCode"], "1");
+        // An app whose only version is quarantined is not installable
+        assert!(index["packages"].get(SUNDHED).is_none());
+        assert_eq!(index["apps"].as_array().expect("apps").len(), 1);
+    }
+
+    #[test]
+    fn test_timestamp_does_not_regress_behind_issued() {
+        let mut repo = fixture();
+        let last_change = repo.timestamp();
+        // A clock moved backwards since a later index was issued
+        repo.issued_timestamp = last_change + 60_000;
+
+        let index =
+            serde_json::to_value(build_index(&repo, &RepoConfig::default())).expect("index json");
+        assert_eq!(index["repo"]["timestamp"], last_change + 60_000);
+
+        repo.issued_timestamp = last_change - 60_000;
+        assert_eq!(repo.timestamp(), last_change);
+    }
+
+    #[test]
+    fn test_index_bytes_are_deterministic() {
+        let serialize = |repo: &Repo| {
+            serde_json::to_vec(&build_index(repo, &RepoConfig::default())).expect("index bytes")
+        };
+        let repo = fixture();
+        let mut reordered = fixture();
+        reordered.apps.reverse();
+        reordered.versions.reverse();
+
+        let bytes = serialize(&repo);
+        assert_eq!(serialize(&repo), bytes);
+        assert_eq!(serialize(&reordered), bytes);
+    }
+
+    #[test]
+    fn test_empty_index() {
+        let repo = Repo {
+            apps: Vec::new(),
+            versions: Vec::new(),
+            issued_timestamp: 0,
+        };
+        let index =
+            serde_json::to_value(build_index(&repo, &RepoConfig::default())).expect("index json");
+        assert_eq!(index["apps"], json!([]));
+        assert_eq!(index["packages"], json!({}));
+        assert_eq!(index["repo"]["timestamp"], 0);
+    }
+
+    fn signer() -> SigningService {
+        SigningService::ephemeral("dk-appstore.test").expect("signing service")
+    }
+
+    fn index_bytes() -> Vec<u8> {
+        serde_json::to_vec(&sample_index()).expect("index bytes")
+    }
+
+    #[tokio::test]
+    async fn test_index_jar_verifies_with_repo_certificate() {
+        let signer = signer();
+        let response = jar_response(&signer, &HeaderMap::new(), &index_bytes()).expect("jar");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "application/java-archive"
+        );
+
+        let jar = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        let index = dk_signing::jar::verify_jar(&jar, signer.certificate(), INDEX_ENTRY_NAME)
+            .expect("valid signature");
+        let index: Value = serde_json::from_slice(&index).expect("index json");
+        assert_eq!(index["repo"]["name"], "DK-AppStore");
+    }
+
+    #[tokio::test]
+    async fn test_index_jar_requires_signer() {
+        let result = get_index_jar(State(AppState::disconnected()), HeaderMap::new()).await;
+        assert!(matches!(result, Err(ApiError::Internal(_))));
+    }
+
+    #[tokio::test]
+    async fn test_index_database_error_is_internal() {
+        let result = get_index(
+            State(AppState::disconnected()),
+            Query(IndexQuery::default()),
+            HeaderMap::new(),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiError::Internal(_))));
+    }
+
+    fn index_query(uri: &str) -> IndexQuery {
+        let uri = uri.parse().expect("uri");
+        Query::try_from_uri(&uri).expect("query").0
+    }
+
+    fn accept(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::ACCEPT,
+            HeaderValue::from_str(value).expect("header value"),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_index_query_category() {
+        let query = index_query("/index?category=Public%20Services");
+        assert_eq!(
+            query.category().expect("category"),
+            Some(Category::PublicServices)
+        );
+        assert_eq!(index_query("/index").category().expect("category"), None);
+        let result = index_query("/index?category=%20").category();
+        assert!(matches!(result, Err(ApiError::BadRequest(_))));
+        let long = format!("/index?category={}", "a".repeat(MAX_CATEGORY_LEN + 1));
+        let result = index_query(&long).category();
+        assert!(matches!(result, Err(ApiError::BadRequest(_))));
+    }
+
+    /// Repository with the sample app in the custom category `Kommune`.
+    fn custom_category_repository() -> crate::repository::InMemoryAppRepository {
+        let repository = crate::repository::InMemoryAppRepository::default();
+        let mut app = crate::routes::apps::tests::sample_app();
+        app.categories = vec![Category::Other("Kommune".to_string())];
+        repository.insert_version(crate::routes::apps::tests::sample_version(&app, 1, "1.0"));
+        repository.insert_app(app);
+        repository
+    }
+
+    #[tokio::test]
+    async fn test_known_categories() {
+        let repository = custom_category_repository();
+        let known = |category: Option<Category>| {
+            let repository = &repository;
+            async move {
+                is_known_category(repository, category.as_ref())
+                    .await
+                    .expect("categories")
+            }
+        };
+
+        assert!(known(None).await);
+        assert!(known(Some(Category::Security)).await);
+        assert!(known(Some(Category::Other("Kommune".to_string()))).await);
+        assert!(!known(Some(Category::Other("Astrology".to_string()))).await);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_category_index_is_served_empty() {
+        let state = AppState {
+            apps: std::sync::Arc::new(custom_category_repository()),
+            ..AppState::disconnected()
+        };
+
+        for uri in [
+            "/index?category=Astrology",
+            "/index?category=Astrology&version=2",
+        ] {
+            let query = Query(index_query(uri));
+            let response = get_index(State(state.clone()), query, HeaderMap::new())
+                .await
+                .expect("index");
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .expect("body");
+            let index: Value = serde_json::from_slice(&body).expect("json");
+            assert_eq!(index["packages"], json!({}), "{uri}");
+        }
+    }
+
+    /// Index-v1 of the fixture with `sundhed` moved to Sports & Health,
+    /// restricted to `category`.
+    fn category_index(category: &Category) -> serde_json::Value {
+        let mut repo = fixture();
+        repo.apps[1].categories = vec![Category::SportsHealth];
+        repo.retain_category(category);
+        serde_json::to_value(build_index(&repo, &RepoConfig::default())).expect("index json")
+    }
+
+    #[test]
+    fn test_category_index_contains_only_matching_apps() {
+        let index = category_index(&Category::SportsHealth);
+
+        let apps = index["apps"].as_array().expect("apps");
+        assert_eq!(apps.len(), 1);
+        assert_eq!(apps[0]["packageName"], SUNDHED);
+        assert!(apps[0]["categories"]
+            .as_array()
+            .expect("categories")
+            .contains(&json!("Sports & Health")));
+        let packages = index["packages"].as_object().expect("packages");
+        assert_eq!(packages.keys().collect::<Vec<_>>(), [SUNDHED]);
+        assert!(index["repo"]["timestamp"].as_i64().expect("timestamp") > 0);
+        assert_eq!(index["requests"], json!({ "install": [], "uninstall": [] }));
+    }
+
+    #[test]
+    fn test_unknown_category_index_is_empty() {
+        let index = category_index(&Category::Other("Astrology".to_string()));
+
+        assert_eq!(index["apps"], json!([]));
+        assert_eq!(index["packages"], json!({}));
+        assert!(index["repo"]["name"].is_string());
+        assert_eq!(index["requests"], json!({ "install": [], "uninstall": [] }));
+    }
+
+    #[test]
+    fn test_index_format_defaults_to_v1() {
+        let format = IndexFormat::negotiate(&index_query("/index"), &HeaderMap::new());
+        assert_eq!(format.expect("format"), IndexFormat::V1);
+        let format = IndexFormat::negotiate(&index_query("/index"), &accept("application/json"));
+        assert_eq!(format.expect("format"), IndexFormat::V1);
+    }
+
+    #[test]
+    fn test_index_format_v2_via_param() {
+        for uri in ["/index?version=2", "/index?version=v2"] {
+            let format = IndexFormat::negotiate(&index_query(uri), &HeaderMap::new());
+            assert_eq!(format.expect("format"), IndexFormat::V2, "{uri}");
+        }
+        // The parameter takes precedence over the Accept header
+        let format =
+            IndexFormat::negotiate(&index_query("/index?version=1"), &accept("*/*; version=2"));
+        assert_eq!(format.expect("format"), IndexFormat::V1);
+    }
+
+    #[test]
+    fn test_index_format_v2_via_accept() {
+        let headers = accept("text/html, application/json; charset=utf-8; version=\"2\"");
+        let format = IndexFormat::negotiate(&index_query("/index"), &headers);
+        assert_eq!(format.expect("format"), IndexFormat::V2);
+    }
+
+    #[tokio::test]
+    async fn test_index_rejects_unknown_version() {
+        let format = IndexFormat::negotiate(&index_query("/index?version=3"), &HeaderMap::new());
+        assert!(matches!(format, Err(ApiError::BadRequest(_))));
+        let format = IndexFormat::negotiate(&index_query("/index"), &accept("*/*; version=x"));
+        assert!(matches!(format, Err(ApiError::BadRequest(_))));
+
+        // Rejected before the database is consulted
+        let result = get_index(
+            State(AppState::disconnected()),
+            Query(index_query("/index?version=3")),
+            HeaderMap::new(),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiError::BadRequest(_))));
+    }
+
+    fn if_none_match(etag: &HeaderValue) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, etag.clone());
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_index_conditional_get() {
+        let response = json_response(&HeaderMap::new(), index_bytes());
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CACHE_CONTROL],
+            INDEX_CACHE_CONTROL
+        );
+        let etag = response.headers()[header::ETAG].clone();
+
+        let response = json_response(&if_none_match(&etag), index_bytes());
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        assert!(body.is_empty());
+
+        let stale = HeaderValue::from_static("\"0000-json\"");
+        let response = json_response(&if_none_match(&stale), index_bytes());
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_index_jar_conditional_get() {
+        let signer = signer();
+        let response = jar_response(&signer, &HeaderMap::new(), &index_bytes()).expect("jar");
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[header::ETAG].clone();
+        assert!(etag.to_str().expect("etag").ends_with("-jar\""));
+
+        let response = jar_response(&signer, &if_none_match(&etag), &index_bytes()).expect("jar");
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    /// Connect to `DATABASE_URL`, apply migrations, and seed the
+    /// [`fixture`] apps and versions.
+    async fn seeded_db() -> PgPool {
+        let db = crate::state::migrated_db().await;
+
+        let Repo { apps, versions, .. } = fixture();
+        for app in &apps {
+            sqlx::query(
+                "INSERT INTO apps (id, package_id, name, summary, description, categories, \
+                 version_code, version_name, created_at, updated_at) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) \
+                 ON CONFLICT (package_id) DO UPDATE SET name = EXCLUDED.name, \
+                 summary = EXCLUDED.summary, description = EXCLUDED.description, \
+                 categories = EXCLUDED.categories",
+            )
+            .bind(app.id)
+            .bind(app.package_id.as_str())
+            .bind(SqlJson(&app.name))
+            .bind(SqlJson(&app.summary))
+            .bind(SqlJson(&app.description))
+            .bind(
+                app.categories
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>(),
+            )
+            .bind(app.version_code)
+            .bind(&app.version_name)
+            .bind(app.created_at)
+            .bind(app.updated_at)
+            .execute(&db)
+            .await
+            .expect("seed app");
+        }
+
+        for indexed in &versions {
+            let version = &indexed.version;
+            sqlx::query(
+                "INSERT INTO app_versions (id, app_id, version_code, version_name, sha256, \
+                 size, min_sdk, target_sdk, permissions, abis, sig, created_at) \
+                 SELECT $1, id, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12 FROM apps \
+                 WHERE package_id = $2 \
+                 ON CONFLICT (app_id, version_code) DO UPDATE SET abis = EXCLUDED.abis, \
+                 deleted_at = NULL",
+            )
+            .bind(version.id)
+            .bind(indexed.package_id.as_str())
+            .bind(version.version_code)
+            .bind(&version.version_name)
+            .bind(version.sha256.to_string())
+            .bind(version.size)
+            .bind(version.min_sdk)
+            .bind(version.target_sdk)
+            .bind(SqlJson(&version.permissions))
+            .bind(&version.abis)
+            .bind(&indexed.sig)
+            .bind(version.created_at)
+            .execute(&db)
+            .await
+            .expect("seed version");
+        }
+
+        db
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_index_from_database() {
+        let db = seeded_db().await;
+        let state = AppState {
+            apps: std::sync::Arc::new(crate::repository::PgAppRepository::new(db.clone())),
+            db,
+            ..AppState::disconnected()
+        };
+
+        let response = get_index(
+            State(state.clone()),
+            Query(IndexQuery::default()),
+            HeaderMap::new(),
+        )
+        .await
+        .expect("index");
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        let index: Value = serde_json::from_slice(&body).expect("index json");
+        assert_index_structure(&index);
+
+        let response = get_index(
+            State(state),
+            Query(index_query("/index?version=2")),
+            HeaderMap::new(),
+        )
+        .await
+        .expect("index");
+        assert_eq!(response.headers()[header::VARY], "Accept");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        let index: Value = serde_json::from_slice(&body).expect("index json");
+        assert!(index["packages"][BORGER]["versions"].is_object(), "{index}");
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_fingerprint_tracks_repository_changes() {
+        let db = seeded_db().await;
+        let info = RepoConfig::default();
+
+        let fingerprint = Repo::fingerprint(&db, &info).await.expect("fingerprint");
+        assert_eq!(
+            Repo::fingerprint(&db, &info).await.expect("fingerprint"),
+            fingerprint
+        );
+
+        let renamed = RepoConfig {
+            name: "Renamed".to_string(),
+            ..RepoConfig::default()
+        };
+        assert_ne!(
+            Repo::fingerprint(&db, &renamed).await.expect("fingerprint"),
+            fingerprint
+        );
+
+        sqlx::query(
+            "UPDATE app_versions v SET deleted_at = now() FROM apps a \
+             WHERE a.id = v.app_id AND a.package_id = $1 AND v.deleted_at IS NULL",
+        )
+        .bind(BORGER)
+        .execute(&db)
+        .await
+        .expect("unpublish");
+        assert_ne!(
+            Repo::fingerprint(&db, &info).await.expect("fingerprint"),
+            fingerprint
+        );
+    }
 }
@@ -0,0 +1,425 @@
+//! F-Droid `index-v2` endpoint.
+//!
+//! Built from the same [`Repo`] model as `index-v1`, so both formats always
+//! describe the same apps and versions.
+
+use std::collections::BTreeMap;
+
+use axum::{extract::State, http::HeaderMap, response::Response};
+use dk_common::config::RepoConfig;
+use dk_common::localized::{Localized, DEFAULT_LOCALE};
+use dk_common::types::{Category, DonationKind, Sha256};
+use serde::Serialize;
+
+use crate::error::ApiError;
+use crate::index_cache::{self, cache_key};
+use crate::repository::AppRepository;
+use crate::routes::index::{
+    custom_donations, donation_account, is_known_category, json_response, sliced, IndexedVersion,
+    Repo,
+};
+use crate::state::AppState;
+
+/// Repository index in the `index-v2` format.
+#[derive(Serialize)]
+pub struct IndexV2 {
+    repo: RepoV2,
+    packages: BTreeMap<String, PackageV2>,
+}
+
+/// Repository information.
+#[derive(Serialize)]
+pub struct RepoV2 {
+    name: Localized<String>,
+    description: Localized<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    mirrors: Vec<MirrorV2>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon: Option<Localized<IconV2>>,
+    /// Last change to the repository, in milliseconds since the epoch.
+    timestamp: i64,
+}
+
+/// A mirror serving the same repository.
+#[derive(Serialize)]
+pub struct MirrorV2 {
+    url: String,
+}
+
+/// Repository icon, relative to the repository address. Clients treat the
+/// digest and size of a file as optional, and none are known for the icon.
+#[derive(Serialize)]
+pub struct IconV2 {
+    name: String,
+}
+
+/// An app and its published versions.
+#[derive(Serialize)]
+pub struct PackageV2 {
+    metadata: MetadataV2,
+    /// Versions keyed by the SHA-256 of their APK.
+    versions: BTreeMap<String, VersionV2>,
+}
+
+/// App metadata.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataV2 {
+    added: i64,
+    last_updated: i64,
+    name: Localized<String>,
+    summary: Localized<String>,
+    description: Localized<String>,
+    categories: Vec<String>,
+    /// SPDX license identifier.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    license: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    issue_tracker: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    web_site: Option<String>,
+    /// URLs of custom donation pages.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    donate: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    liberapay: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    open_collective: Option<String>,
+}
+
+/// A published version.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionV2 {
+    added: i64,
+    file: FileV2,
+    manifest: ManifestV2,
+    /// Anti-features keyed by F-Droid code, each with a localized reason;
+    /// index-v2 records them per version.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    anti_features: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// Downloadable file, relative to the repository address.
+#[derive(Serialize)]
+pub struct FileV2 {
+    name: String,
+    sha256: Sha256,
+    size: i64,
+}
+
+/// Manifest fields clients use to check compatibility.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestV2 {
+    version_name: String,
+    version_code: i64,
+    uses_sdk: UsesSdkV2,
+    /// ABIs of the bundled native libraries; left out for pure-Java APKs.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    nativecode: Vec<String>,
+    uses_permission: Vec<PermissionV2>,
+}
+
+/// SDK range of a version.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsesSdkV2 {
+    min_sdk_version: i32,
+    target_sdk_version: i32,
+}
+
+/// Requested permission.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionV2 {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_sdk_version: Option<i32>,
+}
+
+impl From<&IndexedVersion> for VersionV2 {
+    fn from(indexed: &IndexedVersion) -> Self {
+        let version = &indexed.version;
+        Self {
+            added: version.created_at.timestamp_millis(),
+            file: FileV2 {
+                name: format!(
+                    "/{}",
+                    indexed.package_id.apk_file_name(version.version_code)
+                ),
+                sha256: version.sha256,
+                size: version.size,
+            },
+            manifest: ManifestV2 {
+                version_name: version.version_name.clone(),
+                version_code: version.version_code,
+                uses_sdk: UsesSdkV2 {
+                    min_sdk_version: version.min_sdk,
+                    target_sdk_version: version.target_sdk,
+                },
+                nativecode: version.abis.clone(),
+                uses_permission: version
+                    .permissions
+                    .iter()
+                    .map(|permission| PermissionV2 {
+                        name: permission.name.clone(),
+                        max_sdk_version: permission.max_sdk,
+                    })
+                    .collect(),
+            },
+            anti_features: BTreeMap::new(),
+        }
+    }
+}
+
+/// Build the index-v2 representation of `repo`.
+///
+/// As in index-v1, apps without a published version are left out.
+fn build(repo: &Repo, info: &RepoConfig) -> IndexV2 {
+    let packages = repo
+        .published_apps()
+        .map(|(app, versions)| {
+            let package = PackageV2 {
+                metadata: MetadataV2 {
+                    added: app.created_at.timestamp_millis(),
+                    last_updated: app.updated_at.max(app.created_at).timestamp_millis(),
+                    name: app.name.clone(),
+                    summary: app.summary.clone(),
+                    description: app.description.clone(),
+                    categories: app.categories.iter().map(ToString::to_string).collect(),
+                    license: app.license.as_ref().map(ToString::to_string),
+                    source_code: app.source_url.as_ref().map(ToString::to_string),
+                    issue_tracker: app.issue_tracker_url.as_ref().map(ToString::to_string),
+                    web_site: app.web_url.as_ref().map(ToString::to_string),
+                    donate: custom_donations(app).collect(),
+                    liberapay: donation_account(app, DonationKind::Liberapay),
+                    open_collective: donation_account(app, DonationKind::OpenCollective),
+                },
+                versions: versions
+                    .into_iter()
+                    .map(|indexed| {
+                        let mut version = VersionV2::from(indexed);
+                        version.anti_features = app
+                            .antifeatures
+                            .iter()
+                            .map(|antifeature| (antifeature.to_string(), BTreeMap::new()))
+                            .collect();
+                        (indexed.version.sha256.to_string(), version)
+                    })
+                    .collect(),
+            };
+            (app.package_id.to_string(), package)
+        })
+        .collect();
+
+    IndexV2 {
+        repo: RepoV2 {
+            name: Localized::single(DEFAULT_LOCALE, info.name.clone()),
+            description: Localized::single(DEFAULT_LOCALE, info.description.clone()),
+            address: info.address.clone(),
+            mirrors: info
+                .mirrors
+                .iter()
+                .map(|url| MirrorV2 { url: url.clone() })
+                .collect(),
+            icon: info.icon.as_ref().map(|icon| {
+                Localized::single(
+                    DEFAULT_LOCALE,
+                    IconV2 {
+                        name: format!("/{icon}"),
+                    },
+                )
+            }),
+            timestamp: repo.timestamp(),
+        },
+        packages,
+    }
+}
+
+/// Load the repository and serialize its `index-v2` representation,
+/// restricted to `category` if given.
+///
+/// # Errors
+///
+/// Returns [`ApiError::Internal`] if the repository cannot be loaded.
+pub async fn generate(
+    apps: &dyn AppRepository,
+    info: &RepoConfig,
+    category: Option<&Category>,
+) -> Result<Vec<u8>, ApiError> {
+    serialize(&apps.index_repo(info, category).await?, info)
+}
+
+/// Serialize the index-v2 representation of `repo`.
+fn serialize(repo: &Repo, info: &RepoConfig) -> Result<Vec<u8>, ApiError> {
+    serde_json::to_vec(&build(repo, info))
+        .map_err(|err| ApiError::Internal(format!("failed to serialize index: {err}")))
+}
+
+/// The serialized index-v2, restricted to `category` if given, from the
+/// cache if the repository is unchanged.
+///
+/// # Errors
+///
+/// Returns [`ApiError::Internal`] if the repository cannot be loaded.
+pub async fn cached(state: &AppState, category: Option<&Category>) -> Result<Vec<u8>, ApiError> {
+    if !is_known_category(state.apps.as_ref(), category).await? {
+        return serialize(&Repo::default(), &state.repo);
+    }
+    let fingerprint = state.apps.index_fingerprint(&state.repo).await?;
+    index_cache::cached(
+        &state.redis,
+        &state.index_flights,
+        &cache_key(&sliced("v2", category), &fingerprint),
+        generate(state.apps.as_ref(), &state.repo, category),
+    )
+    .await
+}
+
+/// Get the repository index in the `index-v2` format.
+///
+/// GET /api/v1/index-v2
+///
+/// Answers `304 Not Modified` when `If-None-Match` holds the current ETag.
+pub async fn get_index_v2(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    Ok(json_response(&headers, cached(&state, None).await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use dk_common::types::{Antifeature, DonationLink, WebUrl};
+
+    use crate::routes::index::tests::{fixture, BORGER, SUNDHED};
+
+    /// Expected index-v2 for the [`fixture`] repository.
+    const GOLDEN: &str = include_str!("testdata/index-v2.json");
+
+    #[test]
+    fn test_index_v2_matches_golden_file() {
+        let index =
+            serde_json::to_value(build(&fixture(), &RepoConfig::default())).expect("index json");
+        let expected: Value = serde_json::from_str(GOLDEN).expect("golden json");
+        assert_eq!(index, expected);
+    }
+
+    #[test]
+    fn test_index_v2_advertises_mirrors() {
+        let info = RepoConfig {
+            mirrors: vec![
+                "https://mirror.appstore.digst.dk/repo".to_string(),
+                "https://appstore.example.eu/repo".to_string(),
+            ],
+            ..RepoConfig::default()
+        };
+        let index = serde_json::to_value(build(&fixture(), &info)).expect("index json");
+        assert_eq!(
+            index["repo"]["mirrors"],
+            serde_json::json!([
+                {"url": "https://mirror.appstore.digst.dk/repo"},
+                {"url": "https://appstore.example.eu/repo"},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_index_v2_describes_configured_repo() {
+        let info = RepoConfig {
+            name: "Borgerapps".to_string(),
+            description: "Apps fra det offentlige".to_string(),
+            icon: Some("icons/borgerapps.png".to_string()),
+            ..RepoConfig::default()
+        };
+        let index = serde_json::to_value(build(&fixture(), &info)).expect("index json");
+        assert_eq!(index["repo"]["name"][DEFAULT_LOCALE], "Borgerapps");
+        assert_eq!(
+            index["repo"]["description"][DEFAULT_LOCALE],
+            "Apps fra det offentlige"
+        );
+        assert_eq!(
+            index["repo"]["icon"][DEFAULT_LOCALE],
+            serde_json::json!({"name": "/icons/borgerapps.png"})
+        );
+    }
+
+    #[test]
+    fn test_index_v2_lists_nativecode_per_version() {
+        let index =
+            serde_json::to_value(build(&fixture(), &RepoConfig::default())).expect("index json");
+
+        let native = &index["packages"][BORGER]["versions"][format!("{:064x}", 2)];
+        assert_eq!(
+            native["manifest"]["nativecode"],
+            serde_json::json!(["arm64-v8a"])
+        );
+        let sundhed = index["packages"][SUNDHED]["versions"]
+            .as_object()
+            .expect("versions");
+        assert!(sundhed
+            .values()
+            .all(|version| version["manifest"].get("nativecode").is_none()));
+    }
+
+    #[test]
+    fn test_index_v2_lists_antifeatures_per_version() {
+        let mut repo = fixture();
+        repo.apps[0].antifeatures = vec![Antifeature::Tracking];
+        let index = serde_json::to_value(build(&repo, &RepoConfig::default())).expect("index json");
+
+        let versions = index["packages"][BORGER]["versions"]
+            .as_object()
+            .expect("versions");
+        assert_eq!(versions.len(), 2);
+        for version in versions.values() {
+            assert_eq!(version["antiFeatures"], serde_json::json!({"Tracking": {}}));
+        }
+        let sundhed = index["packages"][SUNDHED]["versions"]
+            .as_object()
+            .expect("versions");
+        assert!(sundhed
+            .values()
+            .all(|version| version.get("antiFeatures").is_none()));
+    }
+
+    #[test]
+    fn test_index_v2_lists_custom_donations() {
+        let mut repo = fixture();
+        repo.apps[0].donate = ["https://borger.dk/doner", "https://example.dk/donate"]
+            .into_iter()
+            .map(|url| {
+                DonationLink::new(DonationKind::Custom, WebUrl::parse(url).expect("url"))
+                    .expect("donation link")
+            })
+            .chain([DonationLink::new(
+                DonationKind::Liberapay,
+                WebUrl::parse("https://liberapay.com/borger").expect("url"),
+            )
+            .expect("donation link")])
+            .collect();
+        let index = serde_json::to_value(build(&repo, &RepoConfig::default())).expect("index json");
+
+        let metadata = &index["packages"][BORGER]["metadata"];
+        assert_eq!(
+            metadata["donate"],
+            serde_json::json!(["https://borger.dk/doner", "https://example.dk/donate"])
+        );
+        assert_eq!(metadata["liberapay"], "borger");
+        assert!(metadata.get("openCollective").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_index_v2_database_error_is_internal() {
+        let result = get_index_v2(State(AppState::disconnected()), HeaderMap::new()).await;
+        assert!(matches!(result, Err(ApiError::Internal(_))));
+    }
+}
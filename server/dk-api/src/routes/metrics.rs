@@ -1,12 +1,189 @@
-//! Prometheus metrics endpoint.
+//! Prometheus metrics endpoint and request instrumentation.
 
-use axum::response::IntoResponse;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use axum::{
+    body::HttpBody,
+    extract::{MatchedPath, Request},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+use crate::error::ApiError;
+
+/// Total HTTP requests, labelled by method, route, and status class.
+pub const HTTP_REQUESTS_TOTAL: &str = "http_requests_total";
+
+/// HTTP request latency, labelled by method, route, and status class.
+pub const HTTP_REQUEST_DURATION_SECONDS: &str = "http_request_duration_seconds";
+
+/// HTTP response body size, labelled by method, route, and status class.
+pub const HTTP_RESPONSE_SIZE_BYTES: &str = "http_response_size_bytes";
+
+/// Lookups of applications that do not exist.
+pub const APP_NOT_FOUND_TOTAL: &str = "app_not_found_total";
+
+/// Content type of the Prometheus text exposition format.
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Latency histogram buckets, in seconds.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Response size histogram buckets, in bytes; the largest cover APKs.
+const SIZE_BUCKETS: &[f64] = &[
+    100.0,
+    1_000.0,
+    10_000.0,
+    100_000.0,
+    1_000_000.0,
+    10_000_000.0,
+    100_000_000.0,
+];
+
+static RECORDER: OnceLock<Result<PrometheusHandle, String>> = OnceLock::new();
+
+/// Install the global Prometheus recorder, once per process.
+///
+/// Later calls return the handle of the recorder installed first.
+pub fn install_recorder() -> Result<PrometheusHandle, String> {
+    RECORDER
+        .get_or_init(|| {
+            let handle = PrometheusBuilder::new()
+                .set_buckets_for_metric(
+                    Matcher::Full(HTTP_REQUEST_DURATION_SECONDS.to_string()),
+                    LATENCY_BUCKETS,
+                )
+                .and_then(|builder| {
+                    builder.set_buckets_for_metric(
+                        Matcher::Full(HTTP_RESPONSE_SIZE_BYTES.to_string()),
+                        SIZE_BUCKETS,
+                    )
+                })
+                .and_then(PrometheusBuilder::install_recorder)
+                .map_err(|err| format!("failed to install metrics recorder: {err}"))?;
+
+            metrics::describe_counter!(HTTP_REQUESTS_TOTAL, "Total number of HTTP requests");
+            metrics::describe_histogram!(
+                HTTP_REQUEST_DURATION_SECONDS,
+                metrics::Unit::Seconds,
+                "HTTP request latency"
+            );
+            metrics::describe_histogram!(
+                HTTP_RESPONSE_SIZE_BYTES,
+                metrics::Unit::Bytes,
+                "HTTP response body size"
+            );
+            metrics::describe_counter!(
+                APP_NOT_FOUND_TOTAL,
+                "Requests for applications that do not exist"
+            );
+
+            Ok(handle)
+        })
+        .clone()
+}
+
+/// Class of `status` for labels: `2xx`, `4xx`, and so on.
+const fn status_class(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    }
+}
+
+/// Size of the body of `response`, if known up front.
+///
+/// Streamed bodies such as APK downloads report their size in
+/// `Content-Length` only.
+fn response_size(response: &Response) -> Option<u64> {
+    response.body().size_hint().exact().or_else(|| {
+        response
+            .headers()
+            .get(header::CONTENT_LENGTH)?
+            .to_str()
+            .ok()?
+            .parse()
+            .ok()
+    })
+}
+
+/// Middleware recording request count, latency, and response size per
+/// matched route.
+///
+/// Requests are labelled with the route template, e.g.
+/// `/api/v1/apps/:package_id`, and the status class rather than the raw
+/// path and status. Only requests that match a route are recorded, so
+/// arbitrary paths cannot inflate label cardinality.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| "unmatched".to_string(), |path| path.as_str().to_string());
+
+    let response = next.run(request).await;
+
+    let labels = [
+        ("method", method),
+        ("route", route),
+        ("status", status_class(response.status()).to_string()),
+    ];
+    metrics::counter!(HTTP_REQUESTS_TOTAL, &labels).increment(1);
+    metrics::histogram!(HTTP_REQUEST_DURATION_SECONDS, &labels)
+        .record(start.elapsed().as_secs_f64());
+    if let Some(size) = response_size(&response) {
+        #[allow(clippy::cast_precision_loss)] // Sizes are far below 2^52
+        metrics::histogram!(HTTP_RESPONSE_SIZE_BYTES, &labels).record(size as f64);
+    }
+
+    response
+}
 
 /// Prometheus metrics endpoint.
 ///
 /// Returns metrics in Prometheus text format.
-pub async fn metrics_handler() -> impl IntoResponse {
-    // TODO: Integrate with metrics-exporter-prometheus
-    // For now, return a placeholder
-    "# DK-AppStore Metrics\n# TODO: Implement metrics collection\n"
+pub async fn metrics_handler() -> Result<impl IntoResponse, ApiError> {
+    let handle = install_recorder().map_err(ApiError::Internal)?;
+    Ok((
+        [(header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)],
+        handle.render(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+
+    use super::*;
+
+    #[test]
+    fn test_status_class() {
+        assert_eq!(status_class(StatusCode::OK), "2xx");
+        assert_eq!(status_class(StatusCode::NOT_MODIFIED), "3xx");
+        assert_eq!(status_class(StatusCode::NOT_FOUND), "4xx");
+        assert_eq!(status_class(StatusCode::SERVICE_UNAVAILABLE), "5xx");
+    }
+
+    #[test]
+    fn test_response_size() {
+        assert_eq!(response_size(&Response::new(Body::from("hello"))), Some(5));
+
+        let stream = futures_util::stream::empty::<Result<axum::body::Bytes, std::io::Error>>();
+        let mut response = Response::new(Body::from_stream(stream));
+        assert_eq!(response_size(&response), None);
+        response.headers_mut().insert(
+            header::CONTENT_LENGTH,
+            "4096".parse().expect("header value"),
+        );
+        assert_eq!(response_size(&response), Some(4096));
+    }
 }
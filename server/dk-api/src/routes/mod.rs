@@ -1,6 +1,20 @@
 //! API route handlers.
 
 pub mod apps;
+pub mod builds;
+pub mod categories;
+pub mod download;
 pub mod health;
+pub mod icons;
 pub mod index;
+pub mod index_v2;
 pub mod metrics;
+pub mod openapi;
+pub mod permission_diff;
+pub mod quarantine;
+pub mod reindex;
+pub mod repo;
+pub mod scan;
+pub mod screenshots;
+pub mod stats;
+pub mod upload;
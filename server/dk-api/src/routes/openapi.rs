@@ -0,0 +1,118 @@
+//! OpenAPI description of the public API.
+
+// The `OpenApi` derive expands to a `for_each` over the paths
+#![allow(clippy::needless_for_each)]
+
+use axum::Json;
+use utoipa::OpenApi;
+
+use crate::error::ErrorResponse;
+use crate::routes::{apps, categories, download, permission_diff, stats};
+
+/// OpenAPI document of the v1 API, generated from the handler annotations.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "DK-AppStore API", description = "Danish public sector app store"),
+    servers((url = "/api/v1")),
+    paths(
+        apps::list_apps,
+        apps::get_app,
+        apps::batch_get_apps,
+        apps::get_app_versions,
+        apps::delete_version,
+        categories::list_categories,
+        download::download_apk,
+        permission_diff::get_permission_diff,
+        stats::get_stats,
+    ),
+    components(schemas(
+        apps::AppsListResponse,
+        apps::AppSummary,
+        apps::AppDetail,
+        apps::BatchAppsRequest,
+        apps::BatchAppsResponse,
+        apps::AppVersionResponse,
+        apps::VersionSort,
+        categories::CategoriesResponse,
+        categories::CategoryCount,
+        permission_diff::PermissionDiffResponse,
+        stats::RepositoryStats,
+        ErrorResponse,
+    )),
+    tags((name = "apps", description = "Browsing and managing applications"))
+)]
+pub struct ApiDoc;
+
+/// Get the OpenAPI document.
+///
+/// GET /api/v1/openapi.json
+pub async fn get_openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request, http::StatusCode};
+    use serde_json::Value;
+    use tower::ServiceExt;
+    use tower_http::cors::CorsLayer;
+
+    use crate::state::AppState;
+
+    async fn spec() -> Value {
+        let app = crate::create_app(AppState::disconnected(), CorsLayer::new());
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/v1/openapi.json")
+                    .body(Body::empty())
+                    .expect("request"),
+            )
+            .await
+            .expect("response");
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        serde_json::from_slice(&body).expect("spec json")
+    }
+
+    #[tokio::test]
+    async fn test_spec_documents_app_paths() {
+        let spec = spec().await;
+        let app = &spec["paths"]["/apps/{package_id}"]["get"];
+        assert!(app.is_object(), "{spec}");
+        let params: Vec<&str> = app["parameters"]
+            .as_array()
+            .expect("parameters")
+            .iter()
+            .filter_map(|p| p["name"].as_str())
+            .collect();
+        assert_eq!(params, ["package_id", "locale"]);
+
+        let list = &spec["paths"]["/apps"]["get"]["parameters"];
+        for name in ["q", "cursor", "limit", "locale"] {
+            assert!(
+                list.as_array()
+                    .expect("parameters")
+                    .iter()
+                    .any(|p| p["name"] == name),
+                "missing {name}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spec_has_error_schema() {
+        let spec = spec().await;
+        let error = &spec["components"]["schemas"]["ErrorResponse"];
+        for field in ["code", "error", "message"] {
+            assert!(error["properties"][field].is_object(), "missing {field}");
+        }
+        let not_found = &spec["paths"]["/apps/{package_id}"]["get"]["responses"]["404"];
+        assert_eq!(
+            not_found["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/ErrorResponse"
+        );
+    }
+}
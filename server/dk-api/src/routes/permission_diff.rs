@@ -0,0 +1,239 @@
+//! Permission changes between APK versions.
+
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use dk_common::types::{AppVersion, Permission};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::error::ApiError;
+use crate::routes::apps::{app_not_found, version_not_found};
+use crate::state::AppState;
+
+/// Query parameters for [`get_permission_diff`].
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct PermissionDiffQuery {
+    /// Version code to compare with; defaults to the previous version.
+    from: Option<i64>,
+}
+
+/// Permissions gained and lost by a version.
+#[derive(Debug, PartialEq, Eq, Serialize, ToSchema)]
+pub struct PermissionDiffResponse {
+    /// The version compared.
+    pub version_code: i64,
+    /// The version compared with; absent for a first version, which is
+    /// compared with no permissions at all.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_version_code: Option<i64>,
+    /// Permissions requested by `version_code` but not `from_version_code`,
+    /// as `{"name": ..., "maxSdk": ...}` objects, by name.
+    #[schema(value_type = Vec<Object>)]
+    pub added: Vec<Permission>,
+    /// Permissions requested by `from_version_code` but not `version_code`.
+    #[schema(value_type = Vec<Object>)]
+    pub removed: Vec<Permission>,
+}
+
+impl PermissionDiffResponse {
+    /// Permissions gained and lost going from `from` to `to`. Permissions
+    /// are compared by name.
+    fn between(from: Option<&AppVersion>, to: &AppVersion) -> Self {
+        let before = by_name(from);
+        let after = by_name(Some(to));
+        Self {
+            version_code: to.version_code,
+            from_version_code: from.map(|version| version.version_code),
+            added: missing_from(&after, &before),
+            removed: missing_from(&before, &after),
+        }
+    }
+}
+
+/// Permissions of `version`, by name; none without a version.
+fn by_name(version: Option<&AppVersion>) -> BTreeMap<&str, &Permission> {
+    version
+        .into_iter()
+        .flat_map(|version| &version.permissions)
+        .map(|permission| (permission.name.as_str(), permission))
+        .collect()
+}
+
+/// Those of `permissions` whose name is not in `other`.
+fn missing_from(
+    permissions: &BTreeMap<&str, &Permission>,
+    other: &BTreeMap<&str, &Permission>,
+) -> Vec<Permission> {
+    permissions
+        .iter()
+        .filter(|(name, _)| !other.contains_key(*name))
+        .map(|(_, permission)| Permission::clone(permission))
+        .collect()
+}
+
+/// The latest of `versions` with `version_code`.
+fn find(versions: &[AppVersion], version_code: i64) -> Option<&AppVersion> {
+    versions
+        .iter()
+        .filter(|version| version.version_code == version_code)
+        .max_by(|a, b| a.cmp_release(b))
+}
+
+/// Get the permissions an APK version adds and removes.
+///
+/// GET /api/v1/apps/:package_id/versions/:version_code/permission-diff?from=3
+///
+/// Compared with the `from` version, or else with the highest published
+/// version code below `version_code`.
+#[utoipa::path(
+    get,
+    path = "/apps/{package_id}/versions/{version_code}/permission-diff",
+    tag = "apps",
+    params(
+        ("package_id" = String, Path, description = "Package identifier, e.g. `dk.digst.mitid`"),
+        ("version_code" = i64, Path, description = "Android versionCode of the version"),
+        PermissionDiffQuery,
+    ),
+    responses(
+        (status = 200, description = "Permission changes", body = PermissionDiffResponse),
+        (status = 404, description = "No such application or version", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(
+    skip_all,
+    fields(package_id = %package_id, version_code = version_code),
+    err(level = "info", Debug)
+)]
+pub async fn get_permission_diff(
+    State(state): State<AppState>,
+    Path((package_id, version_code)): Path<(String, i64)>,
+    Query(query): Query<PermissionDiffQuery>,
+) -> Result<Json<PermissionDiffResponse>, ApiError> {
+    let versions = state
+        .apps
+        .get_versions(&package_id)
+        .await?
+        .ok_or_else(|| app_not_found(&package_id))?;
+    let version_not_found = |code| version_not_found(&package_id, code);
+
+    let to = find(&versions, version_code).ok_or_else(|| version_not_found(version_code))?;
+    let from = match query.from {
+        Some(from) => Some(find(&versions, from).ok_or_else(|| version_not_found(from))?),
+        None => versions
+            .iter()
+            .map(|version| version.version_code)
+            .filter(|code| *code < version_code)
+            .max()
+            .and_then(|previous| find(&versions, previous)),
+    };
+
+    Ok(Json(PermissionDiffResponse::between(from, to)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use dk_common::types::App;
+
+    use super::*;
+    use crate::repository::InMemoryAppRepository;
+    use crate::routes::apps::tests::{sample_app, sample_version};
+
+    fn permission(name: &str) -> Permission {
+        Permission {
+            name: format!("android.permission.{name}"),
+            max_sdk: None,
+        }
+    }
+
+    fn version(app: &App, version_code: i64, permissions: &[&str]) -> AppVersion {
+        let mut version = sample_version(app, version_code, "1.0");
+        version.permissions = permissions.iter().map(|name| permission(name)).collect();
+        version
+    }
+
+    /// State with versions 1, 2, 3 and 5 of `dk.digst.mitid`.
+    fn state() -> AppState {
+        let repository = InMemoryAppRepository::default();
+        let app = sample_app();
+        repository.insert_version(version(&app, 1, &["INTERNET"]));
+        repository.insert_version(version(&app, 2, &["INTERNET", "CAMERA"]));
+        repository.insert_version(version(&app, 3, &["CAMERA"]));
+        repository.insert_version(version(&app, 5, &["CAMERA"]));
+        repository.insert_app(app);
+        AppState {
+            apps: Arc::new(repository),
+            ..AppState::disconnected()
+        }
+    }
+
+    async fn diff(
+        version_code: i64,
+        from: Option<i64>,
+    ) -> Result<PermissionDiffResponse, ApiError> {
+        get_permission_diff(
+            State(state()),
+            Path(("dk.digst.mitid".to_string(), version_code)),
+            Query(PermissionDiffQuery { from }),
+        )
+        .await
+        .map(|Json(diff)| diff)
+    }
+
+    #[tokio::test]
+    async fn test_added_permission() {
+        let diff = diff(2, None).await.expect("diff");
+        assert_eq!(diff.from_version_code, Some(1));
+        assert_eq!(diff.added, [permission("CAMERA")]);
+        assert!(diff.removed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_removed_permission() {
+        let diff = diff(3, None).await.expect("diff");
+        assert_eq!(diff.from_version_code, Some(2));
+        assert!(diff.added.is_empty());
+        assert_eq!(diff.removed, [permission("INTERNET")]);
+    }
+
+    #[tokio::test]
+    async fn test_identical_permissions_give_empty_diff() {
+        // Version 4 was never published, so 3 is the previous version
+        let diff = diff(5, None).await.expect("diff");
+        assert_eq!(diff.from_version_code, Some(3));
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_explicit_from_version() {
+        let diff = diff(5, Some(1)).await.expect("diff");
+        assert_eq!(diff.from_version_code, Some(1));
+        assert_eq!(diff.added, [permission("CAMERA")]);
+        assert_eq!(diff.removed, [permission("INTERNET")]);
+    }
+
+    #[tokio::test]
+    async fn test_first_version_adds_all_permissions() {
+        let diff = diff(1, None).await.expect("diff");
+        assert_eq!(diff.from_version_code, None);
+        assert_eq!(diff.added, [permission("INTERNET")]);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_versions_are_not_found() {
+        assert!(matches!(
+            diff(4, None).await,
+            Err(ApiError::VersionNotFound(_))
+        ));
+        assert!(matches!(
+            diff(5, Some(4)).await,
+            Err(ApiError::VersionNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,29 @@
+//! Quarantine administration endpoint.
+
+use axum::{extract::State, Json};
+use dk_scanner::{QuarantineStore, QuarantinedVersion};
+
+use crate::error::ApiError;
+use crate::state::AppState;
+
+/// List quarantined APK versions, most recent first.
+///
+/// GET /api/v1/admin/quarantine
+///
+/// Requires an API key.
+pub async fn list_quarantine(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<QuarantinedVersion>>, ApiError> {
+    Ok(Json(QuarantineStore::new(state.db).list().await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_database_error_is_internal() {
+        let result = list_quarantine(State(AppState::disconnected())).await;
+        assert!(matches!(result, Err(ApiError::Internal(_))));
+    }
+}
@@ -0,0 +1,115 @@
+//! Index regeneration endpoint.
+
+use axum::{extract::State, Json};
+use serde::Serialize;
+
+use crate::error::ApiError;
+use crate::index_cache::{self, cache_key};
+use crate::routes::index;
+use crate::routes::index_v2;
+use crate::state::AppState;
+
+/// The index produced by a reindex.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct ReindexResponse {
+    /// Last change to the repository, in milliseconds since the epoch.
+    pub timestamp: i64,
+    /// Number of apps in the index.
+    pub apps: usize,
+}
+
+impl ReindexResponse {
+    /// Summarize the serialized index-v1 `index`.
+    fn of(index: &[u8]) -> Result<Self, ApiError> {
+        let index: serde_json::Value = serde_json::from_slice(index)
+            .map_err(|err| ApiError::Internal(format!("failed to read index: {err}")))?;
+        Ok(Self {
+            timestamp: index["repo"]["timestamp"].as_i64().unwrap_or_default(),
+            apps: index["apps"].as_array().map_or(0, Vec::len),
+        })
+    }
+}
+
+/// Drop every cached index and generate the current ones afresh.
+///
+/// POST /api/v1/admin/reindex
+///
+/// Requires an API key. For use after changing the repository outside the
+/// API, e.g. by a bulk import. Calls made while a generation is running
+/// wait for it instead of starting another.
+#[tracing::instrument(skip_all, err(level = "info", Debug))]
+pub async fn reindex(State(state): State<AppState>) -> Result<Json<ReindexResponse>, ApiError> {
+    match index_cache::invalidate(&state.redis).await {
+        Ok(removed) => tracing::info!(removed, "Invalidated cached indexes"),
+        Err(err) => tracing::warn!(error = %err, "Index cache invalidation failed"),
+    }
+
+    let fingerprint = state.apps.index_fingerprint(&state.repo).await?;
+    let (v1_key, v2_key) = (cache_key("v1", &fingerprint), cache_key("v2", &fingerprint));
+    let (v1, _) = tokio::try_join!(
+        index_cache::regenerate(
+            &state.redis,
+            &state.index_flights,
+            &v1_key,
+            index::generate(state.apps.as_ref(), &state.repo, None),
+        ),
+        index_cache::regenerate(
+            &state.redis,
+            &state.index_flights,
+            &v2_key,
+            index_v2::generate(state.apps.as_ref(), &state.repo, None),
+        ),
+    )?;
+
+    let response = ReindexResponse::of(&v1)?;
+    tracing::info!(
+        timestamp = response.timestamp,
+        apps = response.apps,
+        "Regenerated index"
+    );
+    Ok(Json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+    use tower_http::cors::CorsLayer;
+
+    use super::*;
+
+    #[test]
+    fn test_response_summarizes_index() {
+        let index = br#"{"repo":{"timestamp":1714564800000},"apps":[{},{}],"packages":{}}"#;
+        assert_eq!(
+            ReindexResponse::of(index).expect("summary"),
+            ReindexResponse {
+                timestamp: 1_714_564_800_000,
+                apps: 2,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_database_error_is_internal() {
+        let result = reindex(State(AppState::disconnected())).await;
+        assert!(matches!(result, Err(ApiError::Internal(_))));
+    }
+
+    #[tokio::test]
+    async fn test_requires_api_key() {
+        let app = crate::create_app(AppState::disconnected(), CorsLayer::new());
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/admin/reindex")
+                    .body(Body::empty())
+                    .expect("request"),
+            )
+            .await
+            .expect("response");
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
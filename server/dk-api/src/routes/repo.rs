@@ -0,0 +1,135 @@
+//! Repository signing certificate endpoints.
+//!
+//! F-Droid clients pin a repository by the SHA-256 fingerprint of its
+//! signing certificate, so both are published for bootstrapping trust.
+
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use dk_common::hash::sha256_bytes;
+use dk_signing::{Cert, SigningService};
+use serde::Serialize;
+
+use crate::error::ApiError;
+use crate::state::AppState;
+
+/// Content type of a DER-encoded certificate.
+pub const CERT_CONTENT_TYPE: &str = "application/x-x509-ca-cert";
+
+/// Repository fingerprint response.
+#[derive(Debug, Serialize)]
+pub struct FingerprintResponse {
+    /// Label of the active signing key.
+    pub key_id: String,
+    /// SHA-256 of the DER certificate, as uppercase hex without colons.
+    pub fingerprint: String,
+}
+
+/// Get the fingerprint of the repository signing certificate.
+///
+/// GET /api/v1/repo/fingerprint
+pub async fn get_fingerprint(
+    State(state): State<AppState>,
+) -> Result<Json<FingerprintResponse>, ApiError> {
+    let cert = active_cert(&state)?;
+    Ok(Json(FingerprintResponse {
+        fingerprint: fingerprint(&cert.der),
+        key_id: cert.key_id,
+    }))
+}
+
+/// Get the repository signing certificate.
+///
+/// GET /api/v1/repo/cert
+///
+/// Returns the DER-encoded X.509 certificate of the active signing key.
+pub async fn get_cert(State(state): State<AppState>) -> Result<Response, ApiError> {
+    let cert = active_cert(&state)?;
+    Ok(([(header::CONTENT_TYPE, CERT_CONTENT_TYPE)], cert.der).into_response())
+}
+
+/// The certificate of the key the repository currently signs with.
+fn active_cert(state: &AppState) -> Result<Cert, ApiError> {
+    let signer = state
+        .signer
+        .as_deref()
+        .ok_or_else(|| ApiError::Internal("repository signing is not configured".to_string()))?;
+    find_active(signer)
+}
+
+fn find_active(signer: &SigningService) -> Result<Cert, ApiError> {
+    signer
+        .public_certs()
+        .into_iter()
+        .find(|cert| cert.key_id == signer.active_key_id())
+        .ok_or_else(|| ApiError::Internal("active signing certificate is missing".to_string()))
+}
+
+/// SHA-256 fingerprint of a DER certificate, as uppercase hex.
+#[must_use]
+pub fn fingerprint(der: &[u8]) -> String {
+    sha256_bytes(der).to_string().to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::http::StatusCode;
+
+    use super::*;
+
+    fn signed_state() -> AppState {
+        let mut state = AppState::disconnected();
+        state.signer = Some(Arc::new(
+            SigningService::ephemeral("dk-appstore.test").expect("signing service"),
+        ));
+        state
+    }
+
+    #[tokio::test]
+    async fn test_fingerprint_matches_certificate_digest() {
+        let state = signed_state();
+        let Json(response) = get_fingerprint(State(state.clone()))
+            .await
+            .expect("fingerprint");
+
+        let cert = get_cert(State(state)).await.expect("cert");
+        assert_eq!(cert.status(), StatusCode::OK);
+        assert_eq!(cert.headers()[header::CONTENT_TYPE], CERT_CONTENT_TYPE);
+        let der = axum::body::to_bytes(cert.into_body(), usize::MAX)
+            .await
+            .expect("body");
+
+        let expected = sha256_bytes(&der).to_string().to_uppercase();
+        assert_eq!(response.fingerprint, expected);
+        assert_eq!(response.fingerprint.len(), 64);
+        assert_eq!(response.key_id, dk_signing::DEFAULT_KEY_ID);
+    }
+
+    #[test]
+    fn test_cert_follows_rotation() {
+        let mut signer = SigningService::ephemeral("dk-appstore 2025").expect("signing service");
+        let key = dk_signing::SoftwareSigner::generate().expect("key");
+        let der = key
+            .self_signed_certificate("dk-appstore 2026")
+            .expect("certificate");
+        signer.add_key("2026", key, der.clone()).expect("add key");
+        signer.rotate_to("2026").expect("rotate");
+
+        let cert = find_active(&signer).expect("active cert");
+        assert_eq!(cert.key_id, "2026");
+        assert_eq!(cert.der, der);
+    }
+
+    #[tokio::test]
+    async fn test_requires_signer() {
+        let result = get_fingerprint(State(AppState::disconnected())).await;
+        assert!(matches!(result, Err(ApiError::Internal(_))));
+        let result = get_cert(State(AppState::disconnected())).await;
+        assert!(matches!(result, Err(ApiError::Internal(_))));
+    }
+}
@@ -0,0 +1,173 @@
+//! Security scan report endpoint.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use dk_common::types::ScanStatus;
+use dk_scanner::{ScanReport, ScanReportStore, StoredScanReport};
+use serde::Serialize;
+
+use crate::error::ApiError;
+use crate::routes::apps::parse_package_id;
+use crate::state::AppState;
+
+/// Scan state of an APK version.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScanStatusResponse {
+    /// Outcome of the latest scan, or `pending` if there is none yet.
+    pub status: ScanStatus,
+    /// When the latest scan was stored.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scanned_at: Option<DateTime<Utc>>,
+    /// The latest scan report.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report: Option<ScanReport>,
+}
+
+impl ScanStatusResponse {
+    /// Response for a version that has not been scanned yet.
+    const fn pending() -> Self {
+        Self {
+            status: ScanStatus::Pending,
+            scanned_at: None,
+            report: None,
+        }
+    }
+}
+
+impl From<StoredScanReport> for ScanStatusResponse {
+    fn from(stored: StoredScanReport) -> Self {
+        Self {
+            status: stored.report.status,
+            scanned_at: Some(stored.scanned_at),
+            report: Some(stored.report),
+        }
+    }
+}
+
+/// Get the latest scan report for an APK version.
+///
+/// `GET /api/v1/apps/:package_id/versions/:version_code/scan`
+///
+/// A version that has not been scanned yet is `202 Accepted` with status
+/// `pending` rather than `404`, as its report is still to come.
+#[tracing::instrument(
+    skip_all,
+    fields(package_id = %package_id, version_code = version_code),
+    err(level = "info", Debug)
+)]
+pub async fn get_scan_report(
+    State(state): State<AppState>,
+    Path((package_id, version_code)): Path<(String, i64)>,
+) -> Result<(StatusCode, Json<ScanStatusResponse>), ApiError> {
+    let app_id = parse_package_id(&package_id)?;
+
+    let latest = ScanReportStore::new(state.db)
+        .latest(&app_id, version_code)
+        .await?;
+    Ok(match latest {
+        Some(stored) => (StatusCode::OK, Json(stored.into())),
+        None => (StatusCode::ACCEPTED, Json(ScanStatusResponse::pending())),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use dk_common::types::AppId;
+    use dk_scanner::report::PermissionReview;
+    use dk_scanner::{SignatureCheck, SignatureInfo, SignatureScheme};
+
+    use super::*;
+
+    fn passed_report() -> ScanReport {
+        ScanReport::new(
+            SignatureCheck::Valid(SignatureInfo {
+                scheme: SignatureScheme::V2,
+                certificate_sha256: vec!["ab".repeat(32)],
+                fdroid_sig: vec!["cd".repeat(16)],
+            }),
+            vec![PermissionReview {
+                name: "android.permission.INTERNET".to_string(),
+                max_sdk: None,
+                concerning: false,
+            }],
+            vec![],
+        )
+    }
+
+    #[test]
+    fn test_pending_body_has_only_status() {
+        let body = serde_json::to_value(ScanStatusResponse::pending()).expect("serialize");
+        assert_eq!(body, serde_json::json!({ "status": "pending" }));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_package_id_is_bad_request() {
+        let result = get_scan_report(
+            State(AppState::disconnected()),
+            Path(("../etc".to_string(), 1)),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiError::InvalidPackageId(_))));
+    }
+
+    #[tokio::test]
+    async fn test_database_error_is_internal() {
+        let result = get_scan_report(
+            State(AppState::disconnected()),
+            Path(("dk.digst.mitid".to_string(), 1)),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiError::Internal(_))));
+    }
+
+    /// State over `DATABASE_URL`, with a fresh package holding a passed
+    /// report for version 7.
+    async fn scanned_state() -> (AppState, String, ScanReport) {
+        let db = crate::state::migrated_db().await;
+
+        let package_id = format!("dk.test.scan{}", uuid::Uuid::new_v4().simple());
+        let report = passed_report();
+        ScanReportStore::new(db.clone())
+            .record(&AppId::new(package_id.clone()), 7, &report)
+            .await
+            .expect("record");
+
+        let state = AppState {
+            db,
+            ..AppState::disconnected()
+        };
+        (state, package_id, report)
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_get_stored_passed_report() {
+        let (state, package_id, report) = scanned_state().await;
+
+        let (status, Json(body)) = get_scan_report(State(state), Path((package_id, 7)))
+            .await
+            .expect("report");
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.status, ScanStatus::Passed);
+        assert!(body.scanned_at.is_some());
+        assert_eq!(body.report, Some(report));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_unscanned_version_is_pending() {
+        let (state, package_id, _) = scanned_state().await;
+
+        let (status, Json(body)) = get_scan_report(State(state), Path((package_id, 8)))
+            .await
+            .expect("pending");
+
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(body, ScanStatusResponse::pending());
+    }
+}
@@ -0,0 +1,251 @@
+//! App screenshot endpoints.
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use dk_common::types::{AppId, Screenshot};
+use serde::Deserialize;
+use sqlx::types::Json as SqlJson;
+use tokio_util::io::ReaderStream;
+
+use crate::error::ApiError;
+use crate::routes::apps::{app_not_found, parse_package_id};
+use crate::state::AppState;
+
+/// Query parameters for listing screenshots.
+#[derive(Debug, Default, Deserialize)]
+pub struct ScreenshotsQuery {
+    /// BCP-47 locale code; only screenshots in this locale are listed.
+    locale: Option<String>,
+}
+
+/// Keep the screenshots taken in `locale`, or all of them if no locale was
+/// requested.
+fn filter_locale(screenshots: Vec<Screenshot>, locale: Option<&str>) -> Vec<Screenshot> {
+    match locale.filter(|locale| !locale.is_empty()) {
+        Some(locale) => screenshots
+            .into_iter()
+            .filter(|screenshot| screenshot.locale == locale)
+            .collect(),
+        None => screenshots,
+    }
+}
+
+/// MIME type of a screenshot, by file extension.
+fn screenshot_content_type(name: &str) -> Option<&'static str> {
+    let (_, extension) = name.rsplit_once('.')?;
+    match extension.to_ascii_lowercase().as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// Storage key of the screenshot `name` of `app_id`.
+#[must_use]
+pub fn screenshot_key(app_id: &AppId, name: &str) -> String {
+    format!("screenshots/{app_id}/{name}")
+}
+
+/// List the screenshots of an application.
+///
+/// `GET /api/v1/apps/:package_id/screenshots?locale=da`
+pub async fn list_screenshots(
+    State(state): State<AppState>,
+    Path(package_id): Path<String>,
+    Query(query): Query<ScreenshotsQuery>,
+) -> Result<Json<Vec<Screenshot>>, ApiError> {
+    let SqlJson(screenshots) = sqlx::query_scalar::<_, SqlJson<Vec<Screenshot>>>(
+        "SELECT screenshots FROM apps WHERE package_id = $1",
+    )
+    .bind(&package_id)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| app_not_found(&package_id))?;
+
+    Ok(Json(filter_locale(screenshots, query.locale.as_deref())))
+}
+
+/// Get a screenshot image of an application.
+///
+/// `GET /api/v1/apps/:package_id/screenshots/:name`
+pub async fn get_screenshot(
+    State(state): State<AppState>,
+    Path((package_id, name)): Path<(String, String)>,
+) -> Result<Response, ApiError> {
+    // Validating the package ID and name keeps the key inside the app's
+    // screenshot namespace
+    let app_id = parse_package_id(&package_id)?;
+    if name.contains('/') {
+        return Err(ApiError::BadRequest(format!(
+            "Invalid screenshot name: {name}"
+        )));
+    }
+    let content_type = screenshot_content_type(&name)
+        .ok_or_else(|| ApiError::BadRequest(format!("Unsupported screenshot format: {name}")))?;
+
+    let key = screenshot_key(&app_id, &name);
+    let storage_error = |err| match err {
+        dk_common::Error::NotFound(_) => {
+            ApiError::NotFound(format!("Screenshot not found: {package_id} {name}"))
+        }
+        err => ApiError::from(err),
+    };
+    let size = state.storage.head(&key).await.map_err(storage_error)?.size;
+    let object = state.storage.get(&key, None).await.map_err(storage_error)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_LENGTH, size.to_string()),
+        ],
+        Body::from_stream(ReaderStream::new(object)),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use super::*;
+    use crate::routes::download::tests::TempStorage;
+
+    const PACKAGE: &str = "dk.digst.mitid";
+
+    fn screenshot(locale: &str, name: &str) -> Screenshot {
+        Screenshot {
+            locale: locale.to_string(),
+            url: format!("/api/v1/apps/{PACKAGE}/screenshots/{name}"),
+            width: 1080,
+            height: 1920,
+        }
+    }
+
+    fn sample_screenshots() -> Vec<Screenshot> {
+        vec![
+            screenshot("da", "login-da.png"),
+            screenshot("en", "login-en.png"),
+            screenshot("da", "home-da.png"),
+        ]
+    }
+
+    async fn serve(storage: &TempStorage, name: &str) -> Result<Response, ApiError> {
+        get_screenshot(
+            State(storage.state()),
+            Path((PACKAGE.to_string(), name.to_string())),
+        )
+        .await
+    }
+
+    #[test]
+    fn test_filter_locale() {
+        let danish = filter_locale(sample_screenshots(), Some("da"));
+        assert_eq!(
+            danish,
+            vec![
+                screenshot("da", "login-da.png"),
+                screenshot("da", "home-da.png")
+            ]
+        );
+
+        assert_eq!(filter_locale(sample_screenshots(), None).len(), 3);
+        assert_eq!(filter_locale(sample_screenshots(), Some("")).len(), 3);
+        assert!(filter_locale(sample_screenshots(), Some("de")).is_empty());
+    }
+
+    #[test]
+    fn test_screenshot_content_type() {
+        assert_eq!(screenshot_content_type("home.png"), Some("image/png"));
+        assert_eq!(screenshot_content_type("home.JPG"), Some("image/jpeg"));
+        assert_eq!(screenshot_content_type("home.webp"), Some("image/webp"));
+        assert_eq!(screenshot_content_type("home.gif"), None);
+        assert_eq!(screenshot_content_type("home"), None);
+    }
+
+    #[tokio::test]
+    async fn test_serve_seeded_screenshot() {
+        let storage = TempStorage::new();
+        let dir = storage.dir.join("screenshots").join(PACKAGE);
+        std::fs::create_dir_all(&dir).expect("screenshot dir");
+        std::fs::write(dir.join("home-da.jpg"), b"jpeg bytes").expect("write screenshot");
+
+        let response = serve(&storage, "home-da.jpg").await.expect("screenshot");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/jpeg");
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "10");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        assert_eq!(body.as_ref(), b"jpeg bytes");
+    }
+
+    #[tokio::test]
+    async fn test_serve_missing_screenshot() {
+        let storage = TempStorage::new();
+        assert!(matches!(
+            serve(&storage, "home.png").await,
+            Err(ApiError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_serve_rejects_invalid_names() {
+        let storage = TempStorage::new();
+        for name in ["../secret.png", "home.exe", ".."] {
+            assert!(
+                matches!(serve(&storage, name).await, Err(ApiError::BadRequest(_))),
+                "{name}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_list_screenshots_filters_by_locale() {
+        let db = crate::state::migrated_db().await;
+        sqlx::query(
+            "INSERT INTO apps (id, package_id, name, summary, description, version_code, \
+             version_name, screenshots) VALUES ($1, $2, '{}', '{}', '{}', 1, '1.0', $3) \
+             ON CONFLICT (package_id) DO UPDATE SET screenshots = EXCLUDED.screenshots",
+        )
+        .bind(uuid::Uuid::new_v4())
+        .bind(PACKAGE)
+        .bind(SqlJson(sample_screenshots()))
+        .execute(&db)
+        .await
+        .expect("seed");
+
+        let state = AppState {
+            db,
+            ..AppState::disconnected()
+        };
+        let Json(screenshots) = list_screenshots(
+            State(state),
+            Path(PACKAGE.to_string()),
+            Query(ScreenshotsQuery {
+                locale: Some("en".to_string()),
+            }),
+        )
+        .await
+        .expect("list screenshots");
+
+        assert_eq!(screenshots, vec![screenshot("en", "login-en.png")]);
+    }
+
+    #[tokio::test]
+    async fn test_list_screenshots_database_error_is_internal() {
+        let result = list_screenshots(
+            State(AppState::disconnected()),
+            Path(PACKAGE.to_string()),
+            Query(ScreenshotsQuery::default()),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiError::Internal(_))));
+    }
+}
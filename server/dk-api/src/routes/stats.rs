@@ -0,0 +1,156 @@
+//! Repository statistics endpoint.
+
+use axum::{extract::State, Json};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{PgExecutor, Row};
+use utoipa::ToSchema;
+
+use crate::error::ApiError;
+use crate::state::AppState;
+
+/// Totals over the whole repository.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct RepositoryStats {
+    /// Number of apps.
+    pub apps: i64,
+    /// Number of published versions, across all apps.
+    pub versions: i64,
+    /// Bytes of APKs stored, including unpublished versions, whose files
+    /// are kept.
+    pub storage_bytes: i64,
+    /// Most recent change to an app or new version; absent for an empty
+    /// repository.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_updated: Option<DateTime<Utc>>,
+}
+
+impl RepositoryStats {
+    /// Compute the statistics in `db`.
+    ///
+    /// All of them come from a single statement, so they are read from one
+    /// snapshot and agree with each other.
+    pub async fn load<'e>(db: impl PgExecutor<'e>) -> Result<Self, sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT \
+             (SELECT count(*) FROM apps) AS apps, \
+             (SELECT count(*) FROM app_versions WHERE deleted_at IS NULL) AS versions, \
+             (SELECT coalesce(sum(size), 0)::BIGINT FROM app_versions) AS storage_bytes, \
+             greatest((SELECT max(updated_at) FROM apps), \
+                      (SELECT max(created_at) FROM app_versions)) AS last_updated",
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(Self {
+            apps: row.try_get("apps")?,
+            versions: row.try_get("versions")?,
+            storage_bytes: row.try_get("storage_bytes")?,
+            last_updated: row.try_get("last_updated")?,
+        })
+    }
+}
+
+/// Get totals over the repository, for dashboards.
+///
+/// GET /api/v1/stats
+#[utoipa::path(
+    get,
+    path = "/stats",
+    tag = "apps",
+    responses(
+        (status = 200, description = "Repository statistics", body = RepositoryStats),
+    )
+)]
+pub async fn get_stats(State(state): State<AppState>) -> Result<Json<RepositoryStats>, ApiError> {
+    Ok(Json(RepositoryStats::load(&state.db).await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stats_database_error_is_internal() {
+        let result = get_stats(State(AppState::disconnected())).await;
+        assert!(matches!(result, Err(ApiError::Internal(_))));
+    }
+
+    #[test]
+    fn test_empty_repository_has_no_last_updated() {
+        let stats = RepositoryStats {
+            apps: 0,
+            versions: 0,
+            storage_bytes: 0,
+            last_updated: None,
+        };
+        assert_eq!(
+            serde_json::to_value(stats).expect("serialize"),
+            serde_json::json!({ "apps": 0, "versions": 0, "storage_bytes": 0 })
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_stats_over_seeded_repository() {
+        let db = crate::state::migrated_db().await;
+
+        // Seeded in a transaction that is rolled back, over an emptied
+        // repository, so other tests' rows do not count.
+        let mut tx = db.begin().await.expect("begin");
+        sqlx::query("DELETE FROM apps")
+            .execute(&mut *tx)
+            .await
+            .expect("empty");
+        let at = |day| {
+            Utc.with_ymd_and_hms(2024, 5, day, 12, 0, 0)
+                .single()
+                .expect("date")
+        };
+        let mut app_ids = Vec::new();
+        for (package_id, updated_at) in [("dk.digst.mitid", at(1)), ("dk.skat.tastselv", at(2))] {
+            let id = uuid::Uuid::new_v4();
+            sqlx::query(
+                "INSERT INTO apps (id, package_id, name, summary, description, \
+                 version_code, version_name, created_at, updated_at) \
+                 VALUES ($1, $2, '{}', '{}', '{}', 1, '1.0', $3, $3)",
+            )
+            .bind(id)
+            .bind(package_id)
+            .bind(updated_at)
+            .execute(&mut *tx)
+            .await
+            .expect("insert app");
+            app_ids.push(id);
+        }
+        for (app_id, version_code, size, created_at, deleted_at) in [
+            (app_ids[0], 1_i64, 1_000_i64, at(1), Some(at(4))),
+            (app_ids[0], 2, 2_000, at(3), None),
+            (app_ids[1], 1, 500, at(2), None),
+        ] {
+            sqlx::query(
+                "INSERT INTO app_versions (id, app_id, version_code, version_name, sha256, \
+                 size, min_sdk, target_sdk, created_at, deleted_at) \
+                 VALUES ($1, $2, $3, '1.0', '', $4, 24, 34, $5, $6)",
+            )
+            .bind(uuid::Uuid::new_v4())
+            .bind(app_id)
+            .bind(version_code)
+            .bind(size)
+            .bind(created_at)
+            .bind(deleted_at)
+            .execute(&mut *tx)
+            .await
+            .expect("insert version");
+        }
+
+        let stats = RepositoryStats::load(&mut *tx).await.expect("stats");
+
+        assert_eq!(stats.apps, 2);
+        assert_eq!(stats.versions, 2);
+        assert_eq!(stats.storage_bytes, 3_500);
+        assert_eq!(stats.last_updated, Some(at(3)));
+    }
+}
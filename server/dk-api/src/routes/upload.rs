@@ -0,0 +1,723 @@
+//! APK upload endpoint.
+
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::Arc;
+
+use axum::{
+    body::Bytes,
+    extract::{Multipart, Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use dk_common::config::ScannerConfig;
+use dk_common::hash::sha256_bytes;
+use dk_common::types::{AppId, AppVersion, Sha256};
+use dk_common::webhooks::WebhookEvent;
+use dk_scanner::signature::SignatureInfo;
+use dk_scanner::{ApkMetadata, QuarantineStore, ScanError, ScanReportStore, ScannerService};
+use sqlx::types::Json as SqlJson;
+use sqlx::{PgPool, Postgres, Row, Transaction};
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::routes::apps::{app_not_found, parse_package_id, version_from_row, AppVersionResponse};
+use crate::state::AppState;
+
+/// Name of the multipart field carrying the APK.
+pub const APK_FIELD: &str = "apk";
+
+/// Header carrying the client's idempotency key.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Maximum length of an idempotency key.
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// Hours an idempotency key replays the upload it was first used for.
+const IDEMPOTENCY_KEY_TTL_HOURS: i32 = 24;
+
+/// An uploaded APK written to a temporary file, removed on drop.
+struct TempUpload(PathBuf);
+
+impl TempUpload {
+    fn write(bytes: &[u8]) -> Result<Self, ApiError> {
+        let path = std::env::temp_dir().join(format!("dk-api-upload-{}.apk", Uuid::new_v4()));
+        std::fs::write(&path, bytes)
+            .map_err(|err| ApiError::Internal(format!("writing upload: {err}")))?;
+        Ok(Self(path))
+    }
+
+    fn path(&self) -> &FsPath {
+        &self.0
+    }
+}
+
+impl Drop for TempUpload {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Read the APK from the `apk` field of a multipart upload.
+async fn read_apk(mut multipart: Multipart) -> Result<Bytes, ApiError> {
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|err| ApiError::BadRequest(format!("Invalid multipart body: {err}")))?
+    {
+        if field.name() == Some(APK_FIELD) {
+            return field
+                .bytes()
+                .await
+                .map_err(|err| ApiError::BadRequest(format!("Invalid multipart body: {err}")));
+        }
+    }
+    Err(ApiError::BadRequest(format!(
+        "Missing multipart field '{APK_FIELD}'"
+    )))
+}
+
+/// Read the optional `Idempotency-Key` header.
+fn idempotency_key(headers: &HeaderMap) -> Result<Option<String>, ApiError> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+    match value.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LEN => {
+            Ok(Some(key.to_string()))
+        }
+        _ => Err(ApiError::BadRequest(format!(
+            "Idempotency-Key must be 1 to {MAX_IDEMPOTENCY_KEY_LEN} visible ASCII characters"
+        ))),
+    }
+}
+
+/// Look up the version published by an earlier upload with `key`.
+///
+/// Returns `None` for unknown and expired keys. A key reused for a different
+/// upload is rejected.
+async fn replay(
+    db: &PgPool,
+    key: &str,
+    app_id: &AppId,
+    sha256: &Sha256,
+) -> Result<Option<AppVersion>, ApiError> {
+    let Some(row) = sqlx::query(
+        "SELECT package_id, sha256, version_id FROM idempotency_keys \
+         WHERE key = $1 AND created_at > now() - make_interval(hours => $2)",
+    )
+    .bind(key)
+    .bind(IDEMPOTENCY_KEY_TTL_HOURS)
+    .fetch_optional(db)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let package_id: String = row.try_get("package_id")?;
+    let digest: String = row.try_get("sha256")?;
+    if package_id != app_id.as_str() || digest != sha256.to_string() {
+        return Err(ApiError::UnprocessableEntity(
+            "Idempotency-Key was already used for a different upload".to_string(),
+        ));
+    }
+
+    let version_id: Uuid = row.try_get("version_id")?;
+    let version = sqlx::query("SELECT * FROM app_versions WHERE id = $1")
+        .bind(version_id)
+        .fetch_optional(db)
+        .await?;
+    Ok(version.as_ref().map(version_from_row).transpose()?)
+}
+
+/// Record that the upload with `key` published `version` of `app_id`,
+/// taking over the key if its earlier use has expired.
+async fn remember(
+    db: &PgPool,
+    key: &str,
+    app_id: &AppId,
+    version: &AppVersion,
+) -> Result<(), ApiError> {
+    sqlx::query(
+        "INSERT INTO idempotency_keys (key, package_id, sha256, version_id) \
+         VALUES ($1, $2, $3, $4) \
+         ON CONFLICT (key) DO UPDATE SET package_id = EXCLUDED.package_id, \
+         sha256 = EXCLUDED.sha256, version_id = EXCLUDED.version_id, created_at = now() \
+         WHERE idempotency_keys.created_at <= now() - make_interval(hours => $5)",
+    )
+    .bind(key)
+    .bind(app_id.as_str())
+    .bind(version.sha256.to_string())
+    .bind(version.id)
+    .bind(IDEMPOTENCY_KEY_TTL_HOURS)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Extract the metadata of `apk` and verify its v2/v3 signature.
+///
+/// Malformed and unsigned APKs, and archives beyond the limits of `config`,
+/// are bad requests.
+fn inspect_signed(
+    apk: &Bytes,
+    config: &ScannerConfig,
+) -> Result<(ApkMetadata, SignatureInfo), ApiError> {
+    let invalid = |err| match err {
+        err @ (ScanError::InvalidApk(_) | ScanError::CriticalVulnerability(_)) => {
+            ApiError::BadRequest(format!("Invalid APK: {err}"))
+        }
+        err => ApiError::from(err),
+    };
+
+    let upload = TempUpload::write(apk)?;
+    let metadata = ScannerService::inspect(upload.path(), config).map_err(invalid)?;
+    let signature = dk_scanner::signature::verify_apk_bytes(apk).map_err(invalid)?;
+    Ok((metadata, signature))
+}
+
+/// Scan `apk`, version `version_code` of `app_id`, and store the report.
+///
+/// A critical finding quarantines the version, so it never appears in the
+/// index even once published.
+async fn scan(
+    state: &AppState,
+    app_id: &AppId,
+    version_code: i64,
+    apk: &[u8],
+) -> Result<(), ApiError> {
+    let upload = TempUpload::write(apk)?;
+    let report = ScannerService::new()
+        .with_config((*state.scanner).clone())
+        .with_vulnerability_db(Arc::clone(&state.vulnerabilities))
+        .scan_and_quarantine(upload.path(), &QuarantineStore::new(state.db.clone()))
+        .await?;
+    ScanReportStore::new(state.db.clone())
+        .record(app_id, version_code, &report)
+        .await?;
+    Ok(())
+}
+
+/// Publish a new version of an application.
+///
+/// `POST /api/v1/apps/:package_id/versions`
+///
+/// Accepts a `multipart/form-data` body with the APK in the `apk` field.
+/// The APK must be signed and declare `package_id`, and its version code
+/// must not already be published. Once an app has versions, updates must be
+/// signed with the same certificate.
+///
+/// The APK is scanned before the version is committed; a critical finding
+/// quarantines it, keeping it out of the index.
+///
+/// With an `Idempotency-Key` header, retrying a successful upload within 24
+/// hours returns the original result instead of a conflict. Reusing the key
+/// for a different APK is rejected with `422`.
+#[tracing::instrument(
+    skip_all,
+    fields(package_id = %package_id, version_code = tracing::field::Empty),
+    err(level = "info", Debug)
+)]
+pub async fn upload_version(
+    State(state): State<AppState>,
+    Path(package_id): Path<String>,
+    headers: HeaderMap,
+    multipart: Multipart,
+) -> Result<(StatusCode, Json<AppVersionResponse>), ApiError> {
+    let app_id = parse_package_id(&package_id)?;
+    let key = idempotency_key(&headers)?;
+    let apk = read_apk(multipart).await?;
+
+    // A retry is answered before the APK is inspected again
+    if let Some(key) = &key {
+        if let Some(version) = replay(&state.db, key, &app_id, &sha256_bytes(&apk)).await? {
+            return Ok((StatusCode::CREATED, Json(AppVersionResponse::from(version))));
+        }
+    }
+    let (metadata, signature) = {
+        let (apk, config) = (apk.clone(), Arc::clone(&state.scanner));
+        tokio::task::spawn_blocking(move || inspect_signed(&apk, &config))
+            .await
+            .map_err(|err| ApiError::Internal(err.to_string()))??
+    };
+    tracing::Span::current().record("version_code", metadata.version_code);
+    if metadata.package != app_id {
+        return Err(ApiError::BadRequest(format!(
+            "APK package {} does not match {app_id}",
+            metadata.package
+        )));
+    }
+
+    let app: Uuid = sqlx::query_scalar("SELECT id FROM apps WHERE package_id = $1")
+        .bind(app_id.as_str())
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or_else(|| app_not_found(&package_id))?;
+    let version_code = metadata.version_code;
+    let conflict = || {
+        ApiError::Conflict(format!(
+            "Version {version_code} of {app_id} is already published"
+        ))
+    };
+
+    // Rejected early; the insert below still guards against concurrent uploads
+    let published: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM app_versions WHERE app_id = $1 AND version_code = $2)",
+    )
+    .bind(app)
+    .bind(version_code)
+    .fetch_one(&state.db)
+    .await?;
+    if published {
+        return Err(conflict());
+    }
+
+    // Unpublished versions count too: devices may still have them installed
+    let known_sigs: Vec<String> = sqlx::query_scalar(
+        "SELECT DISTINCT sig FROM app_versions WHERE app_id = $1 AND sig IS NOT NULL",
+    )
+    .bind(app)
+    .fetch_all(&state.db)
+    .await?;
+    let sig = signature.sig();
+    if !known_sigs.is_empty() && !known_sigs.iter().any(|known| Some(known.as_str()) == sig) {
+        return Err(ApiError::BadRequest(format!(
+            "APK is not signed with the certificate of the published versions of {app_id}"
+        )));
+    }
+
+    let version = metadata.into_app_version(app);
+    version.validate()?;
+
+    // The row is locked until the APK is stored, so a concurrent upload of
+    // the same version waits and then conflicts instead of replacing it
+    let mut tx = state.db.begin().await?;
+    if !insert_version(&mut tx, &version, sig).await? {
+        return Err(conflict());
+    }
+    scan(&state, &app_id, version_code, &apk).await?;
+    state
+        .storage
+        .put(&app_id.apk_file_name(version_code), apk)
+        .await?;
+    tx.commit().await?;
+
+    if let Some(key) = &key {
+        remember(&state.db, key, &app_id, &version).await?;
+    }
+    tracing::info!(package_id = %app_id, version_code, "Published APK version");
+    state
+        .webhooks
+        .notify(WebhookEvent::published(&app_id, version_code));
+
+    Ok((StatusCode::CREATED, Json(AppVersionResponse::from(version))))
+}
+
+/// Insert `version` signed by the certificate with F-Droid `sig` in `tx`,
+/// returning `false` if its version code was published concurrently.
+async fn insert_version(
+    tx: &mut Transaction<'_, Postgres>,
+    version: &AppVersion,
+    sig: Option<&str>,
+) -> Result<bool, ApiError> {
+    let result = sqlx::query(
+        "INSERT INTO app_versions (id, app_id, version_code, version_name, sha256, size, \
+         min_sdk, target_sdk, permissions, abis, sig, created_at) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12) \
+         ON CONFLICT (app_id, version_code) DO NOTHING",
+    )
+    .bind(version.id)
+    .bind(version.app_id)
+    .bind(version.version_code)
+    .bind(&version.version_name)
+    .bind(version.sha256.to_string())
+    .bind(version.size)
+    .bind(version.min_sdk)
+    .bind(version.target_sdk)
+    .bind(SqlJson(&version.permissions))
+    .bind(&version.abis)
+    .bind(sig)
+    .bind(version.created_at)
+    .execute(&mut **tx)
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{header, Request};
+    use axum::response::Response;
+    use axum::routing::post;
+    use axum::Router;
+    use dk_scanner::fixtures::{ApkBuilder, ApkSigner, ManifestBuilder};
+    use dk_scanner::signature::fdroid_sig;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::routes::download::tests::TempStorage;
+
+    const BOUNDARY: &str = "dk-api-test-boundary";
+
+    fn signed_apk(package: &str, version_code: u32) -> Vec<u8> {
+        signed_apk_by(package, version_code, &ApkSigner::generate())
+    }
+
+    fn signed_apk_by(package: &str, version_code: u32, signer: &ApkSigner) -> Vec<u8> {
+        let manifest = ManifestBuilder::new(package)
+            .version(version_code, "1.0")
+            .sdk(24, 34)
+            .build();
+        ApkBuilder::new()
+            .entry("AndroidManifest.xml", &manifest)
+            .build_signed_by(signer)
+            .0
+    }
+
+    /// POST `apk` as a multipart upload for `package_id`.
+    async fn upload(state: AppState, package_id: &str, apk: &[u8]) -> Response {
+        upload_with_key(state, package_id, apk, None).await
+    }
+
+    /// POST `apk` like [`upload`], with an optional idempotency key.
+    async fn upload_with_key(
+        state: AppState,
+        package_id: &str,
+        apk: &[u8],
+        key: Option<&str>,
+    ) -> Response {
+        let mut body = format!(
+            "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"{APK_FIELD}\"; \
+             filename=\"app.apk\"\r\nContent-Type: application/octet-stream\r\n\r\n"
+        )
+        .into_bytes();
+        body.extend_from_slice(apk);
+        body.extend_from_slice(format!("\r\n--{BOUNDARY}--\r\n").as_bytes());
+
+        let mut request = Request::post(format!("/apps/{package_id}/versions")).header(
+            header::CONTENT_TYPE,
+            format!("multipart/form-data; boundary={BOUNDARY}"),
+        );
+        if let Some(key) = key {
+            request = request.header(IDEMPOTENCY_KEY_HEADER, key);
+        }
+
+        Router::new()
+            .route("/apps/:package_id/versions", post(upload_version))
+            .with_state(state)
+            .oneshot(request.body(Body::from(body)).expect("request"))
+            .await
+            .expect("response")
+    }
+
+    async fn json(response: Response) -> Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        serde_json::from_slice(&body).expect("json body")
+    }
+
+    #[tokio::test]
+    async fn test_package_id_mismatch() {
+        let storage = TempStorage::new();
+        let apk = signed_apk("dk.skat.tastselv", 1);
+
+        let response = upload(storage.state(), "dk.digst.mitid", &apk).await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(json(response).await["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("dk.skat.tastselv")));
+        assert!(!storage.dir.join("dk.digst.mitid_1.apk").exists());
+    }
+
+    #[tokio::test]
+    async fn test_unsigned_apk_is_rejected() {
+        let storage = TempStorage::new();
+        let manifest = ManifestBuilder::new("dk.digst.mitid")
+            .version(1, "1.0")
+            .build();
+        let apk = ApkBuilder::new()
+            .entry("AndroidManifest.xml", &manifest)
+            .build();
+
+        let response = upload(storage.state(), "dk.digst.mitid", &apk).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_configured_limits_apply() {
+        let storage = TempStorage::new();
+        let state = AppState {
+            scanner: Arc::new(ScannerConfig {
+                max_uncompressed_size: 1,
+                ..ScannerConfig::default()
+            }),
+            ..storage.state()
+        };
+
+        let response = upload(state, "dk.digst.mitid", &signed_apk("dk.digst.mitid", 1)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_missing_apk_field() {
+        let storage = TempStorage::new();
+        let response = Router::new()
+            .route("/apps/:package_id/versions", post(upload_version))
+            .with_state(storage.state())
+            .oneshot(
+                Request::post("/apps/dk.digst.mitid/versions")
+                    .header(
+                        header::CONTENT_TYPE,
+                        format!("multipart/form-data; boundary={BOUNDARY}"),
+                    )
+                    .body(Body::from(format!("--{BOUNDARY}--\r\n")))
+                    .expect("request"),
+            )
+            .await
+            .expect("response");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_idempotency_key_header() {
+        let headers = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(IDEMPOTENCY_KEY_HEADER, value.parse().expect("header value"));
+            headers
+        };
+
+        assert_eq!(idempotency_key(&HeaderMap::new()).expect("no key"), None);
+        assert_eq!(
+            idempotency_key(&headers("ci-run-42"))
+                .expect("key")
+                .as_deref(),
+            Some("ci-run-42")
+        );
+        for invalid in [String::new(), "k".repeat(MAX_IDEMPOTENCY_KEY_LEN + 1)] {
+            assert!(matches!(
+                idempotency_key(&headers(&invalid)),
+                Err(ApiError::BadRequest(_))
+            ));
+        }
+    }
+
+    /// Connect to `DATABASE_URL`, apply migrations, and seed `package_id`
+    /// with no published versions.
+    async fn seeded_state(storage: &TempStorage, package_id: &str) -> AppState {
+        let db = crate::state::migrated_db().await;
+        sqlx::query("DELETE FROM apps WHERE package_id = $1")
+            .bind(package_id)
+            .execute(&db)
+            .await
+            .expect("reset");
+        sqlx::query(
+            "INSERT INTO apps (id, package_id, name, summary, description, version_code, \
+             version_name) VALUES ($1, $2, '{}', '{}', '{}', 1, '1.0')",
+        )
+        .bind(Uuid::new_v4())
+        .bind(package_id)
+        .execute(&db)
+        .await
+        .expect("seed");
+
+        AppState {
+            db,
+            ..storage.state()
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_successful_upload() {
+        let storage = TempStorage::new();
+        let state = seeded_state(&storage, "dk.example.upload").await;
+        let apk = signed_apk("dk.example.upload", 7);
+
+        let response = upload(state, "dk.example.upload", &apk).await;
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let version = json(response).await;
+        assert_eq!(version["version_code"], 7);
+        assert_eq!(version["size"], apk.len());
+        let stored = std::fs::read(storage.dir.join("dk.example.upload_7.apk")).expect("stored");
+        assert_eq!(stored, apk);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_upload_records_signer_sig() {
+        const PACKAGE: &str = "dk.example.sig";
+        let storage = TempStorage::new();
+        let state = seeded_state(&storage, PACKAGE).await;
+        let signer = ApkSigner::generate();
+
+        let response = upload(state.clone(), PACKAGE, &signed_apk_by(PACKAGE, 1, &signer)).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let sig: Option<String> = sqlx::query_scalar(
+            "SELECT v.sig FROM app_versions v JOIN apps a ON a.id = v.app_id \
+             WHERE a.package_id = $1",
+        )
+        .bind(PACKAGE)
+        .fetch_one(&state.db)
+        .await
+        .expect("sig");
+        assert_eq!(sig, Some(fdroid_sig(&signer.certificate)));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_update_by_other_signer_is_rejected() {
+        const PACKAGE: &str = "dk.example.resigned";
+        let storage = TempStorage::new();
+        let state = seeded_state(&storage, PACKAGE).await;
+        let signer = ApkSigner::generate();
+
+        let first = upload(state.clone(), PACKAGE, &signed_apk_by(PACKAGE, 1, &signer)).await;
+        assert_eq!(first.status(), StatusCode::CREATED);
+
+        let resigned = upload(state.clone(), PACKAGE, &signed_apk(PACKAGE, 2)).await;
+        assert_eq!(resigned.status(), StatusCode::BAD_REQUEST);
+        assert!(!storage.dir.join("dk.example.resigned_2.apk").exists());
+        assert_eq!(version_count(&state, PACKAGE).await, 1);
+
+        let update = upload(state.clone(), PACKAGE, &signed_apk_by(PACKAGE, 2, &signer)).await;
+        assert_eq!(update.status(), StatusCode::CREATED);
+        assert_eq!(version_count(&state, PACKAGE).await, 2);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_duplicate_version_code_conflicts() {
+        const PACKAGE: &str = "dk.example.duplicate";
+        let storage = TempStorage::new();
+        let state = seeded_state(&storage, PACKAGE).await;
+        let path = storage.dir.join("dk.example.duplicate_3.apk");
+
+        let first = upload(state.clone(), PACKAGE, &signed_apk(PACKAGE, 3)).await;
+        assert_eq!(first.status(), StatusCode::CREATED);
+        let stored = std::fs::read(&path).expect("stored apk");
+
+        // Freshly signed, so the bytes differ from the published APK
+        let second = upload(state, PACKAGE, &signed_apk(PACKAGE, 3)).await;
+        assert_eq!(second.status(), StatusCode::CONFLICT);
+        assert_eq!(json(second).await["code"], "CONFLICT");
+        assert_eq!(std::fs::read(&path).expect("stored apk"), stored);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_upload_with_critical_finding_stays_out_of_index() {
+        const PACKAGE: &str = "dk.example.vulnerable";
+        const LIBCRYPTO: &[u8] = b"\x7fELF libcrypto 1.1.1k";
+        let storage = TempStorage::new();
+        let seeded = seeded_state(&storage, PACKAGE).await;
+        let vulnerabilities = dk_scanner::VulnerabilityDb::from_json(&format!(
+            r#"[{{"sha256": "{}", "library": "OpenSSL 1.1.1k", "cve": "CVE-2021-3711", "severity": "critical"}}]"#,
+            sha256_bytes(LIBCRYPTO)
+        ))
+        .expect("vulnerability db");
+        let state = AppState {
+            apps: Arc::new(crate::repository::PgAppRepository::new(seeded.db.clone())),
+            vulnerabilities: Arc::new(vulnerabilities),
+            ..seeded
+        };
+        let signer = ApkSigner::generate();
+        let apk = |version_code: u32, libraries: &[(&str, &[u8])]| {
+            let manifest = ManifestBuilder::new(PACKAGE)
+                .version(version_code, "1.0")
+                .sdk(24, 34)
+                .build();
+            libraries
+                .iter()
+                .fold(
+                    ApkBuilder::new().entry("AndroidManifest.xml", &manifest),
+                    |builder, (name, content)| builder.entry(name, content),
+                )
+                .build_signed_by(&signer)
+                .0
+        };
+
+        let clean = upload(state.clone(), PACKAGE, &apk(1, &[])).await;
+        assert_eq!(clean.status(), StatusCode::CREATED);
+        let vulnerable = apk(2, &[("lib/arm64-v8a/libcrypto.so", LIBCRYPTO)]);
+        let vulnerable = upload(state.clone(), PACKAGE, &vulnerable).await;
+        assert_eq!(vulnerable.status(), StatusCode::CREATED);
+
+        let report = ScanReportStore::new(state.db.clone())
+            .latest(&AppId::new(PACKAGE), 2)
+            .await
+            .expect("scan report");
+        assert!(report.is_some());
+        let response = crate::routes::index::get_index(
+            State(state),
+            axum::extract::Query(crate::routes::index::IndexQuery::default()),
+            HeaderMap::new(),
+        )
+        .await
+        .expect("index");
+        let index = json(response).await;
+        let codes: Vec<_> = index["packages"][PACKAGE]
+            .as_array()
+            .expect("published package")
+            .iter()
+            .map(|package| package["versionCode"].clone())
+            .collect();
+        assert_eq!(codes, [1]);
+    }
+
+    /// Number of published versions of `package_id`.
+    async fn version_count(state: &AppState, package_id: &str) -> i64 {
+        sqlx::query_scalar(
+            "SELECT COUNT(*) FROM app_versions v JOIN apps a ON a.id = v.app_id \
+             WHERE a.package_id = $1",
+        )
+        .bind(package_id)
+        .fetch_one(&state.db)
+        .await
+        .expect("count versions")
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_replayed_upload_returns_first_result() {
+        const PACKAGE: &str = "dk.example.replay";
+        let storage = TempStorage::new();
+        let state = seeded_state(&storage, PACKAGE).await;
+        let key = Uuid::new_v4().to_string();
+        let apk = signed_apk(PACKAGE, 5);
+
+        let first = upload_with_key(state.clone(), PACKAGE, &apk, Some(&key)).await;
+        assert_eq!(first.status(), StatusCode::CREATED);
+        let first = json(first).await;
+
+        let replayed = upload_with_key(state.clone(), PACKAGE, &apk, Some(&key)).await;
+        assert_eq!(replayed.status(), StatusCode::CREATED);
+        let replayed = json(replayed).await;
+        for field in ["version_code", "version_name", "sha256", "size"] {
+            assert_eq!(replayed[field], first[field], "{field}");
+        }
+        assert_eq!(version_count(&state, PACKAGE).await, 1);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_reused_key_with_different_apk_is_rejected() {
+        const PACKAGE: &str = "dk.example.reused";
+        let storage = TempStorage::new();
+        let state = seeded_state(&storage, PACKAGE).await;
+        let key = Uuid::new_v4().to_string();
+
+        let apk = signed_apk(PACKAGE, 1);
+        let first = upload_with_key(state.clone(), PACKAGE, &apk, Some(&key)).await;
+        assert_eq!(first.status(), StatusCode::CREATED);
+
+        let second =
+            upload_with_key(state.clone(), PACKAGE, &signed_apk(PACKAGE, 2), Some(&key)).await;
+        assert_eq!(second.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(json(second).await["code"], "UNPROCESSABLE");
+        assert_eq!(version_count(&state, PACKAGE).await, 1);
+    }
+}
@@ -0,0 +1,171 @@
+//! Graceful shutdown on SIGTERM and Ctrl-C.
+
+use std::future::{Future, IntoFuture};
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use axum::Router;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tracing::{error, info, warn};
+
+/// Resolves when the process receives SIGTERM or Ctrl-C.
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl-C: {err}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut stream) => {
+                stream.recv().await;
+            }
+            Err(err) => {
+                error!("Failed to listen for SIGTERM: {err}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {}
+        () = terminate => {}
+    }
+}
+
+/// Serve `app` until `shutdown` resolves, then stop accepting connections
+/// and give in-flight requests up to `drain_timeout` to finish.
+///
+/// Connections still open after the timeout are dropped.
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+    drain_timeout: Duration,
+) -> io::Result<()> {
+    let (draining_tx, draining_rx) = oneshot::channel();
+    let server = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move {
+        shutdown.await;
+        info!(
+            "Shutdown signal received; draining connections for up to {}s",
+            drain_timeout.as_secs()
+        );
+        let _ = draining_tx.send(());
+    })
+    .into_future();
+    tokio::pin!(server);
+
+    tokio::select! {
+        result = &mut server => return result,
+        _ = draining_rx => {}
+    }
+
+    let Ok(result) = tokio::time::timeout(drain_timeout, server).await else {
+        warn!("Drain timeout elapsed; dropping remaining connections");
+        return Ok(());
+    };
+    info!("Shutdown complete");
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::get;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+    use tokio::task::JoinHandle;
+
+    use super::*;
+
+    /// Serve a router whose `/slow` handler takes `delay`, returning its
+    /// address, the shutdown trigger, and the server task.
+    async fn start(
+        delay: Duration,
+        drain_timeout: Duration,
+    ) -> (SocketAddr, oneshot::Sender<()>, JoinHandle<io::Result<()>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let addr = listener.local_addr().expect("local addr");
+        let app = Router::new().route(
+            "/slow",
+            get(move || async move {
+                tokio::time::sleep(delay).await;
+                "done"
+            }),
+        );
+        let (tx, rx) = oneshot::channel::<()>();
+        let shutdown = async {
+            let _ = rx.await;
+        };
+        let server = tokio::spawn(serve(listener, app, shutdown, drain_timeout));
+        (addr, tx, server)
+    }
+
+    /// Send a request for `/slow`, returning the open connection.
+    async fn request_slow(addr: SocketAddr) -> TcpStream {
+        let mut stream = TcpStream::connect(addr).await.expect("connect");
+        stream
+            .write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .expect("write request");
+        // Let the server start handling the request before shutting down
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        stream
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_signal_resolves_serve() {
+        let (_, tx, server) = start(Duration::ZERO, Duration::from_secs(5)).await;
+
+        tx.send(()).expect("trigger shutdown");
+        let result = tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("server stopped")
+            .expect("server task");
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_request_finishes() {
+        let (addr, tx, server) = start(Duration::from_millis(500), Duration::from_secs(5)).await;
+        let mut stream = request_slow(addr).await;
+
+        tx.send(()).expect("trigger shutdown");
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .await
+            .expect("read response");
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.ends_with("done"));
+
+        let result = tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("server stopped")
+            .expect("server task");
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_drain_timeout_bounds_shutdown() {
+        let (addr, tx, server) = start(Duration::from_secs(30), Duration::from_millis(200)).await;
+        let _stream = request_slow(addr).await;
+
+        tx.send(()).expect("trigger shutdown");
+        let result = tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("server stopped despite the hanging request")
+            .expect("server task");
+        assert!(result.is_ok());
+    }
+}
@@ -0,0 +1,167 @@
+//! Shared application state.
+
+use std::sync::Arc;
+
+use dk_build::{ArtifactStore, BuildLogs, BuildQueue};
+use dk_common::config::{BuildConfig, RepoConfig, ScannerConfig, SigningConfig};
+use dk_common::storage::{self, Storage};
+use dk_common::webhooks::Webhooks;
+use dk_common::Config;
+use dk_scanner::VulnerabilityDb;
+use dk_signing::{HsmSigner, SigningResult, SigningService};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+
+use crate::auth::ApiKeys;
+use crate::index_cache::Flights;
+use crate::rate_limit::RateLimiter;
+use crate::repository::{AppRepository, PgAppRepository};
+
+/// State shared by all request handlers.
+#[derive(Clone)]
+pub struct AppState {
+    /// PostgreSQL connection pool.
+    pub db: PgPool,
+    /// Apps and their versions.
+    pub apps: Arc<dyn AppRepository>,
+    /// Redis client; connections are opened on demand.
+    pub redis: redis::Client,
+    /// Index generations in progress, shared by concurrent cache misses.
+    pub index_flights: Arc<Flights>,
+    /// Storage holding the published APKs.
+    pub storage: Arc<dyn Storage>,
+    /// Largest accepted upload request body, in bytes.
+    pub max_upload_bytes: usize,
+    /// Whether downloads are checked against the digest recorded at upload.
+    pub verify_downloads: bool,
+    /// Repository signer, if signing is configured.
+    pub signer: Option<Arc<SigningService>>,
+    /// PKCS#11 token holding the repository key, if signing uses an HSM.
+    pub hsm: Option<Arc<HsmSigner>>,
+    /// API keys accepted by protected endpoints.
+    pub api_keys: ApiKeys,
+    /// Per-client request rate limiter.
+    pub rate_limiter: Arc<RateLimiter>,
+    /// Output of builds run by this server.
+    pub build_logs: Arc<BuildLogs>,
+    /// Builds run by this server, logging to `build_logs`.
+    pub builds: Arc<BuildQueue>,
+    /// Repository metadata advertised in the index.
+    pub repo: Arc<RepoConfig>,
+    /// Limits on the size and contents of uploaded APKs.
+    pub scanner: Arc<ScannerConfig>,
+    /// Known-vulnerable libraries uploads are scanned for.
+    pub vulnerabilities: Arc<VulnerabilityDb>,
+    /// Notifications of published versions.
+    pub webhooks: Arc<Webhooks>,
+}
+
+impl AppState {
+    /// Connect to the database and set up storage described by `config`.
+    pub async fn connect(config: &Config) -> Result<Self, Box<dyn std::error::Error>> {
+        let db = PgPoolOptions::new()
+            .max_connections(config.database.max_connections)
+            .connect(&config.database.url)
+            .await?;
+        let redis = redis::Client::open(config.redis.url.as_str())?;
+        let storage = storage::open(config)?;
+        let build_logs = Arc::default();
+        let vulnerabilities = match &config.scanner.vulnerability_db {
+            Some(path) => VulnerabilityDb::load(path)?,
+            None => VulnerabilityDb::default(),
+        };
+
+        Ok(Self {
+            apps: Arc::new(PgAppRepository::new(db.clone())),
+            db,
+            redis,
+            index_flights: Arc::default(),
+            builds: build_queue(config.build.clone(), &build_logs, &storage),
+            build_logs,
+            storage,
+            max_upload_bytes: config.api.max_upload_bytes,
+            verify_downloads: config.storage.verify_downloads,
+            signer: None,
+            hsm: None,
+            api_keys: ApiKeys::from_config(&config.auth)?,
+            rate_limiter: Arc::new(RateLimiter::new(config.rate_limit.clone())),
+            repo: Arc::new(config.repo.clone()),
+            scanner: Arc::new(config.scanner.clone()),
+            vulnerabilities: Arc::new(vulnerabilities),
+            webhooks: Arc::new(Webhooks::from_config(&config.webhooks)?),
+        })
+    }
+}
+
+/// Queue for builds with `config`, storing their APKs in `storage`.
+fn build_queue(
+    config: BuildConfig,
+    logs: &Arc<BuildLogs>,
+    storage: &Arc<dyn Storage>,
+) -> Arc<BuildQueue> {
+    Arc::new(
+        BuildQueue::new(config, Arc::clone(logs))
+            .with_artifacts(ArtifactStore::new(Arc::clone(storage))),
+    )
+}
+
+/// Load the repository signer, if both key and certificate are configured.
+pub fn load_signer(config: &SigningConfig) -> SigningResult<Option<Arc<SigningService>>> {
+    match (&config.key_path, &config.certificate_path) {
+        (Some(key_path), Some(certificate_path)) => Ok(Some(Arc::new(SigningService::from_files(
+            key_path,
+            certificate_path,
+        )?))),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+impl AppState {
+    /// State backed by a pool that never connects successfully.
+    ///
+    /// Suitable for tests of handlers that do not touch the database.
+    pub fn disconnected() -> Self {
+        let db = PgPoolOptions::new()
+            .acquire_timeout(std::time::Duration::from_secs(1))
+            .connect_lazy("postgres://dk_appstore@127.0.0.1:1/dk_appstore_test")
+            .expect("lazy pool");
+        let redis = redis::Client::open("redis://127.0.0.1:1/").expect("redis client");
+        let storage: Arc<dyn Storage> = Arc::new(storage::FilesystemStorage::new(
+            dk_common::config::StorageConfig::default().apk_dir,
+        ));
+        let build_logs = Arc::default();
+
+        Self {
+            apps: Arc::new(PgAppRepository::new(db.clone())),
+            db,
+            redis,
+            index_flights: Arc::default(),
+            builds: build_queue(BuildConfig::default(), &build_logs, &storage),
+            build_logs,
+            storage,
+            max_upload_bytes: 100 * 1024 * 1024,
+            verify_downloads: false,
+            signer: None,
+            hsm: None,
+            api_keys: ApiKeys::default(),
+            rate_limiter: Arc::default(),
+            repo: Arc::default(),
+            scanner: Arc::default(),
+            vulnerabilities: Arc::default(),
+            webhooks: Arc::default(),
+        }
+    }
+}
+
+/// Pool connected to `DATABASE_URL`, with every migration applied.
+#[cfg(test)]
+pub async fn migrated_db() -> PgPool {
+    let url = std::env::var("DATABASE_URL").expect("DATABASE_URL");
+    let db = PgPool::connect(&url).await.expect("connect");
+    sqlx::migrate!("../migrations")
+        .run(&db)
+        .await
+        .expect("migrate");
+    db
+}
@@ -0,0 +1,198 @@
+//! Persistence of built APKs in artifact storage.
+//!
+//! Each build's APK is stored under `builds/<build-id>.apk`, next to a
+//! `.sha256` object recording its digest. The digest is written last, so an
+//! artifact with a recorded digest is always complete.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use dk_common::hash::sha256_bytes;
+use dk_common::storage::Storage;
+use dk_common::types::Sha256;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncReadExt;
+
+use crate::error::{BuildError, BuildResult};
+use crate::queue::BuildId;
+
+/// A build's APK as recorded in storage.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredArtifact {
+    /// Storage key of the APK.
+    pub key: String,
+    /// SHA-256 of the APK, recorded when it was stored.
+    pub sha256: Sha256,
+}
+
+/// Built APKs kept in a [`Storage`] backend, by build ID.
+#[derive(Clone)]
+pub struct ArtifactStore {
+    storage: Arc<dyn Storage>,
+}
+
+impl ArtifactStore {
+    /// Keep artifacts in `storage`.
+    #[must_use]
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self { storage }
+    }
+
+    /// Store the APK at `apk_path` as the artifact of build `build_id`,
+    /// replacing any earlier one, and record its digest.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BuildError::NotFound`] if there is no file at `apk_path`
+    /// and [`BuildError::Storage`] if it cannot be read or stored.
+    pub async fn store_artifact(
+        &self,
+        build_id: BuildId,
+        apk_path: &Path,
+    ) -> BuildResult<StoredArtifact> {
+        let apk = tokio::fs::read(apk_path)
+            .await
+            .map_err(|err| match err.kind() {
+                std::io::ErrorKind::NotFound => {
+                    BuildError::NotFound(format!("APK not found: {}", apk_path.display()))
+                }
+                _ => BuildError::Storage(format!("{}: {err}", apk_path.display())),
+            })?;
+        let sha256 = sha256_bytes(&apk);
+
+        let key = apk_key(build_id);
+        self.storage
+            .put(&key, Bytes::from(apk))
+            .await
+            .map_err(storage_error)?;
+        self.storage
+            .put(&digest_key(build_id), Bytes::from(sha256.to_string()))
+            .await
+            .map_err(storage_error)?;
+
+        tracing::info!(build_id = %build_id, %key, %sha256, "Stored build artifact");
+        Ok(StoredArtifact { key, sha256 })
+    }
+
+    /// Fetch the APK of build `build_id`, checked against the digest
+    /// recorded when it was stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BuildError::NotFound`] if no artifact was stored for the
+    /// build, [`BuildError::CorruptArtifact`] if the APK no longer matches
+    /// its digest, and [`BuildError::Storage`] if storage fails.
+    pub async fn get_artifact(&self, build_id: BuildId) -> BuildResult<(StoredArtifact, Bytes)> {
+        let recorded = read_object(self.storage.as_ref(), &digest_key(build_id)).await?;
+        let sha256 = std::str::from_utf8(&recorded)
+            .ok()
+            .and_then(|hex| Sha256::parse(hex.trim()).ok())
+            .ok_or_else(|| {
+                BuildError::CorruptArtifact(format!("build {build_id}: unreadable digest"))
+            })?;
+
+        let key = apk_key(build_id);
+        let apk = read_object(self.storage.as_ref(), &key).await?;
+        let actual = sha256_bytes(&apk);
+        if actual != sha256 {
+            return Err(BuildError::CorruptArtifact(format!(
+                "{key}: SHA-256 is {actual}, recorded {sha256}"
+            )));
+        }
+        Ok((StoredArtifact { key, sha256 }, Bytes::from(apk)))
+    }
+}
+
+/// Storage key of the APK of build `build_id`.
+fn apk_key(build_id: BuildId) -> String {
+    format!("builds/{build_id}.apk")
+}
+
+/// Storage key of the recorded digest of build `build_id`'s APK.
+fn digest_key(build_id: BuildId) -> String {
+    format!("builds/{build_id}.apk.sha256")
+}
+
+/// Read the whole object at `key`.
+async fn read_object(storage: &dyn Storage, key: &str) -> BuildResult<Vec<u8>> {
+    let mut reader = storage.get(key, None).await.map_err(storage_error)?;
+    let mut bytes = Vec::new();
+    reader
+        .read_to_end(&mut bytes)
+        .await
+        .map_err(|err| BuildError::Storage(format!("{key}: {err}")))?;
+    Ok(bytes)
+}
+
+/// Map a storage error to a build error.
+fn storage_error(err: dk_common::Error) -> BuildError {
+    match err {
+        dk_common::Error::NotFound(message) => BuildError::NotFound(message),
+        err => BuildError::Storage(err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dk_common::storage::FilesystemStorage;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::source::tests::TempDir;
+
+    /// A store over a fresh directory, and an APK to put in it.
+    fn setup(root: &TempDir) -> (ArtifactStore, std::path::PathBuf) {
+        let apk = root.0.join("app-release.apk");
+        std::fs::write(&apk, b"built apk").expect("write apk");
+        let storage = FilesystemStorage::new(root.0.join("storage"));
+        (ArtifactStore::new(Arc::new(storage)), apk)
+    }
+
+    #[tokio::test]
+    async fn test_store_and_get_artifact() {
+        let root = TempDir::new();
+        let (store, apk) = setup(&root);
+        let build_id = BuildId::from_uuid(Uuid::new_v4());
+
+        let stored = store.store_artifact(build_id, &apk).await.expect("store");
+        assert_eq!(stored.key, format!("builds/{build_id}.apk"));
+        assert_eq!(stored.sha256, sha256_bytes(b"built apk"));
+
+        let (fetched, bytes) = store.get_artifact(build_id).await.expect("get");
+        assert_eq!(fetched, stored);
+        assert_eq!(bytes, Bytes::from_static(b"built apk"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_build_is_not_found() {
+        let root = TempDir::new();
+        let (store, _) = setup(&root);
+
+        let result = store.get_artifact(BuildId::from_uuid(Uuid::new_v4())).await;
+        assert!(matches!(result, Err(BuildError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_missing_apk_is_not_found() {
+        let root = TempDir::new();
+        let (store, _) = setup(&root);
+
+        let result = store
+            .store_artifact(BuildId::from_uuid(Uuid::new_v4()), &root.0.join("none.apk"))
+            .await;
+        assert!(matches!(result, Err(BuildError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_tampered_artifact_is_rejected() {
+        let root = TempDir::new();
+        let (store, apk) = setup(&root);
+        let build_id = BuildId::from_uuid(Uuid::new_v4());
+        let stored = store.store_artifact(build_id, &apk).await.expect("store");
+
+        std::fs::write(root.0.join("storage").join(&stored.key), b"tampered").expect("tamper");
+        let result = store.get_artifact(build_id).await;
+        assert!(matches!(result, Err(BuildError::CorruptArtifact(_))));
+    }
+}
@@ -0,0 +1,280 @@
+//! Cache of build outputs, so an unchanged commit is built only once.
+//!
+//! Entries are small JSON objects under `build-cache/` in artifact storage,
+//! pointing at the artifact of the build that produced them.
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use dk_common::hash::Sha256Hasher;
+use dk_common::storage::Storage;
+use dk_common::types::Sha256;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncReadExt;
+
+use crate::artifacts::{ArtifactStore, StoredArtifact};
+use crate::error::{BuildError, BuildResult};
+use crate::queue::BuildId;
+use crate::spec::BuildSpec;
+
+/// What a build's output depends on. Builds with equal keys produce the
+/// same APK, so only the first needs to run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey {
+    /// Full hash of the commit built.
+    pub source_commit: String,
+    /// Container image the build ran in.
+    pub toolchain_image: String,
+    /// Digest of the build commands, with the environment and output path
+    /// they run with.
+    pub build_commands_hash: Sha256,
+}
+
+impl CacheKey {
+    /// Key of building `spec` at `source_commit`.
+    #[must_use]
+    pub fn new(source_commit: &str, spec: &BuildSpec) -> Self {
+        // NUL cannot occur in commands or paths, so fields cannot run together
+        let mut hasher = Sha256Hasher::new();
+        for command in &spec.build_commands {
+            hasher.update(command.as_bytes());
+            hasher.update(b"\0");
+        }
+        for (name, value) in &spec.env {
+            hasher.update(format!("\0{name}={value}").as_bytes());
+        }
+        hasher.update(b"\0");
+        hasher.update(spec.output_apk_path.as_os_str().as_encoded_bytes());
+
+        Self {
+            source_commit: source_commit.to_string(),
+            toolchain_image: spec.image.clone(),
+            build_commands_hash: hasher.finish(),
+        }
+    }
+
+    /// Storage key of the cache entry.
+    fn storage_key(&self) -> String {
+        let mut hasher = Sha256Hasher::new();
+        for part in [
+            self.source_commit.as_str(),
+            self.toolchain_image.as_str(),
+            &self.build_commands_hash.to_string(),
+        ] {
+            hasher.update(part.as_bytes());
+            hasher.update(b"\0");
+        }
+        format!("build-cache/{}.json", hasher.finish())
+    }
+}
+
+/// A cached build output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedBuild {
+    /// Build that produced the artifact.
+    pub build_id: BuildId,
+    /// The stored artifact.
+    pub artifact: StoredArtifact,
+}
+
+/// Outcome of [`crate::BuildService::build_cached`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheOutcome {
+    /// The artifact, built now or earlier.
+    pub build: CachedBuild,
+    /// Whether the artifact came from the cache, without building.
+    pub hit: bool,
+}
+
+/// Build outputs by [`CacheKey`], kept in artifact storage.
+#[derive(Clone)]
+pub struct BuildCache {
+    storage: Arc<dyn Storage>,
+    artifacts: ArtifactStore,
+}
+
+impl BuildCache {
+    /// Keep cache entries and artifacts in `storage`.
+    #[must_use]
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self {
+            artifacts: ArtifactStore::new(Arc::clone(&storage)),
+            storage,
+        }
+    }
+
+    /// The store holding the cached artifacts.
+    #[must_use]
+    pub const fn artifacts(&self) -> &ArtifactStore {
+        &self.artifacts
+    }
+
+    /// The build cached under `key`, if any. Entries whose artifact has
+    /// since been removed are misses.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BuildError::Storage`] if storage fails and
+    /// [`BuildError::CorruptArtifact`] if the entry cannot be read.
+    pub async fn lookup(&self, key: &CacheKey) -> BuildResult<Option<CachedBuild>> {
+        let storage_key = key.storage_key();
+        let mut reader = match self.storage.get(&storage_key, None).await {
+            Ok(reader) => reader,
+            Err(dk_common::Error::NotFound(_)) => return Ok(None),
+            Err(err) => return Err(BuildError::Storage(err.to_string())),
+        };
+        let mut entry = Vec::new();
+        reader
+            .read_to_end(&mut entry)
+            .await
+            .map_err(|err| BuildError::Storage(format!("{storage_key}: {err}")))?;
+        let cached: CachedBuild = serde_json::from_slice(&entry)
+            .map_err(|err| BuildError::CorruptArtifact(format!("{storage_key}: {err}")))?;
+
+        match self.storage.head(&cached.artifact.key).await {
+            Ok(_) => Ok(Some(cached)),
+            Err(dk_common::Error::NotFound(_)) => Ok(None),
+            Err(err) => Err(BuildError::Storage(err.to_string())),
+        }
+    }
+
+    /// Cache `build` under `key`, replacing any earlier entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BuildError::Storage`] if storage fails.
+    pub async fn record(&self, key: &CacheKey, build: &CachedBuild) -> BuildResult<()> {
+        let entry = serde_json::to_vec(build)
+            .map_err(|err| BuildError::Storage(format!("serializing cache entry: {err}")))?;
+        self.storage
+            .put(&key.storage_key(), Bytes::from(entry))
+            .await
+            .map_err(|err| BuildError::Storage(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use dk_common::config::BuildConfig;
+    use dk_common::storage::FilesystemStorage;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::container::tests::fake_runtime;
+    use crate::source::tests::{bare_repo, TempDir};
+    use crate::spec::tests::spec;
+    use crate::BuildService;
+
+    /// A service whose builds write an APK and count themselves in
+    /// `<root>/builds`.
+    fn counting_service(root: &Path, apk: &Path) -> BuildService {
+        let script = format!(
+            "echo built >> '{}'\nprintf apk > '{}'",
+            root.join("builds").display(),
+            apk.display()
+        );
+        BuildService::with_config(BuildConfig {
+            container_runtime: fake_runtime(root, &script),
+            ..BuildConfig::default()
+        })
+    }
+
+    fn build_count(root: &Path) -> usize {
+        std::fs::read_to_string(root.join("builds")).map_or(0, |builds| builds.lines().count())
+    }
+
+    #[test]
+    fn test_key_depends_on_commit_image_and_commands() {
+        let spec = spec(Path::new("/src"));
+        let key = CacheKey::new("abc123", &spec);
+        assert_eq!(
+            CacheKey::new("abc123", &spec).storage_key(),
+            key.storage_key()
+        );
+
+        let mut other_image = spec.clone();
+        other_image.image = "docker.io/example/android-build@sha256:1111".to_string();
+        let mut other_commands = spec.clone();
+        other_commands
+            .build_commands
+            .push("./gradlew lint".to_string());
+        let mut other_env = spec.clone();
+        other_env.env.insert("CI".to_string(), "false".to_string());
+        for other in [
+            CacheKey::new("def456", &spec),
+            CacheKey::new("abc123", &other_image),
+            CacheKey::new("abc123", &other_commands),
+            CacheKey::new("abc123", &other_env),
+        ] {
+            assert_ne!(other.storage_key(), key.storage_key(), "{other:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_commit_is_built_once() {
+        let root = TempDir::new();
+        let (url, _, _) = bare_repo(&root.0).await;
+        let service = BuildService::new();
+        let checkout = service
+            .fetch_source(&url, "v1.0", &root.0.join("src"))
+            .await
+            .expect("fetch source");
+        let spec = spec(&checkout);
+        let service = counting_service(&root.0, &checkout.join(&spec.output_apk_path));
+        let cache = BuildCache::new(Arc::new(FilesystemStorage::new(root.0.join("storage"))));
+
+        let first_id = BuildId::from_uuid(Uuid::new_v4());
+        let first = service
+            .build_cached(first_id, &spec, &cache)
+            .await
+            .expect("first build");
+        assert!(!first.hit);
+        assert_eq!(build_count(&root.0), 1);
+
+        let second = service
+            .build_cached(BuildId::from_uuid(Uuid::new_v4()), &spec, &cache)
+            .await
+            .expect("second build");
+        assert!(second.hit);
+        assert_eq!(second.build, first.build);
+        assert_eq!(second.build.build_id, first_id);
+        assert_eq!(build_count(&root.0), 1);
+
+        let (_, apk) = cache
+            .artifacts()
+            .get_artifact(second.build.build_id)
+            .await
+            .expect("cached artifact");
+        assert_eq!(apk, Bytes::from_static(b"apk"));
+
+        // Changed commands miss the cache
+        let mut changed = spec.clone();
+        changed.build_commands.push("./gradlew lint".to_string());
+        let third = service
+            .build_cached(BuildId::from_uuid(Uuid::new_v4()), &changed, &cache)
+            .await
+            .expect("third build");
+        assert!(!third.hit);
+        assert_eq!(build_count(&root.0), 2);
+    }
+
+    #[tokio::test]
+    async fn test_entry_without_artifact_is_a_miss() {
+        let root = TempDir::new();
+        let cache = BuildCache::new(Arc::new(FilesystemStorage::new(root.0.join("storage"))));
+        let key = CacheKey::new("abc123", &spec(Path::new("/src")));
+        let build_id = BuildId::from_uuid(Uuid::new_v4());
+        let build = CachedBuild {
+            build_id,
+            artifact: StoredArtifact {
+                key: format!("builds/{build_id}.apk"),
+                sha256: dk_common::hash::sha256_bytes(b"apk"),
+            },
+        };
+
+        cache.record(&key, &build).await.expect("record");
+        assert_eq!(cache.lookup(&key).await.expect("lookup"), None);
+    }
+}
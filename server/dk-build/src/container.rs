@@ -0,0 +1,292 @@
+//! Gradle builds inside pinned container images.
+
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+
+use dk_common::hash;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+
+use crate::error::{BuildError, BuildResult};
+use crate::logs::BuildLog;
+use crate::spec::BuildSpec;
+
+/// Directory the source tree is mounted at inside the container.
+pub const CONTAINER_WORKDIR: &str = "/build";
+
+/// Exit status container runtimes use when the container itself could not
+/// be started (as opposed to the command inside it failing).
+const RUNTIME_FAILURE_STATUS: i32 = 125;
+
+/// Output of a successful build.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildArtifact {
+    /// Absolute path of the produced APK.
+    pub apk_path: PathBuf,
+    /// Lowercase hex SHA-256 of the APK.
+    pub sha256: String,
+    /// Build standard output.
+    pub stdout: String,
+    /// Build standard error.
+    pub stderr: String,
+}
+
+/// Run `spec` with container runtime `runtime`, killing the build after
+/// `timeout`. Output lines are also appended to `log` as they are printed.
+pub async fn run(
+    spec: &BuildSpec,
+    runtime: &str,
+    timeout: Duration,
+    log: Option<&BuildLog>,
+) -> BuildResult<BuildArtifact> {
+    let name = format!("dk-build-{}", uuid::Uuid::new_v4());
+    let mut command = Command::new(runtime);
+    command
+        .args(["run", "--rm", "--name", &name])
+        .arg("--volume")
+        .arg(format!(
+            "{}:{CONTAINER_WORKDIR}:Z",
+            spec.source_dir.display()
+        ))
+        .args(["--workdir", CONTAINER_WORKDIR]);
+    for (key, value) in &spec.env {
+        command.arg("--env").arg(format!("{key}={value}"));
+    }
+    command
+        .arg(&spec.image)
+        .args(spec.command())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let mut child = command
+        .spawn()
+        .map_err(|err| BuildError::ContainerError(format!("{runtime}: {err}")))?;
+
+    // Drain both pipes while waiting so a chatty build cannot block on a full pipe
+    let mut stdout_pipe = child.stdout.take();
+    let mut stderr_pipe = child.stderr.take();
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    let completed = tokio::time::timeout(timeout, async {
+        let (status, (), ()) = tokio::join!(
+            child.wait(),
+            read_pipe(stdout_pipe.as_mut(), &mut stdout, log),
+            read_pipe(stderr_pipe.as_mut(), &mut stderr, log),
+        );
+        status
+    })
+    .await;
+
+    let Ok(status) = completed else {
+        let _ = child.kill().await;
+        // Killing the runtime client does not necessarily stop the container
+        let _ = Command::new(runtime)
+            .args(["rm", "--force", &name])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .await;
+        return Err(BuildError::Timeout(timeout.as_secs()));
+    };
+    let status = status.map_err(|err| BuildError::ContainerError(err.to_string()))?;
+
+    let stdout = String::from_utf8_lossy(&stdout).into_owned();
+    let stderr = String::from_utf8_lossy(&stderr).into_owned();
+
+    if !status.success() {
+        let detail = format!("{status}: {}", stderr.trim());
+        return Err(match status.code() {
+            Some(RUNTIME_FAILURE_STATUS) => BuildError::ContainerError(detail),
+            _ => BuildError::BuildFailed(detail),
+        });
+    }
+
+    let apk_path = spec.source_dir.join(&spec.output_apk_path);
+    let not_produced = |err: &dyn std::fmt::Display| {
+        BuildError::BuildFailed(format!("APK not produced at {}: {err}", apk_path.display()))
+    };
+    let apk = tokio::fs::File::open(&apk_path)
+        .await
+        .map_err(|err| not_produced(&err))?;
+    let sha256 = hash::sha256_reader(apk)
+        .await
+        .map_err(|err| not_produced(&err))?
+        .to_string();
+
+    Ok(BuildArtifact {
+        apk_path,
+        sha256,
+        stdout,
+        stderr,
+    })
+}
+
+/// Read `pipe` to the end into `buffer`, appending each line to `log`.
+async fn read_pipe<R: AsyncRead + Unpin>(
+    pipe: Option<&mut R>,
+    buffer: &mut Vec<u8>,
+    log: Option<&BuildLog>,
+) {
+    let Some(pipe) = pipe else {
+        return;
+    };
+    let mut reader = BufReader::new(pipe);
+    loop {
+        let start = buffer.len();
+        match reader.read_until(b'\n', buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                if let Some(log) = log {
+                    let line = String::from_utf8_lossy(&buffer[start..]);
+                    log.push(line.trim_end_matches(['\r', '\n']));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    use super::*;
+    use crate::source::tests::TempDir;
+    use crate::spec::tests::spec;
+
+    /// Write an executable stand-in for the container runtime that runs
+    /// `script` for `run` invocations and ignores everything else.
+    pub fn fake_runtime(dir: &Path, script: &str) -> String {
+        let path = dir.join("fake-runtime");
+        std::fs::write(
+            &path,
+            format!("#!/bin/sh\ncase \"$1\" in\nrun)\n{script}\n;;\nesac\n"),
+        )
+        .expect("write runtime");
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+            .expect("chmod runtime");
+        path.display().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_build_collects_artifact_and_logs() {
+        let root = TempDir::new();
+        let apk = root.0.join("app-release.apk");
+        let runtime = fake_runtime(
+            &root.0,
+            &format!(
+                "echo \"$@\"\necho 'gradle warning' >&2\nprintf apk > '{}'",
+                apk.display()
+            ),
+        );
+
+        let artifact = run(&spec(&root.0), &runtime, Duration::from_secs(30), None)
+            .await
+            .expect("build");
+        assert_eq!(artifact.apk_path, apk);
+        // SHA-256 of "apk"
+        assert_eq!(
+            artifact.sha256,
+            "dd37c2d7274f7ea982cb83390c36918fee9ce8889073c44b68cdc00bdb8c3e04"
+        );
+        assert!(artifact.stdout.contains("--env CI=true"));
+        assert!(artifact
+            .stdout
+            .contains("android-build@sha256:0000 sh -c ./gradlew --no-daemon assembleRelease"));
+        assert_eq!(artifact.stderr.trim(), "gradle warning");
+    }
+
+    #[tokio::test]
+    async fn test_build_output_is_logged() {
+        let root = TempDir::new();
+        let apk = root.0.join("app-release.apk");
+        let runtime = fake_runtime(
+            &root.0,
+            &format!(
+                "echo '> Task :app:assembleRelease'\necho 'BUILD SUCCESSFUL'\nprintf apk > '{}'",
+                apk.display()
+            ),
+        );
+
+        let log = BuildLog::new();
+        let artifact = run(
+            &spec(&root.0),
+            &runtime,
+            Duration::from_secs(30),
+            Some(&log),
+        )
+        .await
+        .expect("build");
+        assert_eq!(
+            log.lines(),
+            ["> Task :app:assembleRelease", "BUILD SUCCESSFUL"]
+        );
+        assert_eq!(
+            artifact.stdout,
+            "> Task :app:assembleRelease\nBUILD SUCCESSFUL\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_build_timeout() {
+        let root = TempDir::new();
+        let runtime = fake_runtime(&root.0, "sleep 30");
+
+        assert!(matches!(
+            run(&spec(&root.0), &runtime, Duration::from_secs(1), None).await,
+            Err(BuildError::Timeout(1))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_missing_runtime_is_container_error() {
+        let root = TempDir::new();
+        assert!(matches!(
+            run(
+                &spec(&root.0),
+                "/nonexistent/podman",
+                Duration::from_secs(5),
+                None
+            )
+            .await,
+            Err(BuildError::ContainerError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_container_start_failure_is_container_error() {
+        let root = TempDir::new();
+        let runtime = fake_runtime(&root.0, "echo 'image not known' >&2\nexit 125");
+
+        assert!(matches!(
+            run(&spec(&root.0), &runtime, Duration::from_secs(5), None).await,
+            Err(BuildError::ContainerError(detail)) if detail.contains("image not known")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_failed_gradle_build() {
+        let root = TempDir::new();
+        let runtime = fake_runtime(&root.0, "echo 'BUILD FAILED' >&2\nexit 1");
+
+        assert!(matches!(
+            run(&spec(&root.0), &runtime, Duration::from_secs(5), None).await,
+            Err(BuildError::BuildFailed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_missing_apk_is_build_failure() {
+        let root = TempDir::new();
+        let runtime = fake_runtime(&root.0, "echo done");
+
+        assert!(matches!(
+            run(&spec(&root.0), &runtime, Duration::from_secs(5), None).await,
+            Err(BuildError::BuildFailed(_))
+        ));
+    }
+}
@@ -2,6 +2,8 @@
 
 use thiserror::Error;
 
+use crate::repro::ReproReport;
+
 /// Result type for build operations.
 pub type BuildResult<T> = Result<T, BuildError>;
 
@@ -25,10 +27,74 @@ pub enum BuildError {
     Timeout(u64),
 
     /// Reproducibility verification failed.
-    #[error("Reproducibility check failed: builds do not match")]
-    ReproducibilityFailed,
+    #[error("Reproducibility check failed: {0}")]
+    ReproducibilityFailed(ReproReport),
+
+    /// No build with this ID is known.
+    #[error("Build not found: {0}")]
+    NotFound(String),
+
+    /// The build is in a state that does not allow the operation.
+    #[error("Invalid build state: {0}")]
+    InvalidState(String),
 
     /// Container orchestration error.
     #[error("Container error: {0}")]
     ContainerError(String),
+
+    /// Artifact storage failed.
+    #[error("Storage error: {0}")]
+    Storage(String),
+
+    /// A stored artifact no longer matches its recorded digest.
+    #[error("Corrupt artifact: {0}")]
+    CorruptArtifact(String),
+}
+
+impl BuildError {
+    /// Whether the build may succeed if retried unchanged.
+    ///
+    /// Timeouts, container runtime failures, and storage failures are
+    /// usually caused by the build host; the others recur on every attempt.
+    #[must_use]
+    pub const fn is_retryable(&self) -> bool {
+        match self {
+            Self::Timeout(_) | Self::ContainerError(_) | Self::Storage(_) => true,
+            Self::SourceNotFound(_)
+            | Self::InvalidConfig(_)
+            | Self::BuildFailed(_)
+            | Self::ReproducibilityFailed(_)
+            | Self::NotFound(_)
+            | Self::InvalidState(_)
+            | Self::CorruptArtifact(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retryable_classification() {
+        let report = ReproReport {
+            entries_compared: 1,
+            differences: Vec::new(),
+        };
+        let cases = [
+            (BuildError::SourceNotFound(String::new()), false),
+            (BuildError::InvalidConfig(String::new()), false),
+            (BuildError::BuildFailed(String::new()), false),
+            (BuildError::Timeout(60), true),
+            (BuildError::ReproducibilityFailed(report), false),
+            (BuildError::NotFound(String::new()), false),
+            (BuildError::InvalidState(String::new()), false),
+            (BuildError::ContainerError(String::new()), true),
+            (BuildError::Storage(String::new()), true),
+            (BuildError::CorruptArtifact(String::new()), false),
+        ];
+        for (err, retryable) in cases {
+            assert_eq!(err.is_retryable(), retryable, "{err:?}");
+        }
+    }
 }
@@ -2,22 +2,149 @@
 //!
 //! Manages reproducible builds of Android applications.
 
+pub mod artifacts;
+pub mod cache;
+pub mod container;
 pub mod error;
+pub mod logs;
+pub mod queue;
+pub mod repro;
+pub mod source;
+pub mod spec;
 
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub use artifacts::{ArtifactStore, StoredArtifact};
+pub use cache::{BuildCache, CacheKey, CacheOutcome, CachedBuild};
+pub use container::BuildArtifact;
+use dk_common::config::BuildConfig;
+use dk_common::types::BuildStatus;
 pub use error::{BuildError, BuildResult};
+pub use logs::{BuildLog, BuildLogs, LogEvent, LogSubscription};
+pub use queue::{BuildId, BuildQueue, BuildRecord};
+pub use repro::{EntryDiff, ReproReport};
+pub use spec::BuildSpec;
 
-/// Placeholder for build service functionality.
-///
-/// Full implementation will be added in Milestone 3.
+/// Build service for reproducible application builds.
 pub struct BuildService {
-    _private: (),
+    config: BuildConfig,
 }
 
 impl BuildService {
-    /// Create a new build service (placeholder).
+    /// Create a build service with the default configuration.
     #[must_use]
     pub fn new() -> Self {
-        Self { _private: () }
+        Self::with_config(BuildConfig::default())
+    }
+
+    /// Create a build service with the given configuration.
+    #[must_use]
+    pub const fn with_config(config: BuildConfig) -> Self {
+        Self { config }
+    }
+
+    /// Clone `repo_url` into `dest` and check out `git_ref` (a tag, branch,
+    /// or full commit hash), returning the working directory.
+    ///
+    /// Only `clone_depth` commits of history are fetched unless the depth is
+    /// configured as 0. An unreachable repository is reported as
+    /// [`BuildError::SourceNotFound`], a ref it does not contain as
+    /// [`BuildError::InvalidConfig`].
+    pub async fn fetch_source(
+        &self,
+        repo_url: &str,
+        git_ref: &str,
+        dest: &Path,
+    ) -> BuildResult<PathBuf> {
+        source::fetch(repo_url, git_ref, dest, self.config.clone_depth).await
+    }
+
+    /// Run the build described by `spec` in its container image and return
+    /// the produced APK with its digest and the captured build output.
+    ///
+    /// The spec is validated first; an invalid one is reported as
+    /// [`BuildError::InvalidConfig`] without starting a container. Failure
+    /// to start the container is reported as [`BuildError::ContainerError`];
+    /// builds running longer than the spec's `timeout_secs`, or else
+    /// `build_timeout_secs`, are killed and reported as
+    /// [`BuildError::Timeout`].
+    pub async fn build(&self, spec: &BuildSpec) -> BuildResult<BuildArtifact> {
+        self.run(spec, None).await
+    }
+
+    /// Run the build described by `spec` like [`Self::build`], appending its
+    /// output to `log` line by line and finishing the log with the outcome.
+    pub async fn build_logged(
+        &self,
+        spec: &BuildSpec,
+        log: &BuildLog,
+    ) -> BuildResult<BuildArtifact> {
+        let result = self.run(spec, Some(log)).await;
+        log.finish(if result.is_ok() {
+            BuildStatus::Success
+        } else {
+            BuildStatus::Failed
+        });
+        result
+    }
+
+    /// Build `spec` as build `build_id` and store its APK in `cache`,
+    /// unless the checked-out commit was already built with the same image
+    /// and commands; the earlier build's artifact is then returned without
+    /// building.
+    ///
+    /// Errors are those of [`Self::build`], of reading the checked-out
+    /// commit, and of [`ArtifactStore::store_artifact`].
+    pub async fn build_cached(
+        &self,
+        build_id: BuildId,
+        spec: &BuildSpec,
+        cache: &BuildCache,
+    ) -> BuildResult<CacheOutcome> {
+        spec.validate()?;
+        let commit = source::head_commit(&spec.source_dir).await?;
+        let key = CacheKey::new(&commit, spec);
+        if let Some(build) = cache.lookup(&key).await? {
+            tracing::info!(
+                build_id = %build_id,
+                cached_build_id = %build.build_id,
+                package_id = %spec.package_id,
+                %commit,
+                "Build cache hit"
+            );
+            return Ok(CacheOutcome { build, hit: true });
+        }
+
+        let artifact = self.build(spec).await?;
+        let artifact = cache
+            .artifacts()
+            .store_artifact(build_id, &artifact.apk_path)
+            .await?;
+        let build = CachedBuild { build_id, artifact };
+        cache.record(&key, &build).await?;
+        Ok(CacheOutcome { build, hit: false })
+    }
+
+    async fn run(&self, spec: &BuildSpec, log: Option<&BuildLog>) -> BuildResult<BuildArtifact> {
+        spec.validate()?;
+        let timeout = spec.timeout_secs.unwrap_or(self.config.build_timeout_secs);
+        container::run(
+            spec,
+            &self.config.container_runtime,
+            Duration::from_secs(timeout),
+            log,
+        )
+        .await
+    }
+
+    /// Compare two independently built APKs entry by entry.
+    ///
+    /// Timestamps, compression, and signatures are ignored. Differing
+    /// contents yield [`BuildError::ReproducibilityFailed`] with a report
+    /// naming each differing entry.
+    pub fn verify_reproducible(a: &BuildArtifact, b: &BuildArtifact) -> BuildResult<ReproReport> {
+        repro::verify(a, b)
     }
 }
 
@@ -26,3 +153,75 @@ impl Default for BuildService {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::tests::fake_runtime;
+    use crate::source::tests::{bare_repo, TempDir};
+    use crate::spec::tests::spec;
+
+    #[tokio::test]
+    async fn test_fetch_source_checks_out_tag() {
+        let root = TempDir::new();
+        let (url, tagged, _) = bare_repo(&root.0).await;
+
+        let service = BuildService::new();
+        let dest = service
+            .fetch_source(&url, "v1.0", &root.0.join("src"))
+            .await
+            .expect("fetch source");
+        assert_eq!(dest, root.0.join("src"));
+        assert_eq!(source::head_commit(&dest).await.expect("head"), tagged);
+    }
+
+    #[tokio::test]
+    async fn test_build_uses_configured_runtime_and_timeout() {
+        let root = TempDir::new();
+        let service = BuildService::with_config(BuildConfig {
+            container_runtime: fake_runtime(&root.0, "sleep 30"),
+            build_timeout_secs: 1,
+            ..BuildConfig::default()
+        });
+
+        assert!(matches!(
+            service.build(&spec(&root.0)).await,
+            Err(BuildError::Timeout(1))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_spec_timeout_overrides_configured_timeout() {
+        let root = TempDir::new();
+        let service = BuildService::with_config(BuildConfig {
+            container_runtime: fake_runtime(&root.0, "sleep 30"),
+            build_timeout_secs: 60,
+            ..BuildConfig::default()
+        });
+        let mut spec = spec(&root.0);
+        spec.timeout_secs = Some(1);
+
+        assert!(matches!(
+            service.build(&spec).await,
+            Err(BuildError::Timeout(1))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_spec_is_not_built() {
+        let root = TempDir::new();
+        let marker = root.0.join("ran");
+        let service = BuildService::with_config(BuildConfig {
+            container_runtime: fake_runtime(&root.0, &format!("touch '{}'", marker.display())),
+            ..BuildConfig::default()
+        });
+        let mut spec = spec(&root.0);
+        spec.build_commands.clear();
+
+        assert!(matches!(
+            service.build(&spec).await,
+            Err(BuildError::InvalidConfig(_))
+        ));
+        assert!(!marker.exists());
+    }
+}
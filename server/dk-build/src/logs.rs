@@ -0,0 +1,265 @@
+//! Captured build output, replayable and followable while a build runs.
+//!
+//! Every line a build prints is kept in its [`BuildLog`], so a subscriber
+//! joining late replays the output so far and then follows new lines until
+//! the build finishes.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use dk_common::types::BuildStatus;
+use tokio::sync::watch;
+use uuid::Uuid;
+
+/// Output captured so far and, once finished, the build outcome.
+#[derive(Debug, Default)]
+struct LogState {
+    lines: Vec<String>,
+    finished: Option<BuildStatus>,
+}
+
+/// Output of a single build.
+#[derive(Debug)]
+pub struct BuildLog {
+    state: watch::Sender<LogState>,
+}
+
+impl BuildLog {
+    /// Create an empty log for a running build.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            state: watch::Sender::new(LogState::default()),
+        }
+    }
+
+    /// Append a line of output. Lines after [`Self::finish`] are dropped.
+    pub fn push(&self, line: impl Into<String>) {
+        let line = line.into();
+        self.state.send_if_modified(|state| {
+            if state.finished.is_some() {
+                return false;
+            }
+            state.lines.push(line);
+            true
+        });
+    }
+
+    /// Record that the build ended with `status`, closing the log.
+    pub fn finish(&self, status: BuildStatus) {
+        self.state.send_if_modified(|state| {
+            if state.finished.is_some() {
+                return false;
+            }
+            state.finished = Some(status);
+            true
+        });
+    }
+
+    /// The build outcome, if it has finished.
+    #[must_use]
+    pub fn status(&self) -> Option<BuildStatus> {
+        self.state.borrow().finished
+    }
+
+    /// All output captured so far.
+    #[must_use]
+    pub fn lines(&self) -> Vec<String> {
+        self.state.borrow().lines.clone()
+    }
+
+    /// Follow the log from its first line.
+    #[must_use]
+    pub fn subscribe(&self) -> LogSubscription {
+        LogSubscription {
+            receiver: self.state.subscribe(),
+            cursor: 0,
+            done: false,
+        }
+    }
+}
+
+impl Default for BuildLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An event read from a [`LogSubscription`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogEvent {
+    /// A line of build output.
+    Line(String),
+    /// The build ended with this status; no events follow.
+    Finished(BuildStatus),
+}
+
+/// Reader replaying a build's output and then following it.
+#[derive(Debug)]
+pub struct LogSubscription {
+    receiver: watch::Receiver<LogState>,
+    cursor: usize,
+    done: bool,
+}
+
+impl LogSubscription {
+    /// Wait for the next event, or `None` once the log has ended.
+    ///
+    /// A log dropped before its build finished ends without a
+    /// [`LogEvent::Finished`] event.
+    pub async fn next(&mut self) -> Option<LogEvent> {
+        loop {
+            if self.done {
+                return None;
+            }
+            {
+                let state = self.receiver.borrow_and_update();
+                if let Some(line) = state.lines.get(self.cursor) {
+                    self.cursor += 1;
+                    return Some(LogEvent::Line(line.clone()));
+                }
+                if let Some(status) = state.finished {
+                    self.done = true;
+                    return Some(LogEvent::Finished(status));
+                }
+            }
+            if self.receiver.changed().await.is_err() {
+                // The log is gone; hand out whatever was captured before it
+                let line = self.receiver.borrow().lines.get(self.cursor).cloned();
+                if let Some(line) = line {
+                    self.cursor += 1;
+                    return Some(LogEvent::Line(line));
+                }
+                self.done = true;
+            }
+        }
+    }
+}
+
+/// Logs of all builds, by build ID.
+#[derive(Debug, Default)]
+pub struct BuildLogs {
+    logs: Mutex<HashMap<Uuid, Arc<BuildLog>>>,
+}
+
+impl BuildLogs {
+    /// Create an empty registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a fresh log for build `id`, replacing any previous one.
+    pub fn start(&self, id: Uuid) -> Arc<BuildLog> {
+        let log = Arc::new(BuildLog::new());
+        self.lock().insert(id, Arc::clone(&log));
+        log
+    }
+
+    /// The log of build `id`, if it is known.
+    #[must_use]
+    pub fn get(&self, id: Uuid) -> Option<Arc<BuildLog>> {
+        self.lock().get(&id).cloned()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, Arc<BuildLog>>> {
+        // A panic while holding the lock cannot leave the map inconsistent
+        self.logs
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    async fn collect(mut subscription: LogSubscription) -> Vec<LogEvent> {
+        let mut events = Vec::new();
+        while let Some(event) = subscription.next().await {
+            events.push(event);
+        }
+        events
+    }
+
+    #[tokio::test]
+    async fn test_finished_log_replays_then_ends() {
+        let log = BuildLog::new();
+        log.push("> Task :app:assembleRelease");
+        log.push("BUILD SUCCESSFUL");
+        log.finish(BuildStatus::Success);
+
+        assert_eq!(
+            collect(log.subscribe()).await,
+            [
+                LogEvent::Line("> Task :app:assembleRelease".to_string()),
+                LogEvent::Line("BUILD SUCCESSFUL".to_string()),
+                LogEvent::Finished(BuildStatus::Success),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_running_log_streams_incrementally() {
+        let log = Arc::new(BuildLog::new());
+        log.push("first");
+        let mut subscription = log.subscribe();
+        assert_eq!(
+            subscription.next().await,
+            Some(LogEvent::Line("first".to_string()))
+        );
+
+        let writer = Arc::clone(&log);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            writer.push("second");
+            writer.finish(BuildStatus::Failed);
+        });
+
+        assert_eq!(
+            subscription.next().await,
+            Some(LogEvent::Line("second".to_string()))
+        );
+        assert_eq!(
+            subscription.next().await,
+            Some(LogEvent::Finished(BuildStatus::Failed))
+        );
+        assert_eq!(subscription.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_log_ends_subscription() {
+        let log = BuildLog::new();
+        log.push("partial");
+        let subscription = log.subscribe();
+        drop(log);
+
+        assert_eq!(
+            collect(subscription).await,
+            [LogEvent::Line("partial".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_lines_after_finish_are_dropped() {
+        let log = BuildLog::new();
+        log.finish(BuildStatus::Cancelled);
+        log.push("late");
+        log.finish(BuildStatus::Success);
+
+        assert!(log.lines().is_empty());
+        assert_eq!(log.status(), Some(BuildStatus::Cancelled));
+    }
+
+    #[test]
+    fn test_registry() {
+        let logs = BuildLogs::new();
+        let id = Uuid::new_v4();
+        assert!(logs.get(id).is_none());
+
+        logs.start(id).push("line");
+        assert_eq!(logs.get(id).expect("log").lines(), ["line"]);
+    }
+}
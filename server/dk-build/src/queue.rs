@@ -0,0 +1,420 @@
+//! Queue running builds on a bounded number of slots.
+//!
+//! Builds beyond `max_concurrent_builds` wait as [`BuildStatus::Pending`]
+//! until a running build finishes or is cancelled. Every queued build keeps
+//! a [`BuildRecord`] of its status and timing for clients polling it.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use chrono::{DateTime, Utc};
+use dk_common::config::BuildConfig;
+use dk_common::types::{AppId, BuildStatus};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+use tokio::task::AbortHandle;
+use uuid::Uuid;
+
+use crate::artifacts::{ArtifactStore, StoredArtifact};
+use crate::error::{BuildError, BuildResult};
+use crate::logs::BuildLogs;
+use crate::spec::BuildSpec;
+use crate::BuildService;
+
+/// Identifier of a queued build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct BuildId(Uuid);
+
+impl BuildId {
+    /// Wrap an existing build ID.
+    #[must_use]
+    pub const fn from_uuid(id: Uuid) -> Self {
+        Self(id)
+    }
+
+    /// The underlying UUID.
+    #[must_use]
+    pub const fn as_uuid(&self) -> Uuid {
+        self.0
+    }
+}
+
+impl fmt::Display for BuildId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Status and timing of a queued build.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildRecord {
+    /// The build.
+    pub build_id: BuildId,
+    /// Package the build produces.
+    pub package_id: AppId,
+    /// Current status.
+    pub status: BuildStatus,
+    /// When the build was queued.
+    pub queued_at: DateTime<Utc>,
+    /// When the build left the queue and started running.
+    pub started_at: Option<DateTime<Utc>>,
+    /// When the build succeeded, failed, or was cancelled.
+    pub finished_at: Option<DateTime<Utc>>,
+    /// The stored APK, once a build with an artifact store succeeded.
+    pub artifact: Option<StoredArtifact>,
+}
+
+/// A queued build's state.
+struct Job {
+    record: BuildRecord,
+    task: Option<AbortHandle>,
+}
+
+impl Job {
+    /// Move to `next`, recording when the build started or finished.
+    fn transition(&mut self, next: BuildStatus) -> dk_common::Result<()> {
+        self.record.status = self.record.status.transition(next)?;
+        let now = Utc::now();
+        match next {
+            BuildStatus::Pending => {}
+            BuildStatus::Building => self.record.started_at = Some(now),
+            BuildStatus::Success | BuildStatus::Failed | BuildStatus::Cancelled => {
+                self.record.finished_at = Some(now);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Jobs by build ID, shared with the build tasks.
+#[derive(Default)]
+struct Jobs(Mutex<HashMap<BuildId, Job>>);
+
+impl Jobs {
+    fn lock(&self) -> MutexGuard<'_, HashMap<BuildId, Job>> {
+        // Every update is a single assignment, so a poisoned map is consistent
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Move build `id` to `next`, returning whether the transition was
+    /// allowed. Builds cancelled in the meantime stay cancelled.
+    fn transition(&self, id: BuildId, next: BuildStatus) -> bool {
+        self.finish(id, next, None)
+    }
+
+    /// Move build `id` to `next` like [`Self::transition`], recording
+    /// `artifact` if the transition was allowed.
+    fn finish(&self, id: BuildId, next: BuildStatus, artifact: Option<StoredArtifact>) -> bool {
+        self.lock().get_mut(&id).is_some_and(|job| {
+            let allowed = job.transition(next).is_ok();
+            if allowed {
+                job.record.artifact = artifact;
+            }
+            allowed
+        })
+    }
+}
+
+/// Build queue running at most `max_concurrent_builds` builds at a time.
+///
+/// Output of every build is recorded in the queue's [`BuildLogs`] under its
+/// build ID. With an [`ArtifactStore`], the APKs of successful builds are
+/// stored in it.
+pub struct BuildQueue {
+    service: Arc<BuildService>,
+    logs: Arc<BuildLogs>,
+    artifacts: Option<ArtifactStore>,
+    slots: Arc<Semaphore>,
+    jobs: Arc<Jobs>,
+}
+
+impl BuildQueue {
+    /// Create a queue running builds with `config`, logging to `logs`.
+    #[must_use]
+    pub fn new(config: BuildConfig, logs: Arc<BuildLogs>) -> Self {
+        let slots = Arc::new(Semaphore::new(config.max_concurrent_builds));
+        Self {
+            service: Arc::new(BuildService::with_config(config)),
+            logs,
+            artifacts: None,
+            slots,
+            jobs: Arc::default(),
+        }
+    }
+
+    /// Store the APKs of successful builds in `artifacts`. A build whose APK
+    /// cannot be stored fails.
+    #[must_use]
+    pub fn with_artifacts(mut self, artifacts: ArtifactStore) -> Self {
+        self.artifacts = Some(artifacts);
+        self
+    }
+
+    /// Queue the build described by `spec`, starting it as soon as a slot
+    /// is free. Must be called within a Tokio runtime.
+    pub fn enqueue(&self, spec: BuildSpec) -> BuildId {
+        let id = BuildId(Uuid::new_v4());
+        let log = self.logs.start(id.0);
+        self.jobs.lock().insert(
+            id,
+            Job {
+                record: BuildRecord {
+                    build_id: id,
+                    package_id: spec.package_id.clone(),
+                    status: BuildStatus::Pending,
+                    queued_at: Utc::now(),
+                    started_at: None,
+                    finished_at: None,
+                    artifact: None,
+                },
+                task: None,
+            },
+        );
+
+        let service = Arc::clone(&self.service);
+        let artifacts = self.artifacts.clone();
+        let slots = Arc::clone(&self.slots);
+        let jobs = Arc::clone(&self.jobs);
+        let task = tokio::spawn(async move {
+            let Ok(_permit) = slots.acquire_owned().await else {
+                return;
+            };
+            if !jobs.transition(id, BuildStatus::Building) {
+                return;
+            }
+            tracing::info!(
+                build_id = %id,
+                package_id = %spec.package_id,
+                image = %spec.image,
+                "Starting build"
+            );
+            let result = match (service.run(&spec, Some(&log)).await, &artifacts) {
+                (Ok(built), Some(artifacts)) => artifacts
+                    .store_artifact(id, &built.apk_path)
+                    .await
+                    .map(Some),
+                (Ok(_), None) => Ok(None),
+                (Err(err), _) => Err(err),
+            };
+            let (status, artifact) = match result {
+                Ok(artifact) => (BuildStatus::Success, artifact),
+                Err(err) => {
+                    tracing::warn!(build_id = %id, error = %err, "Build failed");
+                    (BuildStatus::Failed, None)
+                }
+            };
+            log.finish(status);
+            jobs.finish(id, status, artifact);
+        });
+
+        // The task may already have finished; a stale handle is harmless
+        if let Some(job) = self.jobs.lock().get_mut(&id) {
+            job.task = Some(task.abort_handle());
+        }
+        id
+    }
+
+    /// Status of build `id`, or `None` if it was never queued.
+    #[must_use]
+    pub fn status(&self, id: BuildId) -> Option<BuildStatus> {
+        self.jobs.lock().get(&id).map(|job| job.record.status)
+    }
+
+    /// Record of build `id`, or `None` if it was never queued.
+    #[must_use]
+    pub fn get(&self, id: BuildId) -> Option<BuildRecord> {
+        self.jobs.lock().get(&id).map(|job| job.record.clone())
+    }
+
+    /// Records of the latest `limit` builds of `package_id`, newest first.
+    #[must_use]
+    pub fn builds_for(&self, package_id: &AppId, limit: usize) -> Vec<BuildRecord> {
+        let mut records: Vec<BuildRecord> = self
+            .jobs
+            .lock()
+            .values()
+            .filter(|job| &job.record.package_id == package_id)
+            .map(|job| job.record.clone())
+            .collect();
+        records.sort_by_key(|record| std::cmp::Reverse(record.queued_at));
+        records.truncate(limit);
+        records
+    }
+
+    /// Cancel pending or running build `id`.
+    ///
+    /// A running build's container runtime client is killed, freeing its
+    /// slot. Finished builds cannot be cancelled.
+    pub fn cancel(&self, id: BuildId) -> BuildResult<()> {
+        let mut jobs = self.jobs.lock();
+        let job = jobs
+            .get_mut(&id)
+            .ok_or_else(|| BuildError::NotFound(id.to_string()))?;
+        job.transition(BuildStatus::Cancelled)
+            .map_err(|err| BuildError::InvalidState(err.to_string()))?;
+        let task = job.task.take();
+        drop(jobs);
+        if let Some(task) = task {
+            task.abort();
+        }
+        if let Some(log) = self.logs.get(id.0) {
+            log.finish(BuildStatus::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::container::tests::fake_runtime;
+    use crate::source::tests::TempDir;
+    use crate::spec::tests::spec;
+
+    /// A runtime whose builds wait until `release` exists, then produce
+    /// the APK in the source directory.
+    fn gated_runtime(dir: &Path) -> String {
+        let release = dir.join("release");
+        fake_runtime(
+            dir,
+            &format!(
+                "while [ ! -e '{}' ]; do sleep 0.05; done\n\
+                 for arg; do case \"$arg\" in *:/build:Z) src=\"${{arg%:/build:Z}}\";; esac; done\n\
+                 printf apk > \"$src/app-release.apk\"",
+                release.display()
+            ),
+        )
+    }
+
+    fn queue(root: &Path, max_concurrent_builds: usize) -> BuildQueue {
+        BuildQueue::new(
+            BuildConfig {
+                container_runtime: gated_runtime(root),
+                max_concurrent_builds,
+                ..BuildConfig::default()
+            },
+            Arc::new(BuildLogs::new()),
+        )
+    }
+
+    /// A build spec with its own source directory under `root`.
+    fn spec_in(root: &Path, name: &str) -> BuildSpec {
+        let dir = root.join(name);
+        std::fs::create_dir_all(&dir).expect("source dir");
+        spec(&dir)
+    }
+
+    /// Wait until build `id` reaches `status`.
+    async fn wait_for(queue: &BuildQueue, id: BuildId, status: BuildStatus) {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while queue.status(id) != Some(status) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("status reached in time");
+    }
+
+    #[tokio::test]
+    async fn test_excess_build_waits_for_free_slot() {
+        let root = TempDir::new();
+        let queue = queue(&root.0, 2);
+
+        let first = queue.enqueue(spec_in(&root.0, "a"));
+        let second = queue.enqueue(spec_in(&root.0, "b"));
+        let third = queue.enqueue(spec_in(&root.0, "c"));
+        wait_for(&queue, first, BuildStatus::Building).await;
+        wait_for(&queue, second, BuildStatus::Building).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(queue.status(third), Some(BuildStatus::Pending));
+
+        std::fs::write(root.0.join("release"), "").expect("release builds");
+        for id in [first, second, third] {
+            wait_for(&queue, id, BuildStatus::Success).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancel_pending_build() {
+        let root = TempDir::new();
+        let queue = queue(&root.0, 1);
+
+        let running = queue.enqueue(spec_in(&root.0, "a"));
+        let pending = queue.enqueue(spec_in(&root.0, "b"));
+        wait_for(&queue, running, BuildStatus::Building).await;
+
+        queue.cancel(pending).expect("cancel");
+        assert_eq!(queue.status(pending), Some(BuildStatus::Cancelled));
+
+        std::fs::write(root.0.join("release"), "").expect("release builds");
+        wait_for(&queue, running, BuildStatus::Success).await;
+        // The cancelled build never started
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(queue.status(pending), Some(BuildStatus::Cancelled));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_running_build_frees_slot() {
+        let root = TempDir::new();
+        let queue = queue(&root.0, 1);
+
+        let running = queue.enqueue(spec_in(&root.0, "a"));
+        let pending = queue.enqueue(spec_in(&root.0, "b"));
+        wait_for(&queue, running, BuildStatus::Building).await;
+
+        queue.cancel(running).expect("cancel");
+        assert_eq!(queue.status(running), Some(BuildStatus::Cancelled));
+        wait_for(&queue, pending, BuildStatus::Building).await;
+
+        let log = queue.logs.get(running.as_uuid()).expect("log");
+        assert_eq!(log.status(), Some(BuildStatus::Cancelled));
+    }
+
+    #[tokio::test]
+    async fn test_record_tracks_timing_and_artifact() {
+        let root = TempDir::new();
+        let storage = dk_common::storage::FilesystemStorage::new(root.0.join("storage"));
+        let queue = queue(&root.0, 1).with_artifacts(ArtifactStore::new(Arc::new(storage)));
+
+        let id = queue.enqueue(spec_in(&root.0, "a"));
+        wait_for(&queue, id, BuildStatus::Building).await;
+        let running = queue.get(id).expect("record");
+        assert_eq!(running.package_id, AppId::new("dk.digst.mitid"));
+        assert_eq!(running.finished_at, None);
+        assert_eq!(running.artifact, None);
+
+        std::fs::write(root.0.join("release"), "").expect("release builds");
+        wait_for(&queue, id, BuildStatus::Success).await;
+        let done = queue.get(id).expect("record");
+        let started_at = done.started_at.expect("started");
+        assert!(done.queued_at <= started_at);
+        assert!(started_at <= done.finished_at.expect("finished"));
+        let artifact = done.artifact.clone().expect("artifact");
+        assert_eq!(artifact.key, format!("builds/{id}.apk"));
+
+        assert_eq!(queue.builds_for(&AppId::new("dk.digst.mitid"), 10), [done]);
+        assert!(queue
+            .builds_for(&AppId::new("dk.digst.other"), 10)
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cannot_cancel_finished_or_unknown_build() {
+        let root = TempDir::new();
+        let queue = queue(&root.0, 1);
+        std::fs::write(root.0.join("release"), "").expect("release builds");
+
+        let id = queue.enqueue(spec_in(&root.0, "a"));
+        wait_for(&queue, id, BuildStatus::Success).await;
+        assert!(matches!(queue.cancel(id), Err(BuildError::InvalidState(_))));
+        assert!(matches!(
+            queue.cancel(BuildId::from_uuid(Uuid::new_v4())),
+            Err(BuildError::NotFound(_))
+        ));
+    }
+}
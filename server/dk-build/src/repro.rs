@@ -0,0 +1,316 @@
+//! Reproducibility verification between independently built APKs.
+//!
+//! Two builds are considered identical when every ZIP entry has the same
+//! name and uncompressed content. Entry timestamps, compression settings,
+//! the APK Signing Block, and v1 JAR signature files are ignored: they
+//! differ between builders without the application itself differing.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+use dk_common::hash::Sha256Hasher;
+use serde::{Deserialize, Serialize};
+use zip::ZipArchive;
+
+use crate::container::BuildArtifact;
+use crate::error::{BuildError, BuildResult};
+
+/// How an entry differs between two builds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EntryDiff {
+    /// Entry exists only in the first build.
+    OnlyInFirst {
+        /// Entry name.
+        name: String,
+    },
+    /// Entry exists only in the second build.
+    OnlyInSecond {
+        /// Entry name.
+        name: String,
+    },
+    /// Entry exists in both builds with different content.
+    ContentDiffers {
+        /// Entry name.
+        name: String,
+        /// SHA-256 of the entry in the first build.
+        first_sha256: String,
+        /// SHA-256 of the entry in the second build.
+        second_sha256: String,
+    },
+}
+
+impl EntryDiff {
+    /// Name of the differing entry.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        match self {
+            Self::OnlyInFirst { name }
+            | Self::OnlyInSecond { name }
+            | Self::ContentDiffers { name, .. } => name,
+        }
+    }
+}
+
+/// Entry-by-entry comparison of two builds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReproReport {
+    /// Number of distinct entry names compared.
+    pub entries_compared: usize,
+    /// Entries that differ; empty when the builds match.
+    pub differences: Vec<EntryDiff>,
+}
+
+impl ReproReport {
+    /// Whether the builds are identical.
+    #[must_use]
+    pub fn is_reproducible(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+impl fmt::Display for ReproReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} entries differ",
+            self.differences.len(),
+            self.entries_compared
+        )?;
+        for (index, diff) in self.differences.iter().enumerate() {
+            f.write_str(if index == 0 { ": " } else { ", " })?;
+            f.write_str(diff.name())?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare the APKs of two artifacts.
+///
+/// Returns the report when they match and
+/// [`BuildError::ReproducibilityFailed`] carrying it when they do not.
+pub fn verify(a: &BuildArtifact, b: &BuildArtifact) -> BuildResult<ReproReport> {
+    let first = entry_digests(&a.apk_path)?;
+    let second = entry_digests(&b.apk_path)?;
+    let report = compare(&first, &second);
+
+    if report.is_reproducible() {
+        Ok(report)
+    } else {
+        Err(BuildError::ReproducibilityFailed(report))
+    }
+}
+
+/// Diff two entry name → digest maps.
+fn compare(first: &BTreeMap<String, String>, second: &BTreeMap<String, String>) -> ReproReport {
+    let names: BTreeSet<&String> = first.keys().chain(second.keys()).collect();
+    let differences = names
+        .iter()
+        .filter_map(|name| match (first.get(*name), second.get(*name)) {
+            (Some(a), Some(b)) if a == b => None,
+            (Some(a), Some(b)) => Some(EntryDiff::ContentDiffers {
+                name: (*name).clone(),
+                first_sha256: a.clone(),
+                second_sha256: b.clone(),
+            }),
+            (Some(_), None) => Some(EntryDiff::OnlyInFirst {
+                name: (*name).clone(),
+            }),
+            (None, _) => Some(EntryDiff::OnlyInSecond {
+                name: (*name).clone(),
+            }),
+        })
+        .collect();
+
+    ReproReport {
+        entries_compared: names.len(),
+        differences,
+    }
+}
+
+/// Whether `name` is part of a v1 JAR signature rather than the app.
+fn is_signature_entry(name: &str) -> bool {
+    let Some(file) = name.strip_prefix("META-INF/") else {
+        return false;
+    };
+    if file.contains('/') {
+        return false;
+    }
+    let upper = file.to_ascii_uppercase();
+    upper == "MANIFEST.MF"
+        || [".SF", ".RSA", ".DSA", ".EC"]
+            .iter()
+            .any(|extension| upper.ends_with(extension))
+}
+
+/// SHA-256 of the uncompressed content of every non-signature entry.
+fn entry_digests(path: &Path) -> BuildResult<BTreeMap<String, String>> {
+    let invalid =
+        |err: &dyn fmt::Display| BuildError::BuildFailed(format!("{}: {err}", path.display()));
+    let file = File::open(path).map_err(|err| invalid(&err))?;
+    let mut archive = ZipArchive::new(BufReader::new(file)).map_err(|err| invalid(&err))?;
+
+    let mut digests = BTreeMap::new();
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index).map_err(|err| invalid(&err))?;
+        if entry.is_dir() || is_signature_entry(entry.name()) {
+            continue;
+        }
+
+        let mut hasher = Sha256Hasher::new();
+        io::copy(&mut entry, &mut hasher).map_err(|err| invalid(&err))?;
+        digests.insert(entry.name().to_string(), hasher.finish().to_string());
+    }
+
+    Ok(digests)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+    use std::path::PathBuf;
+
+    use zip::write::FileOptions;
+    use zip::{CompressionMethod, DateTime, ZipWriter};
+
+    use super::*;
+    use crate::source::tests::TempDir;
+
+    /// Write a zip of `entries` with the given timestamp and compression.
+    fn write_zip(
+        path: &Path,
+        entries: &[(&str, &[u8])],
+        year: u16,
+        method: CompressionMethod,
+    ) -> BuildArtifact {
+        let time = DateTime::from_date_and_time(year, 1, 1, 12, 0, 0).expect("valid date");
+        let options = FileOptions::default()
+            .compression_method(method)
+            .last_modified_time(time);
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            writer.start_file(*name, options).expect("start entry");
+            writer.write_all(content).expect("write entry");
+        }
+        let bytes = writer.finish().expect("finish").into_inner();
+        std::fs::write(path, bytes).expect("write zip");
+
+        BuildArtifact {
+            apk_path: PathBuf::from(path),
+            sha256: String::new(),
+            stdout: String::new(),
+            stderr: String::new(),
+        }
+    }
+
+    const ENTRIES: &[(&str, &[u8])] = &[
+        ("AndroidManifest.xml", b"manifest"),
+        ("classes.dex", b"dex\n035\0classes"),
+        ("res/raw/data.bin", b"data"),
+    ];
+
+    #[test]
+    fn test_identical_zips() {
+        let dir = TempDir::new();
+        let a = write_zip(
+            &dir.0.join("a.apk"),
+            ENTRIES,
+            2024,
+            CompressionMethod::Stored,
+        );
+        let b = write_zip(
+            &dir.0.join("b.apk"),
+            ENTRIES,
+            2024,
+            CompressionMethod::Stored,
+        );
+
+        let report = verify(&a, &b).expect("reproducible");
+        assert_eq!(report.entries_compared, 3);
+        assert!(report.is_reproducible());
+    }
+
+    #[test]
+    fn test_timestamps_and_compression_are_ignored() {
+        let dir = TempDir::new();
+        let a = write_zip(
+            &dir.0.join("a.apk"),
+            ENTRIES,
+            2020,
+            CompressionMethod::Stored,
+        );
+        let b = write_zip(
+            &dir.0.join("b.apk"),
+            ENTRIES,
+            2024,
+            CompressionMethod::Deflated,
+        );
+
+        assert!(verify(&a, &b).expect("reproducible").is_reproducible());
+    }
+
+    #[test]
+    fn test_signature_files_are_ignored() {
+        let dir = TempDir::new();
+        let a = write_zip(
+            &dir.0.join("a.apk"),
+            ENTRIES,
+            2024,
+            CompressionMethod::Stored,
+        );
+        let mut signed = ENTRIES.to_vec();
+        signed.push(("META-INF/MANIFEST.MF", b"Manifest-Version: 1.0"));
+        signed.push(("META-INF/CERT.RSA", b"signature"));
+        let b = write_zip(
+            &dir.0.join("b.apk"),
+            &signed,
+            2024,
+            CompressionMethod::Stored,
+        );
+
+        assert!(verify(&a, &b).expect("reproducible").is_reproducible());
+    }
+
+    #[test]
+    fn test_differing_entry_is_named() {
+        let dir = TempDir::new();
+        let a = write_zip(
+            &dir.0.join("a.apk"),
+            ENTRIES,
+            2024,
+            CompressionMethod::Stored,
+        );
+        let mut changed = ENTRIES.to_vec();
+        changed[1] = ("classes.dex", b"dex\n035\0BuildConfig.TIMESTAMP");
+        changed.push(("assets/build-info.txt", b"built on host-b"));
+        let b = write_zip(
+            &dir.0.join("b.apk"),
+            &changed,
+            2024,
+            CompressionMethod::Stored,
+        );
+
+        let report = match verify(&a, &b) {
+            Err(BuildError::ReproducibilityFailed(report)) => Some(report),
+            _ => None,
+        }
+        .expect("reproducibility failure");
+        let names: Vec<&str> = report.differences.iter().map(EntryDiff::name).collect();
+        assert_eq!(names, ["assets/build-info.txt", "classes.dex"]);
+        assert!(matches!(
+            report.differences[0],
+            EntryDiff::OnlyInSecond { .. }
+        ));
+        assert!(matches!(
+            report.differences[1],
+            EntryDiff::ContentDiffers { .. }
+        ));
+        assert_eq!(
+            report.to_string(),
+            "2 of 4 entries differ: assets/build-info.txt, classes.dex"
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! Source checkout via the `git` command line.
+
+use std::path::{Path, PathBuf};
+
+use tokio::process::Command;
+
+use crate::error::{BuildError, BuildResult};
+
+/// Clone `repo_url` into `dest` and check out `git_ref`.
+///
+/// `git_ref` may be a tag, a branch, or a full commit hash. With a non-zero
+/// `depth` only that many commits of history are fetched.
+pub async fn fetch(repo_url: &str, git_ref: &str, dest: &Path, depth: u32) -> BuildResult<PathBuf> {
+    validate_argument("repository URL", repo_url)?;
+    validate_argument("git ref", git_ref)?;
+
+    tokio::fs::create_dir_all(dest)
+        .await
+        .map_err(|err| BuildError::BuildFailed(format!("{}: {err}", dest.display())))?;
+
+    git(dest, &["init", "--quiet"]).await?;
+    git(dest, &["remote", "add", "origin", repo_url]).await?;
+
+    let depth_arg = format!("--depth={depth}");
+    let mut fetch_args = vec!["fetch", "--quiet", "--no-tags"];
+    if depth > 0 {
+        fetch_args.push(&depth_arg);
+    }
+    fetch_args.extend(["origin", git_ref]);
+
+    if let Err(err) = git(dest, &fetch_args).await {
+        // Tell an unreachable repository apart from a ref it doesn't have
+        return Err(match git(dest, &["ls-remote", "--quiet", "origin"]).await {
+            Ok(_) => BuildError::InvalidConfig(format!("unknown git ref {git_ref}: {err}")),
+            Err(_) => BuildError::SourceNotFound(format!("{repo_url}: {err}")),
+        });
+    }
+
+    git(dest, &["checkout", "--quiet", "--detach", "FETCH_HEAD"]).await?;
+    Ok(dest.to_path_buf())
+}
+
+/// Returns the commit hash checked out in `dir`.
+pub async fn head_commit(dir: &Path) -> BuildResult<String> {
+    let output = git(dir, &["rev-parse", "HEAD"]).await?;
+    Ok(output.trim().to_string())
+}
+
+/// Reject values git would parse as options.
+fn validate_argument(what: &str, value: &str) -> BuildResult<()> {
+    if value.is_empty() || value.starts_with('-') || value.chars().any(char::is_whitespace) {
+        return Err(BuildError::InvalidConfig(format!(
+            "invalid {what}: {value:?}"
+        )));
+    }
+    Ok(())
+}
+
+/// Run git in `dir`, returning stdout. Failures carry git's stderr.
+async fn git(dir: &Path, args: &[&str]) -> BuildResult<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        // Never block on credential prompts
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|err| BuildError::BuildFailed(format!("git: {err}")))?;
+
+    if !output.status.success() {
+        return Err(BuildError::BuildFailed(format!(
+            "git {}: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A temporary directory, removed on drop.
+    pub struct TempDir(pub PathBuf);
+
+    impl TempDir {
+        pub fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("dk-build-test-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).expect("create temp dir");
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Run git for fixture setup.
+    async fn run(dir: &Path, args: &[&str]) -> String {
+        let mut full = vec![
+            "-c",
+            "user.name=DK-AppStore",
+            "-c",
+            "user.email=test@dk-appstore.invalid",
+        ];
+        full.extend(args);
+        git(dir, &full).await.expect("git fixture")
+    }
+
+    /// A bare repository with two commits; the first is tagged `v1.0`.
+    /// Returns its `file://` URL and both commit hashes.
+    pub async fn bare_repo(root: &Path) -> (String, String, String) {
+        let work = root.join("work");
+        let bare = root.join("origin.git");
+        std::fs::create_dir_all(&work).expect("work dir");
+        std::fs::create_dir_all(&bare).expect("bare dir");
+
+        run(&bare, &["init", "--quiet", "--bare"]).await;
+        run(&work, &["init", "--quiet"]).await;
+        std::fs::write(work.join("README"), "v1").expect("write");
+        run(&work, &["add", "README"]).await;
+        run(&work, &["commit", "--quiet", "-m", "v1"]).await;
+        run(&work, &["tag", "v1.0"]).await;
+        let first = head_commit(&work).await.expect("head");
+
+        std::fs::write(work.join("README"), "v2").expect("write");
+        run(&work, &["commit", "--quiet", "-am", "v2"]).await;
+        let second = head_commit(&work).await.expect("head");
+
+        let url = format!("file://{}", bare.display());
+        run(
+            &work,
+            &["push", "--quiet", &url, "HEAD:refs/heads/main", "v1.0"],
+        )
+        .await;
+
+        (url, first, second)
+    }
+
+    #[tokio::test]
+    async fn test_fetch_tag() {
+        let root = TempDir::new();
+        let (url, first, _) = bare_repo(&root.0).await;
+
+        let dest = fetch(&url, "v1.0", &root.0.join("checkout"), 1)
+            .await
+            .expect("fetch");
+        assert_eq!(head_commit(&dest).await.expect("head"), first);
+        assert_eq!(
+            std::fs::read_to_string(dest.join("README")).expect("read"),
+            "v1"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_commit_full_history() {
+        let root = TempDir::new();
+        let (url, first, second) = bare_repo(&root.0).await;
+
+        let dest = fetch(&url, &second, &root.0.join("checkout"), 0)
+            .await
+            .expect("fetch");
+        assert_eq!(head_commit(&dest).await.expect("head"), second);
+        // Full history includes the parent commit
+        let parent = git(&dest, &["rev-parse", "HEAD~1"]).await.expect("parent");
+        assert_eq!(parent.trim(), first);
+    }
+
+    #[tokio::test]
+    async fn test_shallow_fetch_has_single_commit() {
+        let root = TempDir::new();
+        let (url, _, _) = bare_repo(&root.0).await;
+
+        let dest = fetch(&url, "main", &root.0.join("checkout"), 1)
+            .await
+            .expect("fetch");
+        let count = git(&dest, &["rev-list", "--count", "HEAD"])
+            .await
+            .expect("count");
+        assert_eq!(count.trim(), "1");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_ref_is_invalid_config() {
+        let root = TempDir::new();
+        let (url, _, _) = bare_repo(&root.0).await;
+
+        assert!(matches!(
+            fetch(&url, "v9.9", &root.0.join("checkout"), 1).await,
+            Err(BuildError::InvalidConfig(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_missing_repo_is_source_not_found() {
+        let root = TempDir::new();
+        let url = format!("file://{}", root.0.join("missing.git").display());
+
+        assert!(matches!(
+            fetch(&url, "v1.0", &root.0.join("checkout"), 1).await,
+            Err(BuildError::SourceNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_option_like_ref() {
+        let root = TempDir::new();
+        assert!(matches!(
+            fetch("file:///tmp/x.git", "--upload-pack=sh", &root.0, 1).await,
+            Err(BuildError::InvalidConfig(_))
+        ));
+    }
+}
@@ -0,0 +1,184 @@
+//! Build specifications and their validation.
+
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use dk_common::types::AppId;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{BuildError, BuildResult};
+
+/// URL schemes source repositories may be cloned over.
+const SOURCE_SCHEMES: [&str; 5] = ["https", "http", "ssh", "git", "file"];
+
+/// Description of a single application build.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildSpec {
+    /// Package the build produces.
+    pub package_id: AppId,
+    /// Git repository the source is cloned from.
+    pub source_url: String,
+    /// Tag, branch, or full commit hash checked out for the build.
+    pub git_ref: String,
+    /// Container image, pinned by digest (`registry/image@sha256:...`).
+    pub image: String,
+    /// Checked-out source tree, mounted read-write into the container.
+    pub source_dir: PathBuf,
+    /// Shell commands run in order inside the container, stopping at the
+    /// first failure, e.g. `./gradlew --no-daemon assembleRelease`.
+    pub build_commands: Vec<String>,
+    /// Path of the produced APK, relative to `source_dir`.
+    pub output_apk_path: PathBuf,
+    /// Seconds the build may run; the configured `build_timeout_secs`
+    /// applies when unset.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Environment variables set inside the container.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+impl BuildSpec {
+    /// Check that the spec describes a build that can run: a valid package
+    /// ID, a cloneable source URL, a git ref, at least one build command,
+    /// and an output path inside the source tree.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BuildError::InvalidConfig`] naming the first invalid field.
+    pub fn validate(&self) -> BuildResult<()> {
+        let invalid = |problem: String| {
+            BuildError::InvalidConfig(format!("build spec for {}: {problem}", self.package_id))
+        };
+
+        AppId::parse(self.package_id.as_str()).map_err(|err| invalid(err.to_string()))?;
+        let url = url::Url::parse(&self.source_url)
+            .map_err(|err| invalid(format!("invalid source URL {:?}: {err}", self.source_url)))?;
+        if !SOURCE_SCHEMES.contains(&url.scheme()) {
+            return Err(invalid(format!(
+                "source URL must use one of {}, not '{}'",
+                SOURCE_SCHEMES.join(", "),
+                url.scheme()
+            )));
+        }
+        if self.git_ref.trim().is_empty() {
+            return Err(invalid("missing git ref".to_string()));
+        }
+        if self.image.trim().is_empty() {
+            return Err(invalid("missing container image".to_string()));
+        }
+        if self.build_commands.is_empty() {
+            return Err(invalid("no build commands".to_string()));
+        }
+        if self
+            .build_commands
+            .iter()
+            .any(|command| command.trim().is_empty())
+        {
+            return Err(invalid("blank build command".to_string()));
+        }
+        if !is_inside(&self.output_apk_path) {
+            return Err(invalid(format!(
+                "output APK path {} is not inside the source tree",
+                self.output_apk_path.display()
+            )));
+        }
+        if self.timeout_secs == Some(0) {
+            return Err(invalid("timeout must be positive".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Command run inside the container: the build commands chained in a
+    /// shell.
+    pub(crate) fn command(&self) -> Vec<String> {
+        vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            self.build_commands.join(" && "),
+        ]
+    }
+}
+
+/// Whether `path` is a non-empty relative path that stays inside its base.
+fn is_inside(path: &Path) -> bool {
+    path.file_name().is_some()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub fn spec(source_dir: &Path) -> BuildSpec {
+        BuildSpec {
+            package_id: AppId::new("dk.digst.mitid"),
+            source_url: "https://git.example/mitid.git".to_string(),
+            git_ref: "v1.0".to_string(),
+            image: "docker.io/example/android-build@sha256:0000".to_string(),
+            source_dir: source_dir.to_path_buf(),
+            build_commands: vec!["./gradlew --no-daemon assembleRelease".to_string()],
+            output_apk_path: PathBuf::from("app-release.apk"),
+            timeout_secs: None,
+            env: BTreeMap::from([("CI".to_string(), "true".to_string())]),
+        }
+    }
+
+    fn assert_invalid(spec: &BuildSpec, problem: &str) {
+        let result = spec.validate();
+        assert!(
+            matches!(&result, Err(BuildError::InvalidConfig(message)) if message.contains(problem)),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_valid_spec() {
+        assert!(spec(Path::new("/src")).validate().is_ok());
+    }
+
+    #[test]
+    fn test_missing_ref_is_rejected() {
+        let mut spec = spec(Path::new("/src"));
+        spec.git_ref = " ".to_string();
+        assert_invalid(&spec, "missing git ref");
+    }
+
+    #[test]
+    fn test_empty_command_list_is_rejected() {
+        let mut spec = spec(Path::new("/src"));
+        spec.build_commands.clear();
+        assert_invalid(&spec, "no build commands");
+
+        spec.build_commands = vec![String::new()];
+        assert_invalid(&spec, "blank build command");
+    }
+
+    #[test]
+    fn test_invalid_source_url_is_rejected() {
+        let mut spec = spec(Path::new("/src"));
+        spec.source_url = "git.example/mitid.git".to_string();
+        assert_invalid(&spec, "invalid source URL");
+
+        spec.source_url = "ftp://git.example/mitid.git".to_string();
+        assert_invalid(&spec, "source URL must use");
+    }
+
+    #[test]
+    fn test_output_path_must_stay_in_source_tree() {
+        for path in ["", "/tmp/app.apk", "../app.apk", "app/../../app.apk"] {
+            let mut spec = spec(Path::new("/src"));
+            spec.output_apk_path = PathBuf::from(path);
+            assert_invalid(&spec, "not inside the source tree");
+        }
+    }
+
+    #[test]
+    fn test_zero_timeout_is_rejected() {
+        let mut spec = spec(Path::new("/src"));
+        spec.timeout_secs = Some(0);
+        assert_invalid(&spec, "timeout");
+    }
+}
@@ -1,9 +1,21 @@
 //! Configuration management for DK-AppStore.
 
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
 use serde::Deserialize;
 
+use crate::error::{Error, Result};
+use crate::types::AppId;
+
+/// Environment variable naming the configuration file to load.
+pub const CONFIG_FILE_ENV: &str = "DK_APPSTORE_CONFIG";
+
+/// Configuration file loaded, if present, when [`CONFIG_FILE_ENV`] is unset.
+pub const DEFAULT_CONFIG_FILE: &str = "dk-appstore.toml";
+
 /// Application configuration.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 pub struct Config {
     /// Database configuration.
     pub database: DatabaseConfig,
@@ -11,10 +23,39 @@ pub struct Config {
     pub redis: RedisConfig,
     /// API server configuration.
     pub api: ApiConfig,
+    /// Artifact storage configuration.
+    #[serde(default)]
+    pub storage: StorageConfig,
+    /// S3-compatible object storage; APKs are stored on local disk unless set.
+    pub object_store: Option<ObjectStoreConfig>,
+    /// Repository signing configuration.
+    #[serde(default)]
+    pub signing: SigningConfig,
+    /// Build service configuration.
+    #[serde(default)]
+    pub build: BuildConfig,
+    /// APK scanner configuration.
+    #[serde(default)]
+    pub scanner: ScannerConfig,
+    /// Cross-origin resource sharing configuration.
+    #[serde(default)]
+    pub cors: CorsConfig,
+    /// API authentication configuration.
+    #[serde(default)]
+    pub auth: AuthConfig,
+    /// Per-client rate limiting configuration.
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    /// Repository metadata advertised in the index.
+    #[serde(default)]
+    pub repo: RepoConfig,
+    /// Notifications of publish and scan events.
+    #[serde(default)]
+    pub webhooks: WebhookConfig,
 }
 
 /// Database configuration.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 pub struct DatabaseConfig {
     /// PostgreSQL connection URL.
     pub url: String,
@@ -24,14 +65,14 @@ pub struct DatabaseConfig {
 }
 
 /// Redis configuration.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 pub struct RedisConfig {
     /// Redis connection URL.
     pub url: String,
 }
 
 /// API server configuration.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 pub struct ApiConfig {
     /// Host to bind to.
     #[serde(default = "default_host")]
@@ -39,9 +80,381 @@ pub struct ApiConfig {
     /// Port to listen on.
     #[serde(default = "default_port")]
     pub port: u16,
+    /// Seconds in-flight requests may take to finish after a shutdown signal.
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+    /// Log filter directives, e.g. `dk_api=info,tower_http=warn`; `RUST_LOG`
+    /// or the built-in filter applies when unset.
+    #[serde(default)]
+    pub log_level: Option<String>,
+    /// Format of log lines; the `LOG_FORMAT` environment variable overrides
+    /// it.
+    #[serde(default)]
+    pub log_format: LogFormat,
+    /// Largest APK upload request accepted, in bytes; larger bodies are
+    /// rejected with `413 Payload Too Large` before they are read.
+    #[serde(default = "default_max_upload_bytes")]
+    pub max_upload_bytes: usize,
+}
+
+/// Format of log lines written by the servers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Text,
+    /// One JSON object per line, for log aggregation.
+    Json,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(Error::Config(format!(
+                "unknown log format '{other}', expected 'json' or 'text'"
+            ))),
+        }
+    }
+}
+
+/// Artifact storage configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct StorageConfig {
+    /// Directory containing published APK files.
+    #[serde(default = "default_apk_dir")]
+    pub apk_dir: PathBuf,
+    /// Hash every APK before serving it and compare the digest with the
+    /// one recorded at upload. Off by default, as each download then reads
+    /// the APK twice.
+    #[serde(default)]
+    pub verify_downloads: bool,
+}
+
+impl StorageConfig {
+    /// Returns the path of the APK for a given app version.
+    #[must_use]
+    pub fn apk_path(&self, package_id: &AppId, version_code: i64) -> PathBuf {
+        self.apk_dir.join(package_id.apk_file_name(version_code))
+    }
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            apk_dir: default_apk_dir(),
+            verify_downloads: false,
+        }
+    }
+}
+
+/// S3-compatible object storage configuration.
+///
+/// Credentials are read from the environment variables named here rather
+/// than from the configuration itself.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ObjectStoreConfig {
+    /// Service endpoint, e.g. `https://s3.eu-north-1.amazonaws.com`.
+    pub endpoint: url::Url,
+    /// Bucket holding the APKs.
+    pub bucket: String,
+    /// Region the bucket lives in.
+    #[serde(default = "default_region")]
+    pub region: String,
+    /// Address the bucket as `endpoint/bucket` rather than `bucket.endpoint`,
+    /// as most self-hosted services require.
+    #[serde(default = "default_path_style")]
+    pub path_style: bool,
+    /// Environment variable holding the access key ID.
+    #[serde(default = "default_access_key_env")]
+    pub access_key_env: String,
+    /// Environment variable holding the secret access key.
+    #[serde(default = "default_secret_key_env")]
+    pub secret_key_env: String,
+}
+
+/// Repository signing configuration.
+///
+/// Signing is disabled unless both paths are set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct SigningConfig {
+    /// Path to the PKCS#8 (DER) repository signing key.
+    pub key_path: Option<PathBuf>,
+    /// Path to the DER-encoded repository certificate.
+    pub certificate_path: Option<PathBuf>,
+}
+
+/// Build service configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct BuildConfig {
+    /// Commits of history fetched when cloning sources; 0 fetches everything.
+    #[serde(default = "default_clone_depth")]
+    pub clone_depth: u32,
+    /// Container runtime executable (`podman` or `docker`).
+    #[serde(default = "default_container_runtime")]
+    pub container_runtime: String,
+    /// Maximum duration of a single build, in seconds.
+    #[serde(default = "default_build_timeout_secs")]
+    pub build_timeout_secs: u64,
+    /// Builds run at the same time; further builds wait in the queue.
+    #[serde(default = "default_max_concurrent_builds")]
+    pub max_concurrent_builds: usize,
+}
+
+impl Default for BuildConfig {
+    fn default() -> Self {
+        Self {
+            clone_depth: default_clone_depth(),
+            container_runtime: default_container_runtime(),
+            build_timeout_secs: default_build_timeout_secs(),
+            max_concurrent_builds: default_max_concurrent_builds(),
+        }
+    }
 }
 
-fn default_max_connections() -> u32 {
+/// APK scanner configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ScannerConfig {
+    /// APK size in bytes above which a scan warns.
+    #[serde(default = "default_max_apk_size")]
+    pub max_apk_size: u64,
+    /// DEX method references, summed over all `classes*.dex`, above which a
+    /// scan warns. Defaults to the 65,536 reference limit of a single DEX
+    /// file, so apps needing multidex are flagged.
+    #[serde(default = "default_max_dex_methods")]
+    pub max_dex_methods: u32,
+    /// Seconds each scan step that may hang, such as running the checks
+    /// against an APK, is allowed before the scan fails.
+    #[serde(default = "default_tool_timeout_secs")]
+    pub tool_timeout_secs: u64,
+    /// Entries an APK may contain; archives with more are rejected.
+    #[serde(default = "default_max_zip_entries")]
+    pub max_zip_entries: usize,
+    /// Uncompressed size in bytes any single APK entry may have.
+    #[serde(default = "default_max_entry_size")]
+    pub max_entry_size: u64,
+    /// Uncompressed size in bytes of all APK entries together. With
+    /// `max_entry_size`, keeps highly compressed archives from exhausting
+    /// memory during inspection.
+    #[serde(default = "default_max_uncompressed_size")]
+    pub max_uncompressed_size: u64,
+    /// JSON file of known-vulnerable bundled libraries; uploads bundling
+    /// one with a critical vulnerability are quarantined.
+    #[serde(default)]
+    pub vulnerability_db: Option<PathBuf>,
+}
+
+impl Default for ScannerConfig {
+    fn default() -> Self {
+        Self {
+            max_apk_size: default_max_apk_size(),
+            max_dex_methods: default_max_dex_methods(),
+            tool_timeout_secs: default_tool_timeout_secs(),
+            max_zip_entries: default_max_zip_entries(),
+            max_entry_size: default_max_entry_size(),
+            max_uncompressed_size: default_max_uncompressed_size(),
+            vulnerability_db: None,
+        }
+    }
+}
+
+/// Cross-origin resource sharing configuration.
+///
+/// No cross-origin requests are allowed unless `allowed_origins` is set.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CorsConfig {
+    /// Origins allowed to call the API, e.g. `https://appstore.digst.dk`,
+    /// or `*` for any origin.
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    /// HTTP methods allowed in cross-origin requests.
+    #[serde(default = "default_cors_methods")]
+    pub allowed_methods: Vec<String>,
+    /// Whether browsers may send cookies and credentials.
+    #[serde(default)]
+    pub allow_credentials: bool,
+}
+
+impl CorsConfig {
+    /// Whether any origin is allowed.
+    #[must_use]
+    pub fn allows_any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|origin| origin == "*")
+    }
+
+    /// Check that the settings form a policy browsers will honour.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] if the wildcard origin is combined with
+    /// other origins or with `allow_credentials`.
+    pub fn validate(&self) -> Result<()> {
+        if !self.allows_any_origin() {
+            return Ok(());
+        }
+        if self.allowed_origins.len() > 1 {
+            return Err(Error::Config(
+                "cors.allowed_origins: \"*\" cannot be combined with other origins".to_string(),
+            ));
+        }
+        if self.allow_credentials {
+            return Err(Error::Config(
+                "cors.allow_credentials cannot be used with allowed origin \"*\"".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            allowed_methods: default_cors_methods(),
+            allow_credentials: false,
+        }
+    }
+}
+
+/// API authentication configuration.
+///
+/// Keys are stored only as hashes so the configuration does not leak them.
+/// Protected endpoints reject every request while no key is configured.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct AuthConfig {
+    /// Lowercase hex SHA-256 digests of the accepted API keys.
+    #[serde(default)]
+    pub api_key_hashes: Vec<String>,
+}
+
+/// Per-client rate limiting configuration.
+///
+/// Each client IP gets a token bucket holding up to `burst` requests and
+/// refilled at `requests_per_minute`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct RateLimitConfig {
+    /// Sustained request rate per client; 0 disables rate limiting.
+    #[serde(default = "default_requests_per_minute")]
+    pub requests_per_minute: u32,
+    /// Requests a client may make in a burst.
+    #[serde(default = "default_burst")]
+    pub burst: u32,
+    /// Reverse proxies whose `X-Forwarded-For` header is trusted.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: default_requests_per_minute(),
+            burst: default_burst(),
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+/// Webhook notification configuration.
+///
+/// Payloads are signed with HMAC-SHA256 using the secret read from the
+/// environment variable named here rather than from the configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct WebhookConfig {
+    /// HTTP(S) URLs every event is posted to; none disables webhooks.
+    #[serde(default)]
+    pub endpoints: Vec<String>,
+    /// Environment variable holding the signing secret.
+    #[serde(default = "default_webhook_secret_env")]
+    pub secret_env: String,
+    /// Delivery attempts per endpoint before an event is given up.
+    #[serde(default = "default_webhook_max_attempts")]
+    pub max_attempts: u32,
+    /// Delay before the first retry in milliseconds; it doubles with every
+    /// further retry.
+    #[serde(default = "default_webhook_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+    /// Time allowed for each delivery attempt, in seconds.
+    #[serde(default = "default_webhook_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            endpoints: Vec::new(),
+            secret_env: default_webhook_secret_env(),
+            max_attempts: default_webhook_max_attempts(),
+            retry_delay_ms: default_webhook_retry_delay_ms(),
+            timeout_secs: default_webhook_timeout_secs(),
+        }
+    }
+}
+
+/// Repository metadata advertised to F-Droid clients in the index.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct RepoConfig {
+    /// Repository name shown by clients.
+    #[serde(default = "default_repo_name")]
+    pub name: String,
+    /// Repository description shown by clients.
+    #[serde(default = "default_repo_description")]
+    pub description: String,
+    /// Canonical URL of the repository, e.g. `https://appstore.digst.dk/repo`.
+    #[serde(default)]
+    pub address: Option<String>,
+    /// Absolute URLs of mirrors serving the same repository.
+    #[serde(default)]
+    pub mirrors: Vec<String>,
+    /// Repository icon shown by clients, as a path relative to `address`,
+    /// e.g. `icons/dk-appstore.png`.
+    #[serde(default)]
+    pub icon: Option<String>,
+}
+
+impl RepoConfig {
+    /// Check that the address and every mirror are absolute HTTP(S) URLs,
+    /// and that the icon is a path inside the repository.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] naming the first invalid setting.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(address) = &self.address {
+            check_url("repo.address", address, &["https", "http"])?;
+        }
+        for mirror in &self.mirrors {
+            check_url("repo.mirrors", mirror, &["https", "http"])?;
+        }
+        if let Some(icon) = &self.icon {
+            let escapes = icon.starts_with('/') || icon.split('/').any(|part| part == "..");
+            if icon.trim().is_empty() || escapes || url::Url::parse(icon).is_ok() {
+                return Err(invalid(
+                    "repo.icon",
+                    "must be a path relative to the repository address",
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for RepoConfig {
+    fn default() -> Self {
+        Self {
+            name: default_repo_name(),
+            description: default_repo_description(),
+            address: None,
+            mirrors: Vec::new(),
+            icon: None,
+        }
+    }
+}
+
+const fn default_max_connections() -> u32 {
     10
 }
 
@@ -49,35 +462,529 @@ fn default_host() -> String {
     "127.0.0.1".to_string()
 }
 
-fn default_port() -> u16 {
+const fn default_port() -> u16 {
     8080
 }
 
+const fn default_shutdown_timeout_secs() -> u64 {
+    30
+}
+
+const fn default_max_upload_bytes() -> usize {
+    100 * 1024 * 1024
+}
+
+fn default_apk_dir() -> PathBuf {
+    PathBuf::from("data/repo")
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+const fn default_path_style() -> bool {
+    true
+}
+
+fn default_access_key_env() -> String {
+    "AWS_ACCESS_KEY_ID".to_string()
+}
+
+fn default_secret_key_env() -> String {
+    "AWS_SECRET_ACCESS_KEY".to_string()
+}
+
+const fn default_clone_depth() -> u32 {
+    1
+}
+
+fn default_container_runtime() -> String {
+    "podman".to_string()
+}
+
+const fn default_build_timeout_secs() -> u64 {
+    3600
+}
+
+const fn default_max_concurrent_builds() -> usize {
+    2
+}
+
+const fn default_max_apk_size() -> u64 {
+    100 * 1024 * 1024
+}
+
+const fn default_max_dex_methods() -> u32 {
+    65_536
+}
+
+const fn default_tool_timeout_secs() -> u64 {
+    300
+}
+
+const fn default_max_zip_entries() -> usize {
+    65_536
+}
+
+const fn default_max_entry_size() -> u64 {
+    256 * 1024 * 1024
+}
+
+const fn default_max_uncompressed_size() -> u64 {
+    1024 * 1024 * 1024
+}
+
+const fn default_requests_per_minute() -> u32 {
+    120
+}
+
+const fn default_burst() -> u32 {
+    60
+}
+
+fn default_repo_name() -> String {
+    "DK-AppStore".to_string()
+}
+
+fn default_repo_description() -> String {
+    "Danish sovereign app distribution platform".to_string()
+}
+
+fn default_webhook_secret_env() -> String {
+    "DK_APPSTORE_WEBHOOK_SECRET".to_string()
+}
+
+const fn default_webhook_max_attempts() -> u32 {
+    5
+}
+
+const fn default_webhook_retry_delay_ms() -> u64 {
+    500
+}
+
+const fn default_webhook_timeout_secs() -> u64 {
+    10
+}
+
+fn default_cors_methods() -> Vec<String> {
+    vec!["GET".to_string(), "HEAD".to_string()]
+}
+
 impl Config {
     /// Load configuration from environment variables and optional config file.
     ///
+    /// The file named by [`CONFIG_FILE_ENV`] is read first, its format chosen
+    /// by extension (`.toml`, `.yaml`); without it, [`DEFAULT_CONFIG_FILE`]
+    /// is read if present. Environment variables override file values.
+    ///
     /// # Errors
     ///
-    /// Returns an error if required configuration is missing or invalid.
-    pub fn load() -> Result<Self, config::ConfigError> {
+    /// Returns an error if required configuration is missing or invalid, or
+    /// if the file named by [`CONFIG_FILE_ENV`] cannot be read.
+    pub fn load() -> Result<Self> {
         // Load .env file if present (ignore errors)
         let _ = dotenvy::dotenv();
 
-        config::Config::builder()
-            .add_source(config::Environment::with_prefix("DK_APPSTORE").separator("__"))
+        let file = match std::env::var_os(CONFIG_FILE_ENV) {
+            Some(path) => config::File::from(PathBuf::from(path)),
+            None => config::File::from(Path::new(DEFAULT_CONFIG_FILE)).required(false),
+        };
+        Self::from_sources(file, environment())
+    }
+
+    /// Load configuration from the file at `path`, with environment
+    /// variables overriding its values.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, or if required
+    /// configuration is missing or invalid.
+    pub fn load_from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_sources(config::File::from(path.as_ref()), environment())
+    }
+
+    fn from_sources(
+        file: config::File<config::FileSourceFile, config::FileFormat>,
+        environment: config::Environment,
+    ) -> Result<Self> {
+        let config: Self = config::Config::builder()
+            .add_source(file)
+            .add_source(environment)
             .build()?
-            .try_deserialize()
+            .try_deserialize()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check values that deserialize but cannot work, such as malformed URLs.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] naming the offending field and the
+    /// environment variable that sets it.
+    pub fn validate(&self) -> Result<()> {
+        check_url(
+            "database.url",
+            &self.database.url,
+            &["postgres", "postgresql"],
+        )?;
+        check_url(
+            "redis.url",
+            &self.redis.url,
+            &["redis", "rediss", "redis+unix", "unix"],
+        )?;
+        if self.api.port == 0 {
+            return Err(invalid("api.port", "must not be 0"));
+        }
+        if self.build.max_concurrent_builds == 0 {
+            return Err(invalid("build.max_concurrent_builds", "must not be 0"));
+        }
+        if self.webhooks.max_attempts == 0 {
+            return Err(invalid("webhooks.max_attempts", "must not be 0"));
+        }
+        for endpoint in &self.webhooks.endpoints {
+            check_url("webhooks.endpoints", endpoint, &["http", "https"])?;
+        }
+        self.cors.validate()?;
+        self.repo.validate()
+    }
+}
+
+/// Check that `value` of `field` is a URL with one of `schemes`.
+fn check_url(field: &str, value: &str, schemes: &[&str]) -> Result<()> {
+    if value.trim().is_empty() {
+        return Err(invalid(field, "must not be empty"));
+    }
+    let url = url::Url::parse(value)
+        .map_err(|err| invalid(field, &format!("is not a valid URL: {err}")))?;
+    if !schemes.contains(&url.scheme()) {
+        return Err(invalid(
+            field,
+            &format!(
+                "must use scheme {}, not '{}'",
+                schemes.join(" or "),
+                url.scheme()
+            ),
+        ));
     }
+    Ok(())
+}
+
+/// Configuration error for `field`, naming the environment variable too.
+fn invalid(field: &str, problem: &str) -> Error {
+    let variable = format!("DK_APPSTORE__{}", field.replace('.', "__").to_uppercase());
+    Error::Config(format!("{field} ({variable}) {problem}"))
+}
+
+/// Environment variables prefixed `DK_APPSTORE__`, with `__` separating
+/// nested keys and `,` separating list items.
+fn environment() -> config::Environment {
+    config::Environment::with_prefix("DK_APPSTORE")
+        .separator("__")
+        .try_parsing(true)
+        .list_separator(",")
+        .with_list_parse_key("cors.allowed_origins")
+        .with_list_parse_key("cors.allowed_methods")
+        .with_list_parse_key("auth.api_key_hashes")
+        .with_list_parse_key("rate_limit.trusted_proxies")
+        .with_list_parse_key("repo.mirrors")
+        .with_list_parse_key("webhooks.endpoints")
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_log_format() {
+        assert_eq!("json".parse::<LogFormat>().expect("json"), LogFormat::Json);
+        assert_eq!(
+            " Text ".parse::<LogFormat>().expect("text"),
+            LogFormat::Text
+        );
+        assert!("yaml".parse::<LogFormat>().is_err());
+    }
+
     #[test]
     fn test_default_values() {
         assert_eq!(default_max_connections(), 10);
         assert_eq!(default_host(), "127.0.0.1");
         assert_eq!(default_port(), 8080);
+        assert_eq!(default_shutdown_timeout_secs(), 30);
+        assert_eq!(default_max_upload_bytes(), 104_857_600);
+        assert_eq!(default_apk_dir(), PathBuf::from("data/repo"));
+        assert_eq!(default_region(), "us-east-1");
+        assert!(default_path_style());
+        assert_eq!(default_access_key_env(), "AWS_ACCESS_KEY_ID");
+        assert_eq!(default_secret_key_env(), "AWS_SECRET_ACCESS_KEY");
+        assert_eq!(default_clone_depth(), 1);
+        assert_eq!(default_container_runtime(), "podman");
+        assert_eq!(default_build_timeout_secs(), 3600);
+        assert_eq!(default_max_concurrent_builds(), 2);
+        assert_eq!(default_max_apk_size(), 104_857_600);
+        assert_eq!(default_max_dex_methods(), 65_536);
+        assert_eq!(default_tool_timeout_secs(), 300);
+        assert_eq!(default_max_zip_entries(), 65_536);
+        assert_eq!(default_max_entry_size(), 268_435_456);
+        assert_eq!(default_max_uncompressed_size(), 1_073_741_824);
+        assert_eq!(default_cors_methods(), ["GET", "HEAD"]);
+        assert_eq!(default_requests_per_minute(), 120);
+        assert_eq!(default_burst(), 60);
+        assert_eq!(default_repo_name(), "DK-AppStore");
+    }
+
+    fn cors(origins: &[&str], allow_credentials: bool) -> CorsConfig {
+        CorsConfig {
+            allowed_origins: origins.iter().map(ToString::to_string).collect(),
+            allow_credentials,
+            ..CorsConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_cors_validation() {
+        assert!(CorsConfig::default().validate().is_ok());
+        assert!(cors(&["*"], false).validate().is_ok());
+        assert!(cors(&["https://appstore.digst.dk"], true)
+            .validate()
+            .is_ok());
+        assert!(matches!(
+            cors(&["*"], true).validate(),
+            Err(Error::Config(_))
+        ));
+        assert!(matches!(
+            cors(&["*", "https://appstore.digst.dk"], false).validate(),
+            Err(Error::Config(_))
+        ));
+    }
+
+    fn fixture_path() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("src/testdata/config.toml")
+    }
+
+    /// Environment source reading `vars` instead of the process environment.
+    fn env_with(vars: &[(&str, &str)]) -> config::Environment {
+        let vars = vars
+            .iter()
+            .map(|(key, value)| ((*key).to_string(), (*value).to_string()))
+            .collect();
+        environment().source(Some(vars))
+    }
+
+    #[test]
+    fn test_load_toml_file() {
+        let config = Config::from_sources(config::File::from(fixture_path()), env_with(&[]))
+            .expect("config loads");
+        assert_eq!(
+            config.database.url,
+            "postgres://dk:dk@localhost:5432/dk_appstore"
+        );
+        assert_eq!(config.database.max_connections, 4);
+        assert_eq!(config.redis.url, "redis://localhost:6379/");
+        assert_eq!(config.api.host, "0.0.0.0");
+        assert_eq!(config.api.port, 8000);
+        assert_eq!(config.cors.allowed_origins, ["https://appstore.digst.dk"]);
+        // Sections absent from the file keep their defaults
+        assert_eq!(config.build.container_runtime, "podman");
+        assert!(config.object_store.is_none());
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let env = env_with(&[
+            ("DK_APPSTORE__API__PORT", "9090"),
+            (
+                "DK_APPSTORE__RATE_LIMIT__TRUSTED_PROXIES",
+                "10.0.0.1,10.0.0.2",
+            ),
+        ]);
+        let config =
+            Config::from_sources(config::File::from(fixture_path()), env).expect("config loads");
+        assert_eq!(config.api.port, 9090);
+        assert_eq!(config.api.host, "0.0.0.0");
+        assert_eq!(config.rate_limit.trusted_proxies.len(), 2);
+    }
+
+    #[test]
+    fn test_object_store_from_env() {
+        let env = env_with(&[
+            (
+                "DK_APPSTORE__OBJECT_STORE__ENDPOINT",
+                "http://localhost:9000",
+            ),
+            ("DK_APPSTORE__OBJECT_STORE__BUCKET", "apks"),
+        ]);
+        let config =
+            Config::from_sources(config::File::from(fixture_path()), env).expect("config loads");
+        let object_store = config.object_store.expect("object store");
+        assert_eq!(object_store.endpoint.as_str(), "http://localhost:9000/");
+        assert_eq!(object_store.bucket, "apks");
+        assert_eq!(object_store.region, "us-east-1");
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let missing = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/testdata/missing.toml");
+        let env = env_with(&[
+            ("DK_APPSTORE__DATABASE__URL", "postgres://localhost/dk"),
+            ("DK_APPSTORE__REDIS__URL", "redis://localhost/"),
+            ("DK_APPSTORE__API__PORT", "8081"),
+        ]);
+
+        // An explicitly named file must exist
+        let result = Config::from_sources(config::File::from(missing.as_path()), env.clone());
+        assert!(matches!(result, Err(Error::Config(_))));
+
+        // The default file is optional
+        let file = config::File::from(missing.as_path()).required(false);
+        let config = Config::from_sources(file, env).expect("config loads");
+        assert_eq!(config.api.port, 8081);
+    }
+
+    fn valid_config() -> Config {
+        Config::from_sources(config::File::from(fixture_path()), env_with(&[]))
+            .expect("config loads")
+    }
+
+    #[test]
+    fn test_valid_config_passes() {
+        assert!(valid_config().validate().is_ok());
+    }
+
+    #[test]
+    fn test_repo_mirrors_from_file() {
+        let config = valid_config();
+        assert_eq!(config.repo.name, "DK-AppStore");
+        assert_eq!(
+            config.repo.mirrors,
+            [
+                "https://mirror.appstore.digst.dk/repo",
+                "https://appstore.example.eu/repo"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_relative_mirror_url() {
+        let mut config = valid_config();
+        config.repo.mirrors.push("/mirror/repo".to_string());
+        let err = config.validate().expect_err("relative url");
+        assert!(
+            err.to_string()
+                .contains("repo.mirrors (DK_APPSTORE__REPO__MIRRORS) is not a valid URL"),
+            "{err}"
+        );
+
+        let mut config = valid_config();
+        config.repo.mirrors = vec!["ftp://mirror.example/repo".to_string()];
+        assert!(matches!(config.validate(), Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn test_repo_icon_must_be_relative() {
+        let mut config = valid_config();
+        config.repo.icon = Some("icons/dk-appstore.png".to_string());
+        assert!(config.validate().is_ok());
+
+        for icon in [
+            "",
+            "/icons/dk-appstore.png",
+            "../icon.png",
+            "https://cdn.example/icon.png",
+        ] {
+            config.repo.icon = Some(icon.to_string());
+            let err = config.validate().expect_err(icon);
+            assert!(
+                err.to_string()
+                    .contains("repo.icon (DK_APPSTORE__REPO__ICON)"),
+                "{err}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_empty_database_url() {
+        let mut config = valid_config();
+        config.database.url = String::new();
+        let err = config.validate().expect_err("empty url");
+        assert_eq!(
+            err.to_string(),
+            "configuration error: database.url (DK_APPSTORE__DATABASE__URL) must not be empty"
+        );
+    }
+
+    #[test]
+    fn test_database_url_scheme() {
+        let mut config = valid_config();
+        config.database.url = "mysql://localhost/dk".to_string();
+        let err = config.validate().expect_err("wrong scheme");
+        assert!(err
+            .to_string()
+            .contains("must use scheme postgres or postgresql"));
+    }
+
+    #[test]
+    fn test_malformed_redis_url() {
+        let mut config = valid_config();
+        config.redis.url = "localhost:6379".to_string();
+        let err = config.validate().expect_err("malformed url");
+        assert!(matches!(err, Error::Config(_)));
+        assert!(err
+            .to_string()
+            .starts_with("configuration error: redis.url (DK_APPSTORE__REDIS__URL)"));
+    }
+
+    #[test]
+    fn test_zero_port() {
+        let mut config = valid_config();
+        config.api.port = 0;
+        let err = config.validate().expect_err("zero port");
+        assert!(err
+            .to_string()
+            .contains("api.port (DK_APPSTORE__API__PORT) must not be 0"));
+    }
+
+    #[test]
+    fn test_webhook_endpoint_scheme() {
+        let mut config = valid_config();
+        config.webhooks.endpoints = vec!["https://hooks.example/dk-appstore".to_string()];
+        assert!(config.validate().is_ok());
+
+        config
+            .webhooks
+            .endpoints
+            .push("ftp://hooks.example/".to_string());
+        let err = config.validate().expect_err("wrong scheme");
+        assert!(err.to_string().contains("webhooks.endpoints"), "{err}");
+    }
+
+    #[test]
+    fn test_zero_concurrent_builds() {
+        let mut config = valid_config();
+        config.build.max_concurrent_builds = 0;
+        let err = config.validate().expect_err("no build slots");
+        assert!(err.to_string().contains("build.max_concurrent_builds"));
+    }
+
+    #[test]
+    fn test_load_validates() {
+        let env = env_with(&[("DK_APPSTORE__REDIS__URL", "not a url")]);
+        let result = Config::from_sources(config::File::from(fixture_path()), env);
+        assert!(result
+            .expect_err("invalid")
+            .to_string()
+            .contains("redis.url"));
+    }
+
+    #[test]
+    fn test_apk_path() {
+        let storage = StorageConfig {
+            apk_dir: PathBuf::from("/srv/repo"),
+            verify_downloads: false,
+        };
+        assert_eq!(
+            storage.apk_path(&AppId::new("dk.digst.mitid"), 123),
+            PathBuf::from("/srv/repo/dk.digst.mitid_123.apk")
+        );
     }
 }
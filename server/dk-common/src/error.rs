@@ -16,10 +16,29 @@ pub enum Error {
     Database(String),
     /// Configuration error.
     Config(String),
+    /// Artifact storage error.
+    Storage(String),
+    /// Webhook delivery error.
+    Webhook(String),
     /// Internal error.
     Internal(String),
 }
 
+impl Error {
+    /// Whether the failed operation may succeed if retried unchanged.
+    ///
+    /// Database, storage, webhook, and internal errors are usually
+    /// transient, such as a dropped connection; the others recur on every
+    /// attempt.
+    #[must_use]
+    pub const fn is_retryable(&self) -> bool {
+        match self {
+            Self::Database(_) | Self::Storage(_) | Self::Webhook(_) | Self::Internal(_) => true,
+            Self::NotFound(_) | Self::InvalidInput(_) | Self::Config(_) => false,
+        }
+    }
+}
+
 impl std::error::Error for Error {}
 
 impl fmt::Display for Error {
@@ -29,6 +48,8 @@ impl fmt::Display for Error {
             Self::InvalidInput(msg) => write!(f, "invalid input: {msg}"),
             Self::Database(msg) => write!(f, "database error: {msg}"),
             Self::Config(msg) => write!(f, "configuration error: {msg}"),
+            Self::Storage(msg) => write!(f, "storage error: {msg}"),
+            Self::Webhook(msg) => write!(f, "webhook error: {msg}"),
             Self::Internal(msg) => write!(f, "internal error: {msg}"),
         }
     }
@@ -40,6 +61,22 @@ impl From<config::ConfigError> for Error {
     }
 }
 
+#[cfg(feature = "postgres")]
+impl From<sqlx::Error> for Error {
+    /// Missing rows become [`Error::NotFound`] and unique constraint
+    /// violations [`Error::InvalidInput`], as both stem from the request;
+    /// everything else is a [`Error::Database`] failure.
+    fn from(err: sqlx::Error) -> Self {
+        match err {
+            sqlx::Error::RowNotFound => Self::NotFound(err.to_string()),
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => {
+                Self::InvalidInput(db_err.message().to_string())
+            }
+            err => Self::Database(err.to_string()),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -49,4 +86,111 @@ mod tests {
         let err = Error::NotFound("app xyz".to_string());
         assert_eq!(err.to_string(), "not found: app xyz");
     }
+
+    #[test]
+    fn test_retryable_classification() {
+        let cases = [
+            (Error::NotFound(String::new()), false),
+            (Error::InvalidInput(String::new()), false),
+            (Error::Database(String::new()), true),
+            (Error::Config(String::new()), false),
+            (Error::Storage(String::new()), true),
+            (Error::Webhook(String::new()), true),
+            (Error::Internal(String::new()), true),
+        ];
+        for (err, retryable) in cases {
+            assert_eq!(err.is_retryable(), retryable, "{err:?}");
+        }
+    }
+
+    #[cfg(feature = "postgres")]
+    mod sqlx_conversion {
+        use std::borrow::Cow;
+
+        use sqlx::error::{DatabaseError, ErrorKind};
+
+        use super::*;
+
+        /// Database error reported by the server.
+        #[derive(Debug)]
+        struct ServerError {
+            unique_violation: bool,
+            message: &'static str,
+        }
+
+        impl fmt::Display for ServerError {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.message)
+            }
+        }
+
+        impl std::error::Error for ServerError {}
+
+        impl DatabaseError for ServerError {
+            fn message(&self) -> &str {
+                self.message
+            }
+
+            fn code(&self) -> Option<Cow<'_, str>> {
+                None
+            }
+
+            fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+                self
+            }
+
+            fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+                self
+            }
+
+            fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+                self
+            }
+
+            fn kind(&self) -> ErrorKind {
+                if self.unique_violation {
+                    ErrorKind::UniqueViolation
+                } else {
+                    ErrorKind::Other
+                }
+            }
+        }
+
+        fn server_error(unique_violation: bool, message: &'static str) -> sqlx::Error {
+            sqlx::Error::Database(Box::new(ServerError {
+                unique_violation,
+                message,
+            }))
+        }
+
+        #[test]
+        fn test_row_not_found() {
+            assert!(matches!(
+                Error::from(sqlx::Error::RowNotFound),
+                Error::NotFound(_)
+            ));
+        }
+
+        #[test]
+        fn test_unique_violation_is_invalid_input() {
+            let err = Error::from(server_error(
+                true,
+                "duplicate key value violates unique constraint \"apps_package_id_key\"",
+            ));
+            assert!(
+                matches!(err, Error::InvalidInput(ref message) if message.contains("apps_package_id_key")),
+                "{err:?}"
+            );
+        }
+
+        #[test]
+        fn test_other_errors_are_database_errors() {
+            let err = Error::from(server_error(false, "violates foreign key constraint"));
+            assert!(matches!(err, Error::Database(ref message) if message.contains("foreign key")));
+            assert!(matches!(
+                Error::from(sqlx::Error::PoolTimedOut),
+                Error::Database(_)
+            ));
+        }
+    }
 }
@@ -0,0 +1,203 @@
+//! SHA-256 digests for integrity checks.
+//!
+//! Files and streams are hashed in fixed-size chunks, so large APKs are
+//! never held in memory whole.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use ring::digest::{Context, SHA256};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::error::{Error, Result};
+use crate::types::Sha256;
+
+/// Incremental SHA-256 hasher; bytes are fed in with [`Self::update`] or by
+/// writing to it.
+#[derive(Clone)]
+pub struct Sha256Hasher(Context);
+
+impl Sha256Hasher {
+    /// Start an empty digest.
+    #[must_use]
+    pub fn new() -> Self {
+        Self(Context::new(&SHA256))
+    }
+
+    /// Add `bytes` to the digest.
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    /// Digest of everything added so far.
+    #[must_use]
+    pub fn finish(self) -> Sha256 {
+        let mut bytes = [0; 32];
+        bytes.copy_from_slice(self.0.finish().as_ref());
+        Sha256::from_bytes(&bytes)
+    }
+}
+
+impl Default for Sha256Hasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Write for Sha256Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// SHA-256 of `bytes`.
+///
+/// # Example
+///
+/// ```
+/// use dk_common::hash::sha256_bytes;
+///
+/// assert_eq!(
+///     sha256_bytes(b"abc").to_string(),
+///     "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+/// );
+/// ```
+#[must_use]
+pub fn sha256_bytes(bytes: &[u8]) -> Sha256 {
+    let mut hasher = Sha256Hasher::new();
+    hasher.update(bytes);
+    hasher.finish()
+}
+
+/// Lowercase hex encoding of `bytes`.
+///
+/// # Example
+///
+/// ```
+/// use dk_common::hash::hex;
+///
+/// assert_eq!(hex(&[0x00, 0x5a, 0xff]), "005aff");
+/// ```
+#[must_use]
+pub fn hex(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    bytes
+        .iter()
+        .flat_map(|byte| [byte >> 4, byte & 0x0f])
+        .map(|nibble| char::from(DIGITS[usize::from(nibble)]))
+        .collect()
+}
+
+/// SHA-256 of the file at `path`, read in chunks.
+///
+/// # Errors
+///
+/// Returns [`Error::NotFound`] if there is no file at `path` and
+/// [`Error::Storage`] if it cannot be read.
+pub fn sha256_file(path: &Path) -> Result<Sha256> {
+    let io_error = |err: io::Error| match err.kind() {
+        io::ErrorKind::NotFound => Error::NotFound(format!("file not found: {}", path.display())),
+        _ => Error::Storage(format!("{}: {err}", path.display())),
+    };
+
+    let mut file = File::open(path).map_err(io_error)?;
+    let mut hasher = Sha256Hasher::new();
+    io::copy(&mut file, &mut hasher).map_err(io_error)?;
+    Ok(hasher.finish())
+}
+
+/// SHA-256 of everything `reader` yields, read in chunks.
+///
+/// # Errors
+///
+/// Returns [`Error::Storage`] if reading fails.
+pub async fn sha256_reader(mut reader: impl AsyncRead + Unpin) -> Result<Sha256> {
+    let mut hasher = Sha256Hasher::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = reader
+            .read(&mut buffer)
+            .await
+            .map_err(|err| Error::Storage(err.to_string()))?;
+        if read == 0 {
+            return Ok(hasher.finish());
+        }
+        hasher.update(&buffer[..read]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// A temporary file, removed on drop.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn with(contents: &[u8]) -> Self {
+            let path = std::env::temp_dir().join(format!("dk-hash-{}", uuid::Uuid::new_v4()));
+            std::fs::write(&path, contents).expect("write temp file");
+            Self(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_file_digest_matches_bytes_digest() {
+        // Spans several read chunks, with a partial one at the end
+        let contents: Vec<u8> = (0..=u8::MAX).cycle().take(200_003).collect();
+        let file = TempFile::with(&contents);
+
+        assert_eq!(
+            sha256_file(&file.0).expect("hash file"),
+            sha256_bytes(&contents)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reader_digest_matches_bytes_digest() {
+        let contents: Vec<u8> = (0..=u8::MAX).cycle().take(100_000).collect();
+        assert_eq!(
+            sha256_reader(contents.as_slice())
+                .await
+                .expect("hash reader"),
+            sha256_bytes(&contents)
+        );
+    }
+
+    #[test]
+    fn test_empty_file_digest() {
+        let file = TempFile::with(b"");
+        assert_eq!(
+            sha256_file(&file.0).expect("hash file").to_string(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn test_hasher_is_incremental() {
+        let mut hasher = Sha256Hasher::new();
+        hasher.update(b"dk.digst.");
+        hasher.update(b"mitid");
+        assert_eq!(hasher.finish(), sha256_bytes(b"dk.digst.mitid"));
+    }
+
+    #[test]
+    fn test_missing_file_is_not_found() {
+        let path = std::env::temp_dir().join(format!("dk-hash-{}", uuid::Uuid::new_v4()));
+        assert!(matches!(sha256_file(&path), Err(Error::NotFound(_))));
+    }
+}
@@ -4,7 +4,11 @@
 
 pub mod config;
 pub mod error;
+pub mod hash;
+pub mod localized;
+pub mod storage;
 pub mod types;
+pub mod webhooks;
 
 pub use config::Config;
 pub use error::{Error, Result};
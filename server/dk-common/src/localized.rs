@@ -0,0 +1,173 @@
+//! Localized values keyed by BCP-47 locale code.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Locale used when the requested locale is not available.
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// A set of values keyed by BCP-47 locale code (e.g. `"da"`, `"en-US"`).
+///
+/// Serializes as a plain JSON object mapping locale codes to values.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Localized<T>(BTreeMap<String, T>);
+
+impl<T> Localized<T> {
+    /// Create an empty set of localized values.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    /// Create a set containing a single value for `locale`.
+    #[must_use]
+    pub fn single(locale: impl Into<String>, value: T) -> Self {
+        let mut localized = Self::new();
+        localized.insert(locale, value);
+        localized
+    }
+
+    /// Insert a value for `locale`, returning the previous value if any.
+    pub fn insert(&mut self, locale: impl Into<String>, value: T) -> Option<T> {
+        self.0.insert(locale.into(), value)
+    }
+
+    /// Resolve the best value for `locale`.
+    ///
+    /// Tries, in order: an exact (case-insensitive) match, a match on the
+    /// language subtag alone (so `da-DK` finds `da` and vice versa), the
+    /// default locale `en`, and finally any available locale.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dk_common::localized::Localized;
+    ///
+    /// let mut name = Localized::single("en", "Digital identity");
+    /// name.insert("da", "Digital identitet");
+    ///
+    /// assert_eq!(name.get("da-DK"), Some(&"Digital identitet"));
+    /// assert_eq!(name.get("de"), Some(&"Digital identity"));
+    /// ```
+    #[must_use]
+    pub fn get(&self, locale: &str) -> Option<&T> {
+        let language = language_of(locale);
+
+        self.0
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(locale))
+            .or_else(|| {
+                self.0
+                    .iter()
+                    .find(|(key, _)| language_of(key).eq_ignore_ascii_case(language))
+            })
+            .map(|(_, value)| value)
+            .or_else(|| self.0.get(DEFAULT_LOCALE))
+            .or_else(|| self.0.values().next())
+    }
+
+    /// Returns `true` if no locales are present.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterate over `(locale, value)` pairs in locale order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &T)> {
+        self.0
+            .iter()
+            .map(|(locale, value)| (locale.as_str(), value))
+    }
+}
+
+impl<T> Default for Localized<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<BTreeMap<String, T>> for Localized<T> {
+    fn from(values: BTreeMap<String, T>) -> Self {
+        Self(values)
+    }
+}
+
+/// Returns the primary language subtag of a BCP-47 locale code.
+fn language_of(locale: &str) -> &str {
+    locale.split(['-', '_']).next().unwrap_or(locale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Localized<String> {
+        let mut localized = Localized::single("en", "Digital identity".to_string());
+        localized.insert("da", "Digital identitet".to_string());
+        localized
+    }
+
+    #[test]
+    fn test_exact_match() {
+        let localized = sample();
+        assert_eq!(
+            localized.get("da").map(String::as_str),
+            Some("Digital identitet")
+        );
+        assert_eq!(
+            localized.get("EN").map(String::as_str),
+            Some("Digital identity")
+        );
+    }
+
+    #[test]
+    fn test_language_only_fallback() {
+        let localized = sample();
+        assert_eq!(
+            localized.get("da-DK").map(String::as_str),
+            Some("Digital identitet")
+        );
+
+        let regional = Localized::single("de-AT", "Digitale Identität".to_string());
+        assert_eq!(
+            regional.get("de").map(String::as_str),
+            Some("Digitale Identität")
+        );
+    }
+
+    #[test]
+    fn test_default_locale_fallback() {
+        let localized = sample();
+        assert_eq!(
+            localized.get("fr").map(String::as_str),
+            Some("Digital identity")
+        );
+    }
+
+    #[test]
+    fn test_any_locale_fallback() {
+        let localized = Localized::single("sv", "Digital identitet".to_string());
+        assert_eq!(
+            localized.get("fr").map(String::as_str),
+            Some("Digital identitet")
+        );
+    }
+
+    #[test]
+    fn test_empty_map() {
+        let localized: Localized<String> = Localized::new();
+        assert!(localized.is_empty());
+        assert_eq!(localized.get("en"), None);
+    }
+
+    #[test]
+    fn test_serde_as_object() {
+        let json = serde_json::to_value(sample()).expect("serialize");
+        assert_eq!(
+            json,
+            serde_json::json!({"da": "Digital identitet", "en": "Digital identity"})
+        );
+    }
+}
@@ -0,0 +1,202 @@
+//! Local filesystem storage.
+
+use std::io::{ErrorKind, SeekFrom};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use super::{check_key, ObjectMeta, ObjectReader, Storage};
+use crate::error::{Error, Result};
+
+/// Objects stored as files under a root directory.
+#[derive(Debug, Clone)]
+pub struct FilesystemStorage {
+    root: PathBuf,
+}
+
+impl FilesystemStorage {
+    /// Store objects under `root`, which is created on the first write.
+    #[must_use]
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Path of the file holding `key`.
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        check_key(key)?;
+        Ok(self.root.join(key))
+    }
+}
+
+/// Map an I/O error on `key` to a storage error.
+fn io_error(key: &str, err: &std::io::Error) -> Error {
+    if err.kind() == ErrorKind::NotFound {
+        Error::NotFound(format!("object not found: {key}"))
+    } else {
+        Error::Storage(format!("{key}: {err}"))
+    }
+}
+
+#[async_trait]
+impl Storage for FilesystemStorage {
+    async fn get(&self, key: &str, range: Option<Range<u64>>) -> Result<ObjectReader> {
+        let mut file = File::open(self.path(key)?)
+            .await
+            .map_err(|err| io_error(key, &err))?;
+        let Some(range) = range else {
+            return Ok(Box::pin(file));
+        };
+
+        if range.start > 0 {
+            file.seek(SeekFrom::Start(range.start))
+                .await
+                .map_err(|err| io_error(key, &err))?;
+        }
+        Ok(Box::pin(file.take(range.end.saturating_sub(range.start))))
+    }
+
+    async fn put(&self, key: &str, bytes: Bytes) -> Result<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|err| io_error(key, &err))?;
+        }
+
+        // Write under a temporary name so readers never see a partial object
+        let partial = path.with_extension(format!("partial-{}", uuid::Uuid::new_v4()));
+        tokio::fs::write(&partial, &bytes)
+            .await
+            .map_err(|err| io_error(key, &err))?;
+        if let Err(err) = tokio::fs::rename(&partial, &path).await {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(io_error(key, &err));
+        }
+        Ok(())
+    }
+
+    async fn head(&self, key: &str) -> Result<ObjectMeta> {
+        let metadata = tokio::fs::metadata(self.path(key)?)
+            .await
+            .map_err(|err| io_error(key, &err))?;
+        if !metadata.is_file() {
+            return Err(Error::NotFound(format!("object not found: {key}")));
+        }
+        Ok(ObjectMeta {
+            size: metadata.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A storage root in a fresh temporary directory, removed on drop.
+    struct TempRoot(PathBuf);
+
+    impl TempRoot {
+        fn new() -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("dk-storage-test-{}", uuid::Uuid::new_v4()));
+            Self(dir)
+        }
+
+        fn storage(&self) -> FilesystemStorage {
+            FilesystemStorage::new(&self.0)
+        }
+    }
+
+    impl Drop for TempRoot {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    async fn read_all(mut reader: ObjectReader) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await.expect("read object");
+        bytes
+    }
+
+    #[tokio::test]
+    async fn test_put_get_head() {
+        let root = TempRoot::new();
+        let storage = root.storage();
+        storage
+            .put("dk.digst.mitid_1.apk", Bytes::from_static(b"apk bytes"))
+            .await
+            .expect("put");
+
+        let meta = storage.head("dk.digst.mitid_1.apk").await.expect("head");
+        assert_eq!(meta.size, 9);
+        let reader = storage
+            .get("dk.digst.mitid_1.apk", None)
+            .await
+            .expect("get");
+        assert_eq!(read_all(reader).await, b"apk bytes");
+
+        // Only the object remains; the partial file was renamed into place
+        let entries = std::fs::read_dir(&root.0).expect("read dir").count();
+        assert_eq!(entries, 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_range() {
+        let root = TempRoot::new();
+        let storage = root.storage();
+        storage
+            .put("object", Bytes::from_static(b"0123456789"))
+            .await
+            .expect("put");
+
+        let reader = storage.get("object", Some(3..7)).await.expect("get");
+        assert_eq!(read_all(reader).await, b"3456");
+    }
+
+    #[tokio::test]
+    async fn test_put_replaces_object() {
+        let root = TempRoot::new();
+        let storage = root.storage();
+        storage
+            .put("object", Bytes::from_static(b"old"))
+            .await
+            .expect("put");
+        storage
+            .put("object", Bytes::from_static(b"newer"))
+            .await
+            .expect("put");
+
+        assert_eq!(storage.head("object").await.expect("head").size, 5);
+    }
+
+    #[tokio::test]
+    async fn test_missing_object() {
+        let root = TempRoot::new();
+        let storage = root.storage();
+
+        assert!(matches!(
+            storage.head("missing").await,
+            Err(Error::NotFound(_))
+        ));
+        assert!(matches!(
+            storage.get("missing", None).await,
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_escaping_key() {
+        let root = TempRoot::new();
+        let storage = root.storage();
+
+        let result = storage.put("../escaped", Bytes::from_static(b"x")).await;
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+    }
+}
@@ -0,0 +1,99 @@
+//! Artifact storage backends.
+//!
+//! APKs live either on local disk or in an S3-compatible object store,
+//! selected by [`open`]. Objects are addressed by flat keys such as
+//! `dk.digst.mitid_123.apk`.
+
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use tokio::io::AsyncRead;
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+
+pub mod fs;
+pub mod s3;
+
+pub use fs::FilesystemStorage;
+pub use s3::S3Storage;
+
+/// Stream of an object's bytes.
+pub type ObjectReader = Pin<Box<dyn AsyncRead + Send>>;
+
+/// Metadata of a stored object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObjectMeta {
+    /// Size in bytes.
+    pub size: u64,
+}
+
+/// A store of immutable objects.
+///
+/// Missing objects are reported as [`Error::NotFound`]; backend failures as
+/// [`Error::Storage`].
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Stream the object at `key`, or only the bytes in `range`.
+    ///
+    /// `range` must lie within the object, as checked against [`Self::head`].
+    async fn get(&self, key: &str, range: Option<Range<u64>>) -> Result<ObjectReader>;
+
+    /// Store `bytes` at `key`, replacing any existing object.
+    async fn put(&self, key: &str, bytes: Bytes) -> Result<()>;
+
+    /// Look up the metadata of the object at `key`.
+    async fn head(&self, key: &str) -> Result<ObjectMeta>;
+}
+
+/// Open the backend selected by `config`: the object store if one is
+/// configured, otherwise the local APK directory.
+pub fn open(config: &Config) -> Result<Arc<dyn Storage>> {
+    Ok(match &config.object_store {
+        Some(object_store) => Arc::new(S3Storage::from_config(object_store)?),
+        None => Arc::new(FilesystemStorage::new(&config.storage.apk_dir)),
+    })
+}
+
+/// Reject keys that could escape their namespace, such as `../secrets`.
+fn check_key(key: &str) -> Result<()> {
+    let valid = !key.is_empty()
+        && !key.starts_with('/')
+        && !key.contains('\\')
+        && key
+            .split('/')
+            .all(|segment| !segment.is_empty() && segment != "." && segment != "..");
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidInput(format!("invalid storage key '{key}'")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_key() {
+        assert!(check_key("dk.digst.mitid_123.apk").is_ok());
+        assert!(check_key("icons/dk.digst.mitid.png").is_ok());
+        for key in [
+            "",
+            "/etc/passwd",
+            "../secret",
+            "a/../b",
+            "a//b",
+            "a\\b",
+            ".",
+        ] {
+            assert!(
+                matches!(check_key(key), Err(Error::InvalidInput(_))),
+                "{key}"
+            );
+        }
+    }
+}
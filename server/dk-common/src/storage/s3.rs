@@ -0,0 +1,401 @@
+//! S3-compatible object storage.
+//!
+//! Requests are presigned with SigV4 and sent through an [`S3Client`], so
+//! the HTTP layer can be replaced in tests.
+
+use std::ops::Range;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::TryStreamExt;
+use rusty_s3::{Bucket, Credentials, S3Action, UrlStyle};
+use tokio_util::io::StreamReader;
+use url::Url;
+
+use super::{check_key, ObjectMeta, ObjectReader, Storage};
+use crate::config::ObjectStoreConfig;
+use crate::error::{Error, Result};
+
+/// How long presigned request URLs stay valid.
+const SIGNATURE_TTL: Duration = Duration::from_secs(60);
+
+/// HTTP method of an object store request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum S3Method {
+    /// Read an object.
+    Get,
+    /// Read an object's metadata.
+    Head,
+    /// Write an object.
+    Put,
+}
+
+/// A presigned request to the object store.
+pub struct S3Request {
+    /// HTTP method.
+    pub method: S3Method,
+    /// Presigned URL.
+    pub url: Url,
+    /// Bytes of the object to read, if not all of them.
+    pub range: Option<Range<u64>>,
+    /// Object content of a `PUT`.
+    pub body: Option<Bytes>,
+}
+
+/// Response from the object store.
+pub struct S3Response {
+    /// HTTP status code.
+    pub status: u16,
+    /// Value of the `Content-Length` header, if present.
+    pub content_length: Option<u64>,
+    /// Response body.
+    pub body: ObjectReader,
+}
+
+/// Transport sending presigned requests to the object store.
+#[async_trait]
+pub trait S3Client: Send + Sync {
+    /// Send `request`, returning the response whatever its status.
+    async fn send(&self, request: S3Request) -> Result<S3Response>;
+}
+
+/// [`S3Client`] sending requests over HTTP.
+#[derive(Debug, Clone, Default)]
+pub struct HttpClient(reqwest::Client);
+
+#[async_trait]
+impl S3Client for HttpClient {
+    async fn send(&self, request: S3Request) -> Result<S3Response> {
+        let method = match request.method {
+            S3Method::Get => reqwest::Method::GET,
+            S3Method::Head => reqwest::Method::HEAD,
+            S3Method::Put => reqwest::Method::PUT,
+        };
+        let mut builder = self.0.request(method, request.url);
+        if let Some(range) = request.range {
+            builder = builder.header(
+                reqwest::header::RANGE,
+                format!("bytes={}-{}", range.start, range.end.saturating_sub(1)),
+            );
+        }
+        if let Some(body) = request.body {
+            builder = builder.body(body);
+        }
+
+        let response = builder
+            .send()
+            .await
+            .map_err(|err| Error::Storage(format!("object store request failed: {err}")))?;
+        // Read the header itself: a HEAD response has no body to size
+        let content_length = response
+            .headers()
+            .get(reqwest::header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+        let status = response.status().as_u16();
+        let body = response.bytes_stream().map_err(std::io::Error::other);
+
+        Ok(S3Response {
+            status,
+            content_length,
+            body: Box::pin(StreamReader::new(body)),
+        })
+    }
+}
+
+/// Objects stored in an S3-compatible bucket.
+pub struct S3Storage<C = HttpClient> {
+    bucket: Bucket,
+    credentials: Credentials,
+    client: C,
+}
+
+impl S3Storage {
+    /// Connect to the bucket described by `config`, reading credentials
+    /// from the environment variables it names.
+    pub fn from_config(config: &ObjectStoreConfig) -> Result<Self> {
+        let access_key = credential(&config.access_key_env)?;
+        let secret_key = credential(&config.secret_key_env)?;
+        Self::with_client(config, &access_key, &secret_key, HttpClient::default())
+    }
+}
+
+impl<C: S3Client> S3Storage<C> {
+    /// Use the bucket described by `config` through `client`.
+    pub fn with_client(
+        config: &ObjectStoreConfig,
+        access_key: &str,
+        secret_key: &str,
+        client: C,
+    ) -> Result<Self> {
+        let style = if config.path_style {
+            UrlStyle::Path
+        } else {
+            UrlStyle::VirtualHost
+        };
+        let bucket = Bucket::new(
+            config.endpoint.clone(),
+            style,
+            config.bucket.clone(),
+            config.region.clone(),
+        )
+        .map_err(|err| Error::Config(format!("object_store: {err}")))?;
+
+        Ok(Self {
+            bucket,
+            credentials: Credentials::new(access_key, secret_key),
+            client,
+        })
+    }
+
+    /// Send a request for `key`, mapping error statuses to errors.
+    async fn send(&self, key: &str, request: S3Request) -> Result<S3Response> {
+        let method = request.method;
+        let response = self.client.send(request).await?;
+        match response.status {
+            200..=299 => Ok(response),
+            404 => Err(Error::NotFound(format!("object not found: {key}"))),
+            status => Err(Error::Storage(format!(
+                "{method:?} {key}: object store returned status {status}"
+            ))),
+        }
+    }
+}
+
+/// Read the credential held by environment variable `name`.
+fn credential(name: &str) -> Result<String> {
+    std::env::var(name).map_err(|_| {
+        Error::Config(format!(
+            "object_store: environment variable {name} is not set"
+        ))
+    })
+}
+
+#[async_trait]
+impl<C: S3Client> Storage for S3Storage<C> {
+    async fn get(&self, key: &str, range: Option<Range<u64>>) -> Result<ObjectReader> {
+        check_key(key)?;
+        let url = self
+            .bucket
+            .get_object(Some(&self.credentials), key)
+            .sign(SIGNATURE_TTL);
+        let request = S3Request {
+            method: S3Method::Get,
+            url,
+            range,
+            body: None,
+        };
+        Ok(self.send(key, request).await?.body)
+    }
+
+    async fn put(&self, key: &str, bytes: Bytes) -> Result<()> {
+        check_key(key)?;
+        let url = self
+            .bucket
+            .put_object(Some(&self.credentials), key)
+            .sign(SIGNATURE_TTL);
+        let request = S3Request {
+            method: S3Method::Put,
+            url,
+            range: None,
+            body: Some(bytes),
+        };
+        self.send(key, request).await.map(drop)
+    }
+
+    async fn head(&self, key: &str) -> Result<ObjectMeta> {
+        check_key(key)?;
+        let url = self
+            .bucket
+            .head_object(Some(&self.credentials), key)
+            .sign(SIGNATURE_TTL);
+        let request = S3Request {
+            method: S3Method::Head,
+            url,
+            range: None,
+            body: None,
+        };
+        let response = self.send(key, request).await?;
+        let size = response.content_length.ok_or_else(|| {
+            Error::Storage(format!("HEAD {key}: object store sent no Content-Length"))
+        })?;
+        Ok(ObjectMeta { size })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    /// In-memory stand-in for an object store, keyed by URL path.
+    #[derive(Clone, Default)]
+    struct MockS3 {
+        objects: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+        requests: Arc<Mutex<Vec<(S3Method, Url)>>>,
+        /// Status returned for every request instead of serving it.
+        failure: Option<u16>,
+    }
+
+    fn response(status: u16, body: Vec<u8>) -> S3Response {
+        S3Response {
+            status,
+            content_length: Some(body.len() as u64),
+            body: Box::pin(std::io::Cursor::new(body)),
+        }
+    }
+
+    #[async_trait]
+    impl S3Client for MockS3 {
+        async fn send(&self, request: S3Request) -> Result<S3Response> {
+            self.requests
+                .lock()
+                .expect("requests")
+                .push((request.method, request.url.clone()));
+            if let Some(status) = self.failure {
+                return Ok(response(status, Vec::new()));
+            }
+
+            let path = request.url.path().to_string();
+            let mut objects = self.objects.lock().expect("objects");
+            match request.method {
+                S3Method::Put => {
+                    let body = request.body.expect("PUT body");
+                    objects.insert(path, body.to_vec());
+                    Ok(response(200, Vec::new()))
+                }
+                S3Method::Get | S3Method::Head => {
+                    let Some(object) = objects.get(&path) else {
+                        return Ok(response(404, Vec::new()));
+                    };
+                    let body = match &request.range {
+                        Some(range) => {
+                            let start = usize::try_from(range.start).expect("start");
+                            let end = usize::try_from(range.end).expect("end");
+                            object[start..end].to_vec()
+                        }
+                        None => object.clone(),
+                    };
+                    drop(objects);
+                    Ok(response(
+                        if request.range.is_some() { 206 } else { 200 },
+                        body,
+                    ))
+                }
+            }
+        }
+    }
+
+    fn config() -> ObjectStoreConfig {
+        ObjectStoreConfig {
+            endpoint: "http://localhost:9000".parse().expect("endpoint"),
+            bucket: "apks".to_string(),
+            region: "eu-north-1".to_string(),
+            path_style: true,
+            access_key_env: "UNUSED".to_string(),
+            secret_key_env: "UNUSED".to_string(),
+        }
+    }
+
+    fn storage(client: MockS3) -> S3Storage<MockS3> {
+        S3Storage::with_client(&config(), "access", "secret", client).expect("storage")
+    }
+
+    async fn read_all(mut reader: ObjectReader) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await.expect("read object");
+        bytes
+    }
+
+    #[tokio::test]
+    async fn test_put_get_head() {
+        let client = MockS3::default();
+        let storage = storage(client.clone());
+        storage
+            .put("dk.digst.mitid_1.apk", Bytes::from_static(b"apk bytes"))
+            .await
+            .expect("put");
+
+        assert_eq!(
+            storage
+                .head("dk.digst.mitid_1.apk")
+                .await
+                .expect("head")
+                .size,
+            9
+        );
+        let reader = storage
+            .get("dk.digst.mitid_1.apk", None)
+            .await
+            .expect("get");
+        assert_eq!(read_all(reader).await, b"apk bytes");
+        let reader = storage
+            .get("dk.digst.mitid_1.apk", Some(4..9))
+            .await
+            .expect("get");
+        assert_eq!(read_all(reader).await, b"bytes");
+
+        let requests = client.requests.lock().expect("requests");
+        let methods: Vec<S3Method> = requests.iter().map(|(method, _)| *method).collect();
+        assert_eq!(
+            methods,
+            [S3Method::Put, S3Method::Head, S3Method::Get, S3Method::Get]
+        );
+        for (_, url) in requests.iter() {
+            assert_eq!(url.path(), "/apks/dk.digst.mitid_1.apk");
+            assert!(url
+                .query()
+                .is_some_and(|query| query.contains("X-Amz-Signature=")));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_missing_object() {
+        let storage = storage(MockS3::default());
+        assert!(matches!(
+            storage.head("missing").await,
+            Err(Error::NotFound(_))
+        ));
+        assert!(matches!(
+            storage.get("missing", None).await,
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_error_status_is_storage_error() {
+        let storage = storage(MockS3 {
+            failure: Some(503),
+            ..MockS3::default()
+        });
+        let err = storage
+            .put("object", Bytes::from_static(b"x"))
+            .await
+            .expect_err("unavailable");
+        assert!(matches!(err, Error::Storage(_)));
+        assert!(err.to_string().contains("status 503"));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_escaping_key() {
+        let client = MockS3::default();
+        let result = storage(client.clone()).head("../other-bucket/key").await;
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+        assert!(client.requests.lock().expect("requests").is_empty());
+    }
+
+    #[test]
+    fn test_from_config_requires_credentials() {
+        let mut config = config();
+        config.access_key_env = format!("DK_TEST_UNSET_{}", uuid::Uuid::new_v4().simple());
+        let err = S3Storage::from_config(&config)
+            .err()
+            .expect("credentials are not set");
+        assert!(err.to_string().contains(&config.access_key_env));
+    }
+}
@@ -4,6 +4,9 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::error::{Error, Result};
+use crate::localized::Localized;
+
 /// Unique identifier for an application.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct AppId(pub String);
@@ -23,11 +26,65 @@ impl AppId {
         Self(package_id.into())
     }
 
+    /// Parse and validate a package identifier.
+    ///
+    /// Enforces the Android application ID grammar: at least two dot-separated
+    /// segments, each starting with a letter and containing only `[A-Za-z0-9_]`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dk_common::types::AppId;
+    ///
+    /// assert!(AppId::parse("dk.digst.mitid").is_ok());
+    /// assert!(AppId::parse("dk.digst..mitid").is_err());
+    /// ```
+    pub fn parse(package_id: &str) -> Result<Self> {
+        let segments: Vec<&str> = package_id.split('.').collect();
+        if segments.len() < 2 {
+            return Err(Error::InvalidInput(format!(
+                "package id '{package_id}' must have at least two segments"
+            )));
+        }
+
+        for segment in &segments {
+            let valid = segment
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic())
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                return Err(Error::InvalidInput(format!(
+                    "package id '{package_id}' has invalid segment '{segment}'"
+                )));
+            }
+        }
+
+        Ok(Self(package_id.to_string()))
+    }
+
     /// Returns the package identifier as a string slice.
     #[must_use]
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// Returns the repository file name of the APK for `version_code`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dk_common::types::AppId;
+    ///
+    /// let id = AppId::new("dk.digst.mitid");
+    /// assert_eq!(id.apk_file_name(123), "dk.digst.mitid_123.apk");
+    /// ```
+    #[must_use]
+    pub fn apk_file_name(&self, version_code: i64) -> String {
+        format!("{}_{version_code}.apk", self.0)
+    }
 }
 
 impl std::fmt::Display for AppId {
@@ -36,6 +93,537 @@ impl std::fmt::Display for AppId {
     }
 }
 
+/// SHA-256 digest.
+///
+/// Displays and serializes as 64 lowercase hex characters, so digests
+/// compare equal regardless of the case they were supplied in.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Sha256([u8; 32]);
+
+impl Sha256 {
+    /// Wrap a raw digest.
+    #[must_use]
+    pub const fn from_bytes(bytes: &[u8; 32]) -> Self {
+        Self(*bytes)
+    }
+
+    /// Parse a hex digest, accepting either case.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dk_common::types::Sha256;
+    ///
+    /// let hash = Sha256::parse(&"AB".repeat(32)).expect("valid digest");
+    /// assert_eq!(hash.to_string(), "ab".repeat(32));
+    /// assert!(Sha256::parse("abcd").is_err());
+    /// ```
+    pub fn parse(hex: &str) -> Result<Self> {
+        if hex.len() != 64 {
+            return Err(Error::InvalidInput(format!(
+                "sha256 must be 64 hex characters, got {}",
+                hex.len()
+            )));
+        }
+
+        if !hex.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+            return Err(Error::InvalidInput(format!(
+                "sha256 '{hex}' is not hexadecimal"
+            )));
+        }
+
+        let mut bytes = [0; 32];
+        for (byte, pair) in bytes.iter_mut().zip(hex.as_bytes().chunks_exact(2)) {
+            *byte = (nibble(pair[0]) << 4) | nibble(pair[1]);
+        }
+        Ok(Self(bytes))
+    }
+
+    /// Returns the raw digest.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+/// Value of an ASCII hex digit.
+const fn nibble(digit: u8) -> u8 {
+    match digit {
+        b'0'..=b'9' => digit - b'0',
+        b'a'..=b'f' => digit - b'a' + 10,
+        _ => digit.to_ascii_lowercase() - b'a' + 10,
+    }
+}
+
+impl std::fmt::Display for Sha256 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&crate::hash::hex(&self.0))
+    }
+}
+
+impl std::fmt::Debug for Sha256 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Sha256({self})")
+    }
+}
+
+impl TryFrom<String> for Sha256 {
+    type Error = Error;
+
+    fn try_from(hex: String) -> Result<Self> {
+        Self::parse(&hex)
+    }
+}
+
+impl From<Sha256> for String {
+    fn from(hash: Sha256) -> Self {
+        hash.to_string()
+    }
+}
+
+/// Application category, as used by F-Droid clients for browsing.
+///
+/// Serializes to its display string. Unknown names deserialize into
+/// [`Category::Other`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Category {
+    /// Connectivity.
+    Connectivity,
+    /// Development.
+    Development,
+    /// Games.
+    Games,
+    /// Graphics.
+    Graphics,
+    /// Internet.
+    Internet,
+    /// Money.
+    Money,
+    /// Multimedia.
+    Multimedia,
+    /// Navigation.
+    Navigation,
+    /// Phone & SMS.
+    PhoneSms,
+    /// Public sector and government services.
+    PublicServices,
+    /// Reading.
+    Reading,
+    /// Science & Education.
+    ScienceEducation,
+    /// Security.
+    Security,
+    /// Sports & Health.
+    SportsHealth,
+    /// System.
+    System,
+    /// Theming.
+    Theming,
+    /// Time.
+    Time,
+    /// Writing.
+    Writing,
+    /// Any category not in the canonical list.
+    Other(String),
+}
+
+impl Category {
+    /// Returns the canonical list of known categories.
+    #[must_use]
+    pub fn all() -> Vec<Self> {
+        vec![
+            Self::Connectivity,
+            Self::Development,
+            Self::Games,
+            Self::Graphics,
+            Self::Internet,
+            Self::Money,
+            Self::Multimedia,
+            Self::Navigation,
+            Self::PhoneSms,
+            Self::PublicServices,
+            Self::Reading,
+            Self::ScienceEducation,
+            Self::Security,
+            Self::SportsHealth,
+            Self::System,
+            Self::Theming,
+            Self::Time,
+            Self::Writing,
+        ]
+    }
+
+    /// Returns the display name of the category.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Connectivity => "Connectivity",
+            Self::Development => "Development",
+            Self::Games => "Games",
+            Self::Graphics => "Graphics",
+            Self::Internet => "Internet",
+            Self::Money => "Money",
+            Self::Multimedia => "Multimedia",
+            Self::Navigation => "Navigation",
+            Self::PhoneSms => "Phone & SMS",
+            Self::PublicServices => "Public Services",
+            Self::Reading => "Reading",
+            Self::ScienceEducation => "Science & Education",
+            Self::Security => "Security",
+            Self::SportsHealth => "Sports & Health",
+            Self::System => "System",
+            Self::Theming => "Theming",
+            Self::Time => "Time",
+            Self::Writing => "Writing",
+            Self::Other(name) => name,
+        }
+    }
+}
+
+impl std::fmt::Display for Category {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl TryFrom<String> for Category {
+    type Error = Error;
+
+    fn try_from(name: String) -> Result<Self> {
+        let trimmed = name.trim();
+        if trimmed.is_empty() {
+            return Err(Error::InvalidInput(
+                "category must not be empty".to_string(),
+            ));
+        }
+
+        Ok(Self::all()
+            .into_iter()
+            .find(|category| category.as_str() == trimmed)
+            .unwrap_or_else(|| Self::Other(trimmed.to_string())))
+    }
+}
+
+impl From<Category> for String {
+    fn from(category: Category) -> Self {
+        match category {
+            Category::Other(name) => name,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+/// F-Droid anti-feature: something users may not want in an app.
+///
+/// Serializes to the F-Droid code, e.g. `NonFreeNet`. Unlike categories the
+/// set is closed, as clients only explain the codes they know.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Antifeature {
+    /// Shows advertisements.
+    Ads,
+    /// Tracks or reports user activity.
+    Tracking,
+    /// Promotes or depends on a non-free network service.
+    NonFreeNet,
+    /// Promotes non-free add-ons.
+    NonFreeAdd,
+    /// Depends on non-free software.
+    NonFreeDep,
+    /// Contains content unsuitable for some audiences.
+    Nsfw,
+    /// Upstream source code is not free.
+    UpstreamNonFree,
+    /// Contains non-free media.
+    NonFreeAssets,
+    /// Has a known security vulnerability.
+    KnownVuln,
+    /// Is built debuggable.
+    ApplicationDebuggable,
+    /// Source code is no longer available.
+    NoSourceSince,
+    /// Requires a tethered network connection to the developer's servers.
+    TetheredNet,
+}
+
+impl Antifeature {
+    /// All anti-features, in F-Droid's order.
+    pub const ALL: [Self; 12] = [
+        Self::Ads,
+        Self::Tracking,
+        Self::NonFreeNet,
+        Self::NonFreeAdd,
+        Self::NonFreeDep,
+        Self::Nsfw,
+        Self::UpstreamNonFree,
+        Self::NonFreeAssets,
+        Self::KnownVuln,
+        Self::ApplicationDebuggable,
+        Self::NoSourceSince,
+        Self::TetheredNet,
+    ];
+
+    /// Returns the F-Droid code of the anti-feature.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Ads => "Ads",
+            Self::Tracking => "Tracking",
+            Self::NonFreeNet => "NonFreeNet",
+            Self::NonFreeAdd => "NonFreeAdd",
+            Self::NonFreeDep => "NonFreeDep",
+            Self::Nsfw => "NSFW",
+            Self::UpstreamNonFree => "UpstreamNonFree",
+            Self::NonFreeAssets => "NonFreeAssets",
+            Self::KnownVuln => "KnownVuln",
+            Self::ApplicationDebuggable => "ApplicationDebuggable",
+            Self::NoSourceSince => "NoSourceSince",
+            Self::TetheredNet => "TetheredNet",
+        }
+    }
+}
+
+impl std::fmt::Display for Antifeature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for Antifeature {
+    type Err = Error;
+
+    /// Parse a canonical F-Droid code; codes are case-sensitive.
+    fn from_str(code: &str) -> Result<Self> {
+        let code = code.trim();
+        Self::ALL
+            .into_iter()
+            .find(|antifeature| antifeature.as_str() == code)
+            .ok_or_else(|| Error::InvalidInput(format!("unknown anti-feature: {code}")))
+    }
+}
+
+impl TryFrom<String> for Antifeature {
+    type Error = Error;
+
+    fn try_from(code: String) -> Result<Self> {
+        code.parse()
+    }
+}
+
+impl From<Antifeature> for String {
+    fn from(antifeature: Antifeature) -> Self {
+        antifeature.as_str().to_string()
+    }
+}
+
+/// SPDX license identifier of an application, e.g. `GPL-3.0-only`.
+///
+/// Only identifiers on the SPDX license list are accepted, plus the
+/// [`License::NOASSERTION`] sentinel for a license not yet determined.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct License(String);
+
+impl License {
+    /// Sentinel identifier for an unknown or undetermined license.
+    pub const NOASSERTION: &'static str = "NOASSERTION";
+
+    /// Parse and validate an SPDX license identifier.
+    ///
+    /// Identifiers are case-sensitive, as on the SPDX list.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dk_common::types::License;
+    ///
+    /// assert!(License::parse("EUPL-1.2").is_ok());
+    /// assert!(License::parse("Proprietary-ish").is_err());
+    /// ```
+    pub fn parse(id: &str) -> Result<Self> {
+        let id = id.trim();
+        if id == Self::NOASSERTION || spdx::license_id(id).is_some() {
+            Ok(Self(id.to_string()))
+        } else {
+            Err(Error::InvalidInput(format!(
+                "unknown SPDX license identifier: '{id}'"
+            )))
+        }
+    }
+
+    /// Returns the SPDX identifier.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for License {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl TryFrom<String> for License {
+    type Error = Error;
+
+    fn try_from(id: String) -> Result<Self> {
+        Self::parse(&id)
+    }
+}
+
+impl From<License> for String {
+    fn from(license: License) -> Self {
+        license.0
+    }
+}
+
+/// Absolute `http` or `https` URL of a web page, such as an app's source
+/// repository.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct WebUrl(url::Url);
+
+impl WebUrl {
+    /// Parse and validate an absolute `http(s)` URL.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dk_common::types::WebUrl;
+    ///
+    /// assert!(WebUrl::parse("https://github.com/digst/mitid").is_ok());
+    /// assert!(WebUrl::parse("javascript:alert(1)").is_err());
+    /// assert!(WebUrl::parse("/relative/path").is_err());
+    /// ```
+    pub fn parse(value: &str) -> Result<Self> {
+        let url = url::Url::parse(value.trim())
+            .map_err(|err| Error::InvalidInput(format!("invalid URL '{value}': {err}")))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(Error::InvalidInput(format!(
+                "URL '{value}' must use http or https, not '{}'",
+                url.scheme()
+            )));
+        }
+        Ok(Self(url))
+    }
+
+    /// Returns the URL as a string.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl std::fmt::Display for WebUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl TryFrom<String> for WebUrl {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        Self::parse(&value)
+    }
+}
+
+impl From<WebUrl> for String {
+    fn from(url: WebUrl) -> Self {
+        url.0.into()
+    }
+}
+
+/// Donation platform of a [`DonationLink`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DonationKind {
+    /// A `https://liberapay.com/<account>` page.
+    Liberapay,
+    /// A `https://opencollective.com/<collective>` page.
+    OpenCollective,
+    /// Any other donation page.
+    Custom,
+}
+
+impl DonationKind {
+    /// Host of the platform, if the kind is tied to one.
+    const fn host(self) -> Option<&'static str> {
+        match self {
+            Self::Liberapay => Some("liberapay.com"),
+            Self::OpenCollective => Some("opencollective.com"),
+            Self::Custom => None,
+        }
+    }
+}
+
+/// Where users can donate to an app's developers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawDonationLink")]
+pub struct DonationLink {
+    /// Donation platform.
+    pub kind: DonationKind,
+    /// Donation page.
+    pub url: WebUrl,
+}
+
+/// Unvalidated form of [`DonationLink`], as deserialized.
+#[derive(Deserialize)]
+struct RawDonationLink {
+    kind: DonationKind,
+    url: WebUrl,
+}
+
+impl DonationLink {
+    /// Create a donation link, checking that platform links point at an
+    /// account on that platform.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidInput`] if a Liberapay or Open Collective
+    /// link is on another host or names no account.
+    pub fn new(kind: DonationKind, url: WebUrl) -> Result<Self> {
+        let link = Self { kind, url };
+        if let Some(host) = kind.host() {
+            let on_host = link
+                .url
+                .0
+                .host_str()
+                .is_some_and(|actual| actual.trim_start_matches("www.") == host);
+            if !on_host || link.account().is_none() {
+                return Err(Error::InvalidInput(format!(
+                    "{kind:?} link must be https://{host}/<account>, not '{}'",
+                    link.url
+                )));
+            }
+        }
+        Ok(link)
+    }
+
+    /// Account name on the platform: the first path segment of the URL.
+    ///
+    /// `None` for [`DonationKind::Custom`] links.
+    #[must_use]
+    pub fn account(&self) -> Option<&str> {
+        self.kind.host()?;
+        self.url
+            .0
+            .path_segments()?
+            .find(|segment| !segment.is_empty())
+    }
+}
+
+impl TryFrom<RawDonationLink> for DonationLink {
+    type Error = Error;
+
+    fn try_from(raw: RawDonationLink) -> Result<Self> {
+        Self::new(raw.kind, raw.url)
+    }
+}
+
 /// Application metadata.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct App {
@@ -43,12 +631,36 @@ pub struct App {
     pub id: Uuid,
     /// Package identifier (e.g., "dk.digst.mitid").
     pub package_id: AppId,
-    /// Display name.
-    pub name: String,
-    /// Short description.
-    pub summary: String,
-    /// Full description.
-    pub description: String,
+    /// Display name, per locale.
+    pub name: Localized<String>,
+    /// Short description, per locale.
+    pub summary: Localized<String>,
+    /// Full description, per locale.
+    pub description: Localized<String>,
+    /// Categories the app is listed under.
+    #[serde(default)]
+    pub categories: Vec<Category>,
+    /// Screenshots shown on the app page.
+    #[serde(default)]
+    pub screenshots: Vec<Screenshot>,
+    /// Anti-features users are warned about before installing.
+    #[serde(default)]
+    pub antifeatures: Vec<Antifeature>,
+    /// License the app is distributed under, if known.
+    #[serde(default)]
+    pub license: Option<License>,
+    /// Source code repository.
+    #[serde(default)]
+    pub source_url: Option<WebUrl>,
+    /// Where users report bugs.
+    #[serde(default)]
+    pub issue_tracker_url: Option<WebUrl>,
+    /// Project or publisher web site.
+    #[serde(default)]
+    pub web_url: Option<WebUrl>,
+    /// Donation links, in display order.
+    #[serde(default)]
+    pub donate: Vec<DonationLink>,
     /// Current version code.
     pub version_code: i64,
     /// Current version name.
@@ -59,6 +671,32 @@ pub struct App {
     pub updated_at: DateTime<Utc>,
 }
 
+/// An Android permission requested by an APK.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Permission {
+    /// Permission name (e.g., "android.permission.CAMERA").
+    pub name: String,
+    /// Highest SDK version the permission is requested on, if limited.
+    pub max_sdk: Option<i32>,
+}
+
+/// A screenshot of an application.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Screenshot {
+    /// BCP-47 locale the screenshot was taken in (e.g., "da").
+    pub locale: String,
+    /// URL the image is served from.
+    pub url: String,
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+}
+
+/// Highest Android API level accepted as a `target_sdk`: Android 17.
+pub const MAX_SDK: i32 = 37;
+
 /// Application version information.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppVersion {
@@ -71,17 +709,73 @@ pub struct AppVersion {
     /// Version name (Android versionName).
     pub version_name: String,
     /// SHA-256 hash of the APK.
-    pub sha256: String,
+    pub sha256: Sha256,
     /// Size of the APK in bytes.
     pub size: i64,
     /// Minimum Android SDK version.
     pub min_sdk: i32,
     /// Target Android SDK version.
     pub target_sdk: i32,
+    /// Permissions requested by the APK.
+    #[serde(default)]
+    pub permissions: Vec<Permission>,
+    /// ABIs of the bundled native libraries (e.g., "arm64-v8a"); empty for
+    /// pure-Java APKs.
+    #[serde(default)]
+    pub abis: Vec<String>,
+    /// Whether the APK bundles native libraries.
+    #[serde(default)]
+    pub native_code: bool,
     /// When this version was added.
     pub created_at: DateTime<Utc>,
 }
 
+impl AppVersion {
+    /// Check that `1 <= min_sdk <= target_sdk <= MAX_SDK`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidInput`] naming the SDK levels otherwise.
+    pub fn validate(&self) -> Result<()> {
+        let (min_sdk, target_sdk) = (self.min_sdk, self.target_sdk);
+        if min_sdk < 1 {
+            return Err(Error::InvalidInput(format!(
+                "min_sdk must be at least 1, got {min_sdk}"
+            )));
+        }
+        if min_sdk > target_sdk {
+            return Err(Error::InvalidInput(format!(
+                "min_sdk {min_sdk} is above target_sdk {target_sdk}"
+            )));
+        }
+        if target_sdk > MAX_SDK {
+            return Err(Error::InvalidInput(format!(
+                "target_sdk {target_sdk} is above the highest known SDK {MAX_SDK}"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Order versions by release: by `version_code`, then by `created_at`
+    /// when a version code was published more than once.
+    ///
+    /// Version codes compare numerically, so zero and negative codes sort
+    /// before every positive one.
+    #[must_use]
+    pub fn cmp_release(&self, other: &Self) -> std::cmp::Ordering {
+        self.version_code
+            .cmp(&other.version_code)
+            .then_with(|| self.created_at.cmp(&other.created_at))
+    }
+}
+
+/// The latest of `versions` by [`AppVersion::cmp_release`], or `None` if
+/// there are none.
+#[must_use]
+pub fn latest_version(versions: &[AppVersion]) -> Option<&AppVersion> {
+    versions.iter().max_by(|a, b| a.cmp_release(b))
+}
+
 /// Build status for an application.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -98,6 +792,45 @@ pub enum BuildStatus {
     Cancelled,
 }
 
+impl BuildStatus {
+    /// Whether a build may move from `self` to `next`.
+    ///
+    /// Builds go from `Pending` to `Building` and then to `Success` or
+    /// `Failed`, and can be cancelled until they finish. Finished builds
+    /// never change again.
+    #[must_use]
+    pub const fn can_transition_to(self, next: Self) -> bool {
+        matches!(
+            (self, next),
+            (Self::Pending, Self::Building | Self::Cancelled)
+                | (
+                    Self::Building,
+                    Self::Success | Self::Failed | Self::Cancelled
+                )
+        )
+    }
+
+    /// Move from `self` to `next`, returning the new status.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dk_common::types::BuildStatus;
+    ///
+    /// let status = BuildStatus::Pending.transition(BuildStatus::Building).expect("allowed");
+    /// assert!(status.transition(BuildStatus::Pending).is_err());
+    /// ```
+    pub fn transition(self, next: Self) -> Result<Self> {
+        if self.can_transition_to(next) {
+            Ok(next)
+        } else {
+            Err(Error::InvalidInput(format!(
+                "build status cannot change from {self:?} to {next:?}"
+            )))
+        }
+    }
+}
+
 /// Security scan status.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -114,8 +847,24 @@ pub enum ScanStatus {
     Warning,
 }
 
+impl ScanStatus {
+    /// Lowercase name of the status, as serialized.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Scanning => "scanning",
+            Self::Passed => "passed",
+            Self::Failed => "failed",
+            Self::Warning => "warning",
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use chrono::TimeZone;
+
     use super::*;
 
     #[test]
@@ -125,10 +874,501 @@ mod tests {
         assert_eq!(id.as_str(), "dk.digst.mitid");
     }
 
+    #[test]
+    fn test_app_id_parse_valid() {
+        for id in [
+            "dk.digst.mitid",
+            "com.example",
+            "org.fdroid.fdroid_2",
+            "A.b1",
+        ] {
+            let parsed = AppId::parse(id).expect("valid id");
+            assert_eq!(parsed.as_str(), id);
+        }
+    }
+
+    #[test]
+    fn test_app_id_parse_leading_digit() {
+        let err = AppId::parse("dk.1digst.mitid").expect_err("leading digit");
+        assert!(err.to_string().contains("'1digst'"));
+    }
+
+    #[test]
+    fn test_app_id_parse_double_dot() {
+        let err = AppId::parse("dk.digst..mitid").expect_err("double dot");
+        assert!(matches!(err, Error::InvalidInput(_)));
+        assert!(err.to_string().contains("segment ''"));
+    }
+
+    #[test]
+    fn test_app_id_parse_trailing_dot() {
+        assert!(AppId::parse("dk.digst.mitid.").is_err());
+    }
+
+    #[test]
+    fn test_app_id_parse_rejects_single_segment_and_spaces() {
+        assert!(AppId::parse("mitid").is_err());
+        assert!(AppId::parse("Mit ID").is_err());
+        assert!(AppId::parse("dk.mit id").is_err());
+    }
+
+    fn sample_app() -> App {
+        App {
+            id: Uuid::new_v4(),
+            package_id: AppId::new("dk.digst.mitid"),
+            name: Localized::single("en", "MitID".to_string()),
+            summary: Localized::single("en", "Digital identity".to_string()),
+            description: Localized::single("en", "MitID app".to_string()),
+            categories: vec![],
+            screenshots: vec![],
+            antifeatures: vec![],
+            license: None,
+            source_url: None,
+            issue_tracker_url: None,
+            web_url: None,
+            donate: vec![],
+            version_code: 1,
+            version_name: "1.0".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_category_serde_round_trip() {
+        let mut app = sample_app();
+        app.categories = vec![
+            Category::Security,
+            Category::PhoneSms,
+            Category::PublicServices,
+        ];
+
+        let json = serde_json::to_value(&app).expect("serialize");
+        assert_eq!(
+            json["categories"],
+            serde_json::json!(["Security", "Phone & SMS", "Public Services"])
+        );
+
+        let decoded: App = serde_json::from_value(json).expect("deserialize");
+        assert_eq!(decoded.categories, app.categories);
+    }
+
+    #[test]
+    fn test_category_other_round_trip() {
+        let category: Category = serde_json::from_str("\"Banking\"").expect("deserialize");
+        assert_eq!(category, Category::Other("Banking".to_string()));
+        assert_eq!(
+            serde_json::to_string(&category).expect("serialize"),
+            "\"Banking\""
+        );
+    }
+
+    #[test]
+    fn test_category_rejects_empty() {
+        assert!(serde_json::from_str::<Category>("\"  \"").is_err());
+    }
+
+    #[test]
+    fn test_category_all_round_trips() {
+        for category in Category::all() {
+            let parsed = Category::try_from(category.to_string()).expect("known category");
+            assert_eq!(parsed, category);
+        }
+    }
+
+    #[test]
+    fn test_antifeature_serde_round_trip() {
+        let mut app = sample_app();
+        app.antifeatures = vec![
+            Antifeature::Tracking,
+            Antifeature::NonFreeNet,
+            Antifeature::Nsfw,
+        ];
+
+        let json = serde_json::to_value(&app).expect("serialize");
+        assert_eq!(
+            json["antifeatures"],
+            serde_json::json!(["Tracking", "NonFreeNet", "NSFW"])
+        );
+
+        let decoded: App = serde_json::from_value(json).expect("deserialize");
+        assert_eq!(decoded.antifeatures, app.antifeatures);
+    }
+
+    #[test]
+    fn test_antifeature_from_str() {
+        for antifeature in Antifeature::ALL {
+            assert_eq!(
+                antifeature.as_str().parse::<Antifeature>().expect("code"),
+                antifeature
+            );
+        }
+        assert!("Nsfw".parse::<Antifeature>().is_err());
+        assert!("tracking".parse::<Antifeature>().is_err());
+        assert!(serde_json::from_str::<Antifeature>("\"Spyware\"").is_err());
+    }
+
+    #[test]
+    fn test_app_without_antifeatures_deserializes() {
+        let mut json = serde_json::to_value(sample_app()).expect("serialize");
+        json.as_object_mut().expect("object").remove("antifeatures");
+        let app: App = serde_json::from_value(json).expect("deserialize");
+        assert!(app.antifeatures.is_empty());
+    }
+
+    #[test]
+    fn test_license_accepts_spdx_identifier() {
+        let license = License::parse("GPL-3.0-only").expect("valid license");
+        assert_eq!(license.as_str(), "GPL-3.0-only");
+        assert!(License::parse("MIT").is_ok());
+        assert!(License::parse("EUPL-1.2").is_ok());
+    }
+
+    #[test]
+    fn test_license_rejects_unknown_identifier() {
+        let err = License::parse("GPL-3.0-bogus").expect_err("unknown license");
+        assert!(matches!(err, Error::InvalidInput(_)));
+        // Identifiers are case-sensitive
+        assert!(License::parse("gpl-3.0-only").is_err());
+        assert!(License::parse("").is_err());
+    }
+
+    #[test]
+    fn test_license_accepts_noassertion() {
+        let license = License::parse("NOASSERTION").expect("sentinel");
+        assert_eq!(license.as_str(), License::NOASSERTION);
+    }
+
+    #[test]
+    fn test_license_serde() {
+        let mut app = sample_app();
+        app.license = Some(License::parse("Apache-2.0").expect("license"));
+        let json = serde_json::to_value(&app).expect("serialize");
+        assert_eq!(json["license"], "Apache-2.0");
+
+        let decoded: App = serde_json::from_value(json.clone()).expect("deserialize");
+        assert_eq!(decoded.license, app.license);
+
+        let mut json = json;
+        json["license"] = serde_json::json!("Not-A-License");
+        assert!(serde_json::from_value::<App>(json).is_err());
+    }
+
+    #[test]
+    fn test_web_url_accepts_http_and_https() {
+        let url = WebUrl::parse("https://github.com/digst/mitid-android").expect("https");
+        assert_eq!(url.as_str(), "https://github.com/digst/mitid-android");
+        assert!(WebUrl::parse("http://www.mitid.dk").is_ok());
+    }
+
+    #[test]
+    fn test_web_url_rejects_other_schemes() {
+        for value in [
+            "ftp://example.dk/src",
+            "javascript:alert(1)",
+            "www.mitid.dk",
+            "",
+        ] {
+            let err = WebUrl::parse(value).expect_err(value);
+            assert!(matches!(err, Error::InvalidInput(_)), "{value}");
+        }
+    }
+
+    #[test]
+    fn test_app_rejects_invalid_source_url() {
+        let mut json = serde_json::to_value(sample_app()).expect("serialize");
+        json["source_url"] = serde_json::json!("https://github.com/digst/mitid");
+        let app: App = serde_json::from_value(json.clone()).expect("valid url");
+        assert_eq!(
+            app.source_url.as_ref().map(WebUrl::as_str),
+            Some("https://github.com/digst/mitid")
+        );
+
+        json["source_url"] = serde_json::json!("file:///etc/passwd");
+        assert!(serde_json::from_value::<App>(json).is_err());
+    }
+
+    fn donation(kind: DonationKind, url: &str) -> Result<DonationLink> {
+        DonationLink::new(kind, WebUrl::parse(url)?)
+    }
+
+    #[test]
+    fn test_donation_link_accounts() {
+        let link = donation(DonationKind::Liberapay, "https://liberapay.com/mitid/").expect("link");
+        assert_eq!(link.account(), Some("mitid"));
+        let link = donation(
+            DonationKind::OpenCollective,
+            "https://opencollective.com/fdroid",
+        )
+        .expect("link");
+        assert_eq!(link.account(), Some("fdroid"));
+        let link = donation(DonationKind::Custom, "https://example.dk/donate").expect("link");
+        assert_eq!(link.account(), None);
+    }
+
+    #[test]
+    fn test_donation_link_rejects_wrong_platform() {
+        assert!(donation(DonationKind::Liberapay, "https://example.dk/mitid").is_err());
+        assert!(donation(DonationKind::OpenCollective, "https://opencollective.com/").is_err());
+    }
+
+    #[test]
+    fn test_donation_link_serde() {
+        let mut app = sample_app();
+        app.donate =
+            vec![donation(DonationKind::Liberapay, "https://liberapay.com/mitid").expect("link")];
+        let json = serde_json::to_value(&app).expect("serialize");
+        assert_eq!(
+            json["donate"],
+            serde_json::json!([{"kind": "liberapay", "url": "https://liberapay.com/mitid"}])
+        );
+        let decoded: App = serde_json::from_value(json).expect("deserialize");
+        assert_eq!(decoded.donate, app.donate);
+
+        let invalid = serde_json::json!({"kind": "liberapay", "url": "https://example.dk/x"});
+        assert!(serde_json::from_value::<DonationLink>(invalid).is_err());
+        let invalid = serde_json::json!({"kind": "custom", "url": "ftp://example.dk/x"});
+        assert!(serde_json::from_value::<DonationLink>(invalid).is_err());
+    }
+
+    #[test]
+    fn test_permission_serde() {
+        let permission = Permission {
+            name: "android.permission.CAMERA".to_string(),
+            max_sdk: Some(28),
+        };
+        let json = serde_json::to_value(&permission).expect("serialize");
+        assert_eq!(
+            json,
+            serde_json::json!({"name": "android.permission.CAMERA", "maxSdk": 28})
+        );
+    }
+
+    fn sample_version(version_code: i64, created_at: i64) -> AppVersion {
+        AppVersion {
+            id: Uuid::new_v4(),
+            app_id: Uuid::new_v4(),
+            version_code,
+            version_name: format!("{version_code}.{created_at}"),
+            sha256: Sha256::from_bytes(&[0; 32]),
+            size: 1024,
+            min_sdk: 24,
+            target_sdk: 34,
+            permissions: vec![],
+            abis: vec![],
+            native_code: false,
+            created_at: Utc
+                .timestamp_opt(created_at, 0)
+                .single()
+                .expect("valid timestamp"),
+        }
+    }
+
+    #[test]
+    fn test_app_version_sdk_range_accepted() {
+        let mut version = sample_version(1, 0);
+        assert!(version.validate().is_ok());
+        version.min_sdk = 1;
+        version.target_sdk = 1;
+        assert!(version.validate().is_ok());
+        version.target_sdk = MAX_SDK;
+        assert!(version.validate().is_ok());
+    }
+
+    #[test]
+    fn test_app_version_min_sdk_above_target_rejected() {
+        let mut version = sample_version(1, 0);
+        version.min_sdk = 35;
+        let err = version.validate().expect_err("min above target");
+        assert!(matches!(err, Error::InvalidInput(_)));
+        assert!(err
+            .to_string()
+            .contains("min_sdk 35 is above target_sdk 34"));
+    }
+
+    #[test]
+    fn test_app_version_invalid_sdk_levels_rejected() {
+        for (min_sdk, target_sdk) in [(0, 34), (-1, 34), (-5, -1), (24, MAX_SDK + 1)] {
+            let mut version = sample_version(1, 0);
+            version.min_sdk = min_sdk;
+            version.target_sdk = target_sdk;
+            assert!(
+                matches!(version.validate(), Err(Error::InvalidInput(_))),
+                "{min_sdk}..{target_sdk}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_app_version_empty_permissions() {
+        let version = sample_version(1, 0);
+        let json = serde_json::to_value(&version).expect("serialize");
+        assert_eq!(json["permissions"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_sha256_parse_valid() {
+        let hex = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let hash = Sha256::parse(hex).expect("valid digest");
+        assert_eq!(hash.to_string(), hex);
+        assert_eq!(hash.as_bytes()[..2], [0xe3, 0xb0]);
+        assert_eq!(Sha256::from_bytes(hash.as_bytes()), hash);
+    }
+
+    #[test]
+    fn test_sha256_normalizes_uppercase() {
+        let upper = Sha256::parse(&"AB".repeat(32)).expect("uppercase digest");
+        let lower = Sha256::parse(&"ab".repeat(32)).expect("lowercase digest");
+        assert_eq!(upper, lower);
+        assert_eq!(
+            serde_json::to_value(upper).expect("serialize"),
+            serde_json::json!("ab".repeat(32))
+        );
+    }
+
+    #[test]
+    fn test_sha256_rejects_invalid() {
+        assert!(Sha256::parse(&"ab".repeat(31)).is_err());
+        assert!(Sha256::parse(&"ab".repeat(33)).is_err());
+        assert!(Sha256::parse(&"zz".repeat(32)).is_err());
+        assert!(Sha256::parse(&format!("+a{}", "0".repeat(62))).is_err());
+        // Multi-byte characters must not be split into pairs
+        assert!(Sha256::parse(&format!("{}é", "a".repeat(62))).is_err());
+        assert!(serde_json::from_str::<Sha256>("\"abcd\"").is_err());
+    }
+
+    #[test]
+    fn test_latest_version_out_of_order() {
+        let versions = [
+            sample_version(3, 10),
+            sample_version(12, 20),
+            sample_version(7, 30),
+        ];
+        let latest = latest_version(&versions).expect("latest");
+        assert_eq!(latest.version_code, 12);
+        assert!(latest_version(&[]).is_none());
+    }
+
+    #[test]
+    fn test_latest_version_tie_uses_created_at() {
+        let versions = [
+            sample_version(5, 200),
+            sample_version(5, 300),
+            sample_version(5, 100),
+        ];
+        assert_eq!(
+            latest_version(&versions).expect("latest").version_name,
+            "5.300"
+        );
+    }
+
+    #[test]
+    fn test_latest_version_zero_and_negative_codes() {
+        let versions = [
+            sample_version(0, 300),
+            sample_version(1, 100),
+            sample_version(-4, 400),
+        ];
+        assert_eq!(latest_version(&versions).expect("latest").version_code, 1);
+
+        let mut sorted = versions.to_vec();
+        sorted.sort_by(AppVersion::cmp_release);
+        let codes: Vec<i64> = sorted.iter().map(|version| version.version_code).collect();
+        assert_eq!(codes, [-4, 0, 1]);
+    }
+
+    const BUILD_STATUSES: [BuildStatus; 5] = [
+        BuildStatus::Pending,
+        BuildStatus::Building,
+        BuildStatus::Success,
+        BuildStatus::Failed,
+        BuildStatus::Cancelled,
+    ];
+
+    #[test]
+    fn test_build_status_allowed_transitions() {
+        use BuildStatus::{Building, Cancelled, Failed, Pending, Success};
+
+        let allowed = [
+            (Pending, Building),
+            (Pending, Cancelled),
+            (Building, Success),
+            (Building, Failed),
+            (Building, Cancelled),
+        ];
+        for (from, to) in allowed {
+            assert!(from.can_transition_to(to), "{from:?} -> {to:?}");
+            assert_eq!(from.transition(to).expect("allowed"), to);
+        }
+
+        // Nothing else is allowed
+        let count = BUILD_STATUSES
+            .iter()
+            .flat_map(|from| BUILD_STATUSES.iter().map(move |to| (*from, *to)))
+            .filter(|(from, to)| from.can_transition_to(*to))
+            .count();
+        assert_eq!(count, allowed.len());
+    }
+
+    #[test]
+    fn test_build_status_rejected_transitions() {
+        use BuildStatus::{Building, Cancelled, Failed, Pending, Success};
+
+        for (from, to) in [
+            (Pending, Success),
+            (Pending, Failed),
+            (Pending, Pending),
+            (Building, Pending),
+            (Success, Building),
+            (Failed, Success),
+            (Cancelled, Building),
+        ] {
+            let err = from.transition(to).expect_err("disallowed");
+            assert!(matches!(err, Error::InvalidInput(_)), "{from:?} -> {to:?}");
+        }
+        assert_eq!(
+            Pending
+                .transition(Success)
+                .expect_err("disallowed")
+                .to_string(),
+            "invalid input: build status cannot change from Pending to Success"
+        );
+    }
+
+    #[test]
+    fn test_build_status_terminal_states_go_nowhere() {
+        for terminal in [
+            BuildStatus::Success,
+            BuildStatus::Failed,
+            BuildStatus::Cancelled,
+        ] {
+            for next in BUILD_STATUSES {
+                assert!(
+                    !terminal.can_transition_to(next),
+                    "{terminal:?} -> {next:?}"
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_build_status_serde() {
         let status = BuildStatus::Success;
         let json = serde_json::to_string(&status).expect("serialize");
         assert_eq!(json, "\"success\"");
     }
+
+    #[test]
+    fn test_scan_status_as_str_matches_serde() {
+        for status in [
+            ScanStatus::Pending,
+            ScanStatus::Scanning,
+            ScanStatus::Passed,
+            ScanStatus::Failed,
+            ScanStatus::Warning,
+        ] {
+            let json = serde_json::to_value(status).expect("serialize");
+            assert_eq!(json, status.as_str());
+        }
+    }
 }
@@ -0,0 +1,435 @@
+//! Webhook notifications of publish and scan events.
+//!
+//! Each event is posted as JSON to every configured endpoint. The body is
+//! signed with HMAC-SHA256 and the signature sent as `sha256=<hex>` in
+//! [`SIGNATURE_HEADER`], so receivers can check it came from us. Timeouts,
+//! connection failures, `429` and `5xx` responses are retried with
+//! exponential backoff; other responses are final.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use ring::hmac;
+use serde::Serialize;
+
+use crate::config::WebhookConfig;
+use crate::error::{Error, Result};
+use crate::hash::hex;
+use crate::types::{AppId, ScanStatus};
+
+/// Header carrying the HMAC-SHA256 signature of the request body.
+pub const SIGNATURE_HEADER: &str = "X-DK-Signature";
+
+/// What happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum WebhookEventKind {
+    /// A new APK version was published.
+    #[serde(rename = "app.published")]
+    AppPublished,
+    /// A security scan of an APK version finished.
+    #[serde(rename = "scan.completed")]
+    ScanCompleted,
+}
+
+/// Payload posted to webhook endpoints.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WebhookEvent {
+    /// What happened.
+    pub event: WebhookEventKind,
+    /// Package of the version concerned.
+    pub package_id: AppId,
+    /// Android versionCode of the version concerned.
+    pub version_code: i64,
+    /// `published` for publications, the scan status for scans.
+    pub status: String,
+}
+
+impl WebhookEvent {
+    /// `version_code` of `package_id` was published.
+    #[must_use]
+    pub fn published(package_id: &AppId, version_code: i64) -> Self {
+        Self {
+            event: WebhookEventKind::AppPublished,
+            package_id: package_id.clone(),
+            version_code,
+            status: "published".to_string(),
+        }
+    }
+
+    /// The scan of `version_code` of `package_id` finished with `status`.
+    #[must_use]
+    pub fn scan_completed(package_id: &AppId, version_code: i64, status: ScanStatus) -> Self {
+        Self {
+            event: WebhookEventKind::ScanCompleted,
+            package_id: package_id.clone(),
+            version_code,
+            status: status.as_str().to_string(),
+        }
+    }
+}
+
+/// Why a delivery attempt failed.
+enum Failure {
+    /// The endpoint may accept the event if asked again.
+    Transient(String),
+    /// Asking again will not help.
+    Permanent(String),
+}
+
+/// Sends events to the configured webhook endpoints.
+pub struct Webhooks {
+    client: reqwest::Client,
+    endpoints: Vec<String>,
+    key: hmac::Key,
+    max_attempts: u32,
+    retry_delay: Duration,
+}
+
+impl Webhooks {
+    /// Webhooks described by `config`, reading the signing secret from the
+    /// environment variable it names.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] if endpoints are configured but the secret
+    /// is not set.
+    pub fn from_config(config: &WebhookConfig) -> Result<Self> {
+        if config.endpoints.is_empty() {
+            return Self::with_secret(config, &[]);
+        }
+        let secret = std::env::var(&config.secret_env).map_err(|_| {
+            Error::Config(format!(
+                "webhooks: environment variable {} is not set",
+                config.secret_env
+            ))
+        })?;
+        Self::with_secret(config, secret.as_bytes())
+    }
+
+    /// Webhooks described by `config`, signing with `secret`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] if the HTTP client cannot be set up.
+    pub fn with_secret(config: &WebhookConfig, secret: &[u8]) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()
+            .map_err(|err| Error::Config(format!("webhooks: {err}")))?;
+        Ok(Self {
+            client,
+            endpoints: config.endpoints.clone(),
+            key: hmac::Key::new(hmac::HMAC_SHA256, secret),
+            max_attempts: config.max_attempts.max(1),
+            retry_delay: Duration::from_millis(config.retry_delay_ms),
+        })
+    }
+
+    /// Whether any endpoint is configured.
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        !self.endpoints.is_empty()
+    }
+
+    /// Deliver `event` in the background, logging endpoints that never
+    /// accept it.
+    pub fn notify(self: &Arc<Self>, event: WebhookEvent) {
+        if !self.is_enabled() {
+            return;
+        }
+        let webhooks = Arc::clone(self);
+        tokio::spawn(async move {
+            // Failures are logged per endpoint by `deliver`
+            let _ = webhooks.deliver(&event).await;
+        });
+    }
+
+    /// Deliver `event` to every endpoint, retrying failed deliveries.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Webhook`] if any endpoint did not accept the event;
+    /// the others still receive it.
+    pub async fn deliver(&self, event: &WebhookEvent) -> Result<()> {
+        let body = serde_json::to_vec(event)
+            .map_err(|err| Error::Internal(format!("webhook payload: {err}")))?;
+        let signature = signature(&self.key, &body);
+
+        let deliveries = self
+            .endpoints
+            .iter()
+            .map(|endpoint| self.deliver_to(endpoint, &body, &signature));
+        let mut failed = Vec::new();
+        for (endpoint, result) in self
+            .endpoints
+            .iter()
+            .zip(futures_util::future::join_all(deliveries).await)
+        {
+            if let Err(err) = result {
+                tracing::error!(
+                    endpoint = %endpoint,
+                    event = ?event.event,
+                    package_id = %event.package_id,
+                    version_code = event.version_code,
+                    error = %err,
+                    "Webhook delivery failed"
+                );
+                failed.push(endpoint.as_str());
+            }
+        }
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Webhook(format!(
+                "not delivered to {}",
+                failed.join(", ")
+            )))
+        }
+    }
+
+    /// Post `body` to `endpoint` until it is accepted, a response shows
+    /// retrying is pointless, or the attempts run out.
+    async fn deliver_to(&self, endpoint: &str, body: &[u8], signature: &str) -> Result<()> {
+        let mut delay = self.retry_delay;
+        let mut attempt = 1;
+        loop {
+            match self.post(endpoint, body, signature).await {
+                Ok(()) => return Ok(()),
+                Err(Failure::Transient(reason)) if attempt < self.max_attempts => {
+                    tracing::warn!(
+                        endpoint,
+                        attempt,
+                        %reason,
+                        "Webhook delivery failed, retrying in {}ms",
+                        delay.as_millis()
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay = delay.saturating_mul(2);
+                    attempt += 1;
+                }
+                Err(Failure::Transient(reason)) => {
+                    return Err(Error::Webhook(format!("{reason} after {attempt} attempts")));
+                }
+                Err(Failure::Permanent(reason)) => return Err(Error::Webhook(reason)),
+            }
+        }
+    }
+
+    async fn post(
+        &self,
+        endpoint: &str,
+        body: &[u8],
+        signature: &str,
+    ) -> std::result::Result<(), Failure> {
+        let response = self
+            .client
+            .post(endpoint)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, signature)
+            .body(body.to_vec())
+            .send()
+            .await
+            .map_err(|err| Failure::Transient(format!("request failed: {err}")))?;
+
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            Err(Failure::Transient(format!("endpoint returned {status}")))
+        } else {
+            Err(Failure::Permanent(format!("endpoint returned {status}")))
+        }
+    }
+}
+
+impl Default for Webhooks {
+    /// Webhooks with no endpoints, which send nothing.
+    fn default() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoints: Vec::new(),
+            key: hmac::Key::new(hmac::HMAC_SHA256, &[]),
+            max_attempts: 1,
+            retry_delay: Duration::ZERO,
+        }
+    }
+}
+
+/// Value of [`SIGNATURE_HEADER`] for `body`.
+fn signature(key: &hmac::Key, body: &[u8]) -> String {
+    format!("sha256={}", hex(hmac::sign(key, body).as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::*;
+
+    const SECRET: &[u8] = b"webhook-test-secret";
+
+    /// A request received by [`serve`].
+    struct Received {
+        signature: Option<String>,
+        body: Vec<u8>,
+    }
+
+    /// Start an HTTP server answering one request with each of `statuses`
+    /// in turn, returning its URL and the requests it received.
+    async fn serve(statuses: Vec<u16>) -> (String, Arc<Mutex<Vec<Received>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let url = format!("http://{}/hooks", listener.local_addr().expect("address"));
+        let received = Arc::new(Mutex::new(Vec::new()));
+
+        let log = Arc::clone(&received);
+        tokio::spawn(async move {
+            for status in statuses {
+                let (mut stream, _) = listener.accept().await.expect("accept");
+                let request = read_request(&mut stream).await;
+                log.lock().expect("received").push(request);
+                let response = format!(
+                    "HTTP/1.1 {status} Status\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+                );
+                stream
+                    .write_all(response.as_bytes())
+                    .await
+                    .expect("respond");
+            }
+        });
+        (url, received)
+    }
+
+    async fn read_request(stream: &mut TcpStream) -> Received {
+        let mut data = Vec::new();
+        let mut buf = [0; 4096];
+        let head_len = loop {
+            let read = stream.read(&mut buf).await.expect("read");
+            assert!(read > 0, "connection closed mid-request");
+            data.extend_from_slice(&buf[..read]);
+            if let Some(end) = data.windows(4).position(|window| window == b"\r\n\r\n") {
+                break end + 4;
+            }
+        };
+
+        let head = String::from_utf8_lossy(&data[..head_len]).into_owned();
+        let header = |name: &str| {
+            head.lines().find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                key.eq_ignore_ascii_case(name)
+                    .then(|| value.trim().to_string())
+            })
+        };
+        let length: usize =
+            header("content-length").map_or(0, |value| value.parse().expect("content-length"));
+        while data.len() < head_len + length {
+            let read = stream.read(&mut buf).await.expect("read body");
+            assert!(read > 0, "connection closed mid-body");
+            data.extend_from_slice(&buf[..read]);
+        }
+
+        Received {
+            signature: header(SIGNATURE_HEADER),
+            body: data[head_len..head_len + length].to_vec(),
+        }
+    }
+
+    fn webhooks(url: &str, max_attempts: u32) -> Webhooks {
+        let config = WebhookConfig {
+            endpoints: vec![url.to_string()],
+            max_attempts,
+            retry_delay_ms: 1,
+            ..WebhookConfig::default()
+        };
+        Webhooks::with_secret(&config, SECRET).expect("webhooks")
+    }
+
+    fn published() -> WebhookEvent {
+        WebhookEvent::published(&AppId::new("dk.digst.mitid"), 7)
+    }
+
+    #[tokio::test]
+    async fn test_delivers_signed_payload() {
+        let (url, received) = serve(vec![204]).await;
+
+        webhooks(&url, 3)
+            .deliver(&published())
+            .await
+            .expect("delivered");
+
+        let received = std::mem::take(&mut *received.lock().expect("received"));
+        assert_eq!(received.len(), 1);
+        let payload: serde_json::Value =
+            serde_json::from_slice(&received[0].body).expect("JSON body");
+        assert_eq!(
+            payload,
+            serde_json::json!({
+                "event": "app.published",
+                "package_id": "dk.digst.mitid",
+                "version_code": 7,
+                "status": "published",
+            })
+        );
+
+        let signature = received[0].signature.as_deref().expect("signature header");
+        let hex = signature.strip_prefix("sha256=").expect("sha256= prefix");
+        let tag: Vec<u8> = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("hex"))
+            .collect();
+        let key = hmac::Key::new(hmac::HMAC_SHA256, SECRET);
+        assert!(hmac::verify(&key, &received[0].body, &tag).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_server_error_is_retried() {
+        let (url, received) = serve(vec![500, 200]).await;
+
+        webhooks(&url, 3)
+            .deliver(&published())
+            .await
+            .expect("delivered on retry");
+
+        let received = std::mem::take(&mut *received.lock().expect("received"));
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0].body, received[1].body);
+        assert_eq!(received[0].signature, received[1].signature);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let (url, received) = serve(vec![503, 503, 200]).await;
+
+        let result = webhooks(&url, 2).deliver(&published()).await;
+
+        assert!(matches!(result, Err(Error::Webhook(_))));
+        assert_eq!(received.lock().expect("received").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_client_error_is_not_retried() {
+        let (url, received) = serve(vec![400, 200]).await;
+
+        let result = webhooks(&url, 3).deliver(&published()).await;
+
+        assert!(matches!(result, Err(Error::Webhook(_))));
+        assert_eq!(received.lock().expect("received").len(), 1);
+    }
+
+    #[test]
+    fn test_scan_completed_carries_scan_status() {
+        let package_id = AppId::new("dk.digst.mitid");
+        let event = WebhookEvent::scan_completed(&package_id, 7, ScanStatus::Failed);
+        let payload = serde_json::to_value(event).expect("serialize");
+        assert_eq!(payload["event"], "scan.completed");
+        assert_eq!(payload["status"], "failed");
+    }
+
+    #[test]
+    fn test_disabled_without_endpoints() {
+        let webhooks = Webhooks::from_config(&WebhookConfig::default()).expect("webhooks");
+        assert!(!webhooks.is_enabled());
+    }
+}
@@ -0,0 +1,339 @@
+//! APK archive access and extracted metadata.
+
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
+
+use chrono::Utc;
+use dk_common::config::ScannerConfig;
+use dk_common::hash;
+use dk_common::types::{AppId, AppVersion, Permission, Sha256};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+use crate::error::{ScanError, ScanResult};
+use crate::manifest::AndroidManifest;
+
+/// Path of the binary manifest inside an APK.
+pub const MANIFEST_ENTRY: &str = "AndroidManifest.xml";
+
+/// Metadata extracted from an APK.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApkMetadata {
+    /// Application package name.
+    pub package: AppId,
+    /// Android versionCode.
+    pub version_code: i64,
+    /// Android versionName.
+    pub version_name: String,
+    /// Minimum Android SDK version.
+    pub min_sdk: i32,
+    /// Target Android SDK version.
+    pub target_sdk: i32,
+    /// Requested permissions.
+    pub permissions: Vec<Permission>,
+    /// ABIs with native libraries under `lib/`, sorted; empty for pure-Java
+    /// APKs.
+    pub abis: Vec<String>,
+    /// SHA-256 of the APK file.
+    pub sha256: Sha256,
+    /// Size of the APK in bytes.
+    pub size: i64,
+}
+
+impl ApkMetadata {
+    /// Build the [`AppVersion`] record for this APK under application `app_id`.
+    #[must_use]
+    pub fn into_app_version(self, app_id: Uuid) -> AppVersion {
+        AppVersion {
+            id: Uuid::new_v4(),
+            app_id,
+            version_code: self.version_code,
+            version_name: self.version_name,
+            sha256: self.sha256,
+            size: self.size,
+            min_sdk: self.min_sdk,
+            target_sdk: self.target_sdk,
+            permissions: self.permissions,
+            native_code: !self.abis.is_empty(),
+            abis: self.abis,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// Read the manifest and file digest of the APK at `path`, within the
+/// archive limits in `config`.
+pub(crate) fn inspect(path: &Path, config: &ScannerConfig) -> ScanResult<ApkMetadata> {
+    let mut apk = Apk::open(path, config)?;
+    let manifest = apk
+        .read(MANIFEST_ENTRY)?
+        .ok_or_else(|| ScanError::InvalidApk(format!("{MANIFEST_ENTRY} missing")))?;
+    let manifest = AndroidManifest::parse(&manifest)?;
+    let abis = native_abis(apk.file_names());
+    let (sha256, size) = sha256_file(path)?;
+
+    Ok(ApkMetadata {
+        package: manifest.package,
+        version_code: manifest.version_code,
+        version_name: manifest.version_name,
+        min_sdk: manifest.min_sdk,
+        target_sdk: manifest.target_sdk,
+        permissions: manifest.permissions,
+        abis,
+        sha256,
+        size,
+    })
+}
+
+/// ABIs with native libraries (`lib/<abi>/*.so`) among the entry `names`,
+/// sorted and deduplicated.
+#[allow(clippy::case_sensitive_file_extension_comparisons)] // Android matches exactly
+fn native_abis<'a>(names: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let abis: BTreeSet<&str> = names
+        .filter_map(|name| {
+            let (abi, library) = name.strip_prefix("lib/")?.split_once('/')?;
+            (!abi.is_empty() && library.ends_with(".so") && !library.contains('/')).then_some(abi)
+        })
+        .collect();
+    abis.into_iter().map(ToString::to_string).collect()
+}
+
+/// An opened APK archive.
+pub(crate) struct Apk {
+    archive: ZipArchive<BufReader<File>>,
+}
+
+impl Apk {
+    /// Open the APK at `path`, rejecting archives whose entries exceed the
+    /// limits in `config` or escape the archive root.
+    pub(crate) fn open(path: &Path, config: &ScannerConfig) -> ScanResult<Self> {
+        let invalid =
+            |message: String| ScanError::InvalidApk(format!("{}: {message}", path.display()));
+        let file = File::open(path).map_err(|err| io_error(path, &err))?;
+        let mut archive =
+            ZipArchive::new(BufReader::new(file)).map_err(|err| invalid(err.to_string()))?;
+        check_entries(&mut archive, config).map_err(invalid)?;
+        Ok(Self { archive })
+    }
+
+    /// Names of all entries in the archive.
+    pub(crate) fn file_names(&self) -> impl Iterator<Item = &str> {
+        self.archive.file_names()
+    }
+
+    /// Names of the DEX files (`classes.dex`, `classes2.dex`, ...), in order.
+    #[allow(clippy::case_sensitive_file_extension_comparisons)] // Android matches exactly
+    pub(crate) fn dex_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .file_names()
+            .filter(|name| {
+                name.starts_with("classes") && name.ends_with(".dex") && !name.contains('/')
+            })
+            .map(ToString::to_string)
+            .collect();
+        // classes10.dex sorts before classes2.dex lexically; order by length first
+        names.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+        names
+    }
+
+    /// Read entry `name`, or `None` if the archive has no such entry.
+    pub(crate) fn read(&mut self, name: &str) -> ScanResult<Option<Vec<u8>>> {
+        let mut entry = match self.archive.by_name(name) {
+            Ok(entry) => entry,
+            Err(ZipError::FileNotFound) => return Ok(None),
+            Err(err) => return Err(ScanError::InvalidApk(format!("{name}: {err}"))),
+        };
+
+        // Sizes were checked against the limits on opening; never read past
+        // them, even if the entry decompresses to more than it declares
+        let invalid = |err: std::io::Error| ScanError::InvalidApk(format!("{name}: {err}"));
+        let declared = entry.size();
+        let mut bytes = Vec::new();
+        Read::by_ref(&mut entry)
+            .take(declared)
+            .read_to_end(&mut bytes)
+            .map_err(invalid)?;
+        if entry.read(&mut [0; 1]).map_err(invalid)? > 0 {
+            return Err(ScanError::InvalidApk(format!(
+                "{name}: larger than its declared size of {declared} bytes"
+            )));
+        }
+        Ok(Some(bytes))
+    }
+}
+
+/// Check the central directory of `archive` against the limits in `config`,
+/// before any entry is decompressed.
+fn check_entries<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    config: &ScannerConfig,
+) -> Result<(), String> {
+    if archive.len() > config.max_zip_entries {
+        return Err(format!(
+            "{} entries, above the limit of {}",
+            archive.len(),
+            config.max_zip_entries
+        ));
+    }
+
+    let mut total = 0u64;
+    for index in 0..archive.len() {
+        let entry = archive.by_index_raw(index).map_err(|err| err.to_string())?;
+        let name = entry.name();
+        if escapes_root(name) {
+            return Err(format!("entry {name:?} escapes the archive"));
+        }
+        if entry.size() > config.max_entry_size {
+            return Err(format!(
+                "{name} is {} bytes uncompressed, above the limit of {}",
+                entry.size(),
+                config.max_entry_size
+            ));
+        }
+        total = total.saturating_add(entry.size());
+        if total > config.max_uncompressed_size {
+            return Err(format!(
+                "entries exceed the uncompressed size limit of {} bytes",
+                config.max_uncompressed_size
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Whether entry `name` is absolute or has a `..` component.
+fn escapes_root(name: &str) -> bool {
+    name.starts_with(['/', '\\']) || name.split(['/', '\\']).any(|component| component == "..")
+}
+
+/// Stream the file at `path` through SHA-256, returning the digest and size.
+pub(crate) fn sha256_file(path: &Path) -> ScanResult<(Sha256, i64)> {
+    let size = std::fs::metadata(path)
+        .map_err(|err| io_error(path, &err))?
+        .len();
+    let size = i64::try_from(size)
+        .map_err(|_| ScanError::InvalidApk(format!("{}: file too large", path.display())))?;
+    let sha256 = hash::sha256_file(path).map_err(|err| match err {
+        dk_common::Error::NotFound(_) => ScanError::ApkNotFound(path.display().to_string()),
+        err => ScanError::InvalidApk(err.to_string()),
+    })?;
+    Ok((sha256, size))
+}
+
+/// Map an I/O error on `path` to a scan error.
+pub(crate) fn io_error(path: &Path, err: &std::io::Error) -> ScanError {
+    match err.kind() {
+        std::io::ErrorKind::NotFound => ScanError::ApkNotFound(path.display().to_string()),
+        _ => ScanError::InvalidApk(format!("{}: {err}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::write::FileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+
+    use super::*;
+    use crate::fixtures::{ApkBuilder, TempApk};
+
+    fn open(apk: &[u8], config: &ScannerConfig) -> ScanResult<Apk> {
+        Apk::open(TempApk::write(apk).path(), config)
+    }
+
+    fn limits(
+        max_zip_entries: usize,
+        max_entry_size: u64,
+        max_uncompressed_size: u64,
+    ) -> ScannerConfig {
+        ScannerConfig {
+            max_zip_entries,
+            max_entry_size,
+            max_uncompressed_size,
+            ..ScannerConfig::default()
+        }
+    }
+
+    /// An archive with one deflated entry of `size` zero bytes, compressing
+    /// to a tiny fraction of that.
+    fn zip_bomb(size: usize) -> Vec<u8> {
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        writer
+            .start_file("assets/bomb.bin", options)
+            .expect("start entry");
+        writer.write_all(&vec![0; size]).expect("write entry");
+        writer.finish().expect("finish zip").into_inner()
+    }
+
+    #[test]
+    fn test_entries_within_limits_are_read() {
+        let apk = ApkBuilder::new().entry("assets/data.bin", &[7; 64]).build();
+        let mut apk = open(&apk, &limits(1, 64, 64)).expect("open");
+        assert_eq!(
+            apk.read("assets/data.bin").expect("read"),
+            Some(vec![7; 64])
+        );
+    }
+
+    #[test]
+    fn test_high_ratio_entry_is_rejected() {
+        let bomb = zip_bomb(4 * 1024 * 1024);
+        assert!(bomb.len() < 64 * 1024);
+        let err = open(&bomb, &limits(16, 1024 * 1024, u64::MAX))
+            .err()
+            .expect("bomb rejected");
+        assert!(matches!(err, ScanError::InvalidApk(message) if message.contains("bomb.bin")));
+    }
+
+    #[test]
+    fn test_total_uncompressed_size_is_limited() {
+        let apk = ApkBuilder::new()
+            .entry("classes.dex", &[0; 600])
+            .entry("classes2.dex", &[0; 600])
+            .build();
+        assert!(open(&apk, &limits(16, 1024, 1200)).is_ok());
+        assert!(matches!(
+            open(&apk, &limits(16, 1024, 1000)),
+            Err(ScanError::InvalidApk(_))
+        ));
+    }
+
+    #[test]
+    fn test_entry_count_is_limited() {
+        let apk = ApkBuilder::new()
+            .entry("a", b"a")
+            .entry("b", b"b")
+            .entry("c", b"c")
+            .build();
+        assert!(matches!(
+            open(&apk, &limits(2, 1024, 1024)),
+            Err(ScanError::InvalidApk(_))
+        ));
+    }
+
+    #[test]
+    fn test_path_traversal_entry_is_rejected() {
+        for name in [
+            "../evil.so",
+            "lib/arm64-v8a/../../../evil.so",
+            "/etc/evil",
+            "..\\evil",
+        ] {
+            let apk = ApkBuilder::new().entry(name, b"evil").build();
+            assert!(
+                matches!(
+                    open(&apk, &ScannerConfig::default()),
+                    Err(ScanError::InvalidApk(message)) if message.contains("escapes")
+                ),
+                "{name} accepted"
+            );
+        }
+        assert!(!escapes_root("lib/arm64-v8a/libfoo..so"));
+    }
+}
@@ -0,0 +1,250 @@
+//! Vulnerable bundled library detection.
+//!
+//! Lists the native libraries (`.so`) and JARs bundled in an APK, hashes
+//! them, and looks the hashes up in a database of known-vulnerable builds.
+//! Each match is reported as a finding carrying the CVE ID and the severity
+//! recorded in the database.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use dk_common::config::ScannerConfig;
+use dk_common::hash::sha256_bytes;
+use dk_common::types::Sha256;
+use serde::{Deserialize, Serialize};
+
+use crate::apk::Apk;
+use crate::error::{ScanError, ScanResult};
+use crate::finding::{ScanFinding, Severity};
+
+/// Kind of a library bundled in an APK.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LibraryKind {
+    /// Native shared object, e.g. `lib/arm64-v8a/libcrypto.so`.
+    Native,
+    /// Java archive, e.g. `assets/plugin.jar`.
+    Jar,
+}
+
+impl LibraryKind {
+    /// Kind of the archive entry `name`, if it is a bundled library.
+    #[allow(clippy::case_sensitive_file_extension_comparisons)] // As Android loads them
+    fn of(name: &str) -> Option<Self> {
+        if name.ends_with('/') {
+            None
+        } else if name.ends_with(".so") {
+            Some(Self::Native)
+        } else if name.ends_with(".jar") {
+            Some(Self::Jar)
+        } else {
+            None
+        }
+    }
+}
+
+/// A library bundled in an APK.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundledLibrary {
+    /// Path of the entry inside the APK.
+    pub path: String,
+    /// Kind of library.
+    pub kind: LibraryKind,
+    /// SHA-256 of the entry's contents.
+    pub sha256: Sha256,
+}
+
+/// A known-vulnerable library build.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Vulnerability {
+    /// SHA-256 of the vulnerable library file.
+    pub sha256: Sha256,
+    /// Library name and version, e.g. `OpenSSL 1.1.1k`.
+    pub library: String,
+    /// CVE identifier, e.g. `CVE-2021-3711`.
+    pub cve: String,
+    /// Severity of the vulnerability.
+    pub severity: Severity,
+}
+
+/// Known-vulnerable libraries, indexed by file hash.
+///
+/// Loaded from a JSON array of [`Vulnerability`] records.
+#[derive(Debug, Clone, Default)]
+pub struct VulnerabilityDb {
+    by_hash: HashMap<Sha256, Vec<Vulnerability>>,
+}
+
+impl VulnerabilityDb {
+    /// Build a database from `vulnerabilities`.
+    #[must_use]
+    pub fn new(vulnerabilities: impl IntoIterator<Item = Vulnerability>) -> Self {
+        let mut by_hash: HashMap<Sha256, Vec<Vulnerability>> = HashMap::new();
+        for vulnerability in vulnerabilities {
+            by_hash
+                .entry(vulnerability.sha256)
+                .or_default()
+                .push(vulnerability);
+        }
+        Self { by_hash }
+    }
+
+    /// Parse a database from its JSON representation.
+    pub fn from_json(json: &str) -> ScanResult<Self> {
+        let vulnerabilities: Vec<Vulnerability> = serde_json::from_str(json)
+            .map_err(|err| ScanError::Config(format!("vulnerability database: {err}")))?;
+        Ok(Self::new(vulnerabilities))
+    }
+
+    /// Load a database from the JSON file at `path`.
+    pub fn load(path: &Path) -> ScanResult<Self> {
+        let json = std::fs::read_to_string(path).map_err(|err| {
+            ScanError::Config(format!("vulnerability database {}: {err}", path.display()))
+        })?;
+        Self::from_json(&json)
+    }
+
+    /// Vulnerabilities of the library with hash `sha256`.
+    #[must_use]
+    pub fn lookup(&self, sha256: &Sha256) -> &[Vulnerability] {
+        self.by_hash.get(sha256).map_or(&[], Vec::as_slice)
+    }
+
+    /// Number of vulnerability records.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.by_hash.values().map(Vec::len).sum()
+    }
+
+    /// Whether the database has no records.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.by_hash.is_empty()
+    }
+}
+
+/// List the native libraries and JARs bundled in the APK at `path`, within
+/// the archive limits in `config`.
+pub fn list_libraries(path: &Path, config: &ScannerConfig) -> ScanResult<Vec<BundledLibrary>> {
+    let mut apk = Apk::open(path, config)?;
+    let mut entries: Vec<(String, LibraryKind)> = apk
+        .file_names()
+        .filter_map(|name| LibraryKind::of(name).map(|kind| (name.to_string(), kind)))
+        .collect();
+    entries.sort();
+
+    let mut libraries = Vec::with_capacity(entries.len());
+    for (name, kind) in entries {
+        let data = apk
+            .read(&name)?
+            .ok_or_else(|| ScanError::InvalidApk(format!("{name} missing")))?;
+        let sha256 = sha256_bytes(&data);
+        libraries.push(BundledLibrary {
+            path: name,
+            kind,
+            sha256,
+        });
+    }
+    Ok(libraries)
+}
+
+/// Findings for the `libraries` listed in `db`, one per vulnerability.
+#[must_use]
+pub fn check_libraries(libraries: &[BundledLibrary], db: &VulnerabilityDb) -> Vec<ScanFinding> {
+    libraries
+        .iter()
+        .flat_map(|library| {
+            db.lookup(&library.sha256).iter().map(|vulnerability| {
+                ScanFinding::new(
+                    vulnerability.severity,
+                    "dependency.vulnerable",
+                    format!(
+                        "{}: {} is {}",
+                        vulnerability.cve, library.path, vulnerability.library
+                    ),
+                )
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{ApkBuilder, TempApk};
+
+    /// Contents of the library seeded into the test database.
+    const VULNERABLE: &[u8] = b"\x7fELF libcrypto 1.1.1k";
+
+    fn seeded_db() -> VulnerabilityDb {
+        let hash = sha256_bytes(VULNERABLE);
+        VulnerabilityDb::from_json(&format!(
+            r#"[
+                {{"sha256": "{hash}", "library": "OpenSSL 1.1.1k", "cve": "CVE-2021-3711", "severity": "critical"}},
+                {{"sha256": "{hash}", "library": "OpenSSL 1.1.1k", "cve": "CVE-2021-3712", "severity": "high"}}
+            ]"#
+        ))
+        .expect("database")
+    }
+
+    fn fixture_apk() -> TempApk {
+        TempApk::write(
+            &ApkBuilder::new()
+                .entry("classes.dex", b"dex\n035\0")
+                .entry("lib/arm64-v8a/libcrypto.so", VULNERABLE)
+                .entry("lib/arm64-v8a/libapp.so", b"\x7fELF app")
+                .entry("assets/plugin.jar", b"PK jar")
+                .build(),
+        )
+    }
+
+    #[test]
+    fn test_lists_bundled_libraries() {
+        let libraries =
+            list_libraries(fixture_apk().path(), &ScannerConfig::default()).expect("libraries");
+        let listed: Vec<_> = libraries
+            .iter()
+            .map(|library| (library.path.as_str(), library.kind))
+            .collect();
+        assert_eq!(
+            listed,
+            [
+                ("assets/plugin.jar", LibraryKind::Jar),
+                ("lib/arm64-v8a/libapp.so", LibraryKind::Native),
+                ("lib/arm64-v8a/libcrypto.so", LibraryKind::Native),
+            ]
+        );
+        assert_eq!(libraries[2].sha256, sha256_bytes(VULNERABLE));
+    }
+
+    #[test]
+    fn test_flags_known_vulnerable_library() {
+        let libraries =
+            list_libraries(fixture_apk().path(), &ScannerConfig::default()).expect("libraries");
+        let findings = check_libraries(&libraries, &seeded_db());
+
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].severity, Severity::Critical);
+        assert_eq!(findings[0].code, "dependency.vulnerable");
+        assert_eq!(
+            findings[0].message,
+            "CVE-2021-3711: lib/arm64-v8a/libcrypto.so is OpenSSL 1.1.1k"
+        );
+        assert!(findings[1].message.starts_with("CVE-2021-3712"));
+    }
+
+    #[test]
+    fn test_empty_database_flags_nothing() {
+        let libraries =
+            list_libraries(fixture_apk().path(), &ScannerConfig::default()).expect("libraries");
+        assert!(check_libraries(&libraries, &VulnerabilityDb::default()).is_empty());
+    }
+
+    #[test]
+    fn test_rejects_malformed_database() {
+        assert!(matches!(
+            VulnerabilityDb::from_json(r#"[{"sha256": "abc"}]"#),
+            Err(ScanError::Config(_))
+        ));
+    }
+}
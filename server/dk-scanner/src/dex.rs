@@ -0,0 +1,191 @@
+//! Minimal DEX file reading: just enough of the header and ID tables to list
+//! the classes an APK references.
+
+use crate::error::{ScanError, ScanResult};
+
+/// Size of the DEX header.
+pub const HEADER_SIZE: usize = 0x70;
+
+/// Offset of `string_ids_size` in the header.
+const STRING_IDS_SIZE: usize = 56;
+/// Offset of `type_ids_size` in the header.
+const TYPE_IDS_SIZE: usize = 64;
+/// Size of a `type_id_item`.
+const TYPE_ID_ITEM_SIZE: usize = 4;
+/// Offset of `method_ids_size` in the header.
+pub const METHOD_IDS_SIZE: usize = 88;
+/// Size of a `method_id_item`.
+const METHOD_ID_ITEM_SIZE: usize = 8;
+
+/// A parsed DEX file.
+pub struct Dex<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Dex<'a> {
+    /// Check the DEX magic and wrap `data`.
+    pub fn parse(data: &'a [u8]) -> ScanResult<Self> {
+        if data.len() < HEADER_SIZE || !data.starts_with(b"dex\n") {
+            return Err(invalid("not a DEX file"));
+        }
+        Ok(Self { data })
+    }
+
+    /// Java class names (`com.example.Foo`) of every class type the file
+    /// references, including classes it merely calls into.
+    pub fn class_names(&self) -> ScanResult<Vec<String>> {
+        let (count, offset) = self.bounded_table(TYPE_IDS_SIZE, TYPE_ID_ITEM_SIZE)?;
+        let mut names = Vec::with_capacity(count);
+        for index in 0..count {
+            let string_index = self.u32_at(offset + index * TYPE_ID_ITEM_SIZE)?;
+            let descriptor = self.string(string_index)?;
+            if let Some(class) = descriptor
+                .strip_prefix('L')
+                .and_then(|rest| rest.strip_suffix(';'))
+            {
+                names.push(class.replace('/', "."));
+            }
+        }
+        Ok(names)
+    }
+
+    /// Number of methods the file references, whether defined in it or
+    /// called into; the quantity limited to 65,536 per DEX file.
+    pub fn method_count(&self) -> ScanResult<usize> {
+        let (count, _) = self.bounded_table(METHOD_IDS_SIZE, METHOD_ID_ITEM_SIZE)?;
+        Ok(count)
+    }
+
+    /// The string at `index` in the string ID table.
+    fn string(&self, index: u32) -> ScanResult<String> {
+        let (count, offset) = self.table(STRING_IDS_SIZE)?;
+        let index = usize::try_from(index).map_err(|_| invalid("string index out of range"))?;
+        if index >= count {
+            return Err(invalid("string index out of range"));
+        }
+        let mut position = self.usize_at(offset + index * 4)?;
+
+        // uleb128 UTF-16 length, then NUL-terminated MUTF-8 bytes
+        while self.byte_at(position)? & 0x80 != 0 {
+            position += 1;
+        }
+        position += 1;
+        let bytes = self
+            .data
+            .get(position..)
+            .ok_or_else(|| invalid("string data out of range"))?;
+        let end = bytes
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or_else(|| invalid("unterminated string"))?;
+        Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+    }
+
+    /// Size and offset of the ID table described at header offset `field`.
+    fn table(&self, field: usize) -> ScanResult<(usize, usize)> {
+        Ok((self.usize_at(field)?, self.usize_at(field + 4)?))
+    }
+
+    /// Like [`Self::table`], checking that `item_size`-byte entries of the
+    /// table lie within the file.
+    fn bounded_table(&self, field: usize, item_size: usize) -> ScanResult<(usize, usize)> {
+        let (count, offset) = self.table(field)?;
+        let end = count
+            .checked_mul(item_size)
+            .and_then(|size| size.checked_add(offset))
+            .ok_or_else(|| invalid("ID table out of range"))?;
+        if end > self.data.len() {
+            return Err(invalid("ID table out of range"));
+        }
+        Ok((count, offset))
+    }
+
+    fn byte_at(&self, offset: usize) -> ScanResult<u8> {
+        self.data
+            .get(offset)
+            .copied()
+            .ok_or_else(|| invalid("offset out of range"))
+    }
+
+    fn u32_at(&self, offset: usize) -> ScanResult<u32> {
+        self.data
+            .get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .ok_or_else(|| invalid("offset out of range"))
+    }
+
+    fn usize_at(&self, offset: usize) -> ScanResult<usize> {
+        usize::try_from(self.u32_at(offset)?).map_err(|_| invalid("offset out of range"))
+    }
+}
+
+fn invalid(message: &str) -> ScanError {
+    ScanError::InvalidApk(format!("DEX: {message}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{dex_with_classes, dex_with_methods};
+
+    #[test]
+    fn test_class_names() {
+        let data = dex_with_classes(&["com.example.Main", "androidx.core.app.ActivityCompat"]);
+        let dex = Dex::parse(&data).expect("parse");
+        assert_eq!(
+            dex.class_names().expect("classes"),
+            ["com.example.Main", "androidx.core.app.ActivityCompat"]
+        );
+    }
+
+    #[test]
+    fn test_method_count() {
+        let data = dex_with_methods(3);
+        assert_eq!(
+            Dex::parse(&data)
+                .expect("parse")
+                .method_count()
+                .expect("count"),
+            3
+        );
+
+        let data = dex_with_classes(&["com.example.Main"]);
+        assert_eq!(
+            Dex::parse(&data)
+                .expect("parse")
+                .method_count()
+                .expect("count"),
+            0
+        );
+    }
+
+    #[test]
+    fn test_truncated_method_table() {
+        let data = dex_with_methods(3);
+        let dex = Dex::parse(&data[..data.len() - 1]).expect("header");
+        assert!(matches!(dex.method_count(), Err(ScanError::InvalidApk(_))));
+    }
+
+    #[test]
+    fn test_oversized_type_table() {
+        let mut data = dex_with_classes(&["com.example.Main"]);
+        data[TYPE_IDS_SIZE..TYPE_IDS_SIZE + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let dex = Dex::parse(&data).expect("header");
+        assert!(matches!(dex.class_names(), Err(ScanError::InvalidApk(_))));
+    }
+
+    #[test]
+    fn test_rejects_non_dex() {
+        assert!(matches!(
+            Dex::parse(b"PK\x03\x04"),
+            Err(ScanError::InvalidApk(_))
+        ));
+    }
+
+    #[test]
+    fn test_truncated_tables() {
+        let data = dex_with_classes(&["com.example.Main"]);
+        let dex = Dex::parse(&data[..HEADER_SIZE]).expect("header");
+        assert!(matches!(dex.class_names(), Err(ScanError::InvalidApk(_))));
+    }
+}
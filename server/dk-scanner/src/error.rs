@@ -24,7 +24,21 @@ pub enum ScanError {
     #[error("Scan timed out after {0} seconds")]
     Timeout(u64),
 
+    /// Scanner configuration, such as the vulnerability database, is invalid.
+    #[error("Invalid scanner configuration: {0}")]
+    Config(String),
+
+    /// Scan result or quarantine database error.
+    #[error("Database error: {0}")]
+    Database(String),
+
     /// Critical vulnerability found.
     #[error("Critical vulnerability found: {0}")]
     CriticalVulnerability(String),
 }
+
+impl From<sqlx::Error> for ScanError {
+    fn from(err: sqlx::Error) -> Self {
+        Self::Database(err.to_string())
+    }
+}
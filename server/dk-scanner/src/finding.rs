@@ -0,0 +1,128 @@
+//! Individual scan findings and their severity.
+
+use dk_common::types::ScanStatus;
+use serde::{Deserialize, Serialize};
+
+/// How serious a finding is, from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Recorded for reviewers; no action needed.
+    Info,
+    /// Minor concern.
+    Low,
+    /// Needs a reviewer's attention.
+    Medium,
+    /// Serious concern; needs a reviewer's attention.
+    High,
+    /// The APK must not be published.
+    Critical,
+}
+
+impl Severity {
+    /// Scan status of a report whose most severe finding is `self`.
+    ///
+    /// Critical findings fail the scan, medium and high ones raise a
+    /// warning, and anything less passes.
+    #[must_use]
+    pub const fn status(self) -> ScanStatus {
+        match self {
+            Self::Critical => ScanStatus::Failed,
+            Self::High | Self::Medium => ScanStatus::Warning,
+            Self::Low | Self::Info => ScanStatus::Passed,
+        }
+    }
+}
+
+/// Something a scan check found in an APK.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanFinding {
+    /// How serious the finding is.
+    pub severity: Severity,
+    /// Stable machine-readable identifier, e.g. `signature.invalid`.
+    pub code: String,
+    /// Human-readable explanation for reviewers.
+    pub message: String,
+}
+
+impl ScanFinding {
+    /// Create a finding.
+    #[must_use]
+    pub fn new(severity: Severity, code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code: code.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Overall status implied by `findings`: that of the most severe one, or
+/// [`ScanStatus::Passed`] when there are none.
+#[must_use]
+pub fn derive_status(findings: &[ScanFinding]) -> ScanStatus {
+    findings
+        .iter()
+        .map(|finding| finding.severity)
+        .max()
+        .map_or(ScanStatus::Passed, Severity::status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn findings(severities: &[Severity]) -> Vec<ScanFinding> {
+        severities
+            .iter()
+            .map(|severity| ScanFinding::new(*severity, "test.finding", "test"))
+            .collect()
+    }
+
+    #[test]
+    fn test_no_findings_pass() {
+        assert_eq!(derive_status(&[]), ScanStatus::Passed);
+    }
+
+    #[test]
+    fn test_passed_warning_boundary() {
+        assert_eq!(
+            derive_status(&findings(&[Severity::Info, Severity::Low])),
+            ScanStatus::Passed
+        );
+        assert_eq!(
+            derive_status(&findings(&[Severity::Low, Severity::Medium])),
+            ScanStatus::Warning
+        );
+    }
+
+    #[test]
+    fn test_warning_failed_boundary() {
+        assert_eq!(
+            derive_status(&findings(&[Severity::High, Severity::Medium])),
+            ScanStatus::Warning
+        );
+        assert_eq!(
+            derive_status(&findings(&[
+                Severity::Info,
+                Severity::Critical,
+                Severity::High
+            ])),
+            ScanStatus::Failed
+        );
+    }
+
+    #[test]
+    fn test_severity_json() {
+        let finding = ScanFinding::new(Severity::High, "permission.concerning", "Uses CAMERA");
+        let json = serde_json::to_value(&finding).expect("serialize");
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "severity": "high",
+                "code": "permission.concerning",
+                "message": "Uses CAMERA"
+            })
+        );
+    }
+}
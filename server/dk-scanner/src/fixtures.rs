@@ -0,0 +1,474 @@
+//! Test fixtures: synthetic APKs built in memory.
+//!
+//! Also available to other crates' tests through the `fixtures` feature.
+
+#![allow(missing_docs, clippy::expect_used, clippy::missing_panics_doc)]
+
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+
+use dk_common::hash::hex;
+use dk_signing::apk::{content_digest, ZipLayout, APK_SIG_BLOCK_MAGIC};
+use dk_signing::{der, Signer, SoftwareSigner};
+use ring::digest::{digest, SHA256};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::dex::{HEADER_SIZE, METHOD_IDS_SIZE};
+use crate::manifest::{
+    ATTR_MAX_SDK_VERSION, ATTR_MIN_SDK_VERSION, ATTR_NAME, ATTR_TARGET_SDK_VERSION,
+    ATTR_VERSION_CODE, ATTR_VERSION_NAME, NO_ENTRY, RES_STRING_POOL_TYPE, RES_XML_END_ELEMENT_TYPE,
+    RES_XML_RESOURCE_MAP_TYPE, RES_XML_START_ELEMENT_TYPE, RES_XML_TYPE, TYPE_INT_DEC, TYPE_STRING,
+    UTF8_FLAG,
+};
+use crate::signature::{APK_SIGNATURE_SCHEME_V2_BLOCK_ID, SIGNATURE_ECDSA_WITH_SHA256};
+
+/// Builds an APK-shaped ZIP archive with stored (uncompressed) entries.
+#[derive(Default)]
+pub struct ApkBuilder {
+    entries: Vec<(String, Vec<u8>)>,
+}
+
+impl ApkBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an entry with the given contents.
+    #[must_use]
+    pub fn entry(mut self, name: &str, content: &[u8]) -> Self {
+        self.entries.push((name.to_string(), content.to_vec()));
+        self
+    }
+
+    /// Build the unsigned archive.
+    pub fn build(&self) -> Vec<u8> {
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in &self.entries {
+            writer.start_file(name, options).expect("start entry");
+            writer.write_all(content).expect("write entry");
+        }
+        writer.finish().expect("finish zip").into_inner()
+    }
+
+    /// Build the archive and sign it with APK Signature Scheme v2 using a
+    /// fresh key. Returns the APK and the signer certificate fingerprint.
+    pub fn build_signed(&self) -> (Vec<u8>, String) {
+        self.build_signed_by(&ApkSigner::generate())
+    }
+
+    /// Build the archive and sign it with APK Signature Scheme v2 by
+    /// `signer`. Returns the APK and the signer certificate fingerprint.
+    pub fn build_signed_by(&self, signer: &ApkSigner) -> (Vec<u8>, String) {
+        let apk = self.build();
+        let ApkSigner {
+            key: signer,
+            certificate,
+        } = signer;
+        let spki = der::parse_certificate(certificate)
+            .expect("parse certificate")
+            .spki
+            .to_vec();
+
+        // Without a signing block the digest sections are identical to the
+        // signed APK's, since the EOCD already points at the signing block
+        // insertion point.
+        let layout = ZipLayout::parse(&apk).expect("zip layout");
+        let digest_value = content_digest(&apk, &layout, &SHA256).expect("content digest");
+
+        let signed_data = concat(&[
+            &prefixed(&prefixed(&concat(&[
+                &SIGNATURE_ECDSA_WITH_SHA256.to_le_bytes(),
+                &prefixed(&digest_value),
+            ]))),
+            &prefixed(&prefixed(certificate)),
+            &prefixed(&[]),
+        ]);
+        let signature = signer.sign(&signed_data).expect("sign");
+        let signer_block = concat(&[
+            &prefixed(&signed_data),
+            &prefixed(&prefixed(&concat(&[
+                &SIGNATURE_ECDSA_WITH_SHA256.to_le_bytes(),
+                &prefixed(&signature),
+            ]))),
+            &prefixed(&spki),
+        ]);
+        let value = prefixed(&prefixed(&signer_block));
+
+        let pair = concat(&[&APK_SIGNATURE_SCHEME_V2_BLOCK_ID.to_le_bytes(), &value]);
+        let pairs = concat(&[&(pair.len() as u64).to_le_bytes(), &pair]);
+        let block_size = (pairs.len() + 8 + APK_SIG_BLOCK_MAGIC.len()) as u64;
+        let block = concat(&[
+            &block_size.to_le_bytes(),
+            &pairs,
+            &block_size.to_le_bytes(),
+            APK_SIG_BLOCK_MAGIC,
+        ]);
+
+        let mut signed_apk = apk[..layout.cd_offset].to_vec();
+        signed_apk.extend_from_slice(&block);
+        signed_apk.extend_from_slice(&apk[layout.cd_offset..]);
+        let eocd = layout.eocd_offset + block.len();
+        let cd_offset = u32::try_from(layout.cd_offset + block.len()).expect("small apk");
+        signed_apk[eocd + 16..eocd + 20].copy_from_slice(&cd_offset.to_le_bytes());
+
+        (signed_apk, hex(digest(&SHA256, certificate).as_ref()))
+    }
+}
+
+/// A signing key with its self-signed certificate, to sign several APKs
+/// as the same developer.
+pub struct ApkSigner {
+    key: SoftwareSigner,
+    pub certificate: Vec<u8>,
+}
+
+impl ApkSigner {
+    /// Generate a fresh key and certificate.
+    pub fn generate() -> Self {
+        let key = SoftwareSigner::generate().expect("generate key");
+        let certificate = key
+            .self_signed_certificate("dk-scanner.test")
+            .expect("certificate");
+        Self { key, certificate }
+    }
+}
+
+/// Builds a binary `AndroidManifest.xml`.
+pub struct ManifestBuilder {
+    package: String,
+    version: Option<(u32, String)>,
+    sdk: Option<(u32, u32)>,
+    permissions: Vec<(String, Option<u32>)>,
+    utf16: bool,
+}
+
+impl ManifestBuilder {
+    pub fn new(package: &str) -> Self {
+        Self {
+            package: package.to_string(),
+            version: None,
+            sdk: None,
+            permissions: Vec::new(),
+            utf16: false,
+        }
+    }
+
+    /// Encode the string pool as UTF-16 rather than UTF-8.
+    #[must_use]
+    pub const fn utf16(mut self) -> Self {
+        self.utf16 = true;
+        self
+    }
+
+    #[must_use]
+    pub fn version(mut self, code: u32, name: &str) -> Self {
+        self.version = Some((code, name.to_string()));
+        self
+    }
+
+    #[must_use]
+    pub const fn sdk(mut self, min: u32, target: u32) -> Self {
+        self.sdk = Some((min, target));
+        self
+    }
+
+    #[must_use]
+    pub fn permission(mut self, name: &str) -> Self {
+        self.permissions.push((name.to_string(), None));
+        self
+    }
+
+    #[must_use]
+    pub fn permission_max_sdk(mut self, name: &str, max_sdk: u32) -> Self {
+        self.permissions.push((name.to_string(), Some(max_sdk)));
+        self
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let mut xml = BinaryXml::new(self.utf16);
+
+        let mut attributes = vec![(false, "package", AttrValue::Str(self.package.clone()))];
+        if let Some((code, name)) = &self.version {
+            attributes.push((true, "versionCode", AttrValue::Int(*code)));
+            attributes.push((true, "versionName", AttrValue::Str(name.clone())));
+        }
+        xml.start("manifest", &attributes);
+
+        if let Some((min, target)) = self.sdk {
+            xml.start(
+                "uses-sdk",
+                &[
+                    (true, "minSdkVersion", AttrValue::Int(min)),
+                    (true, "targetSdkVersion", AttrValue::Int(target)),
+                ],
+            );
+            xml.end("uses-sdk");
+        }
+
+        for (name, max_sdk) in &self.permissions {
+            let mut attributes = vec![(true, "name", AttrValue::Str(name.clone()))];
+            if let Some(max_sdk) = max_sdk {
+                attributes.push((true, "maxSdkVersion", AttrValue::Int(*max_sdk)));
+            }
+            xml.start("uses-permission", &attributes);
+            xml.end("uses-permission");
+        }
+
+        xml.end("manifest");
+        xml.finish()
+    }
+}
+
+/// `android:` attributes with resource IDs; they lead the string pool so the
+/// resource map can index them.
+const ANDROID_ATTRIBUTES: [(&str, u32); 6] = [
+    ("name", ATTR_NAME),
+    ("versionCode", ATTR_VERSION_CODE),
+    ("versionName", ATTR_VERSION_NAME),
+    ("minSdkVersion", ATTR_MIN_SDK_VERSION),
+    ("targetSdkVersion", ATTR_TARGET_SDK_VERSION),
+    ("maxSdkVersion", ATTR_MAX_SDK_VERSION),
+];
+
+const ANDROID_NAMESPACE: &str = "http://schemas.android.com/apk/res/android";
+
+enum AttrValue {
+    Str(String),
+    Int(u32),
+}
+
+/// Minimal binary XML writer with a UTF-8 or UTF-16 string pool.
+struct BinaryXml {
+    strings: Vec<String>,
+    body: Vec<u8>,
+    utf16: bool,
+}
+
+impl BinaryXml {
+    fn new(utf16: bool) -> Self {
+        Self {
+            strings: ANDROID_ATTRIBUTES
+                .iter()
+                .map(|(name, _)| (*name).to_string())
+                .collect(),
+            body: Vec::new(),
+            utf16,
+        }
+    }
+
+    fn intern(&mut self, value: &str) -> u32 {
+        let index = self
+            .strings
+            .iter()
+            .position(|existing| existing == value)
+            .unwrap_or_else(|| {
+                self.strings.push(value.to_string());
+                self.strings.len() - 1
+            });
+        u32::try_from(index).expect("small pool")
+    }
+
+    fn start(&mut self, name: &str, attributes: &[(bool, &str, AttrValue)]) {
+        let name = self.intern(name);
+        let mut attrs = Vec::new();
+        for (android, attr_name, value) in attributes {
+            let ns = if *android {
+                self.intern(ANDROID_NAMESPACE)
+            } else {
+                NO_ENTRY
+            };
+            let attr_name = self.intern(attr_name);
+            let (raw, data_type, data) = match value {
+                AttrValue::Str(value) => {
+                    let index = self.intern(value);
+                    (index, TYPE_STRING, index)
+                }
+                AttrValue::Int(value) => (NO_ENTRY, TYPE_INT_DEC, *value),
+            };
+            attrs.extend_from_slice(&concat(&[
+                &ns.to_le_bytes(),
+                &attr_name.to_le_bytes(),
+                &raw.to_le_bytes(),
+                &8u16.to_le_bytes(),
+                &[0, data_type],
+                &data.to_le_bytes(),
+            ]));
+        }
+
+        let count = u16::try_from(attributes.len()).expect("few attributes");
+        let ext = concat(&[
+            &NO_ENTRY.to_le_bytes(),
+            &name.to_le_bytes(),
+            &20u16.to_le_bytes(),
+            &20u16.to_le_bytes(),
+            &count.to_le_bytes(),
+            &[0; 6],
+            &attrs,
+        ]);
+        self.node(RES_XML_START_ELEMENT_TYPE, &ext);
+    }
+
+    fn end(&mut self, name: &str) {
+        let name = self.intern(name);
+        self.node(
+            RES_XML_END_ELEMENT_TYPE,
+            &concat(&[&NO_ENTRY.to_le_bytes(), &name.to_le_bytes()]),
+        );
+    }
+
+    /// Append a node chunk (16-byte header with line number and comment).
+    fn node(&mut self, chunk_type: u16, ext: &[u8]) {
+        let header = concat(&[&1u32.to_le_bytes(), &NO_ENTRY.to_le_bytes()]);
+        self.body
+            .extend_from_slice(&chunk(chunk_type, 16, &concat(&[&header, ext])));
+    }
+
+    fn finish(self) -> Vec<u8> {
+        let mut offsets = Vec::new();
+        let mut data = Vec::new();
+        for value in &self.strings {
+            offsets
+                .extend_from_slice(&u32::try_from(data.len()).expect("small pool").to_le_bytes());
+            let units: Vec<u16> = value.encode_utf16().collect();
+            if self.utf16 {
+                let len = u16::try_from(units.len()).expect("short string");
+                data.extend_from_slice(&len.to_le_bytes());
+                data.extend(units.iter().flat_map(|unit| unit.to_le_bytes()));
+                data.extend_from_slice(&[0, 0]);
+            } else {
+                let utf16_len = u8::try_from(units.len()).expect("short string");
+                let len = u8::try_from(value.len()).expect("short string");
+                data.extend_from_slice(&[utf16_len, len]);
+                data.extend_from_slice(value.as_bytes());
+                data.push(0);
+            }
+        }
+        while data.len() % 4 != 0 {
+            data.push(0);
+        }
+
+        let count = u32::try_from(self.strings.len()).expect("small pool");
+        let strings_start = u32::try_from(28 + offsets.len()).expect("small pool");
+        let pool = chunk(
+            RES_STRING_POOL_TYPE,
+            28,
+            &concat(&[
+                &count.to_le_bytes(),
+                &0u32.to_le_bytes(),
+                &(if self.utf16 { 0 } else { UTF8_FLAG }).to_le_bytes(),
+                &strings_start.to_le_bytes(),
+                &0u32.to_le_bytes(),
+                &offsets,
+                &data,
+            ]),
+        );
+
+        let ids: Vec<u8> = ANDROID_ATTRIBUTES
+            .iter()
+            .flat_map(|(_, id)| id.to_le_bytes())
+            .collect();
+        let resource_map = chunk(RES_XML_RESOURCE_MAP_TYPE, 8, &ids);
+
+        chunk(
+            RES_XML_TYPE,
+            8,
+            &concat(&[&pool, &resource_map, &self.body]),
+        )
+    }
+}
+
+/// A chunk with the standard type/header size/size header.
+fn chunk(chunk_type: u16, header_size: u16, rest: &[u8]) -> Vec<u8> {
+    let size = u32::try_from(rest.len() + 8).expect("small chunk");
+    concat(&[
+        &chunk_type.to_le_bytes(),
+        &header_size.to_le_bytes(),
+        &size.to_le_bytes(),
+        rest,
+    ])
+}
+
+/// Build a DEX file whose type table lists `classes` (dotted names).
+pub fn dex_with_classes(classes: &[&str]) -> Vec<u8> {
+    let count = u32::try_from(classes.len()).expect("few classes");
+    let string_ids_off = u32::try_from(HEADER_SIZE).expect("header size");
+    let type_ids_off = string_ids_off + 4 * count;
+    let data_off = type_ids_off + 4 * count;
+
+    let mut string_ids = Vec::new();
+    let mut type_ids = Vec::new();
+    let mut data = Vec::new();
+    for (index, class) in (0u32..).zip(classes) {
+        let descriptor = format!("L{};", class.replace('.', "/"));
+        let offset = data_off + u32::try_from(data.len()).expect("small dex");
+        string_ids.extend_from_slice(&offset.to_le_bytes());
+        type_ids.extend_from_slice(&index.to_le_bytes());
+        data.push(u8::try_from(descriptor.len()).expect("short descriptor"));
+        data.extend_from_slice(descriptor.as_bytes());
+        data.push(0);
+    }
+
+    let mut header = vec![0; HEADER_SIZE];
+    header[..8].copy_from_slice(b"dex\n035\0");
+    let file_size = data_off + u32::try_from(data.len()).expect("small dex");
+    for (offset, value) in [
+        (32, file_size),
+        (36, string_ids_off),
+        (40, 0x1234_5678),
+        (56, count),
+        (60, string_ids_off),
+        (64, count),
+        (68, type_ids_off),
+    ] {
+        header[offset..offset + 4].copy_from_slice(&u32::to_le_bytes(value));
+    }
+
+    concat(&[&header, &string_ids, &type_ids, &data])
+}
+
+/// Build a DEX file referencing `count` methods.
+pub fn dex_with_methods(count: u32) -> Vec<u8> {
+    let mut dex = dex_with_classes(&[]);
+    let method_ids_off = u32::try_from(dex.len()).expect("small dex");
+    dex[METHOD_IDS_SIZE..METHOD_IDS_SIZE + 4].copy_from_slice(&count.to_le_bytes());
+    dex[METHOD_IDS_SIZE + 4..METHOD_IDS_SIZE + 8].copy_from_slice(&method_ids_off.to_le_bytes());
+    dex.resize(
+        dex.len() + 8 * usize::try_from(count).expect("few methods"),
+        0,
+    );
+    dex
+}
+
+/// An APK written to a temporary file, removed on drop.
+pub struct TempApk(PathBuf);
+
+impl TempApk {
+    pub fn write(bytes: &[u8]) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("dk-scanner-test-{}.apk", uuid::Uuid::new_v4()));
+        std::fs::write(&path, bytes).expect("write apk");
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempApk {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// u32 little-endian length-prefixed bytes.
+fn prefixed(bytes: &[u8]) -> Vec<u8> {
+    let len = u32::try_from(bytes.len()).expect("small section");
+    concat(&[&len.to_le_bytes(), bytes])
+}
+
+fn concat(parts: &[&[u8]]) -> Vec<u8> {
+    parts.concat()
+}
@@ -2,27 +2,444 @@
 //!
 //! Orchestrates security scanning of Android applications.
 
+pub mod apk;
+pub mod dependencies;
+mod dex;
 pub mod error;
+pub mod finding;
+pub mod limits;
+pub mod manifest;
+pub mod quarantine;
+pub mod report;
+pub mod results;
+pub mod signature;
+pub mod timeout;
+pub mod trackers;
 
+#[cfg(any(test, feature = "fixtures"))]
+pub mod fixtures;
+
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use dk_common::config::ScannerConfig;
+use futures_util::StreamExt;
+
+pub use apk::ApkMetadata;
+pub use dependencies::{BundledLibrary, LibraryKind, Vulnerability, VulnerabilityDb};
 pub use error::{ScanError, ScanResult};
+pub use finding::{derive_status, ScanFinding, Severity};
+pub use manifest::AndroidManifest;
+pub use quarantine::{QuarantineStore, QuarantinedVersion};
+pub use report::{PermissionReview, ScanReport, SignatureCheck};
+pub use results::{ScanReportStore, StoredScanReport};
+pub use signature::{verify_apk_signature, SignatureInfo, SignatureScheme};
+pub use timeout::with_timeout;
+pub use trackers::{detect_trackers, TrackerHit};
 
-/// Placeholder for scanner service functionality.
-///
-/// Full implementation will be added in Milestone 4.
+/// Security scanner for uploaded APKs.
+#[derive(Clone)]
 pub struct ScannerService {
-    _private: (),
+    config: ScannerConfig,
+    vulnerabilities: Arc<VulnerabilityDb>,
 }
 
 impl ScannerService {
-    /// Create a new scanner service (placeholder).
+    /// Create a new scanner service with default limits and an empty
+    /// vulnerability database.
     #[must_use]
     pub fn new() -> Self {
-        Self { _private: () }
+        Self {
+            config: ScannerConfig::default(),
+            vulnerabilities: Arc::default(),
+        }
+    }
+
+    /// Check APK size and DEX method count against the limits in `config`.
+    #[must_use]
+    pub fn with_config(mut self, config: ScannerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Check bundled libraries against `vulnerabilities`.
+    #[must_use]
+    pub fn with_vulnerability_db(
+        mut self,
+        vulnerabilities: impl Into<Arc<VulnerabilityDb>>,
+    ) -> Self {
+        self.vulnerabilities = vulnerabilities.into();
+        self
+    }
+
+    /// Extract package identity, version, SDK levels, and permissions from
+    /// the APK at `path`.
+    ///
+    /// A missing or malformed `AndroidManifest.xml`, or an archive beyond
+    /// the limits of `config`, is reported as [`ScanError::InvalidApk`].
+    pub fn inspect(path: &Path, config: &ScannerConfig) -> ScanResult<ApkMetadata> {
+        apk::inspect(path, config)
+    }
+
+    /// Run all checks against the APK at `path` and report the outcome.
+    ///
+    /// Findings are recorded in the report rather than returned as errors;
+    /// only an unreadable APK or manifest fails the call.
+    pub fn scan(&self, path: &Path) -> ScanResult<ScanReport> {
+        self.scan_apk(path).map(|(_, report)| report)
+    }
+
+    /// Scan the APK at `path` like [`Self::scan`], quarantining its version
+    /// in `quarantine` if any finding is critical, so it never reaches the
+    /// index.
+    ///
+    /// The checks run on the blocking thread pool; they and the quarantine
+    /// update each fail with [`ScanError::Timeout`] after the configured
+    /// `tool_timeout_secs`.
+    pub async fn scan_and_quarantine(
+        &self,
+        path: &Path,
+        quarantine: &QuarantineStore,
+    ) -> ScanResult<ScanReport> {
+        let (metadata, report) = self.scan_in_background(path.to_path_buf()).await?;
+        if let Some(reason) = quarantine_reason(&report) {
+            tracing::warn!(
+                package_id = %metadata.package,
+                version_code = metadata.version_code,
+                %reason,
+                "Quarantining APK version"
+            );
+            with_timeout(
+                self.config.tool_timeout_secs,
+                quarantine.quarantine(&metadata.package, metadata.version_code, &reason),
+            )
+            .await?;
+        }
+        Ok(report)
+    }
+
+    /// Scan each of `paths` like [`Self::scan`], several at a time.
+    ///
+    /// A failed scan does not stop the others: every path is returned, in
+    /// input order, with its own outcome. Each scan is limited to the
+    /// configured `tool_timeout_secs`.
+    pub async fn scan_many(
+        &self,
+        paths: impl IntoIterator<Item = PathBuf>,
+    ) -> Vec<(PathBuf, ScanResult<ScanReport>)> {
+        let limit = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        futures_util::stream::iter(paths)
+            .map(|path| async move {
+                let result = self
+                    .scan_in_background(path.clone())
+                    .await
+                    .map(|(_, report)| report);
+                (path, result)
+            })
+            .buffered(limit)
+            .collect()
+            .await
+    }
+
+    /// Run [`Self::scan_apk`] on the blocking thread pool, within the
+    /// configured `tool_timeout_secs`.
+    async fn scan_in_background(&self, path: PathBuf) -> ScanResult<(ApkMetadata, ScanReport)> {
+        let scanner = self.clone();
+        with_timeout(self.config.tool_timeout_secs, async move {
+            tokio::task::spawn_blocking(move || scanner.scan_apk(&path))
+                .await
+                .map_err(|err| ScanError::ToolFailed(format!("scan task failed: {err}")))?
+        })
+        .await
+    }
+
+    fn scan_apk(&self, path: &Path) -> ScanResult<(ApkMetadata, ScanReport)> {
+        let metadata = apk::inspect(path, &self.config)?;
+
+        let apk = std::fs::read(path).map_err(|err| apk::io_error(path, &err))?;
+        let signature = match signature::verify_apk_bytes(&apk) {
+            Ok(info) => SignatureCheck::Valid(info),
+            Err(err @ (ScanError::CriticalVulnerability(_) | ScanError::InvalidApk(_))) => {
+                SignatureCheck::Invalid {
+                    reason: err.to_string(),
+                }
+            }
+            Err(err) => return Err(err),
+        };
+
+        let permissions = metadata
+            .permissions
+            .iter()
+            .map(PermissionReview::from)
+            .collect();
+
+        let libraries = dependencies::list_libraries(path, &self.config)?;
+        let apk_size = u64::try_from(metadata.size)
+            .map_err(|_| ScanError::InvalidApk(format!("{}: invalid size", path.display())))?;
+        let dex_methods = limits::count_dex_methods(path, &self.config)?;
+
+        let report = ScanReport::new(signature, permissions, detect_trackers(path, &self.config)?)
+            .with_findings(dependencies::check_libraries(
+                &libraries,
+                &self.vulnerabilities,
+            ))
+            .with_findings(limits::check_limits(&self.config, apk_size, dex_methods));
+        Ok((metadata, report))
     }
 }
 
+/// Why `report` requires quarantine: its critical findings, if any.
+fn quarantine_reason(report: &ScanReport) -> Option<String> {
+    let critical: Vec<String> = report
+        .findings
+        .iter()
+        .filter(|finding| finding.severity == Severity::Critical)
+        .map(|finding| format!("{}: {}", finding.code, finding.message))
+        .collect();
+    (!critical.is_empty()).then(|| critical.join("; "))
+}
+
 impl Default for ScannerService {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use dk_common::types::ScanStatus;
+
+    use super::*;
+    use crate::fixtures::{
+        dex_with_classes, dex_with_methods, ApkBuilder, ManifestBuilder, TempApk,
+    };
+
+    #[test]
+    fn test_inspect_fixture_apk() {
+        let manifest = ManifestBuilder::new("dk.digst.mitid")
+            .version(10_203, "1.2.3")
+            .sdk(24, 34)
+            .permission("android.permission.INTERNET")
+            .permission("android.permission.CAMERA")
+            .build();
+        let apk = ApkBuilder::new()
+            .entry(apk::MANIFEST_ENTRY, &manifest)
+            .entry("classes.dex", b"dex\n035\0")
+            .build();
+        let file = TempApk::write(&apk);
+
+        let metadata =
+            ScannerService::inspect(file.path(), &ScannerConfig::default()).expect("inspect");
+        assert_eq!(metadata.package.as_str(), "dk.digst.mitid");
+        assert_eq!(metadata.version_code, 10_203);
+        assert_eq!(metadata.version_name, "1.2.3");
+        assert_eq!(metadata.min_sdk, 24);
+        assert_eq!(metadata.target_sdk, 34);
+        let permissions: Vec<_> = metadata
+            .permissions
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        assert_eq!(
+            permissions,
+            ["android.permission.INTERNET", "android.permission.CAMERA"]
+        );
+        assert_eq!(metadata.size, i64::try_from(apk.len()).expect("size"));
+        let digest = ring::digest::digest(&ring::digest::SHA256, &apk);
+        assert_eq!(metadata.sha256.as_bytes()[..], *digest.as_ref());
+
+        let app_id = uuid::Uuid::new_v4();
+        let version = metadata.clone().into_app_version(app_id);
+        assert_eq!(version.app_id, app_id);
+        assert_eq!(version.version_code, 10_203);
+        assert_eq!(version.sha256, metadata.sha256);
+        assert_eq!(version.permissions, metadata.permissions);
+        assert!(metadata.abis.is_empty());
+        assert!(!version.native_code);
+    }
+
+    #[test]
+    fn test_inspect_native_abis() {
+        let manifest = ManifestBuilder::new("dk.digst.mitid")
+            .version(1, "1.0")
+            .build();
+        let apk = ApkBuilder::new()
+            .entry(apk::MANIFEST_ENTRY, &manifest)
+            .entry("lib/armeabi-v7a/libcrypto.so", b"\x7fELF")
+            .entry("lib/arm64-v8a/libcrypto.so", b"\x7fELF")
+            .entry("lib/arm64-v8a/libmitid.so", b"\x7fELF")
+            .entry("lib/x86_64/README.txt", b"not a library")
+            .build();
+        let file = TempApk::write(&apk);
+
+        let metadata =
+            ScannerService::inspect(file.path(), &ScannerConfig::default()).expect("inspect");
+        assert_eq!(metadata.abis, ["arm64-v8a", "armeabi-v7a"]);
+
+        let version = metadata.into_app_version(uuid::Uuid::new_v4());
+        assert_eq!(version.abis, ["arm64-v8a", "armeabi-v7a"]);
+        assert!(version.native_code);
+    }
+
+    #[test]
+    fn test_inspect_without_manifest() {
+        let apk = ApkBuilder::new()
+            .entry("classes.dex", b"dex\n035\0")
+            .build();
+        let file = TempApk::write(&apk);
+
+        assert!(matches!(
+            ScannerService::inspect(file.path(), &ScannerConfig::default()),
+            Err(ScanError::InvalidApk(_))
+        ));
+    }
+
+    #[test]
+    fn test_scan_signed_apk() {
+        let manifest = ManifestBuilder::new("dk.digst.mitid")
+            .version(1, "1.0")
+            .permission("android.permission.INTERNET")
+            .build();
+        let (apk, _) = ApkBuilder::new()
+            .entry(apk::MANIFEST_ENTRY, &manifest)
+            .entry(
+                "classes.dex",
+                &dex_with_classes(&["com.google.firebase.analytics.FirebaseAnalytics"]),
+            )
+            .build_signed();
+        let file = TempApk::write(&apk);
+
+        let report = ScannerService::new().scan(file.path()).expect("scan");
+        assert_eq!(report.status, ScanStatus::Passed);
+        assert!(matches!(report.signature, SignatureCheck::Valid(_)));
+        assert_eq!(report.trackers.len(), 1);
+    }
+
+    #[test]
+    fn test_scan_unsigned_apk_fails() {
+        let manifest = ManifestBuilder::new("dk.digst.mitid")
+            .version(1, "1.0")
+            .build();
+        let apk = ApkBuilder::new()
+            .entry(apk::MANIFEST_ENTRY, &manifest)
+            .build();
+        let file = TempApk::write(&apk);
+
+        let report = ScannerService::new().scan(file.path()).expect("scan");
+        assert_eq!(report.status, ScanStatus::Failed);
+        assert!(matches!(report.signature, SignatureCheck::Invalid { .. }));
+    }
+
+    #[test]
+    fn test_scan_fails_on_critical_cve() {
+        let library = b"\x7fELF libcrypto 1.1.1k";
+        let manifest = ManifestBuilder::new("dk.digst.mitid")
+            .version(1, "1.0")
+            .build();
+        let (apk, _) = ApkBuilder::new()
+            .entry(apk::MANIFEST_ENTRY, &manifest)
+            .entry("lib/arm64-v8a/libcrypto.so", library)
+            .build_signed();
+        let file = TempApk::write(&apk);
+
+        let db = VulnerabilityDb::new([Vulnerability {
+            sha256: dk_common::hash::sha256_bytes(library),
+            library: "OpenSSL 1.1.1k".to_string(),
+            cve: "CVE-2021-3711".to_string(),
+            severity: Severity::Critical,
+        }]);
+
+        let clean = ScannerService::new().scan(file.path()).expect("scan");
+        assert_eq!(clean.status, ScanStatus::Passed);
+
+        let report = ScannerService::new()
+            .with_vulnerability_db(db)
+            .scan(file.path())
+            .expect("scan");
+        assert_eq!(report.status, ScanStatus::Failed);
+        assert!(matches!(report.signature, SignatureCheck::Valid(_)));
+        assert_eq!(report.findings[0].code, "dependency.vulnerable");
+    }
+
+    #[test]
+    fn test_scan_warns_over_method_limit() {
+        let manifest = ManifestBuilder::new("dk.digst.mitid")
+            .version(1, "1.0")
+            .build();
+        let (apk, _) = ApkBuilder::new()
+            .entry(apk::MANIFEST_ENTRY, &manifest)
+            .entry("classes.dex", &dex_with_methods(3))
+            .build_signed();
+        let file = TempApk::write(&apk);
+
+        let scanner = |max_dex_methods| {
+            ScannerService::new().with_config(ScannerConfig {
+                max_dex_methods,
+                ..ScannerConfig::default()
+            })
+        };
+        let report = scanner(3).scan(file.path()).expect("scan");
+        assert_eq!(report.status, ScanStatus::Passed);
+
+        let report = scanner(2).scan(file.path()).expect("scan");
+        assert_eq!(report.status, ScanStatus::Warning);
+        assert_eq!(report.findings[0].code, "dex.too_many_methods");
+    }
+
+    #[tokio::test]
+    async fn test_scan_many_reports_each_outcome() {
+        let manifest = ManifestBuilder::new("dk.digst.mitid")
+            .version(1, "1.0")
+            .build();
+        let (apk, _) = ApkBuilder::new()
+            .entry(apk::MANIFEST_ENTRY, &manifest)
+            .build_signed();
+        let valid = TempApk::write(&apk);
+        let invalid = TempApk::write(b"not a zip archive");
+        let paths = vec![
+            invalid.path().to_path_buf(),
+            valid.path().to_path_buf(),
+            PathBuf::from("/nonexistent/app.apk"),
+        ];
+
+        let results = ScannerService::new().scan_many(paths.clone()).await;
+
+        let returned: Vec<_> = results.iter().map(|(path, _)| path.clone()).collect();
+        assert_eq!(returned, paths);
+        assert!(matches!(results[0].1, Err(ScanError::InvalidApk(_))));
+        let report = results[1].1.as_ref().expect("valid APK scans");
+        assert_eq!(report.status, ScanStatus::Passed);
+        assert!(matches!(results[2].1, Err(ScanError::ApkNotFound(_))));
+    }
+
+    #[test]
+    fn test_quarantine_reason_lists_critical_findings() {
+        let manifest = ManifestBuilder::new("dk.digst.mitid")
+            .version(1, "1.0")
+            .build();
+        let apk = ApkBuilder::new()
+            .entry(apk::MANIFEST_ENTRY, &manifest)
+            .build();
+        let file = TempApk::write(&apk);
+
+        let report = ScannerService::new().scan(file.path()).expect("scan");
+        let reason = quarantine_reason(&report).expect("unsigned APK is quarantined");
+        assert!(reason.starts_with("signature.invalid: "));
+
+        let (apk, _) = ApkBuilder::new()
+            .entry(apk::MANIFEST_ENTRY, &manifest)
+            .build_signed();
+        let file = TempApk::write(&apk);
+        let report = ScannerService::new().scan(file.path()).expect("scan");
+        assert_eq!(quarantine_reason(&report), None);
+    }
+
+    #[test]
+    fn test_inspect_missing_file() {
+        assert!(matches!(
+            ScannerService::inspect(Path::new("/nonexistent/app.apk"), &ScannerConfig::default()),
+            Err(ScanError::ApkNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,118 @@
+//! APK size and DEX method-count limits.
+//!
+//! Oversized APKs and apps referencing more methods than a single DEX file
+//! can hold install poorly on some devices and launchers. Exceeding a
+//! configured limit raises a warning for reviewers.
+
+use std::path::Path;
+
+use dk_common::config::ScannerConfig;
+
+use crate::apk::Apk;
+use crate::dex::Dex;
+use crate::error::{ScanError, ScanResult};
+use crate::finding::{ScanFinding, Severity};
+
+/// Count the method references of all DEX files in the APK at `path`,
+/// within the archive limits in `config`.
+pub fn count_dex_methods(path: &Path, config: &ScannerConfig) -> ScanResult<u64> {
+    let mut apk = Apk::open(path, config)?;
+    let mut total = 0u64;
+    for name in apk.dex_names() {
+        let data = apk
+            .read(&name)?
+            .ok_or_else(|| ScanError::InvalidApk(format!("{name} missing")))?;
+        let count = Dex::parse(&data)?.method_count()?;
+        total += u64::try_from(count)
+            .map_err(|_| ScanError::InvalidApk(format!("{name}: method count out of range")))?;
+    }
+    Ok(total)
+}
+
+/// Findings for an APK of `apk_size` bytes referencing `dex_methods`
+/// methods, one per limit in `config` it exceeds.
+#[must_use]
+pub fn check_limits(config: &ScannerConfig, apk_size: u64, dex_methods: u64) -> Vec<ScanFinding> {
+    let mut findings = Vec::new();
+    if apk_size > config.max_apk_size {
+        findings.push(ScanFinding::new(
+            Severity::Medium,
+            "apk.too_large",
+            format!(
+                "APK is {apk_size} bytes, above the limit of {} bytes",
+                config.max_apk_size
+            ),
+        ));
+    }
+    if dex_methods > u64::from(config.max_dex_methods) {
+        findings.push(ScanFinding::new(
+            Severity::Medium,
+            "dex.too_many_methods",
+            format!(
+                "DEX files reference {dex_methods} methods, above the limit of {}",
+                config.max_dex_methods
+            ),
+        ));
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{dex_with_methods, ApkBuilder, TempApk};
+
+    fn config(max_apk_size: u64, max_dex_methods: u32) -> ScannerConfig {
+        ScannerConfig {
+            max_apk_size,
+            max_dex_methods,
+            ..ScannerConfig::default()
+        }
+    }
+
+    fn multidex_apk() -> TempApk {
+        TempApk::write(
+            &ApkBuilder::new()
+                .entry("classes.dex", &dex_with_methods(3))
+                .entry("classes2.dex", &dex_with_methods(2))
+                .build(),
+        )
+    }
+
+    #[test]
+    fn test_counts_methods_across_dex_files() {
+        assert_eq!(
+            count_dex_methods(multidex_apk().path(), &ScannerConfig::default()).expect("count"),
+            5
+        );
+    }
+
+    #[test]
+    fn test_under_method_threshold() {
+        let methods =
+            count_dex_methods(multidex_apk().path(), &ScannerConfig::default()).expect("count");
+        assert!(check_limits(&config(1024, 5), 100, methods).is_empty());
+    }
+
+    #[test]
+    fn test_over_method_threshold() {
+        let methods =
+            count_dex_methods(multidex_apk().path(), &ScannerConfig::default()).expect("count");
+        let findings = check_limits(&config(1024, 4), 100, methods);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].code, "dex.too_many_methods");
+        assert_eq!(findings[0].severity, Severity::Medium);
+        assert_eq!(
+            findings[0].message,
+            "DEX files reference 5 methods, above the limit of 4"
+        );
+    }
+
+    #[test]
+    fn test_over_size_threshold() {
+        let findings = check_limits(&config(1024, 10), 1025, 0);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].code, "apk.too_large");
+        assert!(check_limits(&config(1024, 10), 1024, 0).is_empty());
+    }
+}
@@ -0,0 +1,536 @@
+//! Decoding of the binary `AndroidManifest.xml` (Android binary XML).
+//!
+//! APKs ship the manifest compiled into a chunked binary format: a string
+//! pool, a resource map tying attribute names to `android:` resource IDs,
+//! and a stream of element start/end chunks. Only the parts needed to read
+//! package identity, SDK levels, and permissions are decoded.
+//!
+//! String pools hold either UTF-8 or UTF-16 strings. Invalid sequences in a
+//! string are replaced with U+FFFD, as tools disagree on encoding details
+//! such as supplementary characters; lengths or offsets that run past the
+//! pool make the manifest invalid.
+
+use dk_common::types::{AppId, Permission};
+
+use crate::error::{ScanError, ScanResult};
+
+/// Chunk type of a binary XML document.
+pub(crate) const RES_XML_TYPE: u16 = 0x0003;
+/// Chunk type of a string pool.
+pub(crate) const RES_STRING_POOL_TYPE: u16 = 0x0001;
+/// Chunk type of the attribute resource ID map.
+pub(crate) const RES_XML_RESOURCE_MAP_TYPE: u16 = 0x0180;
+/// Chunk type of an element start.
+pub(crate) const RES_XML_START_ELEMENT_TYPE: u16 = 0x0102;
+/// Chunk type of an element end.
+pub(crate) const RES_XML_END_ELEMENT_TYPE: u16 = 0x0103;
+
+/// String pool flag: strings are UTF-8 rather than UTF-16.
+pub(crate) const UTF8_FLAG: u32 = 1 << 8;
+
+/// Value type of a string pool reference.
+pub(crate) const TYPE_STRING: u8 = 0x03;
+/// Value type of a decimal integer.
+pub(crate) const TYPE_INT_DEC: u8 = 0x10;
+/// Last integer value type (`TYPE_LAST_INT`).
+const TYPE_LAST_INT: u8 = 0x1f;
+
+/// Index meaning "no string".
+pub(crate) const NO_ENTRY: u32 = u32::MAX;
+
+/// Resource ID of `android:name`.
+pub(crate) const ATTR_NAME: u32 = 0x0101_0003;
+/// Resource ID of `android:versionCode`.
+pub(crate) const ATTR_VERSION_CODE: u32 = 0x0101_021b;
+/// Resource ID of `android:versionName`.
+pub(crate) const ATTR_VERSION_NAME: u32 = 0x0101_021c;
+/// Resource ID of `android:minSdkVersion`.
+pub(crate) const ATTR_MIN_SDK_VERSION: u32 = 0x0101_020c;
+/// Resource ID of `android:targetSdkVersion`.
+pub(crate) const ATTR_TARGET_SDK_VERSION: u32 = 0x0101_0270;
+/// Resource ID of `android:maxSdkVersion`.
+pub(crate) const ATTR_MAX_SDK_VERSION: u32 = 0x0101_0271;
+
+/// Metadata declared in an APK's manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AndroidManifest {
+    /// Application package name.
+    pub package: AppId,
+    /// `android:versionCode`.
+    pub version_code: i64,
+    /// `android:versionName`, empty if not declared.
+    pub version_name: String,
+    /// `android:minSdkVersion`, 1 if not declared.
+    pub min_sdk: i32,
+    /// `android:targetSdkVersion`, defaulting to the minimum SDK.
+    pub target_sdk: i32,
+    /// Requested permissions.
+    pub permissions: Vec<Permission>,
+}
+
+impl AndroidManifest {
+    /// Decode a binary `AndroidManifest.xml`.
+    pub fn parse(data: &[u8]) -> ScanResult<Self> {
+        let elements = decode(data)?;
+
+        let manifest = elements
+            .iter()
+            .find(|element| element.depth == 0 && element.name == "manifest")
+            .ok_or_else(|| invalid("missing <manifest> element"))?;
+        let package = manifest
+            .string("package", None)
+            .ok_or_else(|| invalid("manifest has no package"))?;
+        let package = AppId::parse(package).map_err(|err| invalid(&err.to_string()))?;
+        let version_code: i64 = manifest
+            .integer("versionCode", Some(ATTR_VERSION_CODE))?
+            .ok_or_else(|| invalid("manifest has no versionCode"))?;
+        let version_name = manifest
+            .string("versionName", Some(ATTR_VERSION_NAME))
+            .unwrap_or_default()
+            .to_string();
+
+        // Manifest-level children only; nested elements are not declarations
+        let children = || elements.iter().filter(|element| element.depth == 1);
+
+        let uses_sdk = children().find(|element| element.name == "uses-sdk");
+        let min_sdk: i32 = uses_sdk
+            .map(|element| element.integer("minSdkVersion", Some(ATTR_MIN_SDK_VERSION)))
+            .transpose()?
+            .flatten()
+            .unwrap_or(1);
+        let target_sdk: i32 = uses_sdk
+            .map(|element| element.integer("targetSdkVersion", Some(ATTR_TARGET_SDK_VERSION)))
+            .transpose()?
+            .flatten()
+            .unwrap_or(min_sdk);
+
+        let mut permissions = Vec::new();
+        for element in children().filter(|element| {
+            element.name == "uses-permission" || element.name == "uses-permission-sdk-23"
+        }) {
+            let Some(name) = element.string("name", Some(ATTR_NAME)) else {
+                continue;
+            };
+            permissions.push(Permission {
+                name: name.to_string(),
+                max_sdk: element.integer("maxSdkVersion", Some(ATTR_MAX_SDK_VERSION))?,
+            });
+        }
+
+        Ok(Self {
+            package,
+            version_code,
+            version_name,
+            min_sdk,
+            target_sdk,
+            permissions,
+        })
+    }
+}
+
+/// A decoded element start.
+#[derive(Debug)]
+struct Element {
+    /// Nesting depth; the root element is at depth 0.
+    depth: usize,
+    name: String,
+    attributes: Vec<Attribute>,
+}
+
+#[derive(Debug)]
+struct Attribute {
+    name: String,
+    resource_id: Option<u32>,
+    value: Value,
+}
+
+#[derive(Debug)]
+enum Value {
+    String(String),
+    Integer(u32),
+    Other,
+}
+
+impl Element {
+    /// Find an attribute by resource ID, falling back to its name.
+    fn attribute(&self, name: &str, resource_id: Option<u32>) -> Option<&Value> {
+        self.attributes
+            .iter()
+            .find(|attr| resource_id.is_some() && attr.resource_id == resource_id)
+            .or_else(|| self.attributes.iter().find(|attr| attr.name == name))
+            .map(|attr| &attr.value)
+    }
+
+    fn string(&self, name: &str, resource_id: Option<u32>) -> Option<&str> {
+        match self.attribute(name, resource_id) {
+            Some(Value::String(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Integer attribute value; numeric strings are accepted as well.
+    fn integer<T: TryFrom<u32> + std::str::FromStr>(
+        &self,
+        name: &str,
+        resource_id: Option<u32>,
+    ) -> ScanResult<Option<T>> {
+        let bad_value = || invalid(&format!("<{}> has invalid {name}", self.name));
+        match self.attribute(name, resource_id) {
+            None => Ok(None),
+            Some(Value::Integer(value)) => T::try_from(*value).map(Some).map_err(|_| bad_value()),
+            Some(Value::String(value)) => value.parse().map(Some).map_err(|_| bad_value()),
+            Some(Value::Other) => Err(bad_value()),
+        }
+    }
+}
+
+/// Decode the element starts of a binary XML document.
+fn decode(data: &[u8]) -> ScanResult<Vec<Element>> {
+    if u16_at(data, 0)? != RES_XML_TYPE {
+        return Err(invalid("manifest is not binary XML"));
+    }
+    let header_size = usize::from(u16_at(data, 2)?);
+    let size = usize_at(data, 4)?;
+    let data = data
+        .get(..size)
+        .ok_or_else(|| invalid("truncated manifest"))?;
+
+    let mut strings = Vec::new();
+    let mut resource_ids: &[u8] = &[];
+    let mut elements = Vec::new();
+    let mut depth = 0usize;
+
+    let mut offset = header_size;
+    while offset < data.len() {
+        let chunk_type = u16_at(data, offset)?;
+        let chunk_size = usize_at(data, offset + 4)?;
+        if chunk_size < 8 {
+            return Err(invalid("malformed manifest chunk"));
+        }
+        let chunk = offset
+            .checked_add(chunk_size)
+            .and_then(|end| data.get(offset..end))
+            .ok_or_else(|| invalid("truncated manifest chunk"))?;
+
+        match chunk_type {
+            RES_STRING_POOL_TYPE => strings = string_pool(chunk)?,
+            RES_XML_RESOURCE_MAP_TYPE => {
+                resource_ids = chunk
+                    .get(usize::from(u16_at(chunk, 2)?)..)
+                    .ok_or_else(|| invalid("malformed resource map"))?;
+            }
+            RES_XML_START_ELEMENT_TYPE => {
+                elements.push(start_element(chunk, depth, &strings, resource_ids)?);
+                depth += 1;
+            }
+            RES_XML_END_ELEMENT_TYPE => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+
+        offset += chunk_size;
+    }
+
+    Ok(elements)
+}
+
+/// Decode an element start chunk.
+fn start_element(
+    chunk: &[u8],
+    depth: usize,
+    strings: &[String],
+    resource_ids: &[u8],
+) -> ScanResult<Element> {
+    let ext = usize::from(u16_at(chunk, 2)?);
+    let name = string_at(strings, u32_at(chunk, ext + 4)?)?;
+    let attribute_start = usize::from(u16_at(chunk, ext + 8)?);
+    let attribute_size = usize::from(u16_at(chunk, ext + 10)?);
+    let attribute_count = usize::from(u16_at(chunk, ext + 12)?);
+
+    let mut attributes = Vec::with_capacity(attribute_count);
+    for index in 0..attribute_count {
+        let attr = ext + attribute_start + index * attribute_size;
+        let name_index = u32_at(chunk, attr + 4)?;
+        let raw_value = u32_at(chunk, attr + 8)?;
+        let data_type = *chunk
+            .get(attr + 15)
+            .ok_or_else(|| invalid("truncated manifest attribute"))?;
+        let data = u32_at(chunk, attr + 16)?;
+
+        let value = match data_type {
+            TYPE_STRING => Value::String(string_at(strings, data)?.to_string()),
+            TYPE_INT_DEC..=TYPE_LAST_INT => Value::Integer(data),
+            _ if raw_value != NO_ENTRY => Value::String(string_at(strings, raw_value)?.to_string()),
+            _ => Value::Other,
+        };
+
+        let resource_id = usize::try_from(name_index)
+            .ok()
+            .and_then(|index| u32_at(resource_ids, index * 4).ok());
+
+        attributes.push(Attribute {
+            name: string_at(strings, name_index)?.to_string(),
+            resource_id,
+            value,
+        });
+    }
+
+    Ok(Element {
+        depth,
+        name: name.to_string(),
+        attributes,
+    })
+}
+
+/// Decode a string pool chunk.
+fn string_pool(chunk: &[u8]) -> ScanResult<Vec<String>> {
+    let header_size = usize::from(u16_at(chunk, 2)?);
+    let count = usize_at(chunk, 8)?;
+    let utf8 = u32_at(chunk, 16)? & UTF8_FLAG != 0;
+    let strings_start = usize_at(chunk, 20)?;
+
+    (0..count)
+        .map(|index| {
+            let entry = index
+                .checked_mul(4)
+                .and_then(|entry| entry.checked_add(header_size))
+                .ok_or_else(|| invalid("manifest string pool too large"))?;
+            let offset = offset_by(strings_start, usize_at(chunk, entry)?)?;
+            if utf8 {
+                utf8_string(chunk, offset)
+            } else {
+                utf16_string(chunk, offset)
+            }
+        })
+        .collect()
+}
+
+/// Decode a UTF-8 pool string: UTF-16 length, UTF-8 length, bytes.
+fn utf8_string(chunk: &[u8], offset: usize) -> ScanResult<String> {
+    let (_, offset) = utf8_length(chunk, offset)?;
+    let (len, offset) = utf8_length(chunk, offset)?;
+    let bytes = chunk
+        .get(offset..offset_by(offset, len)?)
+        .ok_or_else(|| invalid("truncated manifest string"))?;
+    Ok(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// Read a one- or two-byte UTF-8 pool length.
+fn utf8_length(chunk: &[u8], offset: usize) -> ScanResult<(usize, usize)> {
+    let byte = |at: usize| {
+        chunk
+            .get(at)
+            .map(|value| usize::from(*value))
+            .ok_or_else(|| invalid("truncated manifest string"))
+    };
+    let first = byte(offset)?;
+    if first & 0x80 == 0 {
+        Ok((first, offset_by(offset, 1)?))
+    } else {
+        let second = byte(offset_by(offset, 1)?)?;
+        Ok((((first & 0x7f) << 8) | second, offset_by(offset, 2)?))
+    }
+}
+
+/// Decode a UTF-16 pool string: one- or two-unit length, then code units.
+fn utf16_string(chunk: &[u8], offset: usize) -> ScanResult<String> {
+    let first = usize::from(u16_at(chunk, offset)?);
+    let (len, offset) = if first & 0x8000 == 0 {
+        (first, offset_by(offset, 2)?)
+    } else {
+        let second = usize::from(u16_at(chunk, offset_by(offset, 2)?)?);
+        (((first & 0x7fff) << 16) | second, offset_by(offset, 4)?)
+    };
+
+    let bytes = len
+        .checked_mul(2)
+        .and_then(|size| chunk.get(offset..offset.checked_add(size)?))
+        .ok_or_else(|| invalid("truncated manifest string"))?;
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+        .collect();
+    Ok(String::from_utf16_lossy(&units))
+}
+
+/// `base + offset`, unless that overflows.
+fn offset_by(base: usize, offset: usize) -> ScanResult<usize> {
+    base.checked_add(offset)
+        .ok_or_else(|| invalid("manifest offset out of range"))
+}
+
+fn string_at(strings: &[String], index: u32) -> ScanResult<&str> {
+    usize::try_from(index)
+        .ok()
+        .and_then(|index| strings.get(index))
+        .map(String::as_str)
+        .ok_or_else(|| invalid("manifest string index out of range"))
+}
+
+fn u16_at(data: &[u8], offset: usize) -> ScanResult<u16> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| invalid("truncated manifest"))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> ScanResult<u32> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or_else(|| invalid("truncated manifest"))
+}
+
+fn usize_at(data: &[u8], offset: usize) -> ScanResult<usize> {
+    usize::try_from(u32_at(data, offset)?).map_err(|_| invalid("manifest offset out of range"))
+}
+
+fn invalid(message: &str) -> ScanError {
+    ScanError::InvalidApk(format!("AndroidManifest.xml: {message}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::ManifestBuilder;
+
+    #[test]
+    fn test_parse_manifest() {
+        let data = ManifestBuilder::new("dk.digst.mitid")
+            .version(42, "1.4.2")
+            .sdk(26, 34)
+            .permission("android.permission.INTERNET")
+            .permission_max_sdk("android.permission.WRITE_EXTERNAL_STORAGE", 28)
+            .build();
+
+        let manifest = AndroidManifest::parse(&data).expect("parse");
+        assert_eq!(manifest.package.as_str(), "dk.digst.mitid");
+        assert_eq!(manifest.version_code, 42);
+        assert_eq!(manifest.version_name, "1.4.2");
+        assert_eq!(manifest.min_sdk, 26);
+        assert_eq!(manifest.target_sdk, 34);
+        assert_eq!(
+            manifest.permissions,
+            vec![
+                Permission {
+                    name: "android.permission.INTERNET".to_string(),
+                    max_sdk: None,
+                },
+                Permission {
+                    name: "android.permission.WRITE_EXTERNAL_STORAGE".to_string(),
+                    max_sdk: Some(28),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sdk_defaults() {
+        let data = ManifestBuilder::new("dk.example.app")
+            .version(1, "1.0")
+            .build();
+
+        let manifest = AndroidManifest::parse(&data).expect("parse");
+        assert_eq!(manifest.min_sdk, 1);
+        assert_eq!(manifest.target_sdk, 1);
+        assert!(manifest.permissions.is_empty());
+    }
+
+    /// `data` with the only occurrence of `from` replaced by `to`.
+    fn patched(mut data: Vec<u8>, from: &[u8], to: &[u8]) -> Vec<u8> {
+        let at = data
+            .windows(from.len())
+            .position(|window| window == from)
+            .expect("bytes to patch");
+        data[at..at + to.len()].copy_from_slice(to);
+        data
+    }
+
+    #[test]
+    fn test_parse_utf16_string_pool() {
+        let data = ManifestBuilder::new("dk.digst.mitid")
+            .version(3, "2.0 – Æble 🇩🇰")
+            .permission("android.permission.CAMERA")
+            .utf16()
+            .build();
+
+        let manifest = AndroidManifest::parse(&data).expect("parse");
+        assert_eq!(manifest.package.as_str(), "dk.digst.mitid");
+        assert_eq!(manifest.version_name, "2.0 – Æble 🇩🇰");
+        assert_eq!(manifest.permissions[0].name, "android.permission.CAMERA");
+    }
+
+    #[test]
+    fn test_invalid_utf8_is_replaced() {
+        let data = ManifestBuilder::new("dk.example.app")
+            .version(1, "1.x")
+            .build();
+        let data = patched(data, b"1.x", &[b'1', b'.', 0xff]);
+
+        let manifest = AndroidManifest::parse(&data).expect("parse");
+        assert_eq!(manifest.version_name, "1.\u{fffd}");
+    }
+
+    #[test]
+    fn test_unpaired_utf16_surrogate_is_replaced() {
+        let data = ManifestBuilder::new("dk.example.app")
+            .version(1, "1.q")
+            .utf16()
+            .build();
+        let data = patched(
+            data,
+            &[b'1', 0, b'.', 0, b'q', 0],
+            &[b'1', 0, b'.', 0, 0x00, 0xd8],
+        );
+
+        let manifest = AndroidManifest::parse(&data).expect("parse");
+        assert_eq!(manifest.version_name, "1.\u{fffd}");
+    }
+
+    #[test]
+    fn test_rejects_string_past_pool_end() {
+        // The version name is the last pool string; claim it runs on
+        let data = ManifestBuilder::new("dk.example.app")
+            .version(1, "1.x")
+            .build();
+        let data = patched(data, &[3, 3, b'1', b'.', b'x'], &[3, 0x7f]);
+
+        assert!(matches!(
+            AndroidManifest::parse(&data),
+            Err(ScanError::InvalidApk(_))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_invalid_package_after_replacement() {
+        let data = ManifestBuilder::new("dk.example.app")
+            .version(1, "1.0")
+            .build();
+        let data = patched(
+            data,
+            b"example",
+            &[b'e', b'x', 0xc3, b'm', b'p', b'l', b'e'],
+        );
+
+        assert!(matches!(
+            AndroidManifest::parse(&data),
+            Err(ScanError::InvalidApk(_))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_text_xml() {
+        assert!(matches!(
+            AndroidManifest::parse(b"<manifest package=\"dk.example\"/>"),
+            Err(ScanError::InvalidApk(_))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_truncated_manifest() {
+        let data = ManifestBuilder::new("dk.example.app")
+            .version(1, "1.0")
+            .build();
+        assert!(matches!(
+            AndroidManifest::parse(&data[..data.len() / 2]),
+            Err(ScanError::InvalidApk(_))
+        ));
+    }
+}
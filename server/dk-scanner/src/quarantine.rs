@@ -0,0 +1,140 @@
+//! Quarantine of APK versions that failed their security scan.
+//!
+//! A quarantined version is never listed in the repository index, whatever
+//! its publication state.
+
+use chrono::{DateTime, Utc};
+use dk_common::types::AppId;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+
+use crate::error::ScanResult;
+
+/// An APK version withheld from the index.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuarantinedVersion {
+    /// Package the version belongs to.
+    pub package_id: AppId,
+    /// Android versionCode.
+    pub version_code: i64,
+    /// Why the version was quarantined.
+    pub reason: String,
+    /// When the version was quarantined.
+    pub quarantined_at: DateTime<Utc>,
+}
+
+impl QuarantinedVersion {
+    /// Whether this entry quarantines `version_code` of `package_id`.
+    #[must_use]
+    pub fn matches(&self, package_id: &AppId, version_code: i64) -> bool {
+        self.package_id == *package_id && self.version_code == version_code
+    }
+}
+
+/// Quarantined versions, stored in the `quarantined_versions` table.
+#[derive(Debug, Clone)]
+pub struct QuarantineStore {
+    db: PgPool,
+}
+
+impl QuarantineStore {
+    /// Use the quarantine table in `db`.
+    #[must_use]
+    pub const fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Quarantine `version_code` of `package_id`, replacing the reason if
+    /// it is already quarantined.
+    pub async fn quarantine(
+        &self,
+        package_id: &AppId,
+        version_code: i64,
+        reason: &str,
+    ) -> ScanResult<()> {
+        sqlx::query(
+            "INSERT INTO quarantined_versions (package_id, version_code, reason) \
+             VALUES ($1, $2, $3) ON CONFLICT (package_id, version_code) \
+             DO UPDATE SET reason = EXCLUDED.reason, quarantined_at = now()",
+        )
+        .bind(package_id.as_str())
+        .bind(version_code)
+        .bind(reason)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// All quarantined versions, most recent first.
+    pub async fn list(&self) -> ScanResult<Vec<QuarantinedVersion>> {
+        sqlx::query(
+            "SELECT package_id, version_code, reason, quarantined_at \
+             FROM quarantined_versions ORDER BY quarantined_at DESC, package_id, version_code",
+        )
+        .fetch_all(&self.db)
+        .await?
+        .iter()
+        .map(|row| {
+            Ok(QuarantinedVersion {
+                package_id: AppId::new(row.try_get::<String, _>("package_id")?),
+                version_code: row.try_get("version_code")?,
+                reason: row.try_get("reason")?,
+                quarantined_at: row.try_get("quarantined_at")?,
+            })
+        })
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_package_and_version() {
+        let entry = QuarantinedVersion {
+            package_id: AppId::new("dk.digst.mitid"),
+            version_code: 7,
+            reason: "signature.invalid".to_string(),
+            quarantined_at: Utc::now(),
+        };
+        assert!(entry.matches(&AppId::new("dk.digst.mitid"), 7));
+        assert!(!entry.matches(&AppId::new("dk.digst.mitid"), 8));
+        assert!(!entry.matches(&AppId::new("dk.sundhed.app"), 7));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_quarantine_round_trip() {
+        use sqlx::Executor;
+
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL");
+        let db = PgPool::connect(&url).await.expect("connect");
+        db.execute(include_str!(
+            "../../migrations/0004_create_quarantined_versions.sql"
+        ))
+        .await
+        .expect("migrate");
+
+        let store = QuarantineStore::new(db);
+        let package_id = AppId::new(format!("dk.test.q{}", uuid::Uuid::new_v4().simple()));
+        store
+            .quarantine(&package_id, 3, "first")
+            .await
+            .expect("quarantine");
+        store
+            .quarantine(&package_id, 3, "second")
+            .await
+            .expect("quarantine again");
+
+        let entries: Vec<_> = store
+            .list()
+            .await
+            .expect("list")
+            .into_iter()
+            .filter(|entry| entry.package_id == package_id)
+            .collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].reason, "second");
+    }
+}
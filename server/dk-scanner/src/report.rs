@@ -0,0 +1,285 @@
+//! Aggregated scan report.
+
+use dk_common::types::{Antifeature, Permission, ScanStatus};
+use serde::{Deserialize, Serialize};
+
+use crate::finding::{derive_status, ScanFinding, Severity};
+use crate::signature::SignatureInfo;
+use crate::trackers::TrackerHit;
+
+/// Permissions that warrant a closer look during review.
+///
+/// These grant access to location, sensors, communication, or other apps,
+/// or let an app act outside its sandbox.
+pub const CONCERNING_PERMISSIONS: &[&str] = &[
+    "android.permission.ACCESS_BACKGROUND_LOCATION",
+    "android.permission.ACCESS_FINE_LOCATION",
+    "android.permission.BODY_SENSORS",
+    "android.permission.CAMERA",
+    "android.permission.MANAGE_EXTERNAL_STORAGE",
+    "android.permission.QUERY_ALL_PACKAGES",
+    "android.permission.READ_CALL_LOG",
+    "android.permission.READ_CONTACTS",
+    "android.permission.READ_PHONE_STATE",
+    "android.permission.READ_SMS",
+    "android.permission.RECORD_AUDIO",
+    "android.permission.REQUEST_INSTALL_PACKAGES",
+    "android.permission.SEND_SMS",
+    "android.permission.SYSTEM_ALERT_WINDOW",
+];
+
+/// Outcome of the APK signature check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "lowercase")]
+pub enum SignatureCheck {
+    /// The APK carries a valid v2/v3 signature.
+    Valid(SignatureInfo),
+    /// The signature is missing or does not verify.
+    Invalid {
+        /// Why verification failed.
+        reason: String,
+    },
+}
+
+/// A permission requested by the APK, with its review classification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PermissionReview {
+    /// Permission name.
+    pub name: String,
+    /// Highest SDK level the permission is requested on, if limited.
+    pub max_sdk: Option<i32>,
+    /// Whether the permission is in [`CONCERNING_PERMISSIONS`].
+    pub concerning: bool,
+}
+
+impl From<&Permission> for PermissionReview {
+    fn from(permission: &Permission) -> Self {
+        Self {
+            name: permission.name.clone(),
+            max_sdk: permission.max_sdk,
+            concerning: CONCERNING_PERMISSIONS.contains(&permission.name.as_str()),
+        }
+    }
+}
+
+/// Results of all checks run against an APK.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanReport {
+    /// Signature verification result.
+    pub signature: SignatureCheck,
+    /// Requested permissions.
+    pub permissions: Vec<PermissionReview>,
+    /// Detected trackers. Informational; they do not affect the status.
+    pub trackers: Vec<TrackerHit>,
+    /// Findings of all checks, from which the status is derived.
+    #[serde(default)]
+    pub findings: Vec<ScanFinding>,
+    /// Overall outcome.
+    pub status: ScanStatus,
+}
+
+impl ScanReport {
+    /// Assemble a report, computing the overall status.
+    #[must_use]
+    pub fn new(
+        signature: SignatureCheck,
+        permissions: Vec<PermissionReview>,
+        trackers: Vec<TrackerHit>,
+    ) -> Self {
+        let findings = collect_findings(&signature, &permissions, &trackers);
+        let status = derive_status(&findings);
+        Self {
+            signature,
+            permissions,
+            trackers,
+            findings,
+            status,
+        }
+    }
+
+    /// Add the findings of further checks, updating the status.
+    #[must_use]
+    pub fn with_findings(mut self, findings: impl IntoIterator<Item = ScanFinding>) -> Self {
+        self.findings.extend(findings);
+        self.status = derive_status(&self.findings);
+        self
+    }
+
+    /// Anti-features the scan suggests flagging the app with, for a
+    /// reviewer to confirm: `Tracking` if a tracker was detected.
+    #[must_use]
+    pub fn suggested_antifeatures(&self) -> Vec<Antifeature> {
+        if self.trackers.is_empty() {
+            Vec::new()
+        } else {
+            vec![Antifeature::Tracking]
+        }
+    }
+}
+
+/// Findings of the individual checks: an invalid signature is critical,
+/// concerning permissions are medium, and trackers are informational.
+fn collect_findings(
+    signature: &SignatureCheck,
+    permissions: &[PermissionReview],
+    trackers: &[TrackerHit],
+) -> Vec<ScanFinding> {
+    let mut findings = Vec::new();
+    if let SignatureCheck::Invalid { reason } = signature {
+        findings.push(ScanFinding::new(
+            Severity::Critical,
+            "signature.invalid",
+            reason.clone(),
+        ));
+    }
+    findings.extend(
+        permissions
+            .iter()
+            .filter(|permission| permission.concerning)
+            .map(|permission| {
+                ScanFinding::new(
+                    Severity::Medium,
+                    "permission.concerning",
+                    format!("Requests {}", permission.name),
+                )
+            }),
+    );
+    findings.extend(trackers.iter().map(|hit| {
+        ScanFinding::new(
+            Severity::Info,
+            "tracker.detected",
+            format!("Contains {} ({})", hit.tracker, hit.signature),
+        )
+    }));
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::SignatureScheme;
+
+    fn valid() -> SignatureCheck {
+        SignatureCheck::Valid(SignatureInfo {
+            scheme: SignatureScheme::V2,
+            certificate_sha256: vec!["ab".repeat(32)],
+            fdroid_sig: vec!["cd".repeat(16)],
+        })
+    }
+
+    fn permissions(names: &[&str]) -> Vec<PermissionReview> {
+        names
+            .iter()
+            .map(|name| {
+                PermissionReview::from(&Permission {
+                    name: (*name).to_string(),
+                    max_sdk: None,
+                })
+            })
+            .collect()
+    }
+
+    fn tracker() -> TrackerHit {
+        TrackerHit {
+            tracker: "AppsFlyer".to_string(),
+            signature: "com.appsflyer".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_status_passed() {
+        let report = ScanReport::new(
+            valid(),
+            permissions(&["android.permission.INTERNET"]),
+            vec![],
+        );
+        assert_eq!(report.status, ScanStatus::Passed);
+    }
+
+    #[test]
+    fn test_status_warning_on_concerning_permission() {
+        let report = ScanReport::new(
+            valid(),
+            permissions(&["android.permission.INTERNET", "android.permission.CAMERA"]),
+            vec![],
+        );
+        assert_eq!(report.status, ScanStatus::Warning);
+        assert!(!report.permissions[0].concerning);
+        assert!(report.permissions[1].concerning);
+    }
+
+    #[test]
+    fn test_status_failed_on_invalid_signature() {
+        let report = ScanReport::new(
+            SignatureCheck::Invalid {
+                reason: "APK has no v2/v3 signature".to_string(),
+            },
+            permissions(&["android.permission.CAMERA"]),
+            vec![],
+        );
+        assert_eq!(report.status, ScanStatus::Failed);
+        let codes: Vec<_> = report
+            .findings
+            .iter()
+            .map(|finding| finding.code.as_str())
+            .collect();
+        assert_eq!(codes, ["signature.invalid", "permission.concerning"]);
+        assert_eq!(report.findings[0].severity, Severity::Critical);
+    }
+
+    #[test]
+    fn test_trackers_do_not_affect_status() {
+        let report = ScanReport::new(valid(), vec![], vec![tracker()]);
+        assert_eq!(report.status, ScanStatus::Passed);
+        assert_eq!(report.findings[0].severity, Severity::Info);
+        assert_eq!(
+            report.findings[0].message,
+            "Contains AppsFlyer (com.appsflyer)"
+        );
+    }
+
+    #[test]
+    fn test_trackers_suggest_tracking_antifeature() {
+        let report = ScanReport::new(valid(), vec![], vec![tracker()]);
+        assert_eq!(report.suggested_antifeatures(), [Antifeature::Tracking]);
+
+        let report = ScanReport::new(valid(), permissions(&["android.permission.CAMERA"]), vec![]);
+        assert!(report.suggested_antifeatures().is_empty());
+    }
+
+    #[test]
+    fn test_added_critical_finding_fails() {
+        let report = ScanReport::new(valid(), vec![], vec![]).with_findings([ScanFinding::new(
+            Severity::Critical,
+            "dependency.vulnerable",
+            "CVE-2021-3711",
+        )]);
+        assert_eq!(report.status, ScanStatus::Failed);
+        assert_eq!(report.findings.len(), 1);
+    }
+
+    #[test]
+    fn test_report_json() {
+        let report = ScanReport::new(valid(), vec![], vec![tracker()]);
+        let json = serde_json::to_value(&report).expect("serialize");
+        assert_eq!(json["status"], "passed");
+        assert_eq!(json["signature"]["result"], "valid");
+        assert_eq!(json["signature"]["scheme"], "v2");
+        assert_eq!(json["trackers"][0]["tracker"], "AppsFlyer");
+
+        assert_eq!(json["findings"][0]["severity"], "info");
+
+        let back: ScanReport = serde_json::from_value(json).expect("deserialize");
+        assert_eq!(back, report);
+    }
+
+    #[test]
+    fn test_report_without_findings_deserializes() {
+        // Reports stored before findings were recorded
+        let mut json =
+            serde_json::to_value(ScanReport::new(valid(), vec![], vec![])).expect("serialize");
+        json.as_object_mut().expect("object").remove("findings");
+        let report: ScanReport = serde_json::from_value(json).expect("deserialize");
+        assert!(report.findings.is_empty());
+    }
+}
@@ -0,0 +1,144 @@
+//! Stored scan reports.
+//!
+//! Every scan of a version is kept, so a rescan never hides the report it
+//! replaced; readers are usually after the latest one.
+
+use chrono::{DateTime, Utc};
+use dk_common::types::AppId;
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::{PgPool, Row};
+
+use crate::error::ScanResult;
+use crate::report::ScanReport;
+
+/// A scan report of an APK version, as stored.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredScanReport {
+    /// Package the version belongs to.
+    pub package_id: AppId,
+    /// Android versionCode.
+    pub version_code: i64,
+    /// The report.
+    pub report: ScanReport,
+    /// When the report was stored.
+    pub scanned_at: DateTime<Utc>,
+}
+
+/// Scan reports, stored in the `scan_results` table.
+#[derive(Debug, Clone)]
+pub struct ScanReportStore {
+    db: PgPool,
+}
+
+impl ScanReportStore {
+    /// Use the scan results table in `db`.
+    #[must_use]
+    pub const fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Store `report` as the latest scan of `version_code` of `package_id`.
+    pub async fn record(
+        &self,
+        package_id: &AppId,
+        version_code: i64,
+        report: &ScanReport,
+    ) -> ScanResult<()> {
+        sqlx::query(
+            "INSERT INTO scan_results (package_id, version_code, status, report) \
+             VALUES ($1, $2, $3, $4)",
+        )
+        .bind(package_id.as_str())
+        .bind(version_code)
+        .bind(report.status.as_str())
+        .bind(Json(report))
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// The latest report of `version_code` of `package_id`, or `None` if
+    /// it has never been scanned.
+    pub async fn latest(
+        &self,
+        package_id: &AppId,
+        version_code: i64,
+    ) -> ScanResult<Option<StoredScanReport>> {
+        let Some(row) = sqlx::query(
+            "SELECT report, scanned_at FROM scan_results \
+             WHERE package_id = $1 AND version_code = $2 \
+             ORDER BY scanned_at DESC, id DESC LIMIT 1",
+        )
+        .bind(package_id.as_str())
+        .bind(version_code)
+        .fetch_optional(&self.db)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let Json(report) = row.try_get::<Json<ScanReport>, _>("report")?;
+        Ok(Some(StoredScanReport {
+            package_id: package_id.clone(),
+            version_code,
+            report,
+            scanned_at: row.try_get("scanned_at")?,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::SignatureCheck;
+    use crate::signature::{SignatureInfo, SignatureScheme};
+
+    #[tokio::test]
+    #[ignore = "requires DATABASE_URL"]
+    async fn test_latest_report_wins() {
+        use sqlx::Executor;
+
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL");
+        let db = PgPool::connect(&url).await.expect("connect");
+        db.execute(include_str!(
+            "../../migrations/0013_create_scan_results.sql"
+        ))
+        .await
+        .expect("migrate");
+
+        let store = ScanReportStore::new(db);
+        let package_id = AppId::new(format!("dk.test.s{}", uuid::Uuid::new_v4().simple()));
+        assert_eq!(store.latest(&package_id, 1).await.expect("latest"), None);
+
+        let failed = ScanReport::new(
+            SignatureCheck::Invalid {
+                reason: "APK has no v2/v3 signature".to_string(),
+            },
+            vec![],
+            vec![],
+        );
+        let passed = ScanReport::new(
+            SignatureCheck::Valid(SignatureInfo {
+                scheme: SignatureScheme::V2,
+                certificate_sha256: vec!["ab".repeat(32)],
+                fdroid_sig: vec!["cd".repeat(16)],
+            }),
+            vec![],
+            vec![],
+        );
+        store.record(&package_id, 1, &failed).await.expect("record");
+        store
+            .record(&package_id, 1, &passed)
+            .await
+            .expect("record again");
+
+        let latest = store
+            .latest(&package_id, 1)
+            .await
+            .expect("latest")
+            .expect("stored");
+        assert_eq!(latest.report, passed);
+        assert_eq!(store.latest(&package_id, 2).await.expect("latest"), None);
+    }
+}
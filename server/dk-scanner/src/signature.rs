@@ -0,0 +1,468 @@
+//! APK Signature Scheme v2/v3 verification.
+//!
+//! Locates the APK Signing Block between the ZIP entries and the central
+//! directory, verifies each signer's signature over its signed data, checks
+//! the chunked content digest of the whole archive, and reports the signer
+//! certificate fingerprints.
+
+use std::path::Path;
+
+use dk_common::hash::hex;
+use dk_signing::apk::{content_digest, signing_block_start, ZipLayout};
+use dk_signing::{der, SigningError};
+use md5::{Digest, Md5};
+use ring::digest::{self, Algorithm, SHA256, SHA512};
+use ring::signature::{
+    UnparsedPublicKey, VerificationAlgorithm, ECDSA_P256_SHA256_ASN1, RSA_PKCS1_2048_8192_SHA256,
+    RSA_PKCS1_2048_8192_SHA512, RSA_PSS_2048_8192_SHA256, RSA_PSS_2048_8192_SHA512,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::apk::io_error;
+use crate::error::{ScanError, ScanResult};
+
+/// ID of the APK Signature Scheme v2 block.
+pub const APK_SIGNATURE_SCHEME_V2_BLOCK_ID: u32 = 0x7109_871a;
+
+/// ID of the APK Signature Scheme v3 block.
+pub const APK_SIGNATURE_SCHEME_V3_BLOCK_ID: u32 = 0xf053_68c0;
+
+/// Signature algorithm ID for ECDSA with SHA-256.
+pub(crate) const SIGNATURE_ECDSA_WITH_SHA256: u32 = 0x0201;
+
+/// APK signature scheme version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureScheme {
+    /// APK Signature Scheme v2.
+    V2,
+    /// APK Signature Scheme v3.
+    V3,
+}
+
+/// Result of a successful APK signature verification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignatureInfo {
+    /// Scheme the verified signature block uses.
+    pub scheme: SignatureScheme,
+    /// Lowercase hex SHA-256 fingerprints of each signer's certificate.
+    pub certificate_sha256: Vec<String>,
+    /// F-Droid `sig` of each signer's certificate, in the same order.
+    #[serde(default)]
+    pub fdroid_sig: Vec<String>,
+}
+
+impl SignatureInfo {
+    /// F-Droid `sig` of the first signer, as listed in the index.
+    pub fn sig(&self) -> Option<&str> {
+        self.fdroid_sig.first().map(String::as_str)
+    }
+}
+
+/// F-Droid `sig` of a DER `certificate`: the MD5 of its lowercase hex
+/// encoding, itself in lowercase hex.
+pub fn fdroid_sig(certificate: &[u8]) -> String {
+    hex(&Md5::digest(hex(certificate).as_bytes()))
+}
+
+/// Verify the v2/v3 signature of the APK at `path`.
+///
+/// `expected_cert_sha256` is the hex SHA-256 fingerprint (colons optional) of
+/// the certificate the APK must be signed with.
+pub fn verify_apk_signature(path: &Path, expected_cert_sha256: &str) -> ScanResult<SignatureInfo> {
+    let apk = std::fs::read(path).map_err(|err| io_error(path, &err))?;
+
+    let info = verify_apk_bytes(&apk)?;
+
+    let expected = normalize_fingerprint(expected_cert_sha256);
+    if !info.certificate_sha256.contains(&expected) {
+        return Err(ScanError::CriticalVulnerability(format!(
+            "APK signed by unexpected certificate {}",
+            info.certificate_sha256.join(", ")
+        )));
+    }
+
+    Ok(info)
+}
+
+/// Verify the v2/v3 signature of an in-memory APK.
+pub fn verify_apk_bytes(apk: &[u8]) -> ScanResult<SignatureInfo> {
+    let layout = ZipLayout::parse(apk).map_err(invalid_apk)?;
+    let pairs = signing_block_pairs(apk, &layout)?;
+
+    let (scheme, value) = pairs
+        .iter()
+        .find(|(id, _)| *id == APK_SIGNATURE_SCHEME_V3_BLOCK_ID)
+        .map(|(_, value)| (SignatureScheme::V3, *value))
+        .or_else(|| {
+            pairs
+                .iter()
+                .find(|(id, _)| *id == APK_SIGNATURE_SCHEME_V2_BLOCK_ID)
+                .map(|(_, value)| (SignatureScheme::V2, *value))
+        })
+        .ok_or_else(|| {
+            ScanError::CriticalVulnerability("APK has no v2/v3 signature".to_string())
+        })?;
+
+    let mut signers = Cursor::new(value).length_prefixed()?;
+    let (mut certificate_sha256, mut fdroid_sigs) = (Vec::new(), Vec::new());
+    while !signers.is_empty() {
+        let signer = signers.length_prefixed()?;
+        let certificate = verify_signer(apk, &layout, scheme, signer)?;
+        certificate_sha256.push(hex(digest::digest(&SHA256, certificate).as_ref()));
+        fdroid_sigs.push(fdroid_sig(certificate));
+    }
+
+    if certificate_sha256.is_empty() {
+        return Err(ScanError::CriticalVulnerability(
+            "APK signature block has no signers".to_string(),
+        ));
+    }
+
+    Ok(SignatureInfo {
+        scheme,
+        certificate_sha256,
+        fdroid_sig: fdroid_sigs,
+    })
+}
+
+/// Verify one signer and return its DER certificate.
+fn verify_signer<'a>(
+    apk: &[u8],
+    layout: &ZipLayout,
+    scheme: SignatureScheme,
+    mut signer: Cursor<'a>,
+) -> ScanResult<&'a [u8]> {
+    let signed_data = signer.length_prefixed()?.remaining();
+    if scheme == SignatureScheme::V3 {
+        signer.u32()?; // minSdkVersion
+        signer.u32()?; // maxSdkVersion
+    }
+    let mut signatures = signer.length_prefixed()?;
+    let spki = signer.length_prefixed()?.remaining();
+    let public_key = der::parse_spki(spki).map_err(|err| ScanError::InvalidApk(err.to_string()))?;
+
+    // Pick the strongest signature we can verify
+    let mut best: Option<(SignatureAlgorithmId, &[u8])> = None;
+    while !signatures.is_empty() {
+        let mut entry = signatures.length_prefixed()?;
+        let id = entry.u32()?;
+        let signature = entry.length_prefixed()?.remaining();
+        if let Some(algorithm) = SignatureAlgorithmId::from_id(id) {
+            if best.map_or(true, |(current, _)| {
+                algorithm.strength() > current.strength()
+            }) {
+                best = Some((algorithm, signature));
+            }
+        }
+    }
+    let (algorithm, signature) = best.ok_or_else(|| {
+        ScanError::CriticalVulnerability("no supported signature algorithm".to_string())
+    })?;
+
+    UnparsedPublicKey::new(algorithm.verification(), public_key)
+        .verify(signed_data, signature)
+        .map_err(|_| ScanError::CriticalVulnerability("APK signature is invalid".to_string()))?;
+
+    // Signed data is trusted from here on
+    let mut trusted = Cursor::new(signed_data);
+    let mut digests = trusted.length_prefixed()?;
+    let mut certificates = trusted.length_prefixed()?;
+
+    let mut expected_digest = None;
+    while !digests.is_empty() {
+        let mut entry = digests.length_prefixed()?;
+        let id = entry.u32()?;
+        let value = entry.length_prefixed()?.remaining();
+        if id == algorithm.id() {
+            expected_digest = Some(value);
+        }
+    }
+    let expected_digest = expected_digest.ok_or_else(|| {
+        ScanError::CriticalVulnerability("signed data lacks content digest".to_string())
+    })?;
+    if content_digest(apk, layout, algorithm.digest()).map_err(invalid_apk)? != expected_digest {
+        return Err(ScanError::CriticalVulnerability(
+            "APK contents do not match signed digest".to_string(),
+        ));
+    }
+
+    let certificate = certificates.length_prefixed()?.remaining();
+    let fields = der::parse_certificate(certificate)
+        .map_err(|err| ScanError::InvalidApk(err.to_string()))?;
+    if fields.spki != spki {
+        return Err(ScanError::CriticalVulnerability(
+            "signer public key does not match certificate".to_string(),
+        ));
+    }
+
+    Ok(certificate)
+}
+
+/// APK signature algorithms supported for verification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SignatureAlgorithmId {
+    RsaPssSha256,
+    RsaPssSha512,
+    RsaPkcs1Sha256,
+    RsaPkcs1Sha512,
+    EcdsaSha256,
+}
+
+impl SignatureAlgorithmId {
+    const fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0x0101 => Some(Self::RsaPssSha256),
+            0x0102 => Some(Self::RsaPssSha512),
+            0x0103 => Some(Self::RsaPkcs1Sha256),
+            0x0104 => Some(Self::RsaPkcs1Sha512),
+            SIGNATURE_ECDSA_WITH_SHA256 => Some(Self::EcdsaSha256),
+            _ => None,
+        }
+    }
+
+    const fn id(self) -> u32 {
+        match self {
+            Self::RsaPssSha256 => 0x0101,
+            Self::RsaPssSha512 => 0x0102,
+            Self::RsaPkcs1Sha256 => 0x0103,
+            Self::RsaPkcs1Sha512 => 0x0104,
+            Self::EcdsaSha256 => SIGNATURE_ECDSA_WITH_SHA256,
+        }
+    }
+
+    /// Preference order when a signer offers several algorithms.
+    const fn strength(self) -> u8 {
+        match self {
+            Self::RsaPkcs1Sha256 => 1,
+            Self::RsaPssSha256 => 2,
+            Self::EcdsaSha256 => 3,
+            Self::RsaPkcs1Sha512 => 4,
+            Self::RsaPssSha512 => 5,
+        }
+    }
+
+    fn verification(self) -> &'static dyn VerificationAlgorithm {
+        match self {
+            Self::RsaPssSha256 => &RSA_PSS_2048_8192_SHA256,
+            Self::RsaPssSha512 => &RSA_PSS_2048_8192_SHA512,
+            Self::RsaPkcs1Sha256 => &RSA_PKCS1_2048_8192_SHA256,
+            Self::RsaPkcs1Sha512 => &RSA_PKCS1_2048_8192_SHA512,
+            Self::EcdsaSha256 => &ECDSA_P256_SHA256_ASN1,
+        }
+    }
+
+    const fn digest(self) -> &'static Algorithm {
+        match self {
+            Self::RsaPssSha256 | Self::RsaPkcs1Sha256 | Self::EcdsaSha256 => &SHA256,
+            Self::RsaPssSha512 | Self::RsaPkcs1Sha512 => &SHA512,
+        }
+    }
+}
+
+/// Returns the `(id, value)` pairs of the APK Signing Block.
+fn signing_block_pairs<'a>(apk: &'a [u8], layout: &ZipLayout) -> ScanResult<Vec<(u32, &'a [u8])>> {
+    let start = signing_block_start(apk, layout)
+        .map_err(invalid_apk)?
+        .ok_or_else(|| ScanError::CriticalVulnerability("APK has no signing block".to_string()))?;
+
+    let pairs = apk
+        .get(start + 8..layout.cd_offset - 24)
+        .ok_or_else(|| ScanError::InvalidApk("malformed APK signing block".to_string()))?;
+    let mut pairs = Cursor::new(pairs);
+    let mut result = Vec::new();
+    while !pairs.is_empty() {
+        let len = usize::try_from(pairs.u64()?)
+            .map_err(|_| ScanError::InvalidApk("signing block entry too large".to_string()))?;
+        let mut pair = Cursor::new(pairs.take(len)?);
+        let id = pair.u32()?;
+        result.push((id, pair.remaining()));
+    }
+    Ok(result)
+}
+
+/// Scan error for an APK whose ZIP layout `dk_signing` rejected.
+fn invalid_apk(err: SigningError) -> ScanError {
+    match err {
+        SigningError::InvalidApk(msg) => ScanError::InvalidApk(msg),
+        other => ScanError::InvalidApk(other.to_string()),
+    }
+}
+
+/// Normalize a hex fingerprint: lowercase, without separators.
+fn normalize_fingerprint(fingerprint: &str) -> String {
+    fingerprint
+        .chars()
+        .filter(char::is_ascii_hexdigit)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Little-endian reader over signing block structures.
+struct Cursor<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Cursor<'a> {
+    const fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    const fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    const fn remaining(&self) -> &'a [u8] {
+        self.data
+    }
+
+    fn take(&mut self, len: usize) -> ScanResult<&'a [u8]> {
+        if self.data.len() < len {
+            return Err(ScanError::InvalidApk(
+                "truncated APK signature block".to_string(),
+            ));
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> ScanResult<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn u64(&mut self) -> ScanResult<u64> {
+        let low = u64::from(self.u32()?);
+        let high = u64::from(self.u32()?);
+        Ok((high << 32) | low)
+    }
+
+    /// Read a u32 length-prefixed section.
+    fn length_prefixed(&mut self) -> ScanResult<Self> {
+        let len = usize::try_from(self.u32()?)
+            .map_err(|_| ScanError::InvalidApk("length out of range".to_string()))?;
+        self.take(len).map(Self::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dk_signing::apk::APK_SIG_BLOCK_MAGIC;
+
+    use super::*;
+    use crate::fixtures::{ApkBuilder, ApkSigner, TempApk};
+
+    #[test]
+    fn test_signed_apk_verifies() {
+        let (apk, certificate_sha256) = ApkBuilder::new()
+            .entry("classes.dex", b"dex\n035\0")
+            .entry("res/raw/hello.txt", b"hello")
+            .build_signed();
+        let file = TempApk::write(&apk);
+
+        let info = verify_apk_signature(file.path(), &certificate_sha256).expect("verifies");
+        assert_eq!(info.scheme, SignatureScheme::V2);
+        assert_eq!(info.certificate_sha256, vec![certificate_sha256]);
+    }
+
+    #[test]
+    fn test_fdroid_sig_of_signer_certificate() {
+        // MD5 of the ASCII hex "01ab", as F-Droid computes it
+        assert_eq!(
+            fdroid_sig(&[0x01, 0xab]),
+            "6682fbd9acd1efc3add8e794fef14beb"
+        );
+
+        let signer = ApkSigner::generate();
+        let (apk, _) = ApkBuilder::new()
+            .entry("classes.dex", b"dex\n035\0")
+            .build_signed_by(&signer);
+        let info = verify_apk_bytes(&apk).expect("verifies");
+        assert_eq!(info.sig(), Some(fdroid_sig(&signer.certificate).as_str()));
+    }
+
+    #[test]
+    fn test_apk_signed_by_signing_service_verifies() {
+        let service = dk_signing::SigningService::ephemeral("dk-appstore.test").expect("service");
+        let apk = ApkBuilder::new()
+            .entry("classes.dex", b"dex\n035\0")
+            .build();
+        let unsigned = TempApk::write(&apk);
+        let signed = TempApk::write(&[]);
+        service
+            .sign_apk(unsigned.path(), signed.path())
+            .expect("sign");
+
+        let fingerprint = hex(digest::digest(&SHA256, service.certificate()).as_ref());
+        let info = verify_apk_signature(signed.path(), &fingerprint).expect("verifies");
+        assert_eq!(info.scheme, SignatureScheme::V2);
+    }
+
+    #[test]
+    fn test_tampered_entry_fails() {
+        let (mut apk, certificate_sha256) = ApkBuilder::new()
+            .entry("res/raw/hello.txt", b"hello-original")
+            .build_signed();
+
+        let offset = apk
+            .windows(14)
+            .position(|window| window == b"hello-original")
+            .expect("stored entry");
+        apk[offset] = b'j';
+        let file = TempApk::write(&apk);
+
+        assert!(matches!(
+            verify_apk_signature(file.path(), &certificate_sha256),
+            Err(ScanError::CriticalVulnerability(_))
+        ));
+    }
+
+    #[test]
+    fn test_unexpected_certificate_fails() {
+        let (apk, _) = ApkBuilder::new().entry("a.txt", b"a").build_signed();
+        let file = TempApk::write(&apk);
+
+        assert!(matches!(
+            verify_apk_signature(file.path(), &"00".repeat(32)),
+            Err(ScanError::CriticalVulnerability(_))
+        ));
+    }
+
+    #[test]
+    fn test_unsigned_apk_fails() {
+        let apk = ApkBuilder::new().entry("a.txt", b"a").build();
+        assert!(matches!(
+            verify_apk_bytes(&apk),
+            Err(ScanError::CriticalVulnerability(_))
+        ));
+    }
+
+    #[test]
+    fn test_undersized_signing_block_fails() {
+        let (mut apk, _) = ApkBuilder::new().entry("a.txt", b"a").build_signed();
+        let magic = apk
+            .windows(APK_SIG_BLOCK_MAGIC.len())
+            .position(|window| window == APK_SIG_BLOCK_MAGIC)
+            .expect("signing block");
+        apk[magic - 8..magic].copy_from_slice(&16u64.to_le_bytes());
+
+        assert!(matches!(
+            verify_apk_bytes(&apk),
+            Err(ScanError::InvalidApk(_))
+        ));
+    }
+
+    #[test]
+    fn test_not_a_zip() {
+        assert!(matches!(
+            verify_apk_bytes(b"definitely not an apk"),
+            Err(ScanError::InvalidApk(_))
+        ));
+    }
+
+    #[test]
+    fn test_normalize_fingerprint() {
+        assert_eq!(normalize_fingerprint("AB:cd:01"), "abcd01");
+    }
+}
@@ -0,0 +1,46 @@
+//! Time limits for scan steps that may hang.
+
+use std::future::Future;
+use std::time::Duration;
+
+use crate::error::{ScanError, ScanResult};
+
+/// Run `future`, failing with [`ScanError::Timeout`] if it takes longer
+/// than `secs` seconds.
+///
+/// The future is dropped on timeout; work it handed to another thread,
+/// such as a blocking task, is abandoned rather than stopped.
+pub async fn with_timeout<T>(
+    secs: u64,
+    future: impl Future<Output = ScanResult<T>>,
+) -> ScanResult<T> {
+    tokio::time::timeout(Duration::from_secs(secs), future)
+        .await
+        .unwrap_or(Err(ScanError::Timeout(secs)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_slow_future_times_out() {
+        let result = with_timeout(1, async {
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            Ok(())
+        })
+        .await;
+        assert!(matches!(result, Err(ScanError::Timeout(1))));
+    }
+
+    #[tokio::test]
+    async fn test_fast_future_passes_through() {
+        assert_eq!(with_timeout(1, async { Ok(7) }).await.expect("in time"), 7);
+
+        let result: ScanResult<()> = with_timeout(1, async {
+            Err(ScanError::ToolFailed("crashed".to_string()))
+        })
+        .await;
+        assert!(matches!(result, Err(ScanError::ToolFailed(_))));
+    }
+}
@@ -0,0 +1,193 @@
+//! Tracker and analytics SDK detection.
+//!
+//! Matches the classes referenced by an APK's DEX files against a database
+//! of known tracker package prefixes, in the style of the Exodus Privacy
+//! code signatures. Hits are informational: they are attached to the scan
+//! report for review and never fail a scan by themselves.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use dk_common::config::ScannerConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::apk::Apk;
+use crate::dex::Dex;
+use crate::error::{ScanError, ScanResult};
+
+/// A known tracker and the class-name prefixes that identify it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tracker {
+    /// Tracker name as shown to reviewers.
+    pub name: &'static str,
+    /// Java package prefixes whose presence indicates the tracker.
+    pub signatures: &'static [&'static str],
+}
+
+/// Built-in tracker signature database.
+pub const TRACKERS: &[Tracker] = &[
+    Tracker {
+        name: "Google Firebase Analytics",
+        signatures: &[
+            "com.google.firebase.analytics",
+            "com.google.android.gms.measurement",
+        ],
+    },
+    Tracker {
+        name: "Google Analytics",
+        signatures: &["com.google.android.gms.analytics"],
+    },
+    Tracker {
+        name: "Google CrashLytics",
+        signatures: &["com.crashlytics", "com.google.firebase.crashlytics"],
+    },
+    Tracker {
+        name: "Google AdMob",
+        signatures: &["com.google.android.gms.ads"],
+    },
+    Tracker {
+        name: "Facebook Analytics",
+        signatures: &["com.facebook.appevents"],
+    },
+    Tracker {
+        name: "AppsFlyer",
+        signatures: &["com.appsflyer"],
+    },
+    Tracker {
+        name: "Adjust",
+        signatures: &["com.adjust.sdk"],
+    },
+    Tracker {
+        name: "Flurry",
+        signatures: &["com.flurry"],
+    },
+    Tracker {
+        name: "Mixpanel",
+        signatures: &["com.mixpanel"],
+    },
+    Tracker {
+        name: "Amplitude",
+        signatures: &["com.amplitude"],
+    },
+    Tracker {
+        name: "Branch",
+        signatures: &["io.branch"],
+    },
+    Tracker {
+        name: "OneSignal",
+        signatures: &["com.onesignal"],
+    },
+];
+
+/// A tracker found in an APK.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct TrackerHit {
+    /// Name of the detected tracker.
+    pub tracker: String,
+    /// Signature (class-name prefix) that matched.
+    pub signature: String,
+}
+
+/// Detect known trackers in the APK at `path`, within the archive limits in
+/// `config`.
+pub fn detect_trackers(path: &Path, config: &ScannerConfig) -> ScanResult<Vec<TrackerHit>> {
+    let mut apk = Apk::open(path, config)?;
+    let mut classes = Vec::new();
+    for name in apk.dex_names() {
+        let data = apk
+            .read(&name)?
+            .ok_or_else(|| ScanError::InvalidApk(format!("{name} missing")))?;
+        classes.extend(Dex::parse(&data)?.class_names()?);
+    }
+
+    Ok(match_trackers(TRACKERS, &classes))
+}
+
+/// Match `classes` against `trackers`, one hit per matching signature.
+pub fn match_trackers(trackers: &[Tracker], classes: &[String]) -> Vec<TrackerHit> {
+    let mut hits = BTreeSet::new();
+    for class in classes {
+        for tracker in trackers {
+            for signature in tracker.signatures {
+                if in_package(class, signature) {
+                    hits.insert(TrackerHit {
+                        tracker: tracker.name.to_string(),
+                        signature: (*signature).to_string(),
+                    });
+                }
+            }
+        }
+    }
+    hits.into_iter().collect()
+}
+
+/// Whether `class` is in package `prefix` or one of its subpackages.
+fn in_package(class: &str, prefix: &str) -> bool {
+    class
+        .strip_prefix(prefix)
+        .is_some_and(|rest| rest.starts_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apk::MANIFEST_ENTRY;
+    use crate::fixtures::{dex_with_classes, ApkBuilder, ManifestBuilder, TempApk};
+
+    #[test]
+    fn test_detects_firebase_analytics() {
+        let apk = ApkBuilder::new()
+            .entry(
+                MANIFEST_ENTRY,
+                &ManifestBuilder::new("dk.example.app")
+                    .version(1, "1.0")
+                    .build(),
+            )
+            .entry(
+                "classes.dex",
+                &dex_with_classes(&[
+                    "dk.example.app.MainActivity",
+                    "com.google.firebase.analytics.FirebaseAnalytics",
+                ]),
+            )
+            .build();
+        let file = TempApk::write(&apk);
+
+        let hits = detect_trackers(file.path(), &ScannerConfig::default()).expect("detect");
+        assert_eq!(
+            hits,
+            vec![TrackerHit {
+                tracker: "Google Firebase Analytics".to_string(),
+                signature: "com.google.firebase.analytics".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_scans_all_dex_files() {
+        let apk = ApkBuilder::new()
+            .entry("classes.dex", &dex_with_classes(&["dk.example.Main"]))
+            .entry(
+                "classes2.dex",
+                &dex_with_classes(&["com.appsflyer.AppsFlyerLib"]),
+            )
+            .build();
+        let file = TempApk::write(&apk);
+
+        let hits = detect_trackers(file.path(), &ScannerConfig::default()).expect("detect");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].tracker, "AppsFlyer");
+    }
+
+    #[test]
+    fn test_clean_app_has_no_hits() {
+        let classes = vec!["dk.digst.mitid.MainActivity".to_string()];
+        assert!(match_trackers(TRACKERS, &classes).is_empty());
+    }
+
+    #[test]
+    fn test_prefix_matches_package_boundary() {
+        assert!(in_package("com.flurry.sdk.Agent", "com.flurry"));
+        assert!(!in_package("com.flurryx.Agent", "com.flurry"));
+    }
+}
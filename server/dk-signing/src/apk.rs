@@ -0,0 +1,359 @@
+//! APK Signature Scheme v2 signing.
+//!
+//! Android verifies v2 signatures over the whole archive rather than over
+//! individual entries: an APK Signing Block holding the signature is inserted
+//! between the ZIP entries and the central directory, and the end of central
+//! directory record is updated to point past it.
+//!
+//! Only v2 is applied. Devices older than Android 7.0 also need a v1 (JAR)
+//! signature, so APKs signed here should declare `minSdkVersion` 24 or later.
+
+use std::path::Path;
+
+use ring::digest::{self, Algorithm, SHA256};
+
+use crate::der;
+use crate::error::{SigningError, SigningResult};
+use crate::{SignatureAlgorithm, SigningService};
+
+/// ID of the APK Signature Scheme v2 block.
+pub const APK_SIGNATURE_SCHEME_V2_BLOCK_ID: u32 = 0x7109_871a;
+
+/// Magic at the end of the APK Signing Block.
+pub const APK_SIG_BLOCK_MAGIC: &[u8; 16] = b"APK Sig Block 42";
+
+/// Size of the chunks the content digest is computed over.
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Signature of the ZIP end of central directory record.
+const EOCD_SIGNATURE: u32 = 0x0605_4b50;
+
+/// Minimum size of the end of central directory record.
+const EOCD_MIN_SIZE: usize = 22;
+
+/// Offset of the central directory offset within the EOCD record.
+const EOCD_CD_OFFSET: usize = 16;
+
+/// APK signature algorithm ID of `algorithm`.
+const fn signature_algorithm_id(algorithm: SignatureAlgorithm) -> u32 {
+    match algorithm {
+        SignatureAlgorithm::EcdsaP256Sha256 => 0x0201,
+        SignatureAlgorithm::RsaPkcs1Sha256 => 0x0103,
+    }
+}
+
+/// Offsets of the ZIP structures the signing scheme depends on.
+#[derive(Debug, Clone, Copy)]
+pub struct ZipLayout {
+    /// Offset of the central directory.
+    pub cd_offset: usize,
+    /// Offset of the end of central directory record.
+    pub eocd_offset: usize,
+}
+
+impl ZipLayout {
+    /// Locate the central directory and end of central directory record.
+    pub fn parse(apk: &[u8]) -> SigningResult<Self> {
+        let invalid = |msg: &str| SigningError::InvalidApk(msg.to_string());
+        if apk.len() < EOCD_MIN_SIZE {
+            return Err(invalid("file too small to be a ZIP archive"));
+        }
+
+        // The EOCD may be followed by a comment of up to 65535 bytes
+        let earliest = apk
+            .len()
+            .saturating_sub(EOCD_MIN_SIZE + usize::from(u16::MAX));
+        let eocd_offset = (earliest..=apk.len() - EOCD_MIN_SIZE)
+            .rev()
+            .find(|&offset| read_u32(apk, offset) == Some(EOCD_SIGNATURE))
+            .ok_or_else(|| invalid("end of central directory not found"))?;
+
+        let cd_size = read_u32(apk, eocd_offset + 12).ok_or_else(|| invalid("truncated EOCD"))?;
+        let cd_offset =
+            read_u32(apk, eocd_offset + EOCD_CD_OFFSET).ok_or_else(|| invalid("truncated EOCD"))?;
+        let cd_offset = usize::try_from(cd_offset).map_err(|_| invalid("bad CD offset"))?;
+        let cd_size = usize::try_from(cd_size).map_err(|_| invalid("bad CD size"))?;
+        if cd_offset.checked_add(cd_size) != Some(eocd_offset) {
+            return Err(invalid("central directory is not followed by EOCD"));
+        }
+
+        Ok(Self {
+            cd_offset,
+            eocd_offset,
+        })
+    }
+}
+
+/// Offset of the APK Signing Block preceding the central directory, if
+/// `apk` has one.
+pub fn signing_block_start(apk: &[u8], layout: &ZipLayout) -> SigningResult<Option<usize>> {
+    let cd = layout.cd_offset;
+    if cd < 32 || apk.get(cd - 16..cd) != Some(&APK_SIG_BLOCK_MAGIC[..]) {
+        return Ok(None);
+    }
+
+    // The size counts the pairs, itself and the magic, so it is at least 24
+    let invalid = || SigningError::InvalidApk("malformed APK signing block".to_string());
+    let footer_size = read_u64(apk, cd - 24).ok_or_else(invalid)?;
+    let footer_size = usize::try_from(footer_size)
+        .ok()
+        .filter(|&size| (24..=cd).contains(&size))
+        .ok_or_else(invalid)?;
+    let start = cd
+        .checked_sub(footer_size)
+        .and_then(|offset| offset.checked_sub(8))
+        .ok_or_else(invalid)?;
+    if read_u64(apk, start).and_then(|size| usize::try_from(size).ok()) != Some(footer_size) {
+        return Err(invalid());
+    }
+
+    Ok(Some(start))
+}
+
+/// Chunked content digest of `apk` with `algorithm`, as covered by a v2 or v3 signature.
+///
+/// Covers the entries, central directory, and EOCD, with any signing block
+/// left out and the EOCD pointing at where it starts.
+pub fn content_digest(
+    apk: &[u8],
+    layout: &ZipLayout,
+    algorithm: &'static Algorithm,
+) -> SigningResult<Vec<u8>> {
+    let block_start = signing_block_start(apk, layout)?.unwrap_or(layout.cd_offset);
+    let block_start_u32 = u32::try_from(block_start)
+        .map_err(|_| SigningError::InvalidApk("APK too large".to_string()))?;
+
+    let mut eocd = apk[layout.eocd_offset..].to_vec();
+    eocd[EOCD_CD_OFFSET..EOCD_CD_OFFSET + 4].copy_from_slice(&block_start_u32.to_le_bytes());
+
+    let sections: [&[u8]; 3] = [
+        &apk[..block_start],
+        &apk[layout.cd_offset..layout.eocd_offset],
+        &eocd,
+    ];
+
+    let chunk_count: usize = sections
+        .iter()
+        .map(|section| section.len().div_ceil(CHUNK_SIZE))
+        .sum();
+    let chunk_count = u32::try_from(chunk_count)
+        .map_err(|_| SigningError::InvalidApk("APK too large".to_string()))?;
+
+    let mut top = digest::Context::new(algorithm);
+    top.update(&[0x5a]);
+    top.update(&chunk_count.to_le_bytes());
+    for chunk in sections
+        .iter()
+        .flat_map(|section| section.chunks(CHUNK_SIZE))
+    {
+        // Chunks are at most CHUNK_SIZE, which fits in u32
+        let len = u32::try_from(chunk.len()).unwrap_or(u32::MAX);
+        let mut context = digest::Context::new(algorithm);
+        context.update(&[0xa5]);
+        context.update(&len.to_le_bytes());
+        context.update(chunk);
+        top.update(context.finish().as_ref());
+    }
+
+    Ok(top.finish().as_ref().to_vec())
+}
+
+/// Sign `apk` with APK Signature Scheme v2 using the active key of
+/// `service`, and return the signed APK.
+///
+/// Fails with [`SigningError::InvalidApk`] if `apk` is not a ZIP archive or
+/// is already signed with v2 or later; strip the existing signing block to
+/// re-sign.
+pub fn sign_apk(service: &SigningService, apk: &[u8]) -> SigningResult<Vec<u8>> {
+    let layout = ZipLayout::parse(apk)?;
+    if signing_block_start(apk, &layout)?.is_some() {
+        return Err(SigningError::InvalidApk(
+            "APK already has a signing block".to_string(),
+        ));
+    }
+
+    let algorithm_id = signature_algorithm_id(service.algorithm());
+    let certificate = service.certificate();
+    let spki = der::parse_certificate(certificate)?.spki;
+
+    let digest = concat(&[
+        &algorithm_id.to_le_bytes(),
+        &prefixed(&content_digest(apk, &layout, &SHA256)?)?,
+    ]);
+    let signed_data = concat(&[
+        &prefixed(&prefixed(&digest)?)?,
+        &prefixed(&prefixed(certificate)?)?,
+        &prefixed(&[])?, // additional attributes
+    ]);
+    let signature = concat(&[
+        &algorithm_id.to_le_bytes(),
+        &prefixed(&service.sign(&signed_data)?)?,
+    ]);
+    let signer = concat(&[
+        &prefixed(&signed_data)?,
+        &prefixed(&prefixed(&signature)?)?,
+        &prefixed(spki)?,
+    ]);
+    let block = signing_block(
+        APK_SIGNATURE_SCHEME_V2_BLOCK_ID,
+        &prefixed(&prefixed(&signer)?)?,
+    );
+
+    let cd_offset = u32::try_from(layout.cd_offset + block.len())
+        .map_err(|_| SigningError::InvalidApk("APK too large".to_string()))?;
+    let mut signed_apk = Vec::with_capacity(apk.len() + block.len());
+    signed_apk.extend_from_slice(&apk[..layout.cd_offset]);
+    signed_apk.extend_from_slice(&block);
+    signed_apk.extend_from_slice(&apk[layout.cd_offset..]);
+    let eocd = layout.eocd_offset + block.len();
+    signed_apk[eocd + EOCD_CD_OFFSET..eocd + EOCD_CD_OFFSET + 4]
+        .copy_from_slice(&cd_offset.to_le_bytes());
+
+    Ok(signed_apk)
+}
+
+/// Sign the APK at `unsigned_apk` with [`sign_apk`] and write it to `out`.
+pub(crate) fn sign_apk_file(
+    service: &SigningService,
+    unsigned_apk: &Path,
+    out: &Path,
+) -> SigningResult<()> {
+    let apk = std::fs::read(unsigned_apk)
+        .map_err(|err| SigningError::InvalidApk(format!("{}: {err}", unsigned_apk.display())))?;
+    let signed = sign_apk(service, &apk)?;
+    std::fs::write(out, signed)
+        .map_err(|err| SigningError::SigningFailed(format!("{}: {err}", out.display())))
+}
+
+/// An APK Signing Block holding the single pair `id`, `value`.
+fn signing_block(id: u32, value: &[u8]) -> Vec<u8> {
+    let pair_len = 4 + value.len() as u64;
+    // Pair length prefix, pair, trailing block size, and magic
+    let block_size = 8 + pair_len + 8 + APK_SIG_BLOCK_MAGIC.len() as u64;
+    concat(&[
+        &block_size.to_le_bytes(),
+        &pair_len.to_le_bytes(),
+        &id.to_le_bytes(),
+        value,
+        &block_size.to_le_bytes(),
+        APK_SIG_BLOCK_MAGIC,
+    ])
+}
+
+/// `bytes` prefixed with their u32 little-endian length.
+fn prefixed(bytes: &[u8]) -> SigningResult<Vec<u8>> {
+    let len = u32::try_from(bytes.len())
+        .map_err(|_| SigningError::SigningFailed("signing block section too large".to_string()))?;
+    Ok(concat(&[&len.to_le_bytes(), bytes]))
+}
+
+fn concat(parts: &[&[u8]]) -> Vec<u8> {
+    parts.concat()
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    bytes.try_into().ok().map(u32::from_le_bytes)
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset.checked_add(8)?)?;
+    bytes.try_into().ok().map(u64::from_le_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::write::FileOptions;
+    use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+    use super::*;
+
+    /// A minimal unsigned APK-shaped archive.
+    fn unsigned_apk() -> Vec<u8> {
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in [
+            ("AndroidManifest.xml", b"<manifest/>".as_slice()),
+            ("classes.dex", b"dex\n035\0"),
+        ] {
+            writer.start_file(name, options).expect("start entry");
+            writer.write_all(content).expect("write entry");
+        }
+        writer.finish().expect("finish zip").into_inner()
+    }
+
+    /// Split `data` into its u32 length-prefixed head and the rest.
+    fn take_prefixed(data: &[u8]) -> (&[u8], &[u8]) {
+        let len = read_u32(data, 0).expect("length") as usize;
+        (&data[4..4 + len], &data[4 + len..])
+    }
+
+    #[test]
+    fn test_signed_apk_has_valid_v2_block() {
+        let service = SigningService::ephemeral("dk-appstore.test").expect("service");
+        let apk = unsigned_apk();
+        let signed_apk = sign_apk(&service, &apk).expect("sign");
+
+        // Still a readable ZIP with the same entries
+        let archive = ZipArchive::new(Cursor::new(signed_apk.as_slice())).expect("zip");
+        assert_eq!(archive.len(), 2);
+
+        let layout = ZipLayout::parse(&signed_apk).expect("layout");
+        let original = ZipLayout::parse(&apk).expect("layout");
+        let start = signing_block_start(&signed_apk, &layout).expect("block");
+        assert_eq!(start, Some(original.cd_offset));
+        let block = &signed_apk[original.cd_offset..layout.cd_offset];
+        let pairs = &block[8..block.len() - 24];
+        assert_eq!(read_u32(pairs, 8), Some(APK_SIGNATURE_SCHEME_V2_BLOCK_ID));
+
+        let (signers, _) = take_prefixed(&pairs[12..]);
+        let (signer, _) = take_prefixed(signers);
+        let (signed_data, rest) = take_prefixed(signer);
+        let (signatures, rest) = take_prefixed(rest);
+        let (spki, _) = take_prefixed(rest);
+        let (signature, _) = take_prefixed(signatures);
+        assert_eq!(read_u32(signature, 0), Some(0x0201));
+        let (signature, _) = take_prefixed(&signature[4..]);
+        service
+            .verify(signed_data, signature, None)
+            .expect("signature");
+        assert_eq!(
+            spki,
+            der::parse_certificate(service.certificate())
+                .expect("certificate")
+                .spki
+        );
+
+        // The signed digest covers the original archive
+        let (digests, _) = take_prefixed(signed_data);
+        let (digest, _) = take_prefixed(digests);
+        let (digest, _) = take_prefixed(&digest[4..]);
+        let expected = content_digest(&apk, &original, &SHA256).expect("digest");
+        assert_eq!(digest, expected);
+        assert_eq!(
+            content_digest(&signed_apk, &layout, &SHA256).expect("digest"),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_already_signed_apk_is_rejected() {
+        let service = SigningService::ephemeral("dk-appstore.test").expect("service");
+        let signed = sign_apk(&service, &unsigned_apk()).expect("sign");
+        assert!(matches!(
+            sign_apk(&service, &signed),
+            Err(SigningError::InvalidApk(_))
+        ));
+    }
+
+    #[test]
+    fn test_not_a_zip_is_rejected() {
+        let service = SigningService::ephemeral("dk-appstore.test").expect("service");
+        assert!(matches!(
+            sign_apk(&service, b"not an apk at all, just text"),
+            Err(SigningError::InvalidApk(_))
+        ));
+    }
+}
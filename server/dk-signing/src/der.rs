@@ -0,0 +1,323 @@
+//! Minimal DER encoding and decoding for PKCS#7 and X.509 structures.
+//!
+//! Only the handful of ASN.1 constructs needed for JAR signatures are
+//! supported; this is not a general-purpose ASN.1 library.
+
+use crate::error::{SigningError, SigningResult};
+
+/// ASN.1 tag for INTEGER.
+pub const TAG_INTEGER: u8 = 0x02;
+/// ASN.1 tag for BIT STRING.
+pub const TAG_BIT_STRING: u8 = 0x03;
+/// ASN.1 tag for OCTET STRING.
+pub const TAG_OCTET_STRING: u8 = 0x04;
+/// ASN.1 tag for NULL.
+pub const TAG_NULL: u8 = 0x05;
+/// ASN.1 tag for OBJECT IDENTIFIER.
+pub const TAG_OID: u8 = 0x06;
+/// ASN.1 tag for `UTF8String`.
+pub const TAG_UTF8_STRING: u8 = 0x0C;
+/// ASN.1 tag for `UTCTime`.
+pub const TAG_UTC_TIME: u8 = 0x17;
+/// ASN.1 tag for `GeneralizedTime`.
+pub const TAG_GENERALIZED_TIME: u8 = 0x18;
+/// ASN.1 tag for SEQUENCE.
+pub const TAG_SEQUENCE: u8 = 0x30;
+/// ASN.1 tag for SET.
+pub const TAG_SET: u8 = 0x31;
+/// ASN.1 tag for the constructed, context-specific `[0]` tag.
+pub const TAG_CONTEXT_0: u8 = 0xA0;
+
+/// OID 1.2.840.113549.1.7.1 (PKCS#7 data).
+pub const OID_PKCS7_DATA: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x01];
+/// OID 1.2.840.113549.1.7.2 (PKCS#7 signedData).
+pub const OID_PKCS7_SIGNED_DATA: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x02];
+/// OID 2.16.840.1.101.3.4.2.1 (SHA-256).
+pub const OID_SHA256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+/// OID 1.2.840.113549.1.1.1 (rsaEncryption).
+pub const OID_RSA_ENCRYPTION: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x01];
+/// OID 1.2.840.10045.3.1.7 (prime256v1, the P-256 curve).
+pub const OID_PRIME256V1: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x03, 0x01, 0x07];
+/// OID 1.2.840.10045.4.3.2 (ecdsa-with-SHA256).
+pub const OID_ECDSA_WITH_SHA256: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x04, 0x03, 0x02];
+
+/// Encode a single tag-length-value element.
+pub fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(content.len() + 6);
+    out.push(tag);
+
+    match u8::try_from(content.len()) {
+        Ok(short) if short < 0x80 => out.push(short),
+        _ => {
+            let bytes = content.len().to_be_bytes();
+            let skip = bytes.iter().take_while(|byte| **byte == 0).count();
+            let significant = &bytes[skip..];
+            // At most `size_of::<usize>()` bytes, so the count fits in one byte
+            out.push(0x80 | significant.len().to_le_bytes()[0]);
+            out.extend_from_slice(significant);
+        }
+    }
+
+    out.extend_from_slice(content);
+    out
+}
+
+/// Encode a constructed element whose content is the concatenation of `parts`.
+pub fn constructed(tag: u8, parts: &[&[u8]]) -> Vec<u8> {
+    tlv(tag, &parts.concat())
+}
+
+/// Encode a SEQUENCE of already-encoded elements.
+pub fn sequence(parts: &[&[u8]]) -> Vec<u8> {
+    constructed(TAG_SEQUENCE, parts)
+}
+
+/// Encode a SET of already-encoded elements.
+pub fn set(parts: &[&[u8]]) -> Vec<u8> {
+    constructed(TAG_SET, parts)
+}
+
+/// Encode an OBJECT IDENTIFIER from its content bytes.
+pub fn oid(encoded: &[u8]) -> Vec<u8> {
+    tlv(TAG_OID, encoded)
+}
+
+/// Encode a small non-negative INTEGER.
+pub fn small_integer(value: u8) -> Vec<u8> {
+    if value < 0x80 {
+        tlv(TAG_INTEGER, &[value])
+    } else {
+        tlv(TAG_INTEGER, &[0, value])
+    }
+}
+
+/// Encode an OCTET STRING.
+pub fn octet_string(content: &[u8]) -> Vec<u8> {
+    tlv(TAG_OCTET_STRING, content)
+}
+
+/// Encode a BIT STRING with no unused bits.
+pub fn bit_string(content: &[u8]) -> Vec<u8> {
+    tlv(TAG_BIT_STRING, &[&[0], content].concat())
+}
+
+/// Encode an `AlgorithmIdentifier` without parameters.
+pub fn algorithm(oid_bytes: &[u8]) -> Vec<u8> {
+    sequence(&[&oid(oid_bytes)])
+}
+
+/// Encode an `AlgorithmIdentifier` with NULL parameters.
+pub fn algorithm_with_null(oid_bytes: &[u8]) -> Vec<u8> {
+    sequence(&[&oid(oid_bytes), &tlv(TAG_NULL, &[])])
+}
+
+/// Encode an unsigned big-endian magnitude as a DER INTEGER.
+pub fn unsigned_integer(magnitude: &[u8]) -> Vec<u8> {
+    let skip = magnitude.iter().take_while(|byte| **byte == 0).count();
+    let trimmed = &magnitude[skip..];
+
+    match trimmed.first() {
+        None => tlv(TAG_INTEGER, &[0]),
+        Some(first) if first & 0x80 != 0 => tlv(TAG_INTEGER, &[&[0], trimmed].concat()),
+        Some(_) => tlv(TAG_INTEGER, trimmed),
+    }
+}
+
+/// Convert a raw `r || s` ECDSA signature (as produced by PKCS#11) into the
+/// ASN.1 DER `Ecdsa-Sig-Value` form used in PKCS#7.
+pub fn ecdsa_signature_to_der(raw: &[u8]) -> SigningResult<Vec<u8>> {
+    if raw.is_empty() || raw.len() % 2 != 0 {
+        return Err(SigningError::SigningFailed(format!(
+            "unexpected ECDSA signature length {}",
+            raw.len()
+        )));
+    }
+
+    let (r, s) = raw.split_at(raw.len() / 2);
+    Ok(sequence(&[&unsigned_integer(r), &unsigned_integer(s)]))
+}
+
+/// A decoded element: its tag, content, and full encoding.
+#[derive(Debug, Clone, Copy)]
+pub struct Element<'a> {
+    /// The element's tag byte.
+    pub tag: u8,
+    /// The content octets.
+    pub content: &'a [u8],
+    /// The full encoding, including tag and length.
+    pub raw: &'a [u8],
+}
+
+/// Sequential reader over DER-encoded elements.
+pub struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    /// Create a reader over `data`.
+    pub const fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Returns `true` if all input has been consumed.
+    pub const fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns the tag of the next element without consuming it.
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// Read the next element, whatever its tag.
+    pub fn next_element(&mut self) -> SigningResult<Element<'a>> {
+        let malformed = || SigningError::InvalidCertificate("malformed DER".to_string());
+
+        let (&tag, rest) = self.data.split_first().ok_or_else(malformed)?;
+        let (&first, rest) = rest.split_first().ok_or_else(malformed)?;
+
+        let (len, rest) = if first < 0x80 {
+            (usize::from(first), rest)
+        } else {
+            let count = usize::from(first & 0x7F);
+            if count == 0 || count > std::mem::size_of::<usize>() || rest.len() < count {
+                return Err(malformed());
+            }
+            let len = rest[..count]
+                .iter()
+                .fold(0usize, |acc, byte| (acc << 8) | usize::from(*byte));
+            (len, &rest[count..])
+        };
+
+        if rest.len() < len {
+            return Err(malformed());
+        }
+
+        let header_len = self.data.len() - rest.len();
+        let raw = &self.data[..header_len + len];
+        let content = &rest[..len];
+        self.data = &rest[len..];
+
+        Ok(Element { tag, content, raw })
+    }
+
+    /// Read the next element and require it to have `tag`.
+    pub fn read(&mut self, tag: u8) -> SigningResult<Element<'a>> {
+        let element = self.next_element()?;
+        if element.tag == tag {
+            Ok(element)
+        } else {
+            Err(SigningError::InvalidCertificate(format!(
+                "expected tag {tag:#04x}, found {:#04x}",
+                element.tag
+            )))
+        }
+    }
+}
+
+/// Fields of an X.509 certificate needed to sign and verify with it.
+#[derive(Debug, Clone, Copy)]
+pub struct CertificateFields<'a> {
+    /// Encoded issuer `Name`.
+    pub issuer: &'a [u8],
+    /// Encoded serial number INTEGER.
+    pub serial: &'a [u8],
+    /// Encoded `SubjectPublicKeyInfo`.
+    pub spki: &'a [u8],
+    /// Raw subject public key (content of the SPKI BIT STRING).
+    pub public_key: &'a [u8],
+}
+
+/// Extract the issuer, serial number, and public key from a DER certificate.
+pub fn parse_certificate(certificate: &[u8]) -> SigningResult<CertificateFields<'_>> {
+    let cert = Reader::new(certificate).read(TAG_SEQUENCE)?;
+    let tbs = Reader::new(cert.content).read(TAG_SEQUENCE)?;
+    let mut fields = Reader::new(tbs.content);
+
+    // Optional explicit version
+    if fields.peek_tag() == Some(TAG_CONTEXT_0) {
+        fields.next_element()?;
+    }
+    let serial = fields.read(TAG_INTEGER)?.raw;
+    fields.read(TAG_SEQUENCE)?; // signature algorithm
+    let issuer = fields.read(TAG_SEQUENCE)?.raw;
+    fields.read(TAG_SEQUENCE)?; // validity
+    fields.read(TAG_SEQUENCE)?; // subject
+    let spki = fields.read(TAG_SEQUENCE)?.raw;
+
+    Ok(CertificateFields {
+        issuer,
+        serial,
+        spki,
+        public_key: parse_spki(spki)?,
+    })
+}
+
+/// Extract the raw public key from a DER `SubjectPublicKeyInfo`.
+pub fn parse_spki(spki: &[u8]) -> SigningResult<&[u8]> {
+    let spki = Reader::new(spki).read(TAG_SEQUENCE)?;
+    let mut spki = Reader::new(spki.content);
+    spki.read(TAG_SEQUENCE)?; // algorithm
+    let key_bits = spki.read(TAG_BIT_STRING)?.content;
+
+    match key_bits.split_first() {
+        Some((0, key)) => Ok(key),
+        _ => Err(SigningError::InvalidCertificate(
+            "unsupported public key encoding".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tlv_short_and_long_lengths() {
+        assert_eq!(tlv(TAG_OCTET_STRING, &[1, 2]), vec![0x04, 0x02, 1, 2]);
+
+        let long = tlv(TAG_OCTET_STRING, &[0; 300]);
+        assert_eq!(&long[..4], &[0x04, 0x82, 0x01, 0x2C]);
+        assert_eq!(long.len(), 304);
+    }
+
+    #[test]
+    fn test_reader_round_trip() {
+        let encoded = sequence(&[&small_integer(1), &octet_string(&[0xAB; 200])]);
+        let outer = Reader::new(&encoded).read(TAG_SEQUENCE).expect("sequence");
+        assert_eq!(outer.raw, encoded.as_slice());
+
+        let mut inner = Reader::new(outer.content);
+        assert_eq!(inner.read(TAG_INTEGER).expect("integer").content, &[1]);
+        assert_eq!(
+            inner.read(TAG_OCTET_STRING).expect("octets").content.len(),
+            200
+        );
+        assert!(inner.is_empty());
+    }
+
+    #[test]
+    fn test_ecdsa_signature_to_der() {
+        let mut raw = vec![0u8; 64];
+        raw[0] = 0x80; // r needs a sign byte
+        raw[32] = 0x00;
+        raw[33] = 0x01; // s has a leading zero to strip
+
+        let encoded = ecdsa_signature_to_der(&raw).expect("encode");
+        let outer = Reader::new(&encoded).read(TAG_SEQUENCE).expect("sequence");
+        let mut inner = Reader::new(outer.content);
+        let r = inner.read(TAG_INTEGER).expect("r");
+        let s = inner.read(TAG_INTEGER).expect("s");
+        assert_eq!(r.content.len(), 33);
+        assert_eq!(r.content[0], 0);
+        assert_eq!(s.content.len(), 31);
+
+        assert!(ecdsa_signature_to_der(&[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_reader_rejects_truncated() {
+        let encoded = octet_string(&[0; 10]);
+        assert!(Reader::new(&encoded[..5]).next_element().is_err());
+    }
+}
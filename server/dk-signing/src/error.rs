@@ -28,11 +28,62 @@ pub enum SigningError {
     #[error("Invalid key: {0}")]
     InvalidKey(String),
 
+    /// Certificate is malformed or unsupported.
+    #[error("Invalid certificate: {0}")]
+    InvalidCertificate(String),
+
     /// Signing operation failed.
     #[error("Signing failed: {0}")]
     SigningFailed(String),
 
+    /// APK is malformed or cannot be signed as given.
+    #[error("Invalid APK: {0}")]
+    InvalidApk(String),
+
     /// Verification failed.
     #[error("Signature verification failed")]
     VerificationFailed,
 }
+
+impl SigningError {
+    /// Whether the failed operation may succeed if retried unchanged.
+    ///
+    /// Only HSM availability problems are transient; key, certificate, and
+    /// authentication errors recur on every attempt.
+    #[must_use]
+    pub const fn is_retryable(&self) -> bool {
+        match self {
+            Self::HsmUnavailable(_) | Self::HsmTimeout => true,
+            Self::HsmAuthFailed
+            | Self::KeyNotFound(_)
+            | Self::InvalidKey(_)
+            | Self::InvalidCertificate(_)
+            | Self::InvalidApk(_)
+            | Self::SigningFailed(_)
+            | Self::VerificationFailed => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retryable_classification() {
+        let cases = [
+            (SigningError::HsmUnavailable(String::new()), true),
+            (SigningError::HsmTimeout, true),
+            (SigningError::HsmAuthFailed, false),
+            (SigningError::KeyNotFound(String::new()), false),
+            (SigningError::InvalidKey(String::new()), false),
+            (SigningError::InvalidCertificate(String::new()), false),
+            (SigningError::InvalidApk(String::new()), false),
+            (SigningError::SigningFailed(String::new()), false),
+            (SigningError::VerificationFailed, false),
+        ];
+        for (err, retryable) in cases {
+            assert_eq!(err.is_retryable(), retryable, "{err:?}");
+        }
+    }
+}
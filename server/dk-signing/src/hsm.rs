@@ -0,0 +1,376 @@
+//! PKCS#11 Hardware Security Module backend.
+//!
+//! The private key never leaves the HSM: each signing operation opens a
+//! session on the configured slot, logs in with the user PIN, locates the key
+//! by label, and asks the token to sign.
+
+use std::path::PathBuf;
+
+use cryptoki::context::{CInitializeArgs, Pkcs11};
+use cryptoki::error::{Error as Pkcs11Error, RvError};
+use cryptoki::mechanism::Mechanism;
+use cryptoki::object::{Attribute, AttributeType, KeyType, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, UserType};
+use cryptoki::slot::Slot;
+use cryptoki::types::AuthPin;
+use dk_common::hash;
+use serde::Deserialize;
+
+use crate::der;
+use crate::error::{SigningError, SigningResult};
+use crate::signer::{SignatureAlgorithm, Signer};
+
+/// Configuration for a PKCS#11 HSM signing key.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HsmConfig {
+    /// Path to the PKCS#11 module (e.g. `/usr/lib/softhsm/libsofthsm2.so`).
+    pub module_path: PathBuf,
+    /// Slot ID holding the signing token.
+    pub slot: u64,
+    /// Name of the environment variable containing the user PIN.
+    pub pin_env_var: String,
+    /// `CKA_LABEL` of the private signing key.
+    pub key_label: String,
+}
+
+/// A signing key held in a PKCS#11 token.
+pub struct HsmSigner {
+    pkcs11: Pkcs11,
+    slot: Slot,
+    pin: AuthPin,
+    key_label: String,
+    algorithm: SignatureAlgorithm,
+}
+
+impl std::fmt::Debug for HsmSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HsmSigner")
+            .field("slot", &self.slot)
+            .field("key_label", &self.key_label)
+            .field("algorithm", &self.algorithm)
+            .finish_non_exhaustive()
+    }
+}
+
+impl HsmSigner {
+    /// Load the PKCS#11 module, select the slot, and locate the signing key.
+    pub fn connect(config: &HsmConfig) -> SigningResult<Self> {
+        let pin = std::env::var(&config.pin_env_var)
+            .map_err(|_| SigningError::HsmAuthFailed)
+            .map(AuthPin::new)?;
+
+        let pkcs11 = Pkcs11::new(&config.module_path).map_err(|err| {
+            SigningError::HsmUnavailable(format!("{}: {err}", config.module_path.display()))
+        })?;
+        match pkcs11.initialize(CInitializeArgs::OsThreads) {
+            Ok(()) | Err(Pkcs11Error::Pkcs11(RvError::CryptokiAlreadyInitialized, ..)) => {}
+            Err(err) => return Err(map_error(err)),
+        }
+
+        let slot = pkcs11
+            .get_slots_with_token()
+            .map_err(map_error)?
+            .into_iter()
+            .find(|slot| slot.id() == config.slot)
+            .ok_or_else(|| {
+                SigningError::HsmUnavailable(format!("no token in slot {}", config.slot))
+            })?;
+
+        let mut signer = Self {
+            pkcs11,
+            slot,
+            pin,
+            key_label: config.key_label.clone(),
+            algorithm: SignatureAlgorithm::EcdsaP256Sha256,
+        };
+
+        // Resolve the key once up front so misconfiguration fails at startup
+        let session = signer.open_session()?;
+        let key = signer.find_key(&session)?;
+        signer.algorithm = key_algorithm(&session, key)?;
+
+        Ok(signer)
+    }
+
+    /// Check that the token is reachable and still holds the signing key,
+    /// without signing anything.
+    pub fn check(&self) -> SigningResult<()> {
+        let session = self.open_session()?;
+        self.find_key(&session).map(|_| ())
+    }
+
+    /// Open a read-only session on the slot and log in as the user.
+    fn open_session(&self) -> SigningResult<Session> {
+        let session = self.pkcs11.open_ro_session(self.slot).map_err(map_error)?;
+        match session.login(UserType::User, Some(&self.pin)) {
+            Ok(()) | Err(Pkcs11Error::Pkcs11(RvError::UserAlreadyLoggedIn, ..)) => Ok(session),
+            Err(err) => Err(map_error(err)),
+        }
+    }
+
+    /// Find the private key with the configured label.
+    fn find_key(&self, session: &Session) -> SigningResult<ObjectHandle> {
+        session
+            .find_objects(&[
+                Attribute::Class(ObjectClass::PRIVATE_KEY),
+                Attribute::Label(self.key_label.as_bytes().to_vec()),
+            ])
+            .map_err(map_error)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| SigningError::KeyNotFound(self.key_label.clone()))
+    }
+}
+
+impl Signer for HsmSigner {
+    fn algorithm(&self) -> SignatureAlgorithm {
+        self.algorithm
+    }
+
+    /// Sign `data` with the HSM key.
+    ///
+    /// ECDSA signatures are returned ASN.1 DER encoded, RSA signatures as
+    /// PKCS#1 v1.5 bytes.
+    fn sign(&self, data: &[u8]) -> SigningResult<Vec<u8>> {
+        let session = self.open_session()?;
+        let key = self.find_key(&session)?;
+
+        match self.algorithm {
+            SignatureAlgorithm::EcdsaP256Sha256 => {
+                let raw = session
+                    .sign(&Mechanism::EcdsaSha256, key, data)
+                    .map_err(map_error)?;
+                der::ecdsa_signature_to_der(&raw)
+            }
+            SignatureAlgorithm::RsaPkcs1Sha256 => session
+                .sign(&Mechanism::Sha256RsaPkcs, key, data)
+                .map_err(map_error),
+        }
+    }
+
+    /// Read the public half of the key pair with the configured label.
+    fn public_key(&self) -> SigningResult<Vec<u8>> {
+        let session = self.open_session()?;
+        let key = session
+            .find_objects(&[
+                Attribute::Class(ObjectClass::PUBLIC_KEY),
+                Attribute::Label(self.key_label.as_bytes().to_vec()),
+            ])
+            .map_err(map_error)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| SigningError::KeyNotFound(format!("{} (public)", self.key_label)))?;
+
+        match self.algorithm {
+            SignatureAlgorithm::EcdsaP256Sha256 => {
+                let attributes = session
+                    .get_attributes(key, &[AttributeType::EcPoint])
+                    .map_err(map_error)?;
+                match attributes.first() {
+                    // CKA_EC_POINT is a DER OCTET STRING wrapping the point
+                    Some(Attribute::EcPoint(point)) => Ok(der::Reader::new(point)
+                        .read(der::TAG_OCTET_STRING)?
+                        .content
+                        .to_vec()),
+                    _ => Err(SigningError::InvalidKey("EC point unavailable".to_string())),
+                }
+            }
+            SignatureAlgorithm::RsaPkcs1Sha256 => {
+                let attributes = session
+                    .get_attributes(
+                        key,
+                        &[AttributeType::Modulus, AttributeType::PublicExponent],
+                    )
+                    .map_err(map_error)?;
+                let mut modulus = None;
+                let mut exponent = None;
+                for attribute in attributes {
+                    match attribute {
+                        Attribute::Modulus(value) => modulus = Some(value),
+                        Attribute::PublicExponent(value) => exponent = Some(value),
+                        _ => {}
+                    }
+                }
+                match (modulus, exponent) {
+                    // DER RSAPublicKey, as carried in the certificate
+                    (Some(modulus), Some(exponent)) => Ok(der::sequence(&[
+                        &der::unsigned_integer(&modulus),
+                        &der::unsigned_integer(&exponent),
+                    ])),
+                    _ => Err(SigningError::InvalidKey(
+                        "RSA public key unavailable".to_string(),
+                    )),
+                }
+            }
+        }
+    }
+}
+
+/// Determine the signature algorithm from the key's `CKA_KEY_TYPE`, and for
+/// EC keys check the curve in `CKA_EC_PARAMS`.
+fn key_algorithm(session: &Session, key: ObjectHandle) -> SigningResult<SignatureAlgorithm> {
+    let attributes = session
+        .get_attributes(key, &[AttributeType::KeyType])
+        .map_err(map_error)?;
+
+    match attributes.first() {
+        Some(Attribute::KeyType(KeyType::EC)) => {
+            let attributes = session
+                .get_attributes(key, &[AttributeType::EcParams])
+                .map_err(map_error)?;
+            match attributes.first() {
+                Some(Attribute::EcParams(params)) => require_p256(params)?,
+                _ => return Err(SigningError::InvalidKey("EC curve unavailable".to_string())),
+            }
+            Ok(SignatureAlgorithm::EcdsaP256Sha256)
+        }
+        Some(Attribute::KeyType(KeyType::RSA)) => Ok(SignatureAlgorithm::RsaPkcs1Sha256),
+        Some(Attribute::KeyType(other)) => Err(SigningError::InvalidKey(format!(
+            "unsupported key type {other:?}"
+        ))),
+        _ => Err(SigningError::InvalidKey("key type unavailable".to_string())),
+    }
+}
+
+/// Check that `params`, a DER `CKA_EC_PARAMS` value, names the P-256 curve.
+///
+/// Keys on any other curve would sign, but not as `EcdsaP256Sha256`, so
+/// clients would reject the signatures.
+fn require_p256(params: &[u8]) -> SigningResult<()> {
+    // Explicit curve parameters are not supported, only named curves
+    let curve = der::Reader::new(params)
+        .read(der::TAG_OID)
+        .map_err(|_| SigningError::InvalidKey("EC key has no named curve".to_string()))?;
+    if curve.content == der::OID_PRIME256V1 {
+        Ok(())
+    } else {
+        Err(SigningError::InvalidKey(format!(
+            "unsupported EC curve {}",
+            hash::hex(curve.content)
+        )))
+    }
+}
+
+/// Map a PKCS#11 error onto the signing error it represents.
+fn map_error(err: Pkcs11Error) -> SigningError {
+    match err {
+        Pkcs11Error::Pkcs11(rv, ..) => match rv {
+            RvError::PinIncorrect
+            | RvError::PinInvalid
+            | RvError::PinLenRange
+            | RvError::PinExpired
+            | RvError::PinLocked
+            | RvError::UserNotLoggedIn
+            | RvError::UserPinNotInitialized => SigningError::HsmAuthFailed,
+            RvError::DeviceError
+            | RvError::DeviceMemory
+            | RvError::DeviceRemoved
+            | RvError::TokenNotPresent
+            | RvError::TokenNotRecognized
+            | RvError::SlotIdInvalid
+            | RvError::SessionCount
+            | RvError::CryptokiNotInitialized => SigningError::HsmUnavailable(rv.to_string()),
+            RvError::KeyHandleInvalid | RvError::ObjectHandleInvalid => {
+                SigningError::KeyNotFound(rv.to_string())
+            }
+            RvError::KeyTypeInconsistent | RvError::KeyFunctionNotPermitted => {
+                SigningError::InvalidKey(rv.to_string())
+            }
+            _ => SigningError::SigningFailed(rv.to_string()),
+        },
+        Pkcs11Error::LibraryLoading(err) => SigningError::HsmUnavailable(err.to_string()),
+        other => SigningError::SigningFailed(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_error() {
+        assert!(matches!(
+            map_error(Pkcs11Error::Pkcs11(RvError::PinIncorrect)),
+            SigningError::HsmAuthFailed
+        ));
+        assert!(matches!(
+            map_error(Pkcs11Error::Pkcs11(RvError::TokenNotPresent)),
+            SigningError::HsmUnavailable(_)
+        ));
+        assert!(matches!(
+            map_error(Pkcs11Error::Pkcs11(RvError::KeyHandleInvalid)),
+            SigningError::KeyNotFound(_)
+        ));
+    }
+
+    #[test]
+    fn test_only_p256_keys_are_accepted() {
+        assert!(require_p256(&der::oid(der::OID_PRIME256V1)).is_ok());
+        // secp384r1
+        assert!(matches!(
+            require_p256(&der::oid(&[0x2B, 0x81, 0x04, 0x00, 0x22])),
+            Err(SigningError::InvalidKey(_))
+        ));
+        // Explicit parameters
+        assert!(matches!(
+            require_p256(&der::sequence(&[&der::small_integer(1)])),
+            Err(SigningError::InvalidKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_missing_pin_is_auth_failure() {
+        let config = HsmConfig {
+            module_path: PathBuf::from("/nonexistent/libpkcs11.so"),
+            slot: 0,
+            pin_env_var: "DK_SIGNING_TEST_PIN_THAT_IS_NOT_SET".to_string(),
+            key_label: "repo".to_string(),
+        };
+        assert!(matches!(
+            HsmSigner::connect(&config),
+            Err(SigningError::HsmAuthFailed)
+        ));
+    }
+
+    /// Signs through SoftHSM and verifies with the exported public key.
+    ///
+    /// Requires an initialized token, e.g.:
+    ///
+    /// ```text
+    /// softhsm2-util --init-token --free --label dk-appstore --pin 1234 --so-pin 5678
+    /// pkcs11-tool --module $SOFTHSM2_MODULE --login --pin 1234 \
+    ///     --keypairgen --key-type EC:prime256v1 --label repo
+    /// ```
+    ///
+    /// and `SOFTHSM2_MODULE`, `SOFTHSM2_SLOT`, and `SOFTHSM2_PIN` set.
+    #[cfg(feature = "softhsm-tests")]
+    #[test]
+    fn test_softhsm_sign() {
+        let config = HsmConfig {
+            module_path: std::env::var("SOFTHSM2_MODULE")
+                .unwrap_or_else(|_| "/usr/lib/softhsm/libsofthsm2.so".to_string())
+                .into(),
+            slot: std::env::var("SOFTHSM2_SLOT")
+                .expect("SOFTHSM2_SLOT")
+                .parse()
+                .expect("numeric slot"),
+            pin_env_var: "SOFTHSM2_PIN".to_string(),
+            key_label: "repo".to_string(),
+        };
+
+        let signer = HsmSigner::connect(&config).expect("connect to SoftHSM");
+        assert_eq!(signer.algorithm(), SignatureAlgorithm::EcdsaP256Sha256);
+        let signature = signer.sign(b"index").expect("sign");
+        signer
+            .verify(b"index", &signature)
+            .expect("signature verifies");
+
+        assert!(matches!(
+            HsmSigner::connect(&HsmConfig {
+                key_label: "missing".to_string(),
+                ..config
+            }),
+            Err(SigningError::KeyNotFound(_))
+        ));
+    }
+}
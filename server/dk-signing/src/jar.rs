@@ -0,0 +1,254 @@
+//! Signed JAR packaging for F-Droid repository indexes.
+//!
+//! F-Droid clients download `index-v1.jar` and verify it with the standard
+//! JAR signature scheme before trusting the index inside. The archive holds
+//! the index entry, a manifest with its digest, a signature file with the
+//! manifest digest, and a detached PKCS#7 signature over the signature file.
+
+use std::io::{Cursor, Read, Write};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::digest::{digest, SHA256};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::der::{self, Reader};
+use crate::error::{SigningError, SigningResult};
+use crate::{SignatureAlgorithm, SigningService};
+
+/// Path of the JAR manifest.
+pub const MANIFEST_PATH: &str = "META-INF/MANIFEST.MF";
+
+/// Path of the signature file.
+pub const SIGNATURE_FILE_PATH: &str = "META-INF/DKAPPSTORE.SF";
+
+/// Path of the PKCS#7 signature block for ECDSA keys.
+pub const EC_SIGNATURE_BLOCK_PATH: &str = "META-INF/DKAPPSTORE.EC";
+
+/// Path of the PKCS#7 signature block for RSA keys.
+pub const RSA_SIGNATURE_BLOCK_PATH: &str = "META-INF/DKAPPSTORE.RSA";
+
+/// Value of the `Created-By` manifest attribute.
+const CREATED_BY: &str = "dk-appstore";
+
+/// Base64-encoded SHA-256 digest of `data`.
+fn sha256_base64(data: &[u8]) -> String {
+    STANDARD.encode(digest(&SHA256, data))
+}
+
+/// The per-entry manifest section for `name`.
+fn manifest_section(name: &str, content: &[u8]) -> String {
+    format!(
+        "Name: {name}\r\nSHA-256-Digest: {}\r\n\r\n",
+        sha256_base64(content)
+    )
+}
+
+/// Build `META-INF/MANIFEST.MF` listing the single `name` entry.
+fn manifest(name: &str, content: &[u8]) -> String {
+    format!(
+        "Manifest-Version: 1.0\r\nCreated-By: {CREATED_BY}\r\n\r\n{}",
+        manifest_section(name, content)
+    )
+}
+
+/// Build the signature file covering `manifest`.
+fn signature_file(manifest: &str, name: &str, content: &[u8]) -> String {
+    format!(
+        "Signature-Version: 1.0\r\nCreated-By: {CREATED_BY}\r\nSHA-256-Digest-Manifest: \
+         {}\r\n\r\nName: {name}\r\nSHA-256-Digest: {}\r\n\r\n",
+        sha256_base64(manifest.as_bytes()),
+        sha256_base64(manifest_section(name, content).as_bytes())
+    )
+}
+
+/// Returns the signature block path used for `algorithm`.
+const fn signature_block_path(algorithm: SignatureAlgorithm) -> &'static str {
+    match algorithm {
+        SignatureAlgorithm::EcdsaP256Sha256 => EC_SIGNATURE_BLOCK_PATH,
+        SignatureAlgorithm::RsaPkcs1Sha256 => RSA_SIGNATURE_BLOCK_PATH,
+    }
+}
+
+/// Build a detached PKCS#7 `SignedData` block for `signature`.
+fn signature_block(
+    certificate: &[u8],
+    algorithm: SignatureAlgorithm,
+    signature: &[u8],
+) -> SigningResult<Vec<u8>> {
+    let cert = der::parse_certificate(certificate)?;
+    let digest_algorithm = der::algorithm(der::OID_SHA256);
+    let signature_algorithm = match algorithm {
+        SignatureAlgorithm::EcdsaP256Sha256 => der::algorithm(der::OID_ECDSA_WITH_SHA256),
+        SignatureAlgorithm::RsaPkcs1Sha256 => der::algorithm_with_null(der::OID_RSA_ENCRYPTION),
+    };
+
+    let signer_info = der::sequence(&[
+        &der::small_integer(1),
+        &der::sequence(&[cert.issuer, cert.serial]),
+        &digest_algorithm,
+        &signature_algorithm,
+        &der::octet_string(signature),
+    ]);
+
+    let signed_data = der::sequence(&[
+        &der::small_integer(1),
+        &der::set(&[&digest_algorithm]),
+        &der::sequence(&[&der::oid(der::OID_PKCS7_DATA)]),
+        &der::constructed(der::TAG_CONTEXT_0, &[certificate]),
+        &der::set(&[&signer_info]),
+    ]);
+
+    Ok(der::sequence(&[
+        &der::oid(der::OID_PKCS7_SIGNED_DATA),
+        &der::constructed(der::TAG_CONTEXT_0, &[&signed_data]),
+    ]))
+}
+
+/// Package `content` as entry `name` in a JAR signed by `service`.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn example(service: &dk_signing::SigningService) -> dk_signing::SigningResult<()> {
+/// let jar = dk_signing::jar::sign_jar(service, "index-v1.json", b"{}")?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn sign_jar(service: &SigningService, name: &str, content: &[u8]) -> SigningResult<Vec<u8>> {
+    let manifest = manifest(name, content);
+    let signature_file = signature_file(&manifest, name, content);
+    let algorithm = service.algorithm();
+    let signature = service.sign(signature_file.as_bytes())?;
+    let block = signature_block(service.certificate(), algorithm, &signature)?;
+
+    let write_failed = |err: &dyn std::fmt::Display| SigningError::SigningFailed(err.to_string());
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+
+    for (path, bytes) in [
+        (MANIFEST_PATH, manifest.as_bytes()),
+        (SIGNATURE_FILE_PATH, signature_file.as_bytes()),
+        (signature_block_path(algorithm), block.as_slice()),
+        (name, content),
+    ] {
+        writer
+            .start_file(path, options)
+            .map_err(|err| write_failed(&err))?;
+        writer.write_all(bytes).map_err(|err| write_failed(&err))?;
+    }
+
+    let cursor = writer.finish().map_err(|err| write_failed(&err))?;
+    Ok(cursor.into_inner())
+}
+
+/// Read a single entry from a JAR archive.
+fn read_entry(archive: &mut ZipArchive<Cursor<&[u8]>>, path: &str) -> SigningResult<Vec<u8>> {
+    let mut file = archive
+        .by_name(path)
+        .map_err(|_| SigningError::VerificationFailed)?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)
+        .map_err(|_| SigningError::VerificationFailed)?;
+    Ok(bytes)
+}
+
+/// Extract the signature octets from a PKCS#7 block produced by [`sign_jar`].
+fn block_signature(block: &[u8]) -> SigningResult<&[u8]> {
+    let content_info = Reader::new(block).read(der::TAG_SEQUENCE)?;
+    let mut content_info = Reader::new(content_info.content);
+    content_info.read(der::TAG_OID)?;
+    let explicit = content_info.read(der::TAG_CONTEXT_0)?;
+
+    let signed_data = Reader::new(explicit.content).read(der::TAG_SEQUENCE)?;
+    let mut signed_data = Reader::new(signed_data.content);
+    signed_data.read(der::TAG_INTEGER)?;
+    signed_data.read(der::TAG_SET)?;
+    signed_data.read(der::TAG_SEQUENCE)?;
+    if signed_data.peek_tag() == Some(der::TAG_CONTEXT_0) {
+        signed_data.next_element()?;
+    }
+    let signer_infos = signed_data.read(der::TAG_SET)?;
+
+    let signer_info = Reader::new(signer_infos.content).read(der::TAG_SEQUENCE)?;
+    let mut signer_info = Reader::new(signer_info.content);
+    signer_info.read(der::TAG_INTEGER)?;
+    signer_info.read(der::TAG_SEQUENCE)?;
+    signer_info.read(der::TAG_SEQUENCE)?;
+    signer_info.read(der::TAG_SEQUENCE)?;
+    Ok(signer_info.read(der::TAG_OCTET_STRING)?.content)
+}
+
+/// Verify a JAR produced by [`sign_jar`] against `certificate` and return the
+/// verified content of entry `name`.
+pub fn verify_jar(jar: &[u8], certificate: &[u8], name: &str) -> SigningResult<Vec<u8>> {
+    let mut archive =
+        ZipArchive::new(Cursor::new(jar)).map_err(|_| SigningError::VerificationFailed)?;
+
+    let manifest_bytes = read_entry(&mut archive, MANIFEST_PATH)?;
+    let signature_file_bytes = read_entry(&mut archive, SIGNATURE_FILE_PATH)?;
+    let content = read_entry(&mut archive, name)?;
+    let (block, algorithm) = match read_entry(&mut archive, EC_SIGNATURE_BLOCK_PATH) {
+        Ok(block) => (block, SignatureAlgorithm::EcdsaP256Sha256),
+        Err(_) => (
+            read_entry(&mut archive, RSA_SIGNATURE_BLOCK_PATH)?,
+            SignatureAlgorithm::RsaPkcs1Sha256,
+        ),
+    };
+
+    // Signature over the signature file
+    let public_key = der::parse_certificate(certificate)?.public_key;
+    algorithm.verify(public_key, &signature_file_bytes, block_signature(&block)?)?;
+
+    // Signature file must describe exactly this manifest and entry
+    let manifest_text =
+        String::from_utf8(manifest_bytes).map_err(|_| SigningError::VerificationFailed)?;
+    if manifest_text != manifest(name, &content)
+        || signature_file_bytes != signature_file(&manifest_text, name, &content).as_bytes()
+    {
+        return Err(SigningError::VerificationFailed);
+    }
+
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A signing service with a freshly generated self-signed certificate.
+    fn test_service() -> SigningService {
+        SigningService::ephemeral("dk-appstore.test").expect("signing service")
+    }
+
+    #[test]
+    fn test_sign_and_verify_jar() {
+        let service = test_service();
+        let index = br#"{"repo":{"name":"DK-AppStore"}}"#;
+
+        let jar = sign_jar(&service, "index-v1.json", index).expect("sign");
+        let content = verify_jar(&jar, service.certificate(), "index-v1.json").expect("verify");
+        assert_eq!(content, index);
+    }
+
+    #[test]
+    fn test_verify_rejects_other_certificate() {
+        let service = test_service();
+        let other = test_service();
+
+        let jar = sign_jar(&service, "index-v1.json", b"{}").expect("sign");
+        assert!(matches!(
+            verify_jar(&jar, other.certificate(), "index-v1.json"),
+            Err(SigningError::VerificationFailed)
+        ));
+    }
+
+    #[test]
+    fn test_manifest_format() {
+        let manifest = manifest("index-v1.json", b"{}");
+        assert!(manifest.starts_with("Manifest-Version: 1.0\r\n"));
+        assert!(manifest.contains("Name: index-v1.json\r\nSHA-256-Digest: "));
+        assert!(manifest.ends_with("\r\n\r\n"));
+    }
+}
@@ -7,31 +7,449 @@
 //! This crate handles cryptographic keys and signing operations.
 //! All changes require security team review.
 
+pub mod apk;
+pub mod der;
 pub mod error;
+pub mod hsm;
+pub mod jar;
+pub mod signer;
+pub mod software;
 
-// HSM integration will be implemented in Phase 1
-// pub mod hsm;
-// pub mod keys;
+use std::path::Path;
 
 pub use error::{SigningError, SigningResult};
+pub use hsm::{HsmConfig, HsmSigner};
+pub use signer::{SignatureAlgorithm, Signer};
+pub use software::SoftwareSigner;
 
-/// Placeholder for signing service functionality.
+/// Key ID given to the key a [`SigningService`] is created with.
+pub const DEFAULT_KEY_ID: &str = "default";
+
+/// A repository certificate and the key it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cert {
+    /// Label of the key.
+    pub key_id: String,
+    /// Signature algorithm of the key.
+    pub algorithm: SignatureAlgorithm,
+    /// DER-encoded X.509 certificate.
+    pub der: Vec<u8>,
+}
+
+impl Cert {
+    /// Verify `signature` over `data` with the certified public key.
+    fn verify(&self, data: &[u8], signature: &[u8]) -> SigningResult<()> {
+        let public_key = der::parse_certificate(&self.der)?.public_key;
+        self.algorithm.verify(public_key, data, signature)
+    }
+}
+
+/// A registered key: its certificate, and its signer unless retired.
+struct Key {
+    cert: Cert,
+    signer: Option<Box<dyn Signer>>,
+}
+
+/// Repository signing service.
+///
+/// Signs with a [`Signer`] (an HSM key in production, an in-memory key for
+/// development) and carries the matching X.509 certificate so signatures can
+/// be packaged for F-Droid clients.
 ///
-/// Full implementation will be added in Phase 1 with HSM integration.
+/// Several keys can be registered under distinct key IDs. New signatures
+/// use the active key, while signatures by any registered key still verify,
+/// so the repository key can be rotated without breaking clients.
 pub struct SigningService {
-    _private: (),
+    keys: Vec<Key>,
+    active: usize,
+}
+
+/// Check that `certificate` certifies the public key of `signer`.
+fn check_certificate(signer: &dyn Signer, certificate: &[u8]) -> SigningResult<()> {
+    let fields = der::parse_certificate(certificate)?;
+    if fields.public_key != signer.public_key()?.as_slice() {
+        return Err(SigningError::InvalidCertificate(
+            "certificate does not match signing key".to_string(),
+        ));
+    }
+    Ok(())
 }
 
 impl SigningService {
-    /// Create a new signing service (placeholder).
+    /// Create a signing service from a signer and its DER certificate.
+    ///
+    /// The key is registered as [`DEFAULT_KEY_ID`] and is active.
+    pub fn new(signer: impl Signer + 'static, certificate: Vec<u8>) -> SigningResult<Self> {
+        // Fail early on certificates we could not embed in a signature
+        check_certificate(&signer, &certificate)?;
+
+        Ok(Self {
+            keys: vec![Key {
+                cert: Cert {
+                    key_id: DEFAULT_KEY_ID.to_string(),
+                    algorithm: signer.algorithm(),
+                    der: certificate,
+                },
+                signer: Some(Box::new(signer)),
+            }],
+            active: 0,
+        })
+    }
+
+    /// Create a signing service backed by a PKCS#11 HSM key.
+    ///
+    /// `certificate` is the DER certificate for the HSM key.
+    pub fn with_hsm(config: &HsmConfig, certificate: Vec<u8>) -> SigningResult<Self> {
+        Self::new(HsmSigner::connect(config)?, certificate)
+    }
+
+    /// Create a signing service from a PKCS#8 (DER) key and its DER certificate.
+    pub fn from_pkcs8(pkcs8: &[u8], certificate: Vec<u8>) -> SigningResult<Self> {
+        Self::new(SoftwareSigner::from_pkcs8(pkcs8)?, certificate)
+    }
+
+    /// Load a PKCS#8 (DER) key and DER certificate from disk.
+    pub fn from_files(key_path: &Path, certificate_path: &Path) -> SigningResult<Self> {
+        let pkcs8 = std::fs::read(key_path)
+            .map_err(|err| SigningError::KeyNotFound(format!("{}: {err}", key_path.display())))?;
+        let certificate = std::fs::read(certificate_path).map_err(|err| {
+            SigningError::InvalidCertificate(format!("{}: {err}", certificate_path.display()))
+        })?;
+
+        Self::from_pkcs8(&pkcs8, certificate)
+    }
+
+    /// Create a signing service with a freshly generated in-memory key and a
+    /// self-signed certificate. For local development and tests only.
+    pub fn ephemeral(common_name: &str) -> SigningResult<Self> {
+        let signer = SoftwareSigner::generate()?;
+        let certificate = signer.self_signed_certificate(common_name)?;
+        Self::new(signer, certificate)
+    }
+
+    /// Register another signing key under `key_id`, without activating it.
+    pub fn add_key(
+        &mut self,
+        key_id: impl Into<String>,
+        signer: impl Signer + 'static,
+        certificate: Vec<u8>,
+    ) -> SigningResult<()> {
+        check_certificate(&signer, &certificate)?;
+        let cert = Cert {
+            key_id: key_id.into(),
+            algorithm: signer.algorithm(),
+            der: certificate,
+        };
+        self.register(Key {
+            cert,
+            signer: Some(Box::new(signer)),
+        })
+    }
+
+    /// Register the certificate of a retired key whose private key is no
+    /// longer available, so its signatures keep verifying.
+    pub fn add_certificate(
+        &mut self,
+        key_id: impl Into<String>,
+        algorithm: SignatureAlgorithm,
+        certificate: Vec<u8>,
+    ) -> SigningResult<()> {
+        der::parse_certificate(&certificate)?;
+        let cert = Cert {
+            key_id: key_id.into(),
+            algorithm,
+            der: certificate,
+        };
+        self.register(Key { cert, signer: None })
+    }
+
+    fn register(&mut self, key: Key) -> SigningResult<()> {
+        if self
+            .keys
+            .iter()
+            .any(|existing| existing.cert.key_id == key.cert.key_id)
+        {
+            return Err(SigningError::InvalidKey(format!(
+                "key '{}' is already registered",
+                key.cert.key_id
+            )));
+        }
+        self.keys.push(key);
+        Ok(())
+    }
+
+    /// Make `key_id` the key new signatures are made with.
+    ///
+    /// To rotate the repository key:
+    ///
+    /// 1. Register the new key with [`Self::add_key`] next to the current one.
+    /// 2. Call `rotate_to` with the new key ID; from then on artifacts are
+    ///    signed with the new key.
+    /// 3. Keep the old key, or its certificate via [`Self::add_certificate`],
+    ///    registered so artifacts it signed still verify.
+    pub fn rotate_to(&mut self, key_id: &str) -> SigningResult<()> {
+        let index = self
+            .keys
+            .iter()
+            .position(|key| key.cert.key_id == key_id)
+            .ok_or_else(|| SigningError::KeyNotFound(key_id.to_string()))?;
+        if self.keys[index].signer.is_none() {
+            return Err(SigningError::InvalidKey(format!(
+                "key '{key_id}' is retired and cannot sign"
+            )));
+        }
+        self.active = index;
+        Ok(())
+    }
+
+    /// Returns the ID of the key new signatures are made with.
     #[must_use]
-    pub fn new() -> Self {
-        Self { _private: () }
+    pub fn active_key_id(&self) -> &str {
+        &self.active_key().cert.key_id
+    }
+
+    /// Returns the certificates of all registered keys, in registration order.
+    #[must_use]
+    pub fn public_certs(&self) -> Vec<Cert> {
+        self.keys.iter().map(|key| key.cert.clone()).collect()
+    }
+
+    fn active_key(&self) -> &Key {
+        &self.keys[self.active]
+    }
+
+    fn active_signer(&self) -> SigningResult<&dyn Signer> {
+        self.active_key()
+            .signer
+            .as_deref()
+            .ok_or_else(|| SigningError::InvalidKey("active key cannot sign".to_string()))
+    }
+
+    /// Sign `data` with the active key.
+    ///
+    /// ECDSA signatures are ASN.1 DER encoded; see [`Self::algorithm`].
+    pub fn sign(&self, data: &[u8]) -> SigningResult<Vec<u8>> {
+        self.active_signer()?.sign(data)
+    }
+
+    /// Sign the APK at `unsigned_apk` with APK Signature Scheme v2 using the
+    /// active key, writing the signed APK to `out`.
+    ///
+    /// Fails with [`SigningError::InvalidApk`] if the input is not an APK or
+    /// is already signed; see [`apk::sign_apk`].
+    pub fn sign_apk(&self, unsigned_apk: &Path, out: &Path) -> SigningResult<()> {
+        apk::sign_apk_file(self, unsigned_apk, out)
+    }
+
+    /// Verify a detached `signature` over `data`, such as one made by
+    /// [`Self::sign`] or offline with a registered key.
+    ///
+    /// Only the key `key_id` is tried if given, otherwise every registered
+    /// key, including retired ones. Fails with
+    /// [`SigningError::VerificationFailed`] if no key verifies the
+    /// signature, or [`SigningError::KeyNotFound`] if `key_id` is not
+    /// registered.
+    pub fn verify(&self, data: &[u8], signature: &[u8], key_id: Option<&str>) -> SigningResult<()> {
+        let mut candidates = self
+            .keys
+            .iter()
+            .filter(|key| key_id.map_or(true, |key_id| key.cert.key_id == key_id))
+            .peekable();
+        if candidates.peek().is_none() {
+            if let Some(key_id) = key_id {
+                return Err(SigningError::KeyNotFound(key_id.to_string()));
+            }
+        }
+
+        if candidates.any(|key| key.cert.verify(data, signature).is_ok()) {
+            Ok(())
+        } else {
+            Err(SigningError::VerificationFailed)
+        }
+    }
+
+    /// Returns the raw public key of the active key.
+    pub fn public_key(&self) -> SigningResult<Vec<u8>> {
+        self.active_signer()?.public_key()
+    }
+
+    /// Returns the algorithm used by [`Self::sign`].
+    #[must_use]
+    pub fn algorithm(&self) -> SignatureAlgorithm {
+        self.active_key().cert.algorithm
+    }
+
+    /// Returns the DER-encoded certificate of the active key.
+    #[must_use]
+    pub fn certificate(&self) -> &[u8] {
+        &self.active_key().cert.der
     }
 }
 
-impl Default for SigningService {
-    fn default() -> Self {
-        Self::new()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ephemeral_service_round_trip() {
+        let service = SigningService::ephemeral("dk-appstore.test").expect("service");
+        let signature = service.sign(b"index").expect("sign");
+        service.verify(b"index", &signature, None).expect("verify");
+        assert_eq!(service.algorithm(), SignatureAlgorithm::EcdsaP256Sha256);
+    }
+
+    /// A fresh software key and its self-signed certificate.
+    fn key(common_name: &str) -> (SoftwareSigner, Vec<u8>) {
+        let signer = SoftwareSigner::generate().expect("generate");
+        let certificate = signer
+            .self_signed_certificate(common_name)
+            .expect("certificate");
+        (signer, certificate)
+    }
+
+    #[test]
+    fn test_rotation_keeps_old_signatures_valid() {
+        let (signer, certificate) = key("dk-appstore 2025");
+        let mut service = SigningService::new(signer, certificate).expect("service");
+        assert_eq!(service.active_key_id(), DEFAULT_KEY_ID);
+        let old_signature = service.sign(b"index").expect("sign");
+
+        let (signer, certificate) = key("dk-appstore 2026");
+        service
+            .add_key("2026", signer, certificate.clone())
+            .expect("add key");
+        // Registering does not change the active key
+        assert_eq!(service.active_key_id(), DEFAULT_KEY_ID);
+
+        service.rotate_to("2026").expect("rotate");
+        assert_eq!(service.active_key_id(), "2026");
+        assert_eq!(service.certificate(), certificate.as_slice());
+        let new_signature = service.sign(b"index").expect("sign");
+
+        service
+            .verify(b"index", &old_signature, None)
+            .expect("old signature");
+        service
+            .verify(b"index", &new_signature, None)
+            .expect("new signature");
+        assert!(matches!(
+            service.verify(b"tampered", &new_signature, None),
+            Err(SigningError::VerificationFailed)
+        ));
+
+        let ids: Vec<String> = service
+            .public_certs()
+            .into_iter()
+            .map(|cert| cert.key_id)
+            .collect();
+        assert_eq!(ids, [DEFAULT_KEY_ID, "2026"]);
+    }
+
+    #[test]
+    fn test_verify_with_multi_cert_set() {
+        let service = SigningService::ephemeral("dk-appstore.test").expect("service");
+        let signature = service.sign(b"artifact").expect("sign");
+
+        // A client holding the published certificates verifies with any of them
+        let (_, other) = key("unrelated");
+        let mut certs = vec![Cert {
+            key_id: "unrelated".to_string(),
+            algorithm: SignatureAlgorithm::EcdsaP256Sha256,
+            der: other,
+        }];
+        certs.extend(service.public_certs());
+        let verified: Vec<&str> = certs
+            .iter()
+            .filter(|cert| cert.verify(b"artifact", &signature).is_ok())
+            .map(|cert| cert.key_id.as_str())
+            .collect();
+        assert_eq!(verified, [DEFAULT_KEY_ID]);
+    }
+
+    #[test]
+    fn test_retired_certificate_verifies_but_cannot_sign() {
+        let (old_signer, old_certificate) = key("old");
+        let old_signature = old_signer.sign(b"index").expect("sign");
+
+        let mut service = SigningService::ephemeral("new").expect("service");
+        service
+            .add_certificate("old", old_signer.algorithm(), old_certificate)
+            .expect("add certificate");
+
+        service
+            .verify(b"index", &old_signature, Some("old"))
+            .expect("retired key verifies");
+        assert!(matches!(
+            service.rotate_to("old"),
+            Err(SigningError::InvalidKey(_))
+        ));
+        assert!(matches!(
+            service.rotate_to("missing"),
+            Err(SigningError::KeyNotFound(_))
+        ));
+        assert_eq!(service.active_key_id(), DEFAULT_KEY_ID);
+    }
+
+    #[test]
+    fn test_verify_detached_signature() {
+        let (signer, certificate) = key("offline");
+        let signature = signer.sign(b"app.apk").expect("sign");
+        let mut service = SigningService::ephemeral("dk-appstore.test").expect("service");
+        service
+            .add_key("offline", signer, certificate)
+            .expect("add key");
+
+        service
+            .verify(b"app.apk", &signature, None)
+            .expect("any key");
+        service
+            .verify(b"app.apk", &signature, Some("offline"))
+            .expect("named key");
+        assert!(matches!(
+            service.verify(b"app.apk tampered", &signature, None),
+            Err(SigningError::VerificationFailed)
+        ));
+    }
+
+    #[test]
+    fn test_verify_with_wrong_key_fails() {
+        let (signer, _) = key("unregistered");
+        let signature = signer.sign(b"app.apk").expect("sign");
+        let service = SigningService::ephemeral("dk-appstore.test").expect("service");
+
+        assert!(matches!(
+            service.verify(b"app.apk", &signature, None),
+            Err(SigningError::VerificationFailed)
+        ));
+        assert!(matches!(
+            service.verify(b"app.apk", &signature, Some(DEFAULT_KEY_ID)),
+            Err(SigningError::VerificationFailed)
+        ));
+        assert!(matches!(
+            service.verify(b"app.apk", &signature, Some("missing")),
+            Err(SigningError::KeyNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_duplicate_key_id() {
+        let mut service = SigningService::ephemeral("dk-appstore.test").expect("service");
+        let (signer, certificate) = key("duplicate");
+        assert!(matches!(
+            service.add_key(DEFAULT_KEY_ID, signer, certificate),
+            Err(SigningError::InvalidKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_mismatched_certificate() {
+        let other = SoftwareSigner::generate().expect("generate");
+        let certificate = other.self_signed_certificate("other").expect("certificate");
+        let signer = SoftwareSigner::generate().expect("generate");
+
+        assert!(matches!(
+            SigningService::new(signer, certificate),
+            Err(SigningError::InvalidCertificate(_))
+        ));
     }
 }
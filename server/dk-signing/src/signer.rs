@@ -0,0 +1,57 @@
+//! Signing key abstraction.
+
+use ring::signature::{
+    UnparsedPublicKey, VerificationAlgorithm, ECDSA_P256_SHA256_ASN1, RSA_PKCS1_2048_8192_SHA256,
+};
+
+use crate::error::{SigningError, SigningResult};
+
+/// Signature algorithm of a signing key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureAlgorithm {
+    /// ECDSA over P-256 with SHA-256, DER encoded signatures.
+    EcdsaP256Sha256,
+    /// RSA PKCS#1 v1.5 with SHA-256.
+    RsaPkcs1Sha256,
+}
+
+impl SignatureAlgorithm {
+    /// The ring verification algorithm for this signature algorithm.
+    pub(crate) fn verification(self) -> &'static dyn VerificationAlgorithm {
+        match self {
+            Self::EcdsaP256Sha256 => &ECDSA_P256_SHA256_ASN1,
+            Self::RsaPkcs1Sha256 => &RSA_PKCS1_2048_8192_SHA256,
+        }
+    }
+
+    /// Verify `signature` over `data` with a raw `public_key`.
+    ///
+    /// The public key is an uncompressed EC point for ECDSA, or a DER
+    /// `RSAPublicKey` for RSA.
+    pub fn verify(self, public_key: &[u8], data: &[u8], signature: &[u8]) -> SigningResult<()> {
+        UnparsedPublicKey::new(self.verification(), public_key)
+            .verify(data, signature)
+            .map_err(|_| SigningError::VerificationFailed)
+    }
+}
+
+/// A private signing key.
+///
+/// Production keys live in an HSM ([`crate::HsmSigner`]); development and
+/// tests use an in-memory key ([`crate::SoftwareSigner`]).
+pub trait Signer: Send + Sync {
+    /// The algorithm signatures are produced with.
+    fn algorithm(&self) -> SignatureAlgorithm;
+
+    /// Sign `data`. ECDSA signatures are ASN.1 DER encoded.
+    fn sign(&self, data: &[u8]) -> SigningResult<Vec<u8>>;
+
+    /// Returns the raw public key; see [`SignatureAlgorithm::verify`].
+    fn public_key(&self) -> SigningResult<Vec<u8>>;
+
+    /// Verify a signature produced by [`Signer::sign`].
+    fn verify(&self, data: &[u8], signature: &[u8]) -> SigningResult<()> {
+        self.algorithm()
+            .verify(&self.public_key()?, data, signature)
+    }
+}
@@ -0,0 +1,158 @@
+//! In-memory software signer for development and tests.
+//!
+//! Uses ECDSA P-256 rather than Ed25519: F-Droid clients verify the index
+//! JAR with Android's JAR verifier, which only accepts RSA, DSA, and EC keys.
+
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+
+use crate::der;
+use crate::error::{SigningError, SigningResult};
+use crate::signer::{SignatureAlgorithm, Signer};
+
+/// OID 1.2.840.10045.2.1 (ecPublicKey).
+const OID_EC_PUBLIC_KEY: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x02, 0x01];
+/// OID 1.2.840.10045.3.1.7 (prime256v1).
+const OID_PRIME256V1: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x03, 0x01, 0x07];
+/// OID 2.5.4.3 (commonName).
+const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+
+/// An ECDSA P-256 key held in process memory.
+pub struct SoftwareSigner {
+    key_pair: EcdsaKeyPair,
+    rng: SystemRandom,
+}
+
+impl std::fmt::Debug for SoftwareSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SoftwareSigner").finish_non_exhaustive()
+    }
+}
+
+impl SoftwareSigner {
+    /// Generate a fresh random key.
+    pub fn generate() -> SigningResult<Self> {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng)
+            .map_err(|_| SigningError::InvalidKey("key generation failed".to_string()))?;
+        Self::from_pkcs8(pkcs8.as_ref())
+    }
+
+    /// Load a PKCS#8 (DER) encoded ECDSA P-256 key.
+    pub fn from_pkcs8(pkcs8: &[u8]) -> SigningResult<Self> {
+        let rng = SystemRandom::new();
+        let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8, &rng)
+            .map_err(|err| SigningError::InvalidKey(err.to_string()))?;
+
+        Ok(Self { key_pair, rng })
+    }
+
+    /// Issue a self-signed DER certificate for this key.
+    ///
+    /// Intended for local development and tests; the certificate has no
+    /// well-defined expiry.
+    pub fn self_signed_certificate(&self, common_name: &str) -> SigningResult<Vec<u8>> {
+        let mut serial = [0u8; 16];
+        self.rng
+            .fill(&mut serial)
+            .map_err(|_| SigningError::SigningFailed("random serial failed".to_string()))?;
+        serial[0] &= 0x7F;
+
+        let name = der::sequence(&[&der::set(&[&der::sequence(&[
+            &der::oid(OID_COMMON_NAME),
+            &der::tlv(der::TAG_UTF8_STRING, common_name.as_bytes()),
+        ])])]);
+        let validity = der::sequence(&[
+            &der::tlv(der::TAG_UTC_TIME, b"000101000000Z"),
+            &der::tlv(der::TAG_GENERALIZED_TIME, b"99991231235959Z"),
+        ]);
+        let spki = der::sequence(&[
+            &der::sequence(&[&der::oid(OID_EC_PUBLIC_KEY), &der::oid(OID_PRIME256V1)]),
+            &der::bit_string(self.key_pair.public_key().as_ref()),
+        ]);
+        let signature_algorithm = der::algorithm(der::OID_ECDSA_WITH_SHA256);
+
+        let tbs = der::sequence(&[
+            &der::constructed(der::TAG_CONTEXT_0, &[&der::small_integer(2)]),
+            &der::unsigned_integer(&serial),
+            &signature_algorithm,
+            &name,
+            &validity,
+            &name,
+            &spki,
+        ]);
+        let signature = self.sign(&tbs)?;
+
+        Ok(der::sequence(&[
+            &tbs,
+            &signature_algorithm,
+            &der::bit_string(&signature),
+        ]))
+    }
+}
+
+impl Signer for SoftwareSigner {
+    fn algorithm(&self) -> SignatureAlgorithm {
+        SignatureAlgorithm::EcdsaP256Sha256
+    }
+
+    fn sign(&self, data: &[u8]) -> SigningResult<Vec<u8>> {
+        self.key_pair
+            .sign(&self.rng, data)
+            .map(|signature| signature.as_ref().to_vec())
+            .map_err(|_| SigningError::SigningFailed("ECDSA signing failed".to_string()))
+    }
+
+    fn public_key(&self) -> SigningResult<Vec<u8>> {
+        Ok(self.key_pair.public_key().as_ref().to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_verify_round_trip() {
+        let signer = SoftwareSigner::generate().expect("generate");
+        let signature = signer.sign(b"index").expect("sign");
+
+        signer.verify(b"index", &signature).expect("verify");
+        assert!(matches!(
+            signer.verify(b"tampered", &signature),
+            Err(SigningError::VerificationFailed)
+        ));
+    }
+
+    #[test]
+    fn test_verify_rejects_other_key() {
+        let signer = SoftwareSigner::generate().expect("generate");
+        let other = SoftwareSigner::generate().expect("generate");
+        let signature = signer.sign(b"index").expect("sign");
+
+        assert!(other.verify(b"index", &signature).is_err());
+    }
+
+    #[test]
+    fn test_self_signed_certificate_matches_key() {
+        let signer = SoftwareSigner::generate().expect("generate");
+        let certificate = signer
+            .self_signed_certificate("dk-appstore.test")
+            .expect("certificate");
+
+        let fields = der::parse_certificate(&certificate).expect("parse");
+        assert_eq!(fields.public_key, signer.public_key().expect("public key"));
+    }
+
+    #[test]
+    fn test_pkcs8_round_trip_from_rcgen() {
+        let cert = rcgen::generate_simple_self_signed(vec!["dk-appstore.test".to_string()])
+            .expect("generate certificate");
+        let signer =
+            SoftwareSigner::from_pkcs8(&cert.serialize_private_key_der()).expect("load key");
+
+        let certificate = cert.serialize_der().expect("serialize certificate");
+        let fields = der::parse_certificate(&certificate).expect("parse");
+        assert_eq!(fields.public_key, signer.public_key().expect("public key"));
+    }
+}
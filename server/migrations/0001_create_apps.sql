@@ -0,0 +1,14 @@
+-- Applications published in the repository.
+CREATE TABLE IF NOT EXISTS apps (
+    id UUID PRIMARY KEY,
+    package_id TEXT NOT NULL UNIQUE,
+    -- Localized strings, stored as {"<locale>": "<value>"} objects
+    name JSONB NOT NULL,
+    summary JSONB NOT NULL,
+    description JSONB NOT NULL,
+    categories TEXT[] NOT NULL DEFAULT '{}',
+    version_code BIGINT NOT NULL,
+    version_name TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
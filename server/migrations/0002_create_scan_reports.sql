@@ -0,0 +1,10 @@
+-- Security scan reports, one per APK version.
+CREATE TABLE IF NOT EXISTS scan_reports (
+    package_id TEXT NOT NULL,
+    version_code BIGINT NOT NULL,
+    status TEXT NOT NULL,
+    -- Serialized dk_scanner::ScanReport
+    report JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (package_id, version_code)
+);
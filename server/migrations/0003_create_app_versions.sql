@@ -0,0 +1,18 @@
+-- Published APK versions of each application.
+CREATE TABLE IF NOT EXISTS app_versions (
+    id UUID PRIMARY KEY,
+    app_id UUID NOT NULL REFERENCES apps (id) ON DELETE CASCADE,
+    version_code BIGINT NOT NULL,
+    version_name TEXT NOT NULL,
+    -- Lowercase hex SHA-256 of the APK
+    sha256 TEXT NOT NULL,
+    size BIGINT NOT NULL,
+    min_sdk INTEGER NOT NULL,
+    target_sdk INTEGER NOT NULL,
+    -- Serialized [{"name": ..., "maxSdk": ...}] list
+    permissions JSONB NOT NULL DEFAULT '[]',
+    -- F-Droid "sig": MD5 of the hex-encoded signing certificate
+    sig TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (app_id, version_code)
+);
@@ -0,0 +1,8 @@
+-- APK versions withheld from the index after failing a security scan.
+CREATE TABLE IF NOT EXISTS quarantined_versions (
+    package_id TEXT NOT NULL,
+    version_code BIGINT NOT NULL,
+    reason TEXT NOT NULL,
+    quarantined_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (package_id, version_code)
+);
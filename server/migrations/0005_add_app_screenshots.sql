@@ -0,0 +1,3 @@
+-- Screenshots shown on app pages, stored as
+-- [{"locale": ..., "url": ..., "width": ..., "height": ...}] arrays
+ALTER TABLE apps ADD COLUMN IF NOT EXISTS screenshots JSONB NOT NULL DEFAULT '[]';
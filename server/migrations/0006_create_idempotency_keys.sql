@@ -0,0 +1,12 @@
+-- Idempotency keys of APK uploads, so retried uploads replay the original
+-- result instead of publishing twice. Keys expire after 24 hours.
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    key TEXT PRIMARY KEY,
+    package_id TEXT NOT NULL,
+    -- Lowercase hex SHA-256 of the uploaded APK
+    sha256 TEXT NOT NULL,
+    version_id UUID NOT NULL REFERENCES app_versions (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idempotency_keys_created_at ON idempotency_keys (created_at);
@@ -0,0 +1,3 @@
+-- Unpublished versions are kept for audit but left out of the index and
+-- version listings.
+ALTER TABLE app_versions ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
@@ -0,0 +1,3 @@
+-- ABIs of the native libraries bundled in each version (e.g. "arm64-v8a");
+-- empty for pure-Java APKs, which run on every ABI.
+ALTER TABLE app_versions ADD COLUMN IF NOT EXISTS abis TEXT[] NOT NULL DEFAULT '{}';
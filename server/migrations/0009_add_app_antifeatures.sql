@@ -0,0 +1,2 @@
+-- F-Droid anti-feature codes of each app (e.g. "Tracking", "NonFreeNet").
+ALTER TABLE apps ADD COLUMN IF NOT EXISTS antifeatures TEXT[] NOT NULL DEFAULT '{}';
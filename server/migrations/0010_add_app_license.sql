@@ -0,0 +1,2 @@
+-- SPDX license identifier of each app (e.g. "GPL-3.0-only"); NULL if unknown.
+ALTER TABLE apps ADD COLUMN IF NOT EXISTS license TEXT;
@@ -0,0 +1,4 @@
+-- Links shown on the app page: source repository, issue tracker, web site.
+ALTER TABLE apps ADD COLUMN IF NOT EXISTS source_url TEXT;
+ALTER TABLE apps ADD COLUMN IF NOT EXISTS issue_tracker_url TEXT;
+ALTER TABLE apps ADD COLUMN IF NOT EXISTS web_url TEXT;
@@ -0,0 +1,2 @@
+-- Donation links of each app: [{"kind": "liberapay", "url": "..."}].
+ALTER TABLE apps ADD COLUMN IF NOT EXISTS donate JSONB NOT NULL DEFAULT '[]';
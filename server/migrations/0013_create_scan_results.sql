@@ -0,0 +1,24 @@
+-- Security scan results, one per scan; the latest for a version is current.
+-- Supersedes scan_reports, which kept only one report per version.
+CREATE TABLE IF NOT EXISTS scan_results (
+    id BIGSERIAL PRIMARY KEY,
+    package_id TEXT NOT NULL,
+    version_code BIGINT NOT NULL,
+    status TEXT NOT NULL,
+    -- Serialized dk_scanner::ScanReport
+    report JSONB NOT NULL,
+    scanned_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS scan_results_version_idx
+    ON scan_results (package_id, version_code, scanned_at DESC);
+
+DO $$
+BEGIN
+    IF to_regclass('scan_reports') IS NOT NULL THEN
+        INSERT INTO scan_results (package_id, version_code, status, report, scanned_at)
+        SELECT package_id, version_code, status, report, created_at FROM scan_reports;
+        DROP TABLE scan_reports;
+    END IF;
+END
+$$;
@@ -0,0 +1,7 @@
+-- Repository-wide state; holds a single row.
+CREATE TABLE IF NOT EXISTS repo_state (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    -- Last index timestamp issued, in milliseconds since the epoch. Clients
+    -- reject an index older than the one they have, so this never decreases.
+    last_index_timestamp BIGINT NOT NULL
+);
@@ -0,0 +1,4 @@
+-- Fingerprint of the repository the last index timestamp was issued for.
+-- A changed repository gets a later timestamp, even if its content is dated
+-- no later than what was issued before.
+ALTER TABLE repo_state ADD COLUMN IF NOT EXISTS last_fingerprint TEXT;